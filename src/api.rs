@@ -0,0 +1,256 @@
+use async_trait::async_trait;
+
+use crate::{
+    cache::Freshness,
+    commands::CommandError,
+    errors::Result,
+    operations::Operation,
+    policies::{BasePolicy, BatchPolicy, InfoPolicy, ScanPolicy, WritePolicy},
+    BatchRead, Bin, Bins, Client, Key, Record, RecordSet,
+};
+
+/// Object-safe abstraction over the database operations of the [`Client`].
+///
+/// Code that accepts an `Arc<dyn ClientApi>` (or a generic `impl ClientApi`) can be given the real
+/// client, a mock in tests, or a wrapper that adds caching, metrics and the like on top of another
+/// implementation.
+///
+/// Unlike the inherent methods of the [`Client`], the bin selection is always passed as [`Bins`],
+/// as generic methods would prevent the trait from being used as a trait object.
+///
+/// # Example
+///
+/// ```rust
+/// use std::sync::Arc;
+///
+/// use windpike::{errors::CommandError, policies::BasePolicy, Bins, ClientApi, Key, Value};
+///
+/// async fn user_age(client: Arc<dyn ClientApi>, id: i64) -> Result<Option<Value>, CommandError> {
+///     let key = Key::new("test", "users", id);
+///     let mut record = client
+///         .get(&BasePolicy::default(), &key, Bins::from(["age"]))
+///         .await?;
+///     Ok(record.bins.remove("age"))
+/// }
+/// ```
+#[async_trait]
+pub trait ClientApi: Send + Sync {
+    /// Read record for the specified key. See [`Client::get`].
+    async fn get(&self, policy: &BasePolicy, key: &Key, bins: Bins)
+        -> Result<Record, CommandError>;
+
+    /// Read record for the specified key, but only if its generation differs from the known
+    /// one. See [`Client::get_if_changed`].
+    async fn get_if_changed(
+        &self,
+        policy: &BasePolicy,
+        key: &Key,
+        bins: Bins,
+        known_generation: u32,
+    ) -> Result<Freshness, CommandError> {
+        let header = self.get(policy, key, Bins::None).await?;
+        if header.generation == known_generation {
+            return Ok(Freshness::NotModified);
+        }
+
+        self.get(policy, key, bins).await.map(Freshness::Modified)
+    }
+
+    /// Read multiple records for the specified batch keys in one batch call. See
+    /// [`Client::batch_get`].
+    async fn batch_get(
+        &self,
+        policy: &BatchPolicy,
+        batch_reads: Vec<BatchRead>,
+    ) -> Result<Vec<BatchRead>>;
+
+    /// Read multiple records with the same bin selection in one batch call. See
+    /// [`Client::batch_get_bins`].
+    async fn batch_get_bins(
+        &self,
+        policy: &BatchPolicy,
+        keys: &[Key],
+        bins: Bins,
+    ) -> Result<Vec<Option<Record>>>;
+
+    /// Write record bin(s). See [`Client::put`].
+    async fn put(
+        &self,
+        policy: &WritePolicy,
+        key: &Key,
+        bins: &[Bin<'_>],
+    ) -> Result<(), CommandError>;
+
+    /// Add integer bin values to existing record bin values. See [`Client::add`].
+    async fn add(
+        &self,
+        policy: &WritePolicy,
+        key: &Key,
+        bins: &[Bin<'_>],
+    ) -> Result<(), CommandError>;
+
+    /// Append bin string values to existing record bin values. See [`Client::append`].
+    async fn append(
+        &self,
+        policy: &WritePolicy,
+        key: &Key,
+        bins: &[Bin<'_>],
+    ) -> Result<(), CommandError>;
+
+    /// Prepend bin string values to existing record bin values. See [`Client::prepend`].
+    async fn prepend(
+        &self,
+        policy: &WritePolicy,
+        key: &Key,
+        bins: &[Bin<'_>],
+    ) -> Result<(), CommandError>;
+
+    /// Delete record for specified key. See [`Client::delete`].
+    async fn delete(&self, policy: &WritePolicy, key: &Key) -> Result<bool, CommandError>;
+
+    /// Reset record's time to expiration. See [`Client::touch`].
+    async fn touch(&self, policy: &WritePolicy, key: &Key) -> Result<(), CommandError>;
+
+    /// Reset record's time to expiration, if the record exists. See [`Client::touch_if_exists`].
+    async fn touch_if_exists(&self, policy: &WritePolicy, key: &Key) -> Result<bool, CommandError>;
+
+    /// Determine if a record key exists. See [`Client::exists`].
+    async fn exists(&self, policy: &WritePolicy, key: &Key) -> Result<bool, CommandError>;
+
+    /// Perform multiple read/write operations on a single key in one batch call. See
+    /// [`Client::operate`].
+    async fn operate(
+        &self,
+        policy: &WritePolicy,
+        key: &Key,
+        ops: &[Operation<'_>],
+    ) -> Result<Record, CommandError>;
+
+    /// Read all records in the specified namespace and set. See [`Client::scan`].
+    async fn scan(
+        &self,
+        policy: &ScanPolicy,
+        namespace: &str,
+        set_name: &str,
+        bins: Bins,
+    ) -> Result<RecordSet>;
+
+    /// Remove all records in the specified namespace/set. See [`Client::truncate`].
+    async fn truncate(
+        &self,
+        policy: &InfoPolicy,
+        namespace: &str,
+        set_name: &str,
+        before_nanos: i64,
+    ) -> Result<()>;
+}
+
+#[async_trait]
+impl ClientApi for Client {
+    async fn get(
+        &self,
+        policy: &BasePolicy,
+        key: &Key,
+        bins: Bins,
+    ) -> Result<Record, CommandError> {
+        Client::get(self, policy, key, bins).await
+    }
+
+    async fn batch_get(
+        &self,
+        policy: &BatchPolicy,
+        batch_reads: Vec<BatchRead>,
+    ) -> Result<Vec<BatchRead>> {
+        Client::batch_get(self, policy, batch_reads).await
+    }
+
+    async fn batch_get_bins(
+        &self,
+        policy: &BatchPolicy,
+        keys: &[Key],
+        bins: Bins,
+    ) -> Result<Vec<Option<Record>>> {
+        Client::batch_get_bins(self, policy, keys, bins).await
+    }
+
+    async fn put(
+        &self,
+        policy: &WritePolicy,
+        key: &Key,
+        bins: &[Bin<'_>],
+    ) -> Result<(), CommandError> {
+        Client::put(self, policy, key, bins).await
+    }
+
+    async fn add(
+        &self,
+        policy: &WritePolicy,
+        key: &Key,
+        bins: &[Bin<'_>],
+    ) -> Result<(), CommandError> {
+        Client::add(self, policy, key, bins).await
+    }
+
+    async fn append(
+        &self,
+        policy: &WritePolicy,
+        key: &Key,
+        bins: &[Bin<'_>],
+    ) -> Result<(), CommandError> {
+        Client::append(self, policy, key, bins).await
+    }
+
+    async fn prepend(
+        &self,
+        policy: &WritePolicy,
+        key: &Key,
+        bins: &[Bin<'_>],
+    ) -> Result<(), CommandError> {
+        Client::prepend(self, policy, key, bins).await
+    }
+
+    async fn delete(&self, policy: &WritePolicy, key: &Key) -> Result<bool, CommandError> {
+        Client::delete(self, policy, key).await
+    }
+
+    async fn touch(&self, policy: &WritePolicy, key: &Key) -> Result<(), CommandError> {
+        Client::touch(self, policy, key).await
+    }
+
+    async fn touch_if_exists(&self, policy: &WritePolicy, key: &Key) -> Result<bool, CommandError> {
+        Client::touch_if_exists(self, policy, key).await
+    }
+
+    async fn exists(&self, policy: &WritePolicy, key: &Key) -> Result<bool, CommandError> {
+        Client::exists(self, policy, key).await
+    }
+
+    async fn operate(
+        &self,
+        policy: &WritePolicy,
+        key: &Key,
+        ops: &[Operation<'_>],
+    ) -> Result<Record, CommandError> {
+        Client::operate(self, policy, key, ops).await
+    }
+
+    async fn scan(
+        &self,
+        policy: &ScanPolicy,
+        namespace: &str,
+        set_name: &str,
+        bins: Bins,
+    ) -> Result<RecordSet> {
+        Client::scan(self, policy, namespace, set_name, bins).await
+    }
+
+    async fn truncate(
+        &self,
+        policy: &InfoPolicy,
+        namespace: &str,
+        set_name: &str,
+        before_nanos: i64,
+    ) -> Result<()> {
+        Client::truncate(self, policy, namespace, set_name, before_nanos).await
+    }
+}
@@ -1,13 +1,19 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{borrow::Cow, collections::HashMap, mem, sync::Arc};
 
-use tokio::sync::Mutex;
+use tokio::{sync::Mutex, time::Instant};
+use tracing::warn;
 
 use crate::{
     cluster::{Cluster, Node},
-    commands::BatchReadCommand,
-    errors::{Error, Result},
+    commands::{
+        self,
+        buffer::{self, BufferError},
+        BatchReadCommand, CommandError,
+    },
+    errors::Result,
+    metrics::CommandKind,
     policies::{BatchPolicy, Concurrency},
-    Bins, Key, Record,
+    Bins, Key, Record, ResultCode,
 };
 
 pub struct BatchExecutor {
@@ -24,25 +30,94 @@ impl BatchExecutor {
         policy: &BatchPolicy,
         batch_reads: Vec<BatchRead>,
     ) -> Result<Vec<BatchRead>> {
-        let jobs = self
-            .get_batch_nodes(&batch_reads)
-            .await
-            .into_values()
-            .map(|(node, reads)| BatchReadCommand::new(policy, node, reads))
-            .collect();
-        let reads = self.execute_batch_jobs(jobs, &policy.concurrency).await?;
+        let start = Instant::now();
+        let result = self.execute_batch_read_inner(policy, batch_reads).await;
+        self.cluster
+            .metrics()
+            .record(CommandKind::Batch, start.elapsed());
+
+        result
+    }
+
+    async fn execute_batch_read_inner(
+        &self,
+        policy: &BatchPolicy,
+        batch_reads: Vec<BatchRead>,
+    ) -> Result<Vec<BatchRead>> {
+        let base_policy = policy.as_ref();
+        let deadline = base_policy.deadline();
+
+        let mut jobs = batch_jobs(policy, deadline, self.get_batch_nodes(&batch_reads).await);
         let mut res: Vec<BatchRead> = vec![];
-        for mut read in reads {
-            res.append(&mut read.batch_reads);
+        let mut iterations = 0;
+
+        loop {
+            iterations += 1;
+
+            let (reads, mut failed) = self.execute_batch_jobs(jobs, &policy.concurrency).await?;
+            for mut read in reads {
+                res.append(&mut read.batch_reads);
+            }
+
+            if failed.is_empty() {
+                break;
+            }
+
+            // Only the keys of the failed nodes are retried, as long as the policy allows it.
+            let exhausted = base_policy
+                .max_retries
+                .map_or(false, |max_retries| iterations > max_retries)
+                || deadline.map_or(false, |deadline| Instant::now() > deadline);
+            if exhausted {
+                if let Some((_, err)) = failed.pop() {
+                    return Err(err.into());
+                }
+            }
+
+            if !base_policy.sleep_between_retries.is_zero() {
+                let sleep = tokio::time::sleep(base_policy.sleep_between_retries);
+                match deadline {
+                    Some(deadline) => {
+                        tokio::time::timeout_at(deadline, sleep).await.ok();
+                    }
+                    None => sleep.await,
+                }
+            }
+
+            let mut nodes = HashMap::new();
+            for (cmd, err) in failed {
+                warn!(
+                    node = cmd.node.name(),
+                    %err,
+                    keys = cmd.batch_reads.len(),
+                    "batch request failed, retrying its keys on other replicas"
+                );
+                let routed = self
+                    .get_retry_nodes(policy, iterations, cmd.batch_reads, &mut nodes)
+                    .await;
+                // Keys without any node to retry on would be reported as not found otherwise.
+                if !routed {
+                    return Err(err.into());
+                }
+            }
+            jobs = batch_jobs(policy, deadline, nodes);
         }
+
         Ok(res)
     }
 
+    /// Execute all batch jobs. Jobs that failed due to network errors are returned separately,
+    /// together with their error, so they can be retried on other nodes. Any other error fails the
+    /// whole batch.
     async fn execute_batch_jobs(
         &self,
         jobs: Vec<BatchReadCommand>,
         concurrency: &Concurrency,
-    ) -> Result<Vec<BatchReadCommand>> {
+    ) -> Result<(Vec<BatchReadCommand>, Vec<(BatchReadCommand, CommandError)>)> {
+        if jobs.is_empty() {
+            return Ok((Vec::new(), Vec::new()));
+        }
+
         let threads = match *concurrency {
             Concurrency::Sequential => 1,
             Concurrency::Parallel(max) => {
@@ -55,7 +130,6 @@ impl BatchExecutor {
         };
         let size = jobs.len() / threads;
         let mut overhead = jobs.len() % threads;
-        let last_err = Arc::<Mutex<Option<Error>>>::default();
         let mut slice_index = 0;
         let mut handles = vec![];
         let res = Arc::new(Mutex::new(vec![]));
@@ -66,16 +140,12 @@ impl BatchExecutor {
                 overhead -= 1;
             }
             let slice = Vec::from(&jobs[slice_index..slice_index + thread_size]);
-            slice_index = thread_size + 1;
-            let last_err = Arc::clone(&last_err);
+            slice_index += thread_size;
             let res = Arc::clone(&res);
             let handle = tokio::spawn(async move {
-                //let next_job = async { jobs.lock().await.next().await};
                 for mut cmd in slice {
-                    if let Err(err) = cmd.execute().await {
-                        *last_err.lock().await = Some(err.into());
-                    };
-                    res.lock().await.push(cmd);
+                    let result = cmd.execute().await;
+                    res.lock().await.push((cmd, result));
                 }
             });
             handles.push(handle);
@@ -85,10 +155,17 @@ impl BatchExecutor {
             handle.await.ok();
         }
 
-        match Arc::try_unwrap(last_err).unwrap().into_inner() {
-            None => Ok(res.lock().await.to_vec()),
-            Some(err) => Err(err),
+        let mut done = Vec::new();
+        let mut failed = Vec::new();
+        for (cmd, result) in mem::take(&mut *res.lock().await) {
+            match result {
+                Ok(()) => done.push(cmd),
+                Err(err) if commands::is_network_error(&err) => failed.push((cmd, err)),
+                Err(err) => return Err(err.into()),
+            }
         }
+
+        Ok((done, failed))
     }
 
     async fn get_batch_nodes(
@@ -107,11 +184,113 @@ impl BatchExecutor {
         map
     }
 
+    /// Assign the keys of a failed batch request to the replicas of their partitions, which move
+    /// on to the next replica with each attempt. Returns `false` if any of the keys couldn't be
+    /// assigned to a node.
+    async fn get_retry_nodes(
+        &self,
+        policy: &BatchPolicy,
+        attempt: usize,
+        batch_reads: Vec<BatchRead>,
+        map: &mut HashMap<String, (Arc<Node>, Vec<BatchRead>)>,
+    ) -> bool {
+        for batch_read in batch_reads {
+            let node = self
+                .cluster
+                .get_retry_node(
+                    &(&batch_read.key).into(),
+                    attempt,
+                    policy.as_ref().read_mode_sc,
+                )
+                .await;
+            match node {
+                Some(node) => map
+                    .entry(node.name().to_owned())
+                    .or_insert_with(|| (Arc::clone(&node), Vec::new()))
+                    .1
+                    .push(batch_read),
+                None => return false,
+            }
+        }
+
+        true
+    }
+
     async fn node_for_key(&self, key: &Key) -> Option<Arc<Node>> {
         self.cluster.get_node(&key.into()).await
     }
 }
 
+/// Create the batch commands for the reads of each node.
+fn batch_jobs(
+    policy: &BatchPolicy,
+    deadline: Option<Instant>,
+    nodes: HashMap<String, (Arc<Node>, Vec<BatchRead>)>,
+) -> Vec<BatchReadCommand> {
+    nodes
+        .into_values()
+        .flat_map(|(node, reads)| {
+            split_batch_reads(policy, reads)
+                .into_iter()
+                .map(move |reads| BatchReadCommand::new(policy, deadline, Arc::clone(&node), reads))
+        })
+        .collect()
+}
+
+/// Bring the results of a batch read into the order of the given keys. Keys that are missing in the
+/// results, for example because no node was found for them, are reported as not found.
+pub(crate) fn align_records(keys: &[Key], reads: Vec<BatchRead>) -> Vec<Option<Record>> {
+    let mut records = HashMap::<_, Vec<_>>::with_capacity(reads.len());
+    for read in reads {
+        records
+            .entry((read.key.namespace, read.key.digest))
+            .or_default()
+            .push(read.record);
+    }
+
+    keys.iter()
+        .map(|key| {
+            records
+                .get_mut(&(key.namespace.clone(), key.digest))
+                .and_then(Vec::pop)
+                .flatten()
+        })
+        .collect()
+}
+
+/// Split the reads for a single node into several requests, if they don't fit into the maximum
+/// request size together, or exceed the maximum amount of keys per request.
+fn split_batch_reads(policy: &BatchPolicy, batch_reads: Vec<BatchRead>) -> Vec<Vec<BatchRead>> {
+    let max_keys = match policy.max_keys_per_request {
+        0 => usize::MAX,
+        max => max,
+    };
+    let mut batches = Vec::new();
+    let mut current = Vec::<BatchRead>::new();
+    let mut size = buffer::BATCH_READ_HEADER_SIZE;
+
+    for batch_read in batch_reads {
+        let mut entry_size = buffer::batch_read_entry_size(policy, &batch_read, current.last());
+
+        if !current.is_empty()
+            && (size + entry_size > buffer::MAX_BUFFER_SIZE || current.len() >= max_keys)
+        {
+            batches.push(mem::take(&mut current));
+            size = buffer::BATCH_READ_HEADER_SIZE;
+            entry_size = buffer::batch_read_entry_size(policy, &batch_read, None);
+        }
+
+        size += entry_size;
+        current.push(batch_read);
+    }
+
+    if !current.is_empty() {
+        batches.push(current);
+    }
+
+    batches
+}
+
 /// Key and bin names used in batch read commands where variable bins are needed for each key.
 #[derive(Clone, Debug)]
 pub struct BatchRead {
@@ -123,6 +302,13 @@ pub struct BatchRead {
 
     /// Will contain the record after the batch read operation.
     pub record: Option<Record>,
+
+    /// Outcome of the read for this key, which tells apart why the [`Self::record`] is missing.
+    /// Besides [`ResultCode::KeyNotFoundError`], this can be an error that only affected this
+    /// record, like [`ResultCode::FilteredOut`] or [`ResultCode::KeyBusy`], while the other keys
+    /// of the batch were still read. Keys that weren't read yet report
+    /// [`ResultCode::KeyNotFoundError`].
+    pub result: ResultCode,
 }
 
 impl BatchRead {
@@ -133,6 +319,7 @@ impl BatchRead {
             key,
             bins,
             record: None,
+            result: ResultCode::KeyNotFoundError,
         }
     }
 
@@ -141,7 +328,244 @@ impl BatchRead {
         let key = &self.key;
         let other_key = &other.key;
         (key.namespace == other_key.namespace)
-            && (match_set && (key.set_name == other_key.set_name))
+            && (!match_set || (key.set_name == other_key.set_name))
             && (self.bins == other.bins)
     }
 }
+
+/// Builder for batch reads that select different bins for each key, as passed to
+/// [`Client::batch_get`](crate::Client::batch_get).
+///
+/// The limits of the wire format are validated when building the list, instead of failing the
+/// whole batch request later on. Bin names are only checked against the longest length that the
+/// protocol allows, as the actual limit depends on the server version.
+///
+/// # Examples
+///
+/// ```rust
+/// use windpike::{BatchBuilder, Bins, Key};
+///
+/// let reads = BatchBuilder::new()
+///     .add(Key::new("test", "test", 1), Bins::All)
+///     .add_header(Key::new("test", "test", 2))
+///     .add_with(Key::new("test", "test", 3), ["a", "b"])
+///     .build()
+///     .unwrap();
+///
+/// assert_eq!(reads.len(), 3);
+/// assert_eq!(reads[1].bins, Bins::None);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct BatchBuilder {
+    reads: Vec<BatchRead>,
+}
+
+impl BatchBuilder {
+    /// Create a new, empty builder.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Read the given bins of the record.
+    #[must_use]
+    pub fn add(mut self, key: Key, bins: Bins) -> Self {
+        self.reads.push(BatchRead::new(key, bins));
+        self
+    }
+
+    /// Read only the header (generation and expiration) of the record.
+    #[must_use]
+    pub fn add_header(self, key: Key) -> Self {
+        self.add(key, Bins::None)
+    }
+
+    /// Read only the bins with the given names.
+    #[must_use]
+    pub fn add_with<I, T>(self, key: Key, bins: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<Cow<'static, str>>,
+    {
+        self.add(key, Bins::from(bins))
+    }
+
+    /// Validate all reads and return them.
+    ///
+    /// # Errors
+    ///
+    /// Fails if a bin name is too long, too many bins are selected for a single key, or a single
+    /// key with its bins doesn't fit into a batch request.
+    pub fn build(self) -> Result<Vec<BatchRead>, BufferError> {
+        // Assume the worst case, where the set name is sent and no header is shared with the
+        // previous key.
+        let policy = BatchPolicy {
+            send_set_name: true,
+            ..BatchPolicy::default()
+        };
+        let max_size = buffer::MAX_BUFFER_SIZE - buffer::BATCH_READ_HEADER_SIZE;
+
+        for read in &self.reads {
+            if let Bins::Some(names) = &read.bins {
+                if names.len() > buffer::MAX_OPERATIONS {
+                    return Err(BufferError::TooManyOperations {
+                        count: names.len(),
+                        max: buffer::MAX_OPERATIONS,
+                    });
+                }
+
+                if let Some(name) = names
+                    .iter()
+                    .find(|name| name.len() > buffer::MAX_LONG_BIN_NAME_LEN)
+                {
+                    return Err(BufferError::BinNameTooLong {
+                        name: name.to_string(),
+                        max: buffer::MAX_LONG_BIN_NAME_LEN,
+                    });
+                }
+            }
+
+            let size = buffer::batch_read_entry_size(&policy, read, None);
+            if size > max_size {
+                return Err(BufferError::SizeExceeded {
+                    size,
+                    max: max_size,
+                });
+            }
+        }
+
+        Ok(self.reads)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::{align_records, split_batch_reads, BatchBuilder, BatchRead};
+    use crate::{
+        commands::buffer::{Buffer, BufferError, MAX_BIN_NAME_LEN},
+        policies::BatchPolicy,
+        Bins, Key, Record,
+    };
+
+    fn reads(count: i64, bins: &Bins) -> Vec<BatchRead> {
+        (0..count)
+            .map(|i| BatchRead::new(Key::new("test", "test", i), bins.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn split_small_batch() {
+        let batches = split_batch_reads(&BatchPolicy::default(), reads(1000, &Bins::All));
+        assert_eq!(batches.len(), 1);
+        assert_eq!(batches[0].len(), 1000);
+    }
+
+    #[test]
+    fn split_large_batch() {
+        let bins = Bins::from((0..100).map(|i| format!("bin_{i:010}")));
+        let mut reads = reads(2000, &bins);
+        // alternate the bins, so each key has to be encoded in full
+        for read in reads.iter_mut().step_by(2) {
+            read.bins = Bins::All;
+        }
+
+        let batches = split_batch_reads(&BatchPolicy::default(), reads);
+        assert!(batches.len() > 1);
+        assert_eq!(batches.iter().map(Vec::len).sum::<usize>(), 2000);
+    }
+
+    #[test]
+    fn split_many_keys() {
+        let batches = split_batch_reads(&BatchPolicy::default(), reads(50_000, &Bins::All));
+        assert_eq!(batches.len(), 10);
+        assert!(batches
+            .iter()
+            .all(|batch| batch.len() == BatchPolicy::DEFAULT_MAX_KEYS_PER_REQUEST));
+
+        // Without a key limit, the batch is only split by the maximum request size, and every
+        // request must still be encodable.
+        let policy = BatchPolicy {
+            max_keys_per_request: 0,
+            ..BatchPolicy::default()
+        };
+        let bins = Bins::from((0..10).map(|i| format!("bin_{i:010}")));
+        let mut reads = reads(50_000, &bins);
+        for read in reads.iter_mut().step_by(2) {
+            read.bins = Bins::All;
+        }
+
+        let batches = split_batch_reads(&policy, reads);
+        assert!(batches.len() > 1);
+        assert_eq!(batches.iter().map(Vec::len).sum::<usize>(), 50_000);
+        for batch in batches {
+            Buffer::new(0)
+                .set_batch_read(&policy, &batch, MAX_BIN_NAME_LEN)
+                .unwrap();
+        }
+    }
+
+    #[test]
+    fn align_records_to_keys() {
+        let keys = [
+            Key::new("test", "test", 1),
+            Key::new("test", "test", 2),
+            Key::new("test", "test", 1),
+            Key::new("test", "test", 3),
+        ];
+        let found = |key: &Key, generation| BatchRead {
+            record: Some(Record::new(None, HashMap::new(), generation, 0)),
+            ..BatchRead::new(key.clone(), Bins::All)
+        };
+        // Results arrive in any order, and keys without a node are missing entirely.
+        let reads = vec![
+            BatchRead::new(keys[1].clone(), Bins::All),
+            found(&keys[0], 1),
+            found(&keys[2], 1),
+        ];
+
+        let records = align_records(&keys, reads);
+
+        assert_eq!(records.len(), 4);
+        assert_eq!(records[0].as_ref().map(|r| r.generation), Some(1));
+        assert!(records[1].is_none());
+        assert_eq!(records[2].as_ref().map(|r| r.generation), Some(1));
+        assert!(records[3].is_none());
+    }
+
+    #[test]
+    fn builder_limits() {
+        let key = Key::new("test", "test", 1);
+
+        let reads = BatchBuilder::new()
+            .add(key.clone(), Bins::All)
+            .add_header(key.clone())
+            .add_with(key.clone(), ["a", "b"])
+            .build()
+            .unwrap();
+        assert_eq!(
+            reads.into_iter().map(|read| read.bins).collect::<Vec<_>>(),
+            [Bins::All, Bins::None, Bins::from(["a", "b"])]
+        );
+
+        assert!(matches!(
+            BatchBuilder::new()
+                .add_with(key.clone(), ["a".repeat(256)])
+                .build(),
+            Err(BufferError::BinNameTooLong { max: 255, .. })
+        ));
+        assert!(matches!(
+            BatchBuilder::new()
+                .add_with(key.clone(), (0..70_000).map(|i| i.to_string()))
+                .build(),
+            Err(BufferError::TooManyOperations { count: 70_000, .. })
+        ));
+        assert!(matches!(
+            BatchBuilder::new()
+                .add_with(key, (0..50_000).map(|i| format!("bin_{i:010}")))
+                .build(),
+            Err(BufferError::SizeExceeded { .. })
+        ));
+    }
+}
@@ -1,6 +1,6 @@
 use std::{borrow::Cow, convert::From};
 
-use crate::value::Value;
+use crate::value::{MapKey, Value};
 
 /// Container object for a record bin, comprising a name and a value.
 #[derive(Clone, Debug, Eq, PartialEq)]
@@ -21,6 +21,81 @@ impl<'a> Bin<'a> {
             value: value.into(),
         }
     }
+
+    /// Construct a new bin that holds a [`Value::List`] with all values of the iterator.
+    ///
+    /// ```
+    /// # use windpike::{Bin, Value};
+    /// let bin = Bin::list("l", 1_i64..=3);
+    /// assert_eq!(Value::List(vec![1.into(), 2.into(), 3.into()]), bin.value);
+    /// ```
+    #[must_use]
+    pub fn list<I>(name: &'a str, values: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<Value>,
+    {
+        Self::new(
+            name,
+            Value::List(values.into_iter().map(Into::into).collect()),
+        )
+    }
+
+    /// Construct a new bin that holds a [`Value::HashMap`] with all key-value pairs of the
+    /// iterator. If a key appears more than once, the last value is kept.
+    ///
+    /// ```
+    /// # use windpike::{Bin, MapKey, Value};
+    /// let bin = Bin::map("m", [("a", 1), ("b", 2)]);
+    /// assert_eq!(
+    ///     Value::from([(MapKey::from("a"), 1.into()), (MapKey::from("b"), 2.into())]),
+    ///     bin.value,
+    /// );
+    /// ```
+    #[must_use]
+    pub fn map<I, K, V>(name: &'a str, entries: I) -> Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: Into<MapKey>,
+        V: Into<Value>,
+    {
+        Self::new(
+            name,
+            Value::HashMap(
+                entries
+                    .into_iter()
+                    .map(|(key, value)| (key.into(), value.into()))
+                    .collect(),
+            ),
+        )
+    }
+
+    /// Construct a new bin that holds any [`Serialize`](serde::Serialize) type, encoded as
+    /// `MessagePack` into a [`Value::Blob`]. Structs are encoded as maps with their field names, so
+    /// fields can be added or reordered later on. Read the value back with
+    /// [`Record::deserialize_bin`](crate::Record::deserialize_bin).
+    ///
+    /// This is a quick way of storing complex types, without modeling them as lists and maps. The
+    /// server can't look into the blob though, so it can't be used in CDT operations or filters.
+    ///
+    /// ```
+    /// # #[cfg(feature = "serde")]
+    /// # {
+    /// # use windpike::Bin;
+    /// let bin = Bin::serialized("tags", &["a", "b"]).unwrap();
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails if the value can't be serialized.
+    #[cfg(feature = "serde")]
+    pub fn serialized<T>(name: &'a str, value: &T) -> Result<Self, rmp_serde::encode::Error>
+    where
+        T: serde::Serialize + ?Sized,
+    {
+        rmp_serde::to_vec_named(value).map(|bytes| Self::new(name, Value::Blob(bytes)))
+    }
 }
 
 impl<'a, T> From<(&'a str, T)> for Bin<'a>
@@ -32,6 +107,50 @@ where
     }
 }
 
+/// Conversion of a Rust type into a list of bins, as used by
+/// [`Client::put_struct`](crate::Client::put_struct).
+///
+/// With the `derive` feature, it can be derived for structs with named fields, where each field
+/// is written to the bin of the same name. Field values are cloned and converted through their
+/// [`Into<Value>`] implementation, and fields of type [`Option`] are left out if they're
+/// [`None`]. The same `#[windpike(rename = "...")]` and `#[windpike(skip)]` attributes as for
+/// [`FromRecord`](crate::FromRecord) are supported.
+///
+/// ```
+/// # #[cfg(feature = "derive")]
+/// # {
+/// use windpike::{Bin, ToBins};
+///
+/// #[derive(ToBins)]
+/// struct User {
+///     name: String,
+///     #[windpike(rename = "years")]
+///     age: u32,
+///     email: Option<String>,
+/// }
+///
+/// let user = User {
+///     name: "alice".to_owned(),
+///     age: 32,
+///     email: None,
+/// };
+/// assert_eq!(
+///     vec![Bin::new("name", "alice"), Bin::new("years", 32)],
+///     user.to_bins()
+/// );
+/// # }
+/// ```
+pub trait ToBins {
+    /// Create the bins that represent this value.
+    fn to_bins(&self) -> Vec<Bin<'_>>;
+}
+
+impl ToBins for [Bin<'_>] {
+    fn to_bins(&self) -> Vec<Bin<'_>> {
+        self.to_vec()
+    }
+}
+
 /// Specify which, if any, bins to return in read operations.
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub enum Bins {
@@ -55,7 +174,10 @@ where
 
 #[cfg(test)]
 mod tests {
-    use super::{Bins, Cow, From};
+    use std::collections::HashMap;
+
+    use super::{Bin, Bins, Cow, From};
+    use crate::{MapKey, Value};
 
     #[test]
     fn into_bins() {
@@ -68,4 +190,32 @@ mod tests {
 
         assert_eq!(expected, Bins::from(["a", "b", "c"]));
     }
+
+    #[test]
+    fn list_bin() {
+        let bin = Bin::list("l", vec!["a", "b"]);
+        assert_eq!("l", bin.name);
+        assert_eq!(Value::List(vec!["a".into(), "b".into()]), bin.value);
+
+        assert_eq!(Value::List(vec![]), Bin::list("l", Vec::<i64>::new()).value);
+    }
+
+    #[test]
+    fn map_bin() {
+        let source = HashMap::from([("a".to_owned(), 1_i64), ("b".to_owned(), 2)]);
+        let bin = Bin::map("m", source.iter().map(|(k, v)| (k.as_str(), *v)));
+
+        let expected = HashMap::from([
+            (MapKey::from("a"), Value::Int(1)),
+            (MapKey::from("b"), Value::Int(2)),
+        ]);
+        assert_eq!("m", bin.name);
+        assert_eq!(Value::HashMap(expected), bin.value);
+
+        let bin = Bin::map("m", [(1, "x"), (1, "y")]);
+        assert_eq!(
+            Value::HashMap(HashMap::from([(MapKey::from(1), Value::from("y"))])),
+            bin.value
+        );
+    }
 }
@@ -0,0 +1,117 @@
+//! Helpers for caches that keep copies of records, like read-heavy caching proxies.
+//!
+//! Every write to a record increases its generation, so a cached copy is still up to date as long
+//! as its generation matches the one on the server. [`Client::get_if_changed`] makes use of this
+//! to only transfer the bins of records that changed since they were cached.
+//!
+//! The generation counter is limited to 16 bits on the server and wraps around, so a record that
+//! received exactly a multiple of 65536 writes since it was cached can't be told apart from an
+//! unchanged one.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use std::collections::HashMap;
+//!
+//! use windpike::{
+//!     cache::Freshness,
+//!     policies::{BasePolicy, ClientPolicy},
+//!     Bins, Client, Key, Record,
+//! };
+//!
+//! # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+//! let client = Client::new(&ClientPolicy::default(), "localhost:3000").await?;
+//! let mut cache = HashMap::<[u8; 20], Record>::new();
+//!
+//! let key = Key::new("test", "test", 1);
+//! let known = cache
+//!     .get(&key.digest())
+//!     .map_or(0, |record| record.generation);
+//! match client
+//!     .get_if_changed(&BasePolicy::default(), &key, Bins::All, known)
+//!     .await?
+//! {
+//!     Freshness::Modified(record) => {
+//!         cache.insert(key.digest(), record);
+//!     }
+//!     Freshness::NotModified => {}
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`Client::get_if_changed`]: crate::Client::get_if_changed
+
+use crate::Record;
+
+/// Outcome of a conditional read with [`Client::get_if_changed`](crate::Client::get_if_changed).
+#[derive(Clone, Debug)]
+pub enum Freshness {
+    /// The record changed, and was read again.
+    Modified(Record),
+    /// The record still has the known generation, so the cached copy is up to date.
+    NotModified,
+}
+
+impl Freshness {
+    /// Whether the record changed since it was cached.
+    #[must_use]
+    pub const fn is_modified(&self) -> bool {
+        matches!(self, Self::Modified(_))
+    }
+
+    /// Get the newly read record, if it changed.
+    #[must_use]
+    pub fn into_record(self) -> Option<Record> {
+        match self {
+            Self::Modified(record) => Some(record),
+            Self::NotModified => None,
+        }
+    }
+}
+
+#[cfg(all(test, feature = "testing"))]
+mod tests {
+    use super::Freshness;
+    use crate::{
+        policies::{BasePolicy, WritePolicy},
+        testing::MockClient,
+        Bin, Bins, ClientApi, Key, Value,
+    };
+
+    #[tokio::test]
+    async fn read_only_changed_records() {
+        let client = MockClient::new();
+        let policy = BasePolicy::default();
+        let key = Key::new("test", "test", 1);
+
+        client
+            .put(&WritePolicy::default(), &key, &[Bin::new("a", 1)])
+            .await
+            .unwrap();
+
+        let record = ClientApi::get_if_changed(&client, &policy, &key, Bins::All, 0)
+            .await
+            .unwrap()
+            .into_record()
+            .unwrap();
+        assert_eq!(Some(&Value::from(1)), record.bins.get("a"));
+
+        let freshness =
+            ClientApi::get_if_changed(&client, &policy, &key, Bins::All, record.generation)
+                .await
+                .unwrap();
+        assert!(matches!(freshness, Freshness::NotModified));
+
+        client
+            .put(&WritePolicy::default(), &key, &[Bin::new("a", 2)])
+            .await
+            .unwrap();
+
+        let freshness =
+            ClientApi::get_if_changed(&client, &policy, &key, Bins::All, record.generation)
+                .await
+                .unwrap();
+        assert!(freshness.is_modified());
+    }
+}
@@ -1,21 +1,39 @@
-use std::{fmt::Write, str, sync::Arc, vec::Vec};
+use std::{collections::HashMap, fmt::Write, ops::Range, str, sync::Arc, time::Duration, vec::Vec};
 
-use tokio::sync::mpsc;
+use tokio::{sync::mpsc, time::Instant};
+use tokio_util::sync::CancellationToken;
+use tracing::warn;
 
 use crate::{
-    batch::BatchExecutor,
-    cluster::Cluster,
+    api::ClientApi,
+    batch::{self, BatchExecutor},
+    cache::Freshness,
+    cluster::{
+        node::{FeatureSupport, PARTITIONS},
+        partition::Partition,
+        Cluster, ClusterError, ClusterInfo, Node, PartitionHealth, PartitionOwners,
+    },
     commands::{
-        CommandError, DeleteCommand, ExistsCommand, OperateCommand, ReadCommand, ScanCommand,
-        TouchCommand, WriteCommand,
+        partition_chunks, split_max_records, split_partitions, until_cancelled, AdminCommand,
+        CommandError, DeleteCommand, ExistsCommand, OperateCommand, ReadCommand, RecordMapper,
+        ScanCommand, TouchCommand, WriteCommand,
     },
     errors::{Error, Result},
-    index::{CollectionIndexType, CreateIndex, IndexType},
-    net::ToHosts,
-    operations::{Operation, OperationType},
-    policies::{BasePolicy, BatchPolicy, ClientPolicy, ScanPolicy, WritePolicy},
-    BatchRead, Bin, Bins, Key, Record, RecordSet, ResultCode,
+    index::{CreateIndex, DropIndex, IndexBuilder, IndexType},
+    info,
+    job::{self, Job},
+    metrics::{CommandKind, LatencySnapshot},
+    net::{Host, ToHosts},
+    operations::{self, OperateResult, Operation, OperationType},
+    policies::{
+        BasePolicy, BatchPolicy, ClientPolicy, InfoPolicy, NamespaceDefaults, ReadModeSc,
+        ScanPolicy, WritePolicy,
+    },
+    record::ProgressTracker,
+    BatchRead, Bin, Bins, FromRecord, Key, Record, RecordSet, ResultCode, ToBins, User,
 };
+#[cfg(feature = "raw")]
+use crate::{commands::RawCommand, raw};
 
 /// Instantiate a Client instance to access an Aerospike database cluster and perform database
 /// operations.
@@ -85,11 +103,189 @@ impl Client {
         self.cluster.close();
     }
 
+    /// Returns the user that the client is authenticated as, together with the roles granted to
+    /// it. This is useful to verify that rotated credentials are picked up as expected.
+    ///
+    /// Returns [`None`] if the client doesn't use any credentials, or the server doesn't have
+    /// security enabled.
+    pub async fn whoami(&self) -> Result<Option<User>, CommandError> {
+        let user = match &self.cluster.client_policy().user_password {
+            Some((user, _)) => user,
+            None => return Ok(None),
+        };
+
+        match AdminCommand::query_user(&self.cluster, user).await {
+            Err(CommandError::ServerError(ResultCode::SecurityNotEnabled)) => Ok(None),
+            result => result,
+        }
+    }
+
     /// Returns `true` if the client is connected to any cluster nodes.
     pub async fn is_connected(&self) -> bool {
         self.cluster.is_connected().await
     }
 
+    /// Waits until the list of cluster nodes stops changing, or the timeout passes. This is useful
+    /// for health checks, to verify the client is in a usable state.
+    ///
+    /// Returns an error if the nodes didn't settle in time, or no node is connected at all.
+    pub async fn wait_until_stable(&self, timeout: Duration) -> Result<(), ClusterError> {
+        if !self.cluster.wait_till_stabilized(timeout).await {
+            return Err(ClusterError::NotStabilized);
+        }
+
+        if self.cluster.is_connected().await {
+            Ok(())
+        } else {
+            Err(ClusterError::Connection)
+        }
+    }
+
+    /// Asks all cluster nodes whether the cluster is stable, which is the case when all of them
+    /// agree on the same cluster key and no partition migrations are running. This is useful to
+    /// hold back traffic during migrations, as commands may be proxied or retried in the meantime.
+    ///
+    /// In contrast to [`Self::wait_until_stable`], this asks the server instead of only checking
+    /// the node list that the client knows about.
+    pub async fn is_cluster_stable(&self, policy: &InfoPolicy) -> Result<bool, ClusterError> {
+        self.cluster.is_stable(policy).await
+    }
+
+    /// Drops all currently known cluster nodes and connects again, starting from the seed hosts
+    /// that the client was initially created with.
+    ///
+    /// This allows to recover from a complete loss of connectivity to the cluster, without having
+    /// to create a new client instance. A client that was [closed](Self::close) can't be
+    /// reconnected.
+    pub async fn reconnect(&self) -> Result<(), ClusterError> {
+        self.cluster.reconnect().await
+    }
+
+    /// Returns the current topology of the cluster, like the amount of partitions and the cluster
+    /// key. The cluster key is requested from a random node, while everything else is the state
+    /// that the client learned during the last cluster tending.
+    ///
+    /// Tools can compare the results of two calls, to detect changes in the topology.
+    pub async fn cluster_info(&self, policy: &InfoPolicy) -> Result<ClusterInfo, ClusterError> {
+        self.cluster.info(policy).await
+    }
+
+    /// Returns the ownership state of the partitions for each namespace, sorted by namespace.
+    ///
+    /// Partitions that are still assigned to nodes that left the cluster are repaired during the
+    /// periodic cluster tending, so a lasting unhealthy state usually indicates a problem with the
+    /// cluster itself. This is mostly meant to be exposed as metric.
+    pub async fn partition_health(&self) -> Vec<PartitionHealth> {
+        self.cluster.partition_health().await
+    }
+
+    /// Enable or disable tracking of command latencies at runtime. Already recorded latencies are
+    /// kept when disabling it. See the [`metrics`](crate::metrics) module for details.
+    pub fn set_latency_metrics(&self, enabled: bool) {
+        self.cluster.metrics().set_enabled(enabled);
+    }
+
+    /// Returns true if command latencies are currently tracked.
+    #[must_use]
+    pub fn latency_metrics_enabled(&self) -> bool {
+        self.cluster.metrics().is_enabled()
+    }
+
+    /// Returns the latencies that were recorded for the given kind of command so far.
+    #[must_use]
+    pub fn latency_snapshot(&self, kind: CommandKind) -> LatencySnapshot {
+        self.cluster.metrics().snapshot(kind)
+    }
+
+    /// Discard all recorded latencies, for example to start a new measuring interval.
+    pub fn reset_latency_metrics(&self) {
+        self.cluster.metrics().reset();
+    }
+
+    /// Register default policies for all single record commands on keys of the given namespace,
+    /// replacing any previously registered ones.
+    ///
+    /// Whenever a command receives a policy that equals the [`Default`] policy, the namespace's
+    /// policy is used instead. This allows configuring the namespaces of an application in one
+    /// place, like a cache namespace that is written with [`CommitLevel::Master`] and a short
+    /// TTL, while the commands keep passing the default policies. See [`NamespaceDefaults`] for
+    /// what can be overridden.
+    ///
+    /// ```rust,no_run
+    /// # use windpike::{policies::{Expiration, NamespaceDefaults, WritePolicy}, Bin, Client, Key};
+    /// # async fn run(client: Client) {
+    /// client.set_namespace_defaults(
+    ///     "cache",
+    ///     NamespaceDefaults {
+    ///         write: Some(
+    ///             WritePolicy::builder()
+    ///                 .commit_master()
+    ///                 .expiration(Expiration::Seconds(3600))
+    ///                 .build(),
+    ///         ),
+    ///         ..NamespaceDefaults::default()
+    ///     },
+    /// );
+    ///
+    /// // Written with the cache namespace's policy.
+    /// let key = Key::new("cache", "sessions", "abc");
+    /// client
+    ///     .put(&WritePolicy::default(), &key, &[Bin::new("user", 42)])
+    ///     .await
+    ///     .unwrap();
+    /// # }
+    /// ```
+    ///
+    /// [`CommitLevel::Master`]: crate::policies::CommitLevel::Master
+    pub fn set_namespace_defaults(
+        &self,
+        namespace: impl Into<String>,
+        defaults: NamespaceDefaults,
+    ) {
+        self.cluster
+            .namespace_defaults()
+            .set(namespace.into(), Some(defaults));
+    }
+
+    /// Remove the default policies of the given namespace, that were registered with
+    /// [`Self::set_namespace_defaults`].
+    pub fn clear_namespace_defaults(&self, namespace: &str) {
+        self.cluster
+            .namespace_defaults()
+            .set(namespace.to_owned(), None);
+    }
+
+    /// Returns the server nodes that are currently part of the cluster.
+    pub async fn nodes(&self) -> Vec<Arc<Node>> {
+        self.cluster.nodes().await
+    }
+
+    /// Returns the node that commands for the given key are sent to, which is the master of the
+    /// key's partition. If the partition map doesn't know the partition yet, a random node is
+    /// returned instead, like for regular commands. Only [`None`] is returned if the client isn't
+    /// connected to any node.
+    pub async fn node_for_key(&self, key: &Key) -> Option<Arc<Node>> {
+        self.cluster.get_node(&Partition::from(key)).await
+    }
+
+    /// Returns the names of the nodes that own the given partition of a namespace, which can be
+    /// computed for a key with [`Key::partition_id`].
+    ///
+    /// Owners that aren't known to the client are left out, so the master is [`None`] while the
+    /// partition has no known master, and the replicas don't move up in its place. Both are empty
+    /// if the namespace or partition are unknown to the client.
+    pub async fn node_names_for_partition(&self, namespace: &str, id: u32) -> PartitionOwners {
+        self.cluster.node_names_for_partition(namespace, id).await
+    }
+
+    /// Returns the hosts that are currently ignored, because they reported a different cluster
+    /// name than [`ClientPolicy::cluster_name`]. Entries expire after
+    /// [`ClientPolicy::blacklist_duration`].
+    #[must_use]
+    pub fn blacklisted_hosts(&self) -> Vec<Host> {
+        self.cluster.blacklisted_hosts()
+    }
+
     /// Returns a list of the names of the active server nodes in the cluster.
     pub async fn node_names(&self) -> Vec<String> {
         self.cluster
@@ -173,17 +369,170 @@ impl Client {
         T: Into<Bins> + Send + Sync + 'static,
     {
         let bins = bins.into();
-        let mut command = ReadCommand::new(policy, Arc::clone(&self.cluster), key, bins);
+        let policy = self
+            .cluster
+            .namespace_defaults()
+            .read_policy(&key.namespace, policy);
+        let mut command = ReadCommand::new(&policy, Arc::clone(&self.cluster), key, bins);
         command.execute().await?;
         Ok(command.record.unwrap())
     }
 
+    /// Read a record like [`Self::get`], but always observe the latest committed write in strong
+    /// consistency namespaces, regardless of the [`BasePolicy::read_mode_sc`]. The read is only
+    /// served by the master of the key's partition.
+    ///
+    /// Namespaces without strong consistency ignore the read mode, so this behaves exactly like
+    /// [`Self::get`] for them.
+    ///
+    /// # Panics
+    /// Panics if the return is invalid
+    pub async fn get_linearizable<T>(
+        &self,
+        policy: &BasePolicy,
+        key: &Key,
+        bins: T,
+    ) -> Result<Record, CommandError>
+    where
+        T: Into<Bins> + Send + Sync + 'static,
+    {
+        let mut policy = self
+            .cluster
+            .namespace_defaults()
+            .read_policy(&key.namespace, policy)
+            .into_owned();
+        policy.read_mode_sc = ReadModeSc::Linearize;
+
+        let mut command = ReadCommand::new(&policy, Arc::clone(&self.cluster), key, bins.into());
+        command.execute().await?;
+        Ok(command.record.unwrap())
+    }
+
+    /// Read record for the specified key, but only if its generation differs from the known
+    /// generation of a cached copy. Otherwise, [`Freshness::NotModified`] is returned.
+    ///
+    /// The generation is checked with a read of the record header first, so unchanged records
+    /// don't transfer any bins. Changed records are read in a second request, and a missing
+    /// record is reported as [`ResultCode::KeyNotFoundError`] as with [`Self::get`]. The server
+    /// can't filter reads by generation, so the two requests can't be combined into one. See the
+    /// [`cache`](crate::cache) module for details.
+    pub async fn get_if_changed<T>(
+        &self,
+        policy: &BasePolicy,
+        key: &Key,
+        bins: T,
+        known_generation: u32,
+    ) -> Result<Freshness, CommandError>
+    where
+        T: Into<Bins> + Send + Sync + 'static,
+    {
+        ClientApi::get_if_changed(self, policy, key, bins.into(), known_generation).await
+    }
+
+    /// Read record for the specified key, and decode it into any type that implements
+    /// [`FromRecord`]. Decoding failures are reported as [`CommandError::Bin`].
+    ///
+    /// ```rust,no_run
+    /// # #[cfg(feature = "derive")]
+    /// # async fn run(client: windpike::Client) -> Result<(), windpike::errors::CommandError> {
+    /// use windpike::{policies::BasePolicy, Bins, FromRecord, Key};
+    ///
+    /// #[derive(FromRecord)]
+    /// struct User {
+    ///     name: String,
+    ///     age: u32,
+    /// }
+    ///
+    /// let key = Key::new("test", "users", 1);
+    /// let user = client
+    ///     .get_as::<User, _>(&BasePolicy::default(), &key, Bins::All)
+    ///     .await?;
+    /// println!("{} is {} years old", user.name, user.age);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn get_as<R, T>(
+        &self,
+        policy: &BasePolicy,
+        key: &Key,
+        bins: T,
+    ) -> Result<R, CommandError>
+    where
+        R: FromRecord,
+        T: Into<Bins> + Send + Sync + 'static,
+    {
+        let record = self.get(policy, key, bins).await?;
+        R::from_record(record).map_err(Into::into)
+    }
+
+    /// Same as [`Self::get`], but aborts the command as soon as the given token is cancelled,
+    /// returning [`CommandError::Cancelled`].
+    ///
+    /// A connection that was still waiting for the response at that point is closed instead of
+    /// being put back into the pool, as it might receive the remaining response data later on.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use std::time::Duration;
+    ///
+    /// use windpike::{
+    ///     errors::CommandError,
+    ///     policies::{BasePolicy, ClientPolicy},
+    ///     Bins, CancellationToken, Client, Key,
+    /// };
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let client = Client::new(&ClientPolicy::default(), "localhost:3000")
+    ///         .await
+    ///         .unwrap();
+    ///
+    ///     let token = CancellationToken::new();
+    ///     let canceller = token.clone();
+    ///     tokio::spawn(async move {
+    ///         tokio::time::sleep(Duration::from_millis(50)).await;
+    ///         canceller.cancel();
+    ///     });
+    ///
+    ///     let key = Key::new("test", "test", "mykey");
+    ///     match client
+    ///         .get_with_cancel(&BasePolicy::default(), &key, Bins::All, &token)
+    ///         .await
+    ///     {
+    ///         Ok(record) => println!("Record: {record:?}"),
+    ///         Err(CommandError::Cancelled) => println!("Lookup was cancelled"),
+    ///         Err(err) => println!("Error fetching record: {err}"),
+    ///     }
+    /// }
+    /// ```
+    pub async fn get_with_cancel<T>(
+        &self,
+        policy: &BasePolicy,
+        key: &Key,
+        bins: T,
+        token: &CancellationToken,
+    ) -> Result<Record, CommandError>
+    where
+        T: Into<Bins> + Send + Sync + 'static,
+    {
+        until_cancelled(token, self.get(policy, key, bins))
+            .await
+            .unwrap_or(Err(CommandError::Cancelled))
+    }
+
     /// Read multiple record for specified batch keys in one batch call. This method allows
     /// different namespaces/bins to be requested for each key in the batch. If the `BatchRead` key
     /// field is not found, the corresponding record field will be `None`. The policy can be used
     /// to specify timeouts and maximum concurrent threads. This method requires Aerospike Server
     /// version >= 3.6.0.
     ///
+    /// Keys don't need to carry the original user key. Keys created with [`Key::from_digest`] are
+    /// sufficient to locate the record, and can be freely mixed with keys from other namespaces.
+    ///
+    /// If the request to a single node fails with a network error, only the keys of that node are
+    /// retried on other nodes, within the retry limits and timeout of the policy.
+    ///
     /// # Examples
     ///
     /// Fetch multiple records in a single client request
@@ -228,6 +577,57 @@ impl Client {
         executor.execute_batch_read(policy, batch_reads).await
     }
 
+    /// Read multiple records with the same bin selection in one batch call. This is a shorthand
+    /// for [`Self::batch_get`] that doesn't require to create a [`BatchRead`] for each key.
+    ///
+    /// The returned records are in the same order as the given keys, with [`None`] for each
+    /// record that doesn't exist.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use windpike::{
+    ///     policies::{BatchPolicy, ClientPolicy},
+    ///     Bins, Client, Key,
+    /// };
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let client = Client::new(&ClientPolicy::default(), "localhost:3000")
+    ///         .await
+    ///         .unwrap();
+    ///
+    ///     let keys = (0..10)
+    ///         .map(|i| Key::new("test", "test", i))
+    ///         .collect::<Vec<_>>();
+    ///     let records = client
+    ///         .batch_get_bins(&BatchPolicy::default(), &keys, Bins::from(["name", "age"]))
+    ///         .await
+    ///         .unwrap();
+    ///
+    ///     for (key, record) in keys.iter().zip(records) {
+    ///         match record {
+    ///             Some(record) => println!("{key:?} => {:?}", record.bins),
+    ///             None => println!("No such record: {key:?}"),
+    ///         }
+    ///     }
+    /// }
+    /// ```
+    pub async fn batch_get_bins(
+        &self,
+        policy: &BatchPolicy,
+        keys: &[Key],
+        bins: Bins,
+    ) -> Result<Vec<Option<Record>>> {
+        let batch_reads = keys
+            .iter()
+            .map(|key| BatchRead::new(key.clone(), bins.clone()))
+            .collect();
+        let reads = self.batch_get(policy, batch_reads).await?;
+
+        Ok(batch::align_records(keys, reads))
+    }
+
     /// Write record bin(s). The policy specifies the transaction timeout, record expiration and
     /// how the transaction is handled when the record already exists.
     ///
@@ -286,8 +686,12 @@ impl Client {
         key: &'a Key,
         bins: &'a [Bin<'b>],
     ) -> Result<(), CommandError> {
+        let policy = self
+            .cluster
+            .namespace_defaults()
+            .write_policy(&key.namespace, policy);
         let mut command = WriteCommand::new(
-            policy,
+            &policy,
             Arc::clone(&self.cluster),
             key,
             bins,
@@ -296,6 +700,43 @@ impl Client {
         command.execute().await
     }
 
+    /// Write the bins of any type that implements [`ToBins`], like structs that derive it. See
+    /// [`Self::put`].
+    ///
+    /// ```rust,no_run
+    /// # #[cfg(feature = "derive")]
+    /// # async fn run(client: windpike::Client) -> Result<(), windpike::errors::CommandError> {
+    /// use windpike::{policies::WritePolicy, Key, ToBins};
+    ///
+    /// #[derive(ToBins)]
+    /// struct User {
+    ///     name: String,
+    ///     age: u32,
+    /// }
+    ///
+    /// let key = Key::new("test", "users", 1);
+    /// let user = User {
+    ///     name: "alice".to_owned(),
+    ///     age: 32,
+    /// };
+    /// client
+    ///     .put_struct(&WritePolicy::default(), &key, &user)
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn put_struct<T>(
+        &self,
+        policy: &WritePolicy,
+        key: &Key,
+        value: &T,
+    ) -> Result<(), CommandError>
+    where
+        T: ToBins + ?Sized + Sync,
+    {
+        self.put(policy, key, &value.to_bins()).await
+    }
+
     /// Add integer bin values to existing record bin values. The policy specifies the transaction
     /// timeout, record expiration and how the transaction is handled when the record already
     /// exists. This call only works for integer values.
@@ -332,8 +773,12 @@ impl Client {
         key: &'a Key,
         bins: &'a [Bin<'b>],
     ) -> Result<(), CommandError> {
+        let policy = self
+            .cluster
+            .namespace_defaults()
+            .write_policy(&key.namespace, policy);
         let mut command = WriteCommand::new(
-            policy,
+            &policy,
             Arc::clone(&self.cluster),
             key,
             bins,
@@ -342,17 +787,22 @@ impl Client {
         command.execute().await
     }
 
-    /// Append bin string values to existing record bin values. The policy specifies the
+    /// Append bin string or blob values to existing record bin values. The policy specifies the
     /// transaction timeout, record expiration and how the transaction is handled when the record
-    /// already exists. This call only works for string values.
+    /// already exists. This call only works for string and blob values, and the appended value
+    /// must be of the same type as the existing one.
     pub async fn append<'a, 'b>(
         &self,
         policy: &'a WritePolicy,
         key: &'a Key,
         bins: &'a [Bin<'b>],
     ) -> Result<(), CommandError> {
+        let policy = self
+            .cluster
+            .namespace_defaults()
+            .write_policy(&key.namespace, policy);
         let mut command = WriteCommand::new(
-            policy,
+            &policy,
             Arc::clone(&self.cluster),
             key,
             bins,
@@ -361,17 +811,22 @@ impl Client {
         command.execute().await
     }
 
-    /// Prepend bin string values to existing record bin values. The policy specifies the
+    /// Prepend bin string or blob values to existing record bin values. The policy specifies the
     /// transaction timeout, record expiration and how the transaction is handled when the record
-    /// already exists. This call only works for string values.
+    /// already exists. This call only works for string and blob values, and the prepended value
+    /// must be of the same type as the existing one.
     pub async fn prepend<'a, 'b>(
         &self,
         policy: &'a WritePolicy,
         key: &'a Key,
         bins: &'a [Bin<'b>],
     ) -> Result<(), CommandError> {
+        let policy = self
+            .cluster
+            .namespace_defaults()
+            .write_policy(&key.namespace, policy);
         let mut command = WriteCommand::new(
-            policy,
+            &policy,
             Arc::clone(&self.cluster),
             key,
             bins,
@@ -408,7 +863,11 @@ impl Client {
     /// }
     /// ```
     pub async fn delete(&self, policy: &WritePolicy, key: &Key) -> Result<bool, CommandError> {
-        let mut command = DeleteCommand::new(policy, Arc::clone(&self.cluster), key);
+        let policy = self
+            .cluster
+            .namespace_defaults()
+            .write_policy(&key.namespace, policy);
+        let mut command = DeleteCommand::new(&policy, Arc::clone(&self.cluster), key);
         command.execute().await?;
         Ok(command.existed)
     }
@@ -442,13 +901,37 @@ impl Client {
     /// }
     /// ```
     pub async fn touch(&self, policy: &WritePolicy, key: &Key) -> Result<(), CommandError> {
-        let mut command = TouchCommand::new(policy, Arc::clone(&self.cluster), key);
+        let policy = self
+            .cluster
+            .namespace_defaults()
+            .write_policy(&key.namespace, policy);
+        let mut command = TouchCommand::new(&policy, Arc::clone(&self.cluster), key);
         command.execute().await
     }
 
+    /// Reset record's time to expiration using the policy's expiration, like [`Self::touch`], but
+    /// treat a missing record as a regular outcome instead of an error.
+    ///
+    /// Returns `true` if the record existed and was touched, or `false` if it did not exist.
+    pub async fn touch_if_exists(
+        &self,
+        policy: &WritePolicy,
+        key: &Key,
+    ) -> Result<bool, CommandError> {
+        match self.touch(policy, key).await {
+            Ok(()) => Ok(true),
+            Err(CommandError::ServerError(ResultCode::KeyNotFoundError)) => Ok(false),
+            Err(err) => Err(err),
+        }
+    }
+
     /// Determine if a record key exists. The policy can be used to specify timeouts.
     pub async fn exists(&self, policy: &WritePolicy, key: &Key) -> Result<bool, CommandError> {
-        let mut command = ExistsCommand::new(policy, Arc::clone(&self.cluster), key);
+        let policy = self
+            .cluster
+            .namespace_defaults()
+            .write_policy(&key.namespace, policy);
+        let mut command = ExistsCommand::new(&policy, Arc::clone(&self.cluster), key);
         command.execute().await?;
         Ok(command.exists)
     }
@@ -494,17 +977,63 @@ impl Client {
         key: &Key,
         ops: &[Operation<'_>],
     ) -> Result<Record, CommandError> {
-        let mut command = OperateCommand::new(policy, Arc::clone(&self.cluster), key, ops);
+        let policy = self
+            .cluster
+            .namespace_defaults()
+            .write_policy(&key.namespace, policy);
+        let mut command = OperateCommand::new(&policy, Arc::clone(&self.cluster), key, ops);
         command.execute().await?;
         Ok(command.read_command.record.unwrap())
     }
 
+    /// Same as [`Self::operate`], but returns the result of each operation separately, in the
+    /// order of the operations, instead of merging them into a record.
+    ///
+    /// This keeps [`Value::Nil`](crate::Value::Nil) results at the position of their operation, and flags writes
+    /// that the server skipped because of their `NO_FAIL` write flags or
+    /// [`WritePolicy::continue_on_error`], as far as that can be detected. See
+    /// [`OperateResult::skipped`] for details. The server is always asked to respond to each
+    /// operation, regardless of [`WritePolicy::respond_per_each_op`].
+    ///
+    /// Reading the record header doesn't produce any result, while reading all bins produces one
+    /// result per bin.
+    pub async fn operate_ordered(
+        &self,
+        policy: &WritePolicy,
+        key: &Key,
+        ops: &[Operation<'_>],
+    ) -> Result<Vec<OperateResult>, CommandError> {
+        let policy = self
+            .cluster
+            .namespace_defaults()
+            .write_policy(&key.namespace, policy);
+        let policy = WritePolicy {
+            respond_per_each_op: true,
+            ..policy.into_owned()
+        };
+        let mut command = OperateCommand::new(&policy, Arc::clone(&self.cluster), key, ops);
+        command.read_command.keep_entries();
+        command.execute().await?;
+
+        let entries = command.read_command.entries.take().unwrap_or_default();
+        if policy.continue_on_error {
+            let ops = ops.iter().map(Operation::with_no_fail).collect::<Vec<_>>();
+            Ok(operations::align_results(&ops, entries))
+        } else {
+            Ok(operations::align_results(ops, entries))
+        }
+    }
+
     /// Read all records in the specified namespace and set and return a record iterator. The scan
     /// executor puts records on a queue in separate threads. The calling thread concurrently pops
     /// records off the queue through the record iterator. Up to `policy.max_concurrent_nodes`
     /// nodes are scanned in parallel. If concurrent nodes is set to zero, the server nodes are
     /// read in series.
     ///
+    /// With a [filter expression](ScanPolicy::filter_expression), only the records that match it
+    /// are returned. This fails with [`Error::InvalidArgument`] if any of the nodes doesn't
+    /// support expression-only queries.
+    ///
     /// # Examples
     ///
     /// ```rust
@@ -550,30 +1079,335 @@ impl Client {
     where
         T: Into<Bins> + Send + Sync + 'static,
     {
+        self.scan_map(policy, namespace, set_name, bins, |record| record)
+            .await
+    }
+
+    /// Read all records in the specified namespace and set like [`Self::scan`], but transform
+    /// each record with the given function before it's queued up.
+    ///
+    /// The transformation runs on the tasks that load the records from the nodes, so it happens
+    /// in parallel for each scanned node and overlaps with the network reads. This is useful for
+    /// expensive conversions, like deserializing the bins into custom types in ETL workloads.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use windpike::{policies::ScanPolicy, Bins, Client};
+    ///
+    /// # async fn run(client: Client) {
+    /// let mut generations = client
+    ///     .scan_map(
+    ///         &ScanPolicy::default(),
+    ///         "test",
+    ///         "demo",
+    ///         Bins::None,
+    ///         |record| record.generation,
+    ///     )
+    ///     .await
+    ///     .unwrap();
+    ///
+    /// while let Some(generation) = generations.next().await {
+    ///     println!("Generation: {}", generation.unwrap());
+    /// }
+    /// # }
+    /// ```
+    ///
+    /// # Panics
+    /// Panics if the async block fails
+    pub async fn scan_map<B, T, F>(
+        &self,
+        policy: &ScanPolicy,
+        namespace: &str,
+        set_name: &str,
+        bins: B,
+        f: F,
+    ) -> Result<RecordSet<T>>
+    where
+        B: Into<Bins> + Send + Sync + 'static,
+        T: Send + 'static,
+        F: Fn(Record) -> T + Send + Sync + 'static,
+    {
+        self.scan_chunk(
+            policy,
+            namespace,
+            set_name,
+            bins.into(),
+            Arc::from([]),
+            Arc::new(f),
+            0..PARTITIONS as u16,
+        )
+        .await
+    }
+
+    /// Read all records in the specified namespace and set like [`Self::scan`], but apply the
+    /// given read operations to each record on the server, and only return their results.
+    ///
+    /// This reduces the network transfer for analytical jobs over large bins. For example,
+    /// [`list::size`](operations::list::size) returns only the size of a list bin, instead of the
+    /// whole list. As the results are stored under the bin name of each operation, only the result
+    /// of the last operation is kept if several operations target the same bin.
+    ///
+    /// Fails with [`Error::InvalidArgument`] if no operations are given, any of them writes data,
+    /// or any of the nodes doesn't support partition queries.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use windpike::{operations::Ops, policies::ScanPolicy, Client};
+    ///
+    /// # async fn run(client: Client) {
+    /// let ops = Ops::new().list_size("events").read("name").build();
+    /// let mut records = client
+    ///     .scan_operate(&ScanPolicy::default(), "test", "demo", ops)
+    ///     .await
+    ///     .unwrap();
+    ///
+    /// while let Some(record) = records.next().await {
+    ///     let record = record.unwrap();
+    ///     println!(
+    ///         "{:?}: {:?}",
+    ///         record.bins.get("name"),
+    ///         record.bins.get("events")
+    ///     );
+    /// }
+    /// # }
+    /// ```
+    pub async fn scan_operate(
+        &self,
+        policy: &ScanPolicy,
+        namespace: &str,
+        set_name: &str,
+        operations: Vec<Operation<'static>>,
+    ) -> Result<RecordSet> {
+        if operations.is_empty() {
+            return Err(Error::InvalidArgument(
+                "scans with operations need at least one operation".to_owned(),
+            ));
+        }
+        if !operations.iter().all(Operation::is_read) {
+            return Err(Error::InvalidArgument(
+                "scans only support read operations".to_owned(),
+            ));
+        }
+
+        self.scan_chunk(
+            policy,
+            namespace,
+            set_name,
+            Bins::All,
+            operations.into(),
+            Arc::new(|record| record),
+            0..PARTITIONS as u16,
+        )
+        .await
+    }
+
+    /// Read all records in the specified namespace and set like [`Self::scan`], but split the
+    /// partitions into the given amount of chunks, each of them read by its own record set.
+    ///
+    /// The chunks cover disjoint, contiguous ranges of partition IDs, so each record is returned
+    /// by exactly one of the record sets. This allows to process the chunks on separate workers,
+    /// without reading any record twice. The [`ScanPolicy::max_records`] limit applies to each
+    /// chunk separately.
+    ///
+    /// Returns an error if the amount of chunks is _zero_ or exceeds the amount of partitions
+    /// (4096).
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use windpike::{policies::ScanPolicy, Bins, Client};
+    ///
+    /// # async fn run(client: Client) {
+    /// let chunks = client
+    ///     .scan_partitioned(&ScanPolicy::default(), "test", "demo", Bins::All, 4)
+    ///     .await
+    ///     .unwrap();
+    ///
+    /// let workers = chunks.into_iter().map(|mut records| {
+    ///     tokio::spawn(async move {
+    ///         while let Some(record) = records.next().await {
+    ///             println!("{:?}", record.unwrap());
+    ///         }
+    ///     })
+    /// });
+    ///
+    /// for worker in workers {
+    ///     worker.await.unwrap();
+    /// }
+    /// # }
+    /// ```
+    pub async fn scan_partitioned<T>(
+        &self,
+        policy: &ScanPolicy,
+        namespace: &str,
+        set_name: &str,
+        bins: T,
+        chunks: usize,
+    ) -> Result<Vec<RecordSet>>
+    where
+        T: Into<Bins> + Send + Sync + 'static,
+    {
+        if chunks == 0 || chunks > PARTITIONS as usize {
+            return Err(Error::InvalidArgument(format!(
+                "amount of chunks must be between 1 and {PARTITIONS}, got {chunks}"
+            )));
+        }
+
         let bins = bins.into();
+        let map: RecordMapper<Record> = Arc::new(|record| record);
+        let mut record_sets = Vec::with_capacity(chunks);
+
+        for chunk in partition_chunks(chunks) {
+            let records = self
+                .scan_chunk(
+                    policy,
+                    namespace,
+                    set_name,
+                    bins.clone(),
+                    Arc::from([]),
+                    Arc::clone(&map),
+                    chunk,
+                )
+                .await?;
+            record_sets.push(records);
+        }
+
+        Ok(record_sets)
+    }
+
+    /// Scan the partitions of the given range on all nodes that own them.
+    #[allow(clippy::too_many_arguments)]
+    async fn scan_chunk<T>(
+        &self,
+        policy: &ScanPolicy,
+        namespace: &str,
+        set_name: &str,
+        bins: Bins,
+        operations: Arc<[Operation<'static>]>,
+        map: RecordMapper<T>,
+        chunk: Range<u16>,
+    ) -> Result<RecordSet<T>>
+    where
+        T: Send + 'static,
+    {
+        if policy.sample_percent == 0 {
+            return Err(Error::InvalidArgument(
+                "sample percent of scans must be between 1 and 100".to_owned(),
+            ));
+        }
+
         let nodes = self.cluster.nodes().await;
-        let (queue_tx, queue_rx) = mpsc::channel(nodes.len().min(128));
-        let recordset = RecordSet::new(queue_rx);
-        let task_id = recordset.task_id();
+        let pquery = nodes
+            .iter()
+            .all(|node| node.supports(FeatureSupport::PQUERY));
+
+        if policy.filter_expression.is_some() && !pquery {
+            return Err(Error::InvalidArgument(
+                "filter expressions on scans require all nodes to support partition queries"
+                    .to_owned(),
+            ));
+        }
 
+        if !operations.is_empty() && !pquery {
+            return Err(Error::InvalidArgument(
+                "operations on scans require all nodes to support partition queries".to_owned(),
+            ));
+        }
+
+        // Each node's partitions can be split across multiple commands, which are scanned in
+        // parallel.
+        let mut node_partitions = Vec::with_capacity(nodes.len());
         for node in nodes {
-            let cluster = Arc::clone(&self.cluster);
-            let node = Arc::clone(&node);
+            let mut partitions = self.cluster.node_partitions(&node, namespace).await;
+            let owned = partitions.len();
+            partitions.retain(|id| chunk.contains(id));
+            // The node doesn't own any of the chunk's partitions, so there is nothing to scan.
+            if owned > 0 && partitions.is_empty() {
+                continue;
+            }
+
+            for partitions in split_partitions(partitions, policy.max_concurrent_partitions) {
+                node_partitions.push((Arc::clone(&node), partitions));
+            }
+        }
+
+        let (queue_tx, queue_rx) = mpsc::channel(node_partitions.len().clamp(1, 128));
+        let cancel = CancellationToken::new();
+        let mut recordset = RecordSet::new(queue_rx, cancel.clone());
+        let task_id = recordset.task_id();
+
+        let counts = node_partitions
+            .iter()
+            .map(|(_, partitions)| partitions.len())
+            .collect::<Vec<_>>();
+        let max_records = split_max_records(policy.max_records, &counts);
+
+        let mut tracker = ProgressTracker::default();
+        let commands = node_partitions
+            .into_iter()
+            .zip(max_records)
+            .enumerate()
+            // The limit is already exhausted by the other nodes.
+            .filter(|(_, (_, max_records))| policy.max_records == 0 || *max_records > 0)
+            .map(|(i, ((node, partitions), max_records))| {
+                let progress = tracker.add_node(node.name(), partitions.len());
+                (i, node, partitions, max_records, progress)
+            })
+            .collect::<Vec<_>>();
+        recordset.set_progress(Arc::new(tracker));
+
+        // Keeps the task IDs of all commands known until they finished, for `kill_job`.
+        let job = self.cluster.jobs().register(task_id, commands.len() as u64);
+
+        for (i, node, partitions, max_records, progress) in commands {
             let policy = policy.clone();
             let namespace = namespace.to_owned();
             let set_name = set_name.to_owned();
             let bins = bins.clone();
+            let operations = Arc::clone(&operations);
             let queue_tx = queue_tx.clone();
+            let map = Arc::clone(&map);
+            let cancel = cancel.clone();
+            let cluster = Arc::clone(&self.cluster);
+            let job = Arc::clone(&job);
+            // Commands that run on the same node must not share a task ID, as the server tracks
+            // each of them as a separate job.
+            let task_id = task_id.wrapping_add(i as u64);
 
             tokio::spawn(async move {
-                let partitions = cluster.node_partitions(&node, &namespace).await;
+                let mut command = ScanCommand::new(
+                    &policy,
+                    Arc::clone(&node),
+                    &namespace,
+                    &set_name,
+                    bins,
+                    operations,
+                    queue_tx,
+                    map,
+                    task_id,
+                    partitions,
+                    max_records,
+                    progress,
+                );
 
-                ScanCommand::new(
-                    &policy, node, &namespace, &set_name, bins, queue_tx, task_id, partitions,
-                )
-                .execute()
-                .await
-                .unwrap();
+                let start = Instant::now();
+                let result = until_cancelled(&cancel, command.execute()).await;
+                cluster.metrics().record(CommandKind::Scan, start.elapsed());
+
+                match result {
+                    Some(result) => result.unwrap(),
+                    // Dropping the command discards the connection, but the node might keep
+                    // scanning until it notices, so abort the job explicitly.
+                    None => {
+                        if let Err(err) = node.abort_job(&InfoPolicy::default(), task_id).await {
+                            warn!(node = node.name(), %err, "failed to abort scan");
+                        }
+                    }
+                }
+
+                drop(job);
             });
         }
         Ok(recordset)
@@ -591,7 +1425,24 @@ impl Client {
     /// zero, only records with a lut less than `before_nanos` are deleted. Units are in
     /// nanoseconds since unix epoch (1970-01-01). Pass in zero to delete all records in the
     /// namespace/set recardless of last update time.
-    pub async fn truncate(&self, namespace: &str, set_name: &str, before_nanos: i64) -> Result<()> {
+    ///
+    /// The namespace and set are checked for existence first, on the same node that receives the
+    /// truncate command, and reported as [`Error::NamespaceNotFound`] or [`Error::SetNotFound`] if
+    /// missing. A set only exists once records were written to it. A command that the server
+    /// refused, like for users without the required role, is reported as [`Error::Forbidden`].
+    /// Any other failure is wrapped in [`Error::Truncate`].
+    pub async fn truncate(
+        &self,
+        policy: &InfoPolicy,
+        namespace: &str,
+        set_name: &str,
+        before_nanos: i64,
+    ) -> Result<()> {
+        let node = self.cluster.get_random_node().await.ok_or(Error::NoNodes)?;
+        check_set_exists(&node, policy, namespace, set_name)
+            .await
+            .map_err(|e| truncate_error(e, namespace))?;
+
         let mut cmd = String::with_capacity(160);
         cmd.push_str("truncate:namespace=");
         cmd.push_str(namespace);
@@ -605,9 +1456,9 @@ impl Client {
             write!(cmd, ";lut={before_nanos}").ok();
         }
 
-        self.send_info_cmd(&cmd)
+        send_node_info_cmd(&node, policy, &cmd)
             .await
-            .map_err(|e| Error::Truncate(Box::new(e)))
+            .map_err(|e| truncate_error(e, namespace))
     }
 
     /// Create a secondary index on a bin containing scalar values. This asynchronous server call
@@ -619,7 +1470,11 @@ impl Client {
     /// within set `bar` and bin `baz`:
     ///
     /// ```rust
-    /// use windpike::{index::IndexType, policies::ClientPolicy, Client};
+    /// use windpike::{
+    ///     index::IndexType,
+    ///     policies::{ClientPolicy, InfoPolicy},
+    ///     Client,
+    /// };
     ///
     /// #[tokio::main]
     /// async fn main() {
@@ -628,7 +1483,14 @@ impl Client {
     ///         .unwrap();
     ///
     ///     match client
-    ///         .create_index("foo", "bar", "baz", "idx_foo_bar_baz", IndexType::Numeric)
+    ///         .create_index(
+    ///             &InfoPolicy::default(),
+    ///             "foo",
+    ///             "bar",
+    ///             "baz",
+    ///             "idx_foo_bar_baz",
+    ///             IndexType::Numeric,
+    ///         )
     ///         .await
     ///     {
     ///         Err(err) => println!("Failed to create index: {err}"),
@@ -638,78 +1500,338 @@ impl Client {
     /// ```
     pub async fn create_index(
         &self,
+        policy: &InfoPolicy,
         namespace: &str,
         set_name: &str,
         bin_name: &str,
         index_name: &str,
         index_type: IndexType,
     ) -> Result<CreateIndex> {
-        self.create_complex_index(namespace, set_name, bin_name, index_name, index_type, None)
-            .await?;
-        Ok(CreateIndex::new(
-            Arc::clone(&self.cluster),
-            namespace.to_owned(),
-            index_name.to_owned(),
-        ))
+        self.create_index_with(
+            policy,
+            &IndexBuilder::new(namespace, bin_name, index_name, index_type).set_name(set_name),
+        )
+        .await
     }
 
-    /// Create a complex secondary index on a bin containing scalar, list or map values. This
-    /// asynchronous server call returns before the command is complete.
-    #[allow(clippy::too_many_arguments)]
-    pub async fn create_complex_index(
+    /// Create a secondary index from a typed [`IndexBuilder`]. The definition is validated
+    /// locally before it is sent, so malformed names fail early with
+    /// [`Error::InvalidArgument`]. The bin name is checked against the limit of the node that
+    /// receives the command, which depends on its support for long bin names. This asynchronous
+    /// server call returns before the command is complete.
+    pub async fn create_index_with(
         &self,
-        namespace: &str,
-        set_name: &str,
-        bin_name: &str,
-        index_name: &str,
-        index_type: IndexType,
-        collection_index_type: Option<CollectionIndexType>,
-    ) -> Result<()> {
-        let cit_str = collection_index_type
-            .map(|v| format!("indextype={v};"))
-            .unwrap_or_default();
-        let cmd = format!(
-            "sindex-create:ns={namespace};set={set_name};indexname={index_name};numbins=1;\
-             {cit_str}indexdata={bin_name},{index_type};priority=normal",
-        );
-        self.send_info_cmd(&cmd)
+        policy: &InfoPolicy,
+        index: &IndexBuilder,
+    ) -> Result<CreateIndex> {
+        let cmd = index.to_command()?;
+        let node = self.cluster.get_random_node().await.ok_or(Error::NoNodes)?;
+        index.validate_bin_name(node.max_bin_name_len())?;
+
+        send_node_info_cmd(&node, policy, &cmd)
             .await
-            .map_err(|e| Error::CreateIndex(Box::new(e)))
+            .map_err(|e| Error::CreateIndex(Box::new(e)))?;
+
+        Ok(CreateIndex::new(
+            Arc::clone(&self.cluster),
+            policy.clone(),
+            index.namespace().to_owned(),
+            index.index_name().to_owned(),
+        ))
     }
 
-    /// Delete secondary index.
+    /// Delete secondary index. This asynchronous server call returns before the index is removed
+    /// from all nodes. Use the returned [`DropIndex`] task to wait for the removal, for example
+    /// before recreating an index with the same name.
     pub async fn drop_index(
         &self,
+        policy: &InfoPolicy,
         namespace: &str,
         set_name: &str,
         index_name: &str,
-    ) -> Result<()> {
+    ) -> Result<DropIndex> {
         let set_name = if set_name.is_empty() {
             String::new()
         } else {
             format!("set={set_name};")
         };
         let cmd = format!("sindex-delete:ns={namespace};{set_name}indexname={index_name}");
-        self.send_info_cmd(&cmd)
+        self.send_info_cmd(policy, &cmd)
             .await
-            .map_err(|e| Error::Truncate(Box::new(e)))
+            .map_err(|e| Error::DropIndex(Box::new(e)))?;
+
+        Ok(DropIndex::new(
+            Arc::clone(&self.cluster),
+            policy.clone(),
+            namespace.to_owned(),
+            index_name.to_owned(),
+        ))
     }
 
-    async fn send_info_cmd(&self, cmd: &str) -> Result<()> {
-        let node = self.cluster.get_random_node().await.ok_or(Error::NoNodes)?;
-        let response = node.info(&[cmd]).await?;
-
-        if let Some(v) = response.values().next() {
-            if v.to_uppercase() == "OK" {
-                return Ok(());
-            } else if v.starts_with("FAIL:") {
-                let result = v.split(':').nth(1).unwrap().parse::<u8>()?;
-                return Err(Error::ServerError(ResultCode::from(result)));
+    /// Send raw info commands to all active cluster nodes concurrently, and return the responses
+    /// of each node by its name. This is useful to compare the state of the nodes, for example
+    /// the partition generation or running migrations.
+    ///
+    /// The [`InfoPolicy::timeout`] applies to each node separately. The whole call fails if any of
+    /// the nodes fails to respond.
+    ///
+    /// ```rust,no_run
+    /// # use windpike::{policies::InfoPolicy, Client};
+    /// # async fn run(client: &Client) -> windpike::errors::Result<()> {
+    /// let responses = client
+    ///     .info_all(&InfoPolicy::default(), &["partition-generation"])
+    ///     .await?;
+    ///
+    /// for (node, response) in responses {
+    ///     println!("{node}: {:?}", response.get("partition-generation"));
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn info_all(
+        &self,
+        policy: &InfoPolicy,
+        commands: &[&str],
+    ) -> Result<HashMap<String, HashMap<String, String>>> {
+        let nodes = self.cluster.nodes().await;
+        if nodes.is_empty() {
+            return Err(Error::NoNodes);
+        }
+
+        let commands = commands
+            .iter()
+            .map(|&cmd| cmd.to_owned())
+            .collect::<Arc<[_]>>();
+
+        let tasks = nodes
+            .into_iter()
+            .filter(|node| node.is_active())
+            .map(|node| {
+                let policy = policy.clone();
+                let commands = Arc::clone(&commands);
+
+                tokio::spawn(async move {
+                    let commands = commands.iter().map(String::as_str).collect::<Vec<_>>();
+                    let response = node.info(&policy, &commands).await;
+                    response.map(|response| (node.name().to_owned(), response))
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let mut responses = HashMap::with_capacity(tasks.len());
+        for task in tasks {
+            let (name, response) = task
+                .await
+                .unwrap_or_else(|err| std::panic::resume_unwind(err.into_panic()))?;
+            responses.insert(name, response);
+        }
+
+        Ok(responses)
+    }
+
+    /// Send a hand-crafted message to the given node, for server features that the client doesn't
+    /// wrap yet. The message is built by the closure, and framed with a proto header before it's
+    /// sent over a pooled connection. See the [`raw`](crate::raw) module for details.
+    ///
+    /// Network errors are retried on the same node until the [`BasePolicy::timeout`] passes, so
+    /// the message should be safe to send multiple times.
+    ///
+    /// This method is only available with the `raw` feature enabled, and is **exempt from
+    /// semantic versioning**.
+    #[cfg(feature = "raw")]
+    pub async fn raw_command(
+        &self,
+        policy: &BasePolicy,
+        node: &Arc<Node>,
+        build: impl FnOnce(&mut raw::Request),
+    ) -> Result<raw::Response, CommandError> {
+        let mut request = raw::Request::default();
+        build(&mut request);
+
+        let mut command = RawCommand::new(policy, Arc::clone(node), request);
+        command.execute().await?;
+        command
+            .response
+            .ok_or(CommandError::Parse("missing response"))
+    }
+
+    /// List the scan and query jobs that are currently known to the cluster nodes, including
+    /// recently finished ones.
+    ///
+    /// Each node reports its own part of a job, therefore the same transaction ID usually shows up
+    /// once per node.
+    pub async fn list_jobs(&self, policy: &InfoPolicy) -> Result<Vec<Job>> {
+        let mut jobs = Vec::new();
+
+        for node in self.cluster.nodes().await {
+            let cmd = if node.supports(FeatureSupport::QUERY_SHOW) {
+                "query-show"
+            } else {
+                "scan-show"
+            };
+
+            let response = node.info(policy, &[cmd]).await?;
+            if let Some(response) = response.get(cmd) {
+                jobs.extend(job::parse_jobs(node.name(), response));
             }
         }
 
-        Err(Error::BadResponse(
-            "unexpected sindex info command response".to_owned(),
-        ))
+        Ok(jobs)
+    }
+
+    /// Estimate the amount of records in the given set, based on the object counts that the
+    /// cluster nodes report. The `set_name` is optional; set to `""` to count all records in
+    /// `namespace`.
+    ///
+    /// Each node counts both the master and replica records it holds, so the sum is divided by the
+    /// replication factor. The result is exact on a stable cluster, but might be off while
+    /// records are migrated between nodes. Use [`Self::count_exact`] if that's not good enough.
+    pub async fn count(&self, policy: &InfoPolicy, namespace: &str, set_name: &str) -> Result<u64> {
+        let nodes = self.cluster.nodes().await;
+        if nodes.is_empty() {
+            return Err(Error::NoNodes);
+        }
+
+        let ns_cmd = format!("namespace/{namespace}");
+        let cmd = if set_name.is_empty() {
+            ns_cmd.clone()
+        } else {
+            format!("sets/{namespace}/{set_name}")
+        };
+
+        let mut objects = 0;
+        let mut replication_factor = 1;
+
+        for node in nodes {
+            let response = node.info(policy, &[&cmd, &ns_cmd]).await?;
+            if let Some(count) = response
+                .get(&cmd)
+                .and_then(|response| info::field_u64(response, "objects"))
+            {
+                objects += count;
+            }
+            if let Some(factor) = response.get(&ns_cmd).and_then(|response| {
+                info::field_u64(response, "effective_replication_factor")
+                    .or_else(|| info::field_u64(response, "replication-factor"))
+            }) {
+                replication_factor = replication_factor.max(factor);
+            }
+        }
+
+        Ok(objects / replication_factor)
+    }
+
+    /// Count the records in the given set exactly, by scanning over it without reading any bins.
+    /// The `set_name` is optional; set to `""` to count all records in `namespace`.
+    ///
+    /// This is far more expensive than [`Self::count`], as every record is sent over the network.
+    pub async fn count_exact(
+        &self,
+        policy: &ScanPolicy,
+        namespace: &str,
+        set_name: &str,
+    ) -> Result<u64> {
+        let mut records = self.scan(policy, namespace, set_name, Bins::None).await?;
+        let mut count = 0;
+
+        while let Some(record) = records.next().await {
+            record?;
+            count += 1;
+        }
+
+        Ok(count)
+    }
+
+    /// Abort the scan or query job with the given transaction ID on all cluster nodes.
+    ///
+    /// Scans that are split into several commands per node run each of them under its own job,
+    /// and passing the [`RecordSet::task_id`] aborts all of them.
+    ///
+    /// Returns [`Error::JobNotFound`] if none of the nodes has an active job with that ID.
+    pub async fn kill_job(&self, policy: &InfoPolicy, trans_id: u64) -> Result<()> {
+        let mut found = false;
+        let task_ids = self.cluster.jobs().task_ids(trans_id);
+
+        for node in self.cluster.nodes().await {
+            for &task_id in &task_ids {
+                found |= node.abort_job(policy, task_id).await?;
+            }
+        }
+
+        if found {
+            Ok(())
+        } else {
+            Err(Error::JobNotFound(trans_id))
+        }
+    }
+
+    async fn send_info_cmd(&self, policy: &InfoPolicy, cmd: &str) -> Result<()> {
+        let node = self.cluster.get_random_node().await.ok_or(Error::NoNodes)?;
+        send_node_info_cmd(&node, policy, cmd).await
+    }
+}
+
+async fn send_node_info_cmd(node: &Node, policy: &InfoPolicy, cmd: &str) -> Result<()> {
+    let response = node.info(policy, &[cmd]).await?;
+
+    if let Some(v) = response.values().next() {
+        if v.to_uppercase() == "OK" {
+            return Ok(());
+        } else if let Some(result) = info::error_code(v) {
+            return Err(Error::ServerError(result));
+        }
+    }
+
+    Err(Error::BadResponse(
+        "unexpected sindex info command response".to_owned(),
+    ))
+}
+
+/// Verify that the namespace, and the set if not empty, are known to the node.
+async fn check_set_exists(
+    node: &Node,
+    policy: &InfoPolicy,
+    namespace: &str,
+    set_name: &str,
+) -> Result<()> {
+    let sets_cmd = format!("sets/{namespace}");
+    let response = node.info(policy, &["namespaces", &sets_cmd]).await?;
+
+    let namespace_exists = response.get("namespaces").map_or(false, |namespaces| {
+        namespaces.split(';').any(|ns| ns == namespace)
+    });
+    if !namespace_exists {
+        return Err(Error::NamespaceNotFound(namespace.to_owned()));
+    }
+
+    let set_exists = set_name.is_empty()
+        || response
+            .get(&sets_cmd)
+            .map_or(false, |sets| info::has_set(sets, set_name));
+    if !set_exists {
+        return Err(Error::SetNotFound {
+            namespace: namespace.to_owned(),
+            set_name: set_name.to_owned(),
+        });
+    }
+
+    Ok(())
+}
+
+/// Turn the failure of a truncate command into a typed error, where possible.
+fn truncate_error(err: Error, namespace: &str) -> Error {
+    match err {
+        Error::NamespaceNotFound(_) | Error::SetNotFound { .. } | Error::NoNodes => err,
+        Error::ServerError(ResultCode::InvalidNamespace) => {
+            Error::NamespaceNotFound(namespace.to_owned())
+        }
+        Error::ServerError(
+            rc @ (ResultCode::AlwaysForbidden
+            | ResultCode::FailForbidden
+            | ResultCode::NotAuthenticated
+            | ResultCode::RoleViolation
+            | ResultCode::NotWhitelisted),
+        ) => Error::Forbidden(rc),
+        err => Error::Truncate(Box::new(err)),
     }
 }
@@ -0,0 +1,96 @@
+use std::{collections::HashMap, sync::Mutex};
+
+use tokio::time::{Duration, Instant};
+
+use crate::net::Host;
+
+/// Hosts that reported a different cluster name than the one configured in the client policy.
+///
+/// Blacklisted hosts are skipped during seeding and peer discovery until their entry expires, so
+/// a seed that belongs to another cluster isn't connected to and validated on every tend run.
+#[derive(Debug)]
+pub(crate) struct HostBlacklist {
+    duration: Duration,
+    hosts: Mutex<HashMap<Host, Instant>>,
+}
+
+impl HostBlacklist {
+    /// Create a new blacklist, which keeps its entries for the given duration. A _zero_ duration
+    /// disables the blacklist.
+    pub fn new(duration: Duration) -> Self {
+        Self {
+            duration,
+            hosts: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Add a host to the list, or extend its expiry if already listed.
+    pub fn insert(&self, host: Host) {
+        if self.duration.is_zero() {
+            return;
+        }
+
+        self.lock().insert(host, Instant::now() + self.duration);
+    }
+
+    /// Check whether the host is currently blacklisted.
+    pub fn contains(&self, host: &Host) -> bool {
+        let mut hosts = self.lock();
+        match hosts.get(host) {
+            Some(&expiry) if expiry > Instant::now() => true,
+            Some(_) => {
+                hosts.remove(host);
+                false
+            }
+            None => false,
+        }
+    }
+
+    /// List all hosts that are currently blacklisted, dropping any expired entries.
+    pub fn hosts(&self) -> Vec<Host> {
+        let mut hosts = self.lock();
+        let now = Instant::now();
+
+        hosts.retain(|_, expiry| *expiry > now);
+        hosts.keys().cloned().collect()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<Host, Instant>> {
+        self.hosts
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::time::Duration;
+
+    use super::HostBlacklist;
+    use crate::net::Host;
+
+    #[test]
+    fn disabled() {
+        let blacklist = HostBlacklist::new(Duration::ZERO);
+        let host = Host::new("127.0.0.1", 3000);
+
+        blacklist.insert(host.clone());
+        assert!(!blacklist.contains(&host));
+        assert!(blacklist.hosts().is_empty());
+    }
+
+    #[test]
+    fn expires() {
+        let blacklist = HostBlacklist::new(Duration::from_millis(50));
+        let host = Host::new("127.0.0.1", 3000);
+
+        blacklist.insert(host.clone());
+        assert!(blacklist.contains(&host));
+        assert!(!blacklist.contains(&Host::new("127.0.0.1", 3001)));
+        assert_eq!(vec![host.clone()], blacklist.hosts());
+
+        std::thread::sleep(Duration::from_millis(60));
+        assert!(!blacklist.contains(&host));
+        assert!(blacklist.hosts().is_empty());
+    }
+}
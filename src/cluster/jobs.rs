@@ -0,0 +1,77 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+/// Scans whose commands run under several consecutive task IDs, by the task ID that their record
+/// set reports.
+///
+/// A scan is split into multiple commands per node, and the server tracks each of them as a
+/// separate job. Killing a job by the record set's task ID must therefore abort all of them.
+#[derive(Debug, Default)]
+pub(crate) struct JobRegistry {
+    jobs: Mutex<HashMap<u64, u64>>,
+}
+
+impl JobRegistry {
+    /// Register a job that uses `count` consecutive task IDs, starting at `task_id`. The entry is
+    /// removed again once the returned guard is dropped.
+    pub fn register(self: &Arc<Self>, task_id: u64, count: u64) -> Arc<JobGuard> {
+        if count > 1 {
+            self.lock().insert(task_id, count);
+        }
+
+        Arc::new(JobGuard {
+            registry: Arc::clone(self),
+            task_id,
+        })
+    }
+
+    /// List all task IDs that belong to the job with the given ID.
+    pub fn task_ids(&self, task_id: u64) -> Vec<u64> {
+        let count = self.lock().get(&task_id).copied().unwrap_or(1);
+        (0..count).map(|i| task_id.wrapping_add(i)).collect()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<u64, u64>> {
+        self.jobs
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+}
+
+/// Registration of a running job, which is shared by all of its commands.
+#[derive(Debug)]
+pub(crate) struct JobGuard {
+    registry: Arc<JobRegistry>,
+    task_id: u64,
+}
+
+impl Drop for JobGuard {
+    fn drop(&mut self) {
+        self.registry.lock().remove(&self.task_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::JobRegistry;
+
+    #[test]
+    fn task_ids() {
+        let registry = Arc::new(JobRegistry::default());
+        assert_eq!(vec![5], registry.task_ids(5));
+
+        let guard = registry.register(u64::MAX, 3);
+        let other = Arc::clone(&guard);
+        assert_eq!(vec![u64::MAX, 0, 1], registry.task_ids(u64::MAX));
+
+        drop(guard);
+        assert_eq!(vec![u64::MAX, 0, 1], registry.task_ids(u64::MAX));
+
+        drop(other);
+        assert_eq!(vec![u64::MAX], registry.task_ids(u64::MAX));
+    }
+}
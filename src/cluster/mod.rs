@@ -1,10 +1,15 @@
+mod blacklist;
+mod jobs;
+mod namespace_defaults;
 pub mod node;
 pub mod node_validator;
 pub mod partition;
 pub mod partition_tokenizer;
+mod rate_limiter;
 
 use std::{
     collections::{HashMap, HashSet},
+    net::IpAddr,
     sync::{
         atomic::{AtomicBool, AtomicUsize, Ordering},
         Arc,
@@ -13,17 +18,28 @@ use std::{
 };
 
 use tokio::{
-    sync::RwLock,
+    sync::{Mutex, RwLock},
     task::JoinError,
     time::{Duration, Instant},
 };
-use tracing::{debug, error, warn};
-
-pub use self::node::Node;
-use self::{node::FeatureSupport, partition::Partition};
+use tracing::{debug, error, info, warn};
+
+use self::{
+    blacklist::HostBlacklist,
+    jobs::JobRegistry,
+    namespace_defaults::NamespaceDefaultsMap,
+    node::{FeatureSupport, PARTITIONS},
+    partition::{Partition, PartitionMap},
+    rate_limiter::RateLimiter,
+};
+pub use self::{
+    node::Node,
+    partition::{PartitionHealth, PartitionOwners},
+};
 use crate::{
-    net::{Host, NetError},
-    policies::ClientPolicy,
+    metrics::Metrics,
+    net::{Host, NetError, Session},
+    policies::{ClientPolicy, InfoPolicy, NodeSelection, ReadModeSc},
 };
 
 type Result<T, E = ClusterError> = std::result::Result<T, E>;
@@ -55,6 +71,12 @@ pub enum ClusterError {
     MissingPartitionGeneration,
     #[error("error during initial cluster tend")]
     InitialTend(#[source] JoinError),
+    #[error("cluster nodes did not stabilize within the given timeout")]
+    NotStabilized,
+    #[error("the cluster connection was closed")]
+    Closed,
+    #[error("info command timed out")]
+    Timeout,
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -63,12 +85,16 @@ pub enum NodeError {
     NoAddress { host: Host },
     #[error("no valid instance for host `{host}`")]
     NoValidInstance { host: Host },
+    #[error("validation of host `{host}` timed out")]
+    Timeout { host: Host },
     #[error("missing node name")]
     MissingNodeName,
     #[error("missing cluster name")]
     MissingClusterName,
-    #[error("cluster name mismatch. Expected `{expected}`, but got `{got}`")]
+    #[error("node name mismatch. Expected `{expected}`, but got `{got}`")]
     NameMismatch { expected: String, got: String },
+    #[error("cluster name mismatch. Expected `{expected}`, but got `{got}`")]
+    ClusterNameMismatch { expected: String, got: String },
     #[error("networking error")]
     Net(#[from] crate::net::NetError),
     #[error("I/O related error")]
@@ -89,13 +115,39 @@ pub enum NodeRefreshError {
     FailedUpdatingPartitions(#[source] ClusterError),
 }
 
+/// Topology of the cluster, as returned by [`Client::cluster_info`](crate::Client::cluster_info).
+///
+/// Comparing the cluster key and the partition generations of two snapshots tells whether nodes
+/// joined or left the cluster, or partitions moved between nodes in the meantime.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ClusterInfo {
+    /// Amount of partitions per namespace, as reported by the nodes.
+    ///
+    /// This is informational only. Key routing, partition maps and scans always use the fixed
+    /// amount of 4096 partitions that all server versions use, so a different count means the
+    /// client can't route commands correctly for the cluster.
+    pub partition_count: usize,
+    /// Key that identifies the current state of the cluster. The server assigns a new key
+    /// whenever nodes join or leave. [`None`] if the queried node didn't report it.
+    pub cluster_key: Option<String>,
+    /// Partition generation of each active node by node name, which increases whenever the
+    /// partitions that the node owns change.
+    pub partition_generations: Vec<(String, isize)>,
+}
+
 // Cluster encapsulates the aerospike cluster nodes and manages
 // them.
 #[derive(Debug)]
 pub struct Cluster {
     // Initial host nodes specified by user.
+    initial_seeds: Vec<Host>,
+
+    // Initial seeds, plus the addresses that their host names resolved to.
     seeds: Arc<RwLock<Vec<Host>>>,
 
+    // Last time that the host names of the initial seeds were resolved.
+    last_seed_resolve: Mutex<Option<Instant>>,
+
     // All aliases for all nodes in cluster.
     aliases: Arc<RwLock<HashMap<Host, Arc<Node>>>>,
 
@@ -103,32 +155,68 @@ pub struct Cluster {
     nodes: Arc<RwLock<Vec<Arc<Node>>>>,
 
     // Hints for best node for a partition
-    partition_write_map: Arc<RwLock<HashMap<String, Vec<Arc<Node>>>>>,
+    partition_write_map: Arc<RwLock<PartitionMap>>,
 
     // Random node index.
     node_index: AtomicUsize,
 
     client_policy: Arc<ClientPolicy>,
 
+    // Limiter for the commands sent to all nodes combined.
+    rate_limiter: Option<Arc<RateLimiter>>,
+
+    // Login state shared by all connections, if authentication is enabled.
+    session: Option<Arc<Session>>,
+
+    // Hosts that belong to a different cluster, and are temporarily ignored.
+    blacklist: HostBlacklist,
+
+    // Scans that run under several task IDs, so they can be killed as a whole.
+    jobs: Arc<JobRegistry>,
+
+    // Whether boolean bins can be written natively, as all nodes support them.
+    bool_bins: AtomicBool,
+    metrics: Metrics,
+
+    // Policies that replace the defaults for commands on specific namespaces.
+    namespace_defaults: NamespaceDefaultsMap,
+
+    // Prevents concurrent modifications of the node list by multiple tend runs.
+    tend_lock: Mutex<()>,
+
     closed: AtomicBool,
 }
 
 impl Cluster {
     pub async fn new(policy: ClientPolicy, hosts: &[Host]) -> Result<Arc<Self>> {
+        let policy_bool_bins = policy.use_bool_bins;
+        let policy_latency_metrics = policy.latency_metrics;
         let cluster = Arc::new(Self {
+            rate_limiter: RateLimiter::new(policy.max_commands_per_second).map(Arc::new),
+            session: Session::new(&policy).map(Arc::new),
+            blacklist: HostBlacklist::new(policy.blacklist_duration),
+            jobs: Arc::default(),
             client_policy: Arc::new(policy),
 
+            initial_seeds: hosts.to_vec(),
             seeds: Arc::new(RwLock::new(hosts.to_vec())),
+            last_seed_resolve: Mutex::new(None),
             aliases: Arc::new(RwLock::new(HashMap::new())),
             nodes: Arc::new(RwLock::new(vec![])),
 
             partition_write_map: Arc::new(RwLock::new(HashMap::new())),
             node_index: AtomicUsize::new(0),
+            bool_bins: AtomicBool::new(policy_bool_bins),
+            metrics: Metrics::new(policy_latency_metrics),
+            namespace_defaults: NamespaceDefaultsMap::default(),
+            tend_lock: Mutex::new(()),
 
             closed: AtomicBool::new(false),
         });
         // try to seed connections for first use
-        Self::wait_till_stabilized(Arc::clone(&cluster)).await;
+        cluster
+            .wait_till_stabilized(cluster.initial_timeout())
+            .await;
 
         // apply policy rules
         if cluster.client_policy.fail_if_not_connected && !cluster.is_connected().await {
@@ -155,6 +243,8 @@ impl Cluster {
     }
 
     async fn tend(&self) -> Result<()> {
+        let _guard = self.tend_lock.lock().await;
+        let resolved_seeds = self.resolve_seeds().await;
         let mut nodes = self.nodes().await;
 
         // All node additions/deletions are performed in tend thread.
@@ -187,11 +277,27 @@ impl Cluster {
                     Err(err) => {
                         node.increase_failures();
                         warn!(?node, %err, "node refresh failed");
+
+                        if let NodeRefreshError::ValidationFailed(err) = &err {
+                            for alias in &*node.aliases().await {
+                                self.check_blacklist(alias, err);
+                            }
+                        }
                     }
                 }
             }
         }
 
+        // Probe new seed addresses, in case they belong to a cluster that replaced the known nodes.
+        if !self.client_policy.single_node_mode {
+            let aliases = self.aliases.read().await;
+            friend_list.extend(
+                resolved_seeds
+                    .into_iter()
+                    .filter(|host| !aliases.contains_key(host)),
+            );
+        }
+
         // Add nodes in a batch.
         let add_list = self.find_new_nodes_to_add(friend_list).await?;
         self.add_nodes_and_aliases(&add_list).await;
@@ -202,62 +308,175 @@ impl Cluster {
         let remove_list = self.find_nodes_to_remove(refresh_count).await?;
         self.remove_nodes_and_aliases(remove_list).await;
 
+        self.heal_partitions().await;
+
         Ok(())
     }
 
-    async fn wait_till_stabilized(cluster: Arc<Self>) {
-        let timeout = cluster
-            .client_policy()
+    // Refresh the partition map from all active nodes, if any partitions are still assigned to
+    // nodes that are gone. The partition generation of the remaining nodes doesn't necessarily
+    // change when a node leaves, so the map would otherwise keep pointing to the removed node.
+    async fn heal_partitions(&self) {
+        // Only the partitions of the single node are known, and there's no other node to ask.
+        if self.client_policy.single_node_mode {
+            return;
+        }
+
+        let unhealthy = self
+            .partition_health()
+            .await
+            .into_iter()
+            .filter(|health| !health.is_healthy())
+            .collect::<Vec<_>>();
+        if unhealthy.is_empty() {
+            return;
+        }
+
+        warn!(
+            ?unhealthy,
+            "found unowned partitions, refreshing partition map"
+        );
+
+        for node in self.nodes().await {
+            if node.is_active() {
+                if let Err(err) = self.update_partitions(Arc::clone(&node)).await {
+                    warn!(?node, %err, "failed refreshing partitions");
+                }
+            }
+        }
+    }
+
+    // Check every namespace for partitions that are owned by nodes, which are not part of the
+    // cluster or not active anymore. Partitions that never had a master in the map, like the ones
+    // of other nodes in single node mode, aren't counted.
+    pub async fn partition_health(&self) -> Vec<PartitionHealth> {
+        let nodes = self.nodes().await;
+        let partitions = self.partition_write_map.read().await;
+
+        let mut health = partitions
+            .iter()
+            .map(|(namespace, table)| PartitionHealth {
+                namespace: namespace.clone(),
+                partitions: table.len(),
+                unowned: (0..table.len())
+                    .filter(|&id| {
+                        table.master(id).map_or(false, |owner| {
+                            !owner.is_active() || !nodes.iter().any(|node| Arc::ptr_eq(node, owner))
+                        })
+                    })
+                    .count(),
+            })
+            .collect::<Vec<_>>();
+
+        health.sort_by(|a, b| a.namespace.cmp(&b.namespace));
+        health
+    }
+
+    fn initial_timeout(&self) -> Duration {
+        self.client_policy
             .timeout
-            .unwrap_or_else(|| Duration::from_secs(3));
+            .unwrap_or_else(|| Duration::from_secs(3))
+    }
+
+    // Tend the cluster until the amount of nodes doesn't change anymore, returning whether that
+    // was the case before the timeout passed.
+    pub async fn wait_till_stabilized(&self, timeout: Duration) -> bool {
         let deadline = Instant::now() + timeout;
 
         let mut count: isize = -1;
         loop {
             if Instant::now() > deadline {
-                break;
+                return false;
             }
 
-            if let Err(err) = cluster.tend().await {
-                error!(error = ?err, "error during initial cluster tend");
+            if let Err(err) = self.tend().await {
+                error!(error = ?err, "error during cluster tend");
             }
 
             let old_count = count;
             // unlikely that there are ever more than isize::MAX nodes
-            count = cluster.node_count().await.try_into().unwrap_or(isize::MAX);
+            count = self.node_count().await.try_into().unwrap_or(isize::MAX);
             if count == old_count {
-                break;
+                return true;
             }
 
             tokio::time::sleep(Duration::from_millis(10)).await;
         }
     }
 
-    pub fn name(&self) -> Option<&str> {
-        self.client_policy.cluster_name.as_deref()
+    // Drop all known nodes and partitions, and start over from the initial seed hosts.
+    pub async fn reconnect(&self) -> Result<()> {
+        if self.closed.load(Ordering::Relaxed) {
+            return Err(ClusterError::Closed);
+        }
+
+        {
+            let _guard = self.tend_lock.lock().await;
+
+            self.remove_nodes_and_aliases(self.nodes().await).await;
+            self.set_partitions(HashMap::new()).await;
+            self.seed_nodes().await?;
+        }
+
+        self.wait_till_stabilized(self.initial_timeout()).await;
+
+        if self.is_connected().await {
+            Ok(())
+        } else {
+            Err(ClusterError::Connection)
+        }
     }
 
     pub fn client_policy(&self) -> &ClientPolicy {
         &self.client_policy
     }
 
-    async fn set_partitions(&self, partitions: HashMap<String, Vec<Arc<Node>>>) {
+    pub(crate) fn metrics(&self) -> &Metrics {
+        &self.metrics
+    }
+
+    pub(crate) fn jobs(&self) -> &Arc<JobRegistry> {
+        &self.jobs
+    }
+
+    pub(crate) fn namespace_defaults(&self) -> &NamespaceDefaultsMap {
+        &self.namespace_defaults
+    }
+
+    pub(crate) fn session(&self) -> Option<&Session> {
+        self.session.as_deref()
+    }
+
+    async fn set_partitions(&self, partitions: PartitionMap) {
         let mut partition_map = self.partition_write_map.write().await;
         *partition_map = partitions;
     }
 
-    fn partitions(&self) -> Arc<RwLock<HashMap<String, Vec<Arc<Node>>>>> {
+    fn partitions(&self) -> Arc<RwLock<PartitionMap>> {
         Arc::clone(&self.partition_write_map)
     }
 
+    pub async fn node_names_for_partition(&self, namespace: &str, id: u32) -> PartitionOwners {
+        let partitions = self.partitions();
+        let partitions = partitions.read().await;
+
+        partitions
+            .get(namespace)
+            .map(|table| table.owner_names(id as usize))
+            .unwrap_or_default()
+    }
+
     pub async fn node_partitions(&self, node: &Node, namespace: &str) -> Vec<u16> {
         let mut res: Vec<u16> = vec![];
         let partitions = self.partitions();
         let partitions = partitions.read().await;
 
-        if let Some(node_array) = partitions.get(namespace) {
-            for (i, tnode) in node_array.iter().enumerate() {
-                if node.name() == tnode.name() {
+        if let Some(table) = partitions.get(namespace) {
+            for (i, tnode) in table.masters().iter().enumerate() {
+                if tnode
+                    .as_ref()
+                    .map_or(false, |tnode| node.name() == tnode.name())
+                {
                     res.push(i as u16);
                 }
             }
@@ -274,21 +493,150 @@ impl Cluster {
         )
         .await?;
 
+        let diffs = partition::diff(&*self.partition_write_map.read().await, &nmap);
+        for diff in diffs {
+            info!(
+                namespace = %diff.namespace,
+                changes = diff.changes(),
+                moved = ?diff.moved,
+                gained = ?diff.gained,
+                lost = ?diff.lost,
+                "partition map changed"
+            );
+        }
+
         self.set_partitions(nmap).await;
 
         Ok(())
     }
 
+    // Ask all active nodes whether the cluster is stable, which is the case when all of them report
+    // the same cluster key for the current amount of nodes and no migrations are running.
+    pub async fn is_stable(&self, policy: &InfoPolicy) -> Result<bool> {
+        let nodes = self.nodes().await;
+        if nodes.is_empty() {
+            return Err(ClusterError::Connection);
+        }
+
+        let command = format!(
+            "cluster-stable:size={};ignore-migrations=false",
+            nodes.len()
+        );
+        let mut keys = Vec::with_capacity(nodes.len());
+        for node in &nodes {
+            // Inactive nodes can't confirm the cluster key, which makes the cluster unstable.
+            let key = if node.is_active() {
+                node.info(policy, &[&command]).await?.remove(&command)
+            } else {
+                None
+            };
+            keys.push(key);
+        }
+
+        Ok(is_same_cluster_key(&keys))
+    }
+
+    // Amount of partitions per namespace, as reported by the first active node. Falls back to the
+    // default amount, if no node is connected.
+    pub async fn partition_count(&self) -> usize {
+        self.nodes()
+            .await
+            .iter()
+            .find(|node| node.is_active())
+            .map_or(PARTITIONS as usize, |node| node.partition_count())
+    }
+
+    // Collect the current topology, asking a random node for the cluster key.
+    pub async fn info(&self, policy: &InfoPolicy) -> Result<ClusterInfo> {
+        const STATISTICS: &str = "statistics";
+
+        let node = self
+            .get_random_node()
+            .await
+            .ok_or(ClusterError::Connection)?;
+        let cluster_key = node
+            .info(policy, &[STATISTICS])
+            .await?
+            .get(STATISTICS)
+            .and_then(|statistics| parse_cluster_key(statistics));
+
+        let mut partition_generations = self
+            .nodes()
+            .await
+            .iter()
+            .filter(|node| node.is_active())
+            .map(|node| (node.name().to_owned(), node.partition_generation()))
+            .collect::<Vec<_>>();
+        partition_generations.sort();
+
+        Ok(ClusterInfo {
+            partition_count: self.partition_count().await,
+            cluster_key,
+            partition_generations,
+        })
+    }
+
+    // Resolve the host names of the initial seeds again, once the configured interval passed, and
+    // use the resulting addresses as seeds. Returns the addresses that weren't seeds before.
+    async fn resolve_seeds(&self) -> Vec<Host> {
+        let interval = match self.client_policy.seed_resolve_interval {
+            Some(interval) if !interval.is_zero() => interval,
+            _ => return Vec::new(),
+        };
+
+        {
+            let mut last = self.last_seed_resolve.lock().await;
+            if last.map_or(false, |last| last.elapsed() < interval) {
+                return Vec::new();
+            }
+            *last = Some(Instant::now());
+        }
+
+        let resolved = resolve_host_names(&self.initial_seeds).await;
+        let mut seeds = self.seeds.write().await;
+        let added = resolved
+            .iter()
+            .filter(|host| !seeds.contains(host))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        if !added.is_empty() {
+            debug!(?added, "discovered new seed addresses");
+        }
+
+        *seeds = self.initial_seeds.iter().cloned().chain(resolved).collect();
+        added
+    }
+
     pub async fn seed_nodes(&self) -> Result<bool, NetError> {
-        let seeds = self.seeds.read().await;
+        let seeds = self.seeds.read().await.clone();
         let mut list = Vec::<Arc<Node>>::new();
 
         debug!(seed_count = seeds.len(), "seeding the cluster");
 
-        for seed in &*seeds {
-            let (name, features, aliases) = match node_validator::validate(self, seed).await {
+        let seeds = seeds
+            .into_iter()
+            .filter(|seed| {
+                let blacklisted = self.blacklist.contains(seed);
+                if blacklisted {
+                    debug!(%seed, "skipping blacklisted seed host");
+                }
+                !blacklisted
+            })
+            .collect();
+
+        let validations = node_validator::validate_all(
+            Arc::clone(&self.client_policy),
+            self.session.clone(),
+            seeds,
+        )
+        .await;
+
+        for (seed, validation) in validations {
+            let (name, features, aliases) = match validation {
                 Ok(v) => v,
                 Err(err) => {
+                    self.check_blacklist(&seed, &err);
                     error!(error = ?err, %seed, "failed to validate seed host");
                     continue;
                 }
@@ -302,6 +650,11 @@ impl Cluster {
             let node = Arc::new(node);
             self.add_aliases(Arc::clone(&node)).await;
             list.push(node);
+
+            // Only a single node is used, so the first valid seed is all we need.
+            if self.client_policy.single_node_mode {
+                break;
+            }
         }
 
         self.add_nodes_and_aliases(&list).await;
@@ -316,9 +669,15 @@ impl Cluster {
         let mut list = Vec::<Arc<Node>>::new();
 
         for host in hosts {
+            if self.blacklist.contains(&host) {
+                debug!(%host, "skipping blacklisted host");
+                continue;
+            }
+
             let (name, features, aliases) = match node_validator::validate(self, &host).await {
                 Ok(v) => v,
                 Err(err) => {
+                    self.check_blacklist(&host, &err);
                     error!(error = ?err, %host, "node validation failed");
                     continue;
                 }
@@ -351,13 +710,41 @@ impl Cluster {
         Ok(list)
     }
 
+    // Blacklist the host if it was rejected for belonging to a different cluster.
+    fn check_blacklist(&self, host: &Host, err: &NodeError) {
+        if let NodeError::ClusterNameMismatch { expected, got } = err {
+            warn!(
+                %host,
+                expected,
+                got,
+                duration = ?self.client_policy.blacklist_duration,
+                "host belongs to a different cluster, blacklisting it"
+            );
+            self.blacklist.insert(host.clone());
+        }
+    }
+
+    /// Hosts that are currently ignored, as they reported a different cluster name than the one
+    /// configured in the client policy.
+    pub fn blacklisted_hosts(&self) -> Vec<Host> {
+        self.blacklist.hosts()
+    }
+
     async fn create_node(
         &self,
         name: String,
         features: FeatureSupport,
         aliases: Vec<Host>,
     ) -> Result<Node, NetError> {
-        Node::new(Arc::clone(&self.client_policy), name, features, aliases).await
+        Node::new(
+            Arc::clone(&self.client_policy),
+            self.rate_limiter.clone(),
+            self.session.clone(),
+            name,
+            features,
+            aliases,
+        )
+        .await
     }
 
     async fn find_nodes_to_remove(&self, refresh_count: usize) -> Result<Vec<Arc<Node>>, NetError> {
@@ -422,6 +809,10 @@ impl Cluster {
             }
         }
         self.remove_nodes(&nodes_to_remove).await;
+
+        for node in nodes_to_remove {
+            tokio::spawn(async move { node.drain().await });
+        }
     }
 
     async fn add_alias(&self, host: Host, node: Arc<Node>) {
@@ -444,9 +835,13 @@ impl Cluster {
 
     async fn find_node_in_partition_map(&self, filter: Arc<Node>) -> bool {
         let partitions = self.partition_write_map.read().await;
-        (*partitions)
-            .values()
-            .any(|map| map.iter().any(|node| node.name() == filter.name()))
+        (*partitions).values().any(|table| {
+            table
+                .masters()
+                .iter()
+                .flatten()
+                .any(|node| node.name() == filter.name())
+        })
     }
 
     async fn add_nodes(&self, friend_list: &[Arc<Node>]) {
@@ -464,10 +859,9 @@ impl Cluster {
             return;
         }
 
-        self.nodes
-            .write()
-            .await
-            .retain(|node| nodes_to_remove.iter().all(|rem| rem.name() != node.name()));
+        let mut nodes = self.nodes.write().await;
+        nodes.retain(|node| nodes_to_remove.iter().all(|rem| rem.name() != node.name()));
+        self.update_features(&nodes);
     }
 
     pub async fn is_connected(&self) -> bool {
@@ -489,18 +883,74 @@ impl Cluster {
     }
 
     async fn set_nodes(&self, new_nodes: Vec<Arc<Node>>) {
+        self.update_features(&new_nodes);
+
         let mut nodes = self.nodes.write().await;
         *nodes = new_nodes;
     }
 
+    // Booleans are only written natively, if every node is able to store them
+    fn update_features(&self, nodes: &[Arc<Node>]) {
+        self.bool_bins.store(
+            self.client_policy.use_bool_bins
+                && nodes
+                    .iter()
+                    .all(|node| node.supports(FeatureSupport::BOOLEAN)),
+            Ordering::Relaxed,
+        );
+    }
+
+    // Returns true if boolean bins can be written natively
+    pub(crate) fn bool_bins(&self) -> bool {
+        self.bool_bins.load(Ordering::Relaxed)
+    }
+
     pub async fn get_node(&self, partition: &Partition<'_>) -> Option<Arc<Node>> {
+        self.get_read_node(partition, ReadModeSc::default()).await
+    }
+
+    /// Pick the node for reading the partition, which may fall back to a replica if the read mode
+    /// allows it.
+    pub async fn get_read_node(
+        &self,
+        partition: &Partition<'_>,
+        read_mode_sc: ReadModeSc,
+    ) -> Option<Arc<Node>> {
+        let candidates = {
+            let partitions = self.partitions();
+            let partitions = partitions.read().await;
+
+            partitions
+                .get(partition.namespace)
+                .map(|table| table.read_nodes(partition.id as usize, read_mode_sc))
+                .unwrap_or_default()
+        };
+
+        match candidates.len() {
+            0 => self.get_random_node().await,
+            1 => candidates.into_iter().next(),
+            _ => match self.pick_node(&candidates) {
+                Some(node) => Some(node),
+                None => self.get_random_node().await,
+            },
+        }
+    }
+
+    /// Pick the node for retrying a read of the partition, which moves on to the next replica
+    /// with each attempt.
+    pub async fn get_retry_node(
+        &self,
+        partition: &Partition<'_>,
+        attempt: usize,
+        read_mode_sc: ReadModeSc,
+    ) -> Option<Arc<Node>> {
         let node = {
             let partitions = self.partitions();
             let partitions = partitions.read().await;
 
             partitions
                 .get(partition.namespace)
-                .and_then(|node_array| node_array.get(partition.id as usize))
+                .and_then(|table| table.retry_node(partition.id as usize, attempt, read_mode_sc))
                 .cloned()
         };
 
@@ -511,15 +961,46 @@ impl Cluster {
         }
     }
 
+    // Pick an active node according to the configured selection strategy.
     pub async fn get_random_node(&self) -> Option<Arc<Node>> {
-        let node_array = self.nodes().await;
-        let length = node_array.len();
+        self.pick_node(&self.nodes().await)
+    }
 
-        (0..length)
-            .find_map(|_| {
-                let index = (self.node_index.fetch_add(1, Ordering::Relaxed) + 1) % length;
-                node_array.get(index).filter(|node| node.is_active())
-            })
+    // Pick one of the given nodes according to the configured selection strategy, skipping
+    // inactive ones.
+    fn pick_node(&self, nodes: &[Arc<Node>]) -> Option<Arc<Node>> {
+        let length = nodes.len();
+
+        match self.client_policy.node_selection {
+            NodeSelection::RoundRobin => (0..length)
+                .find_map(|_| {
+                    let index = (self.node_index.fetch_add(1, Ordering::Relaxed) + 1) % length;
+                    nodes.get(index).filter(|node| node.is_active())
+                })
+                .map(Arc::clone),
+            NodeSelection::LeastConnections => self.select_node(nodes, Node::connections_in_use),
+            NodeSelection::LowestLatency => {
+                self.select_node(nodes, |node| node.recent_latency().unwrap_or_default())
+            }
+        }
+    }
+
+    // Pick the active node with the lowest key. The search starts at a rotating offset, so ties
+    // are resolved in round-robin order.
+    fn select_node<K: Ord>(
+        &self,
+        nodes: &[Arc<Node>],
+        key: impl Fn(&Node) -> K,
+    ) -> Option<Arc<Node>> {
+        if nodes.is_empty() {
+            return None;
+        }
+
+        let offset = self.node_index.fetch_add(1, Ordering::Relaxed) + 1;
+        (0..nodes.len())
+            .map(|i| &nodes[(offset + i) % nodes.len()])
+            .filter(|node| node.is_active())
+            .min_by_key(|node| key(node))
             .map(Arc::clone)
     }
 
@@ -535,3 +1016,104 @@ impl Cluster {
         self.closed.store(true, Ordering::Relaxed);
     }
 }
+
+// Whether all nodes responded to `cluster-stable` with the same cluster key, rather than an error
+// like `ERROR::unstable-cluster`.
+fn is_same_cluster_key(keys: &[Option<String>]) -> bool {
+    let first = match keys.first() {
+        Some(Some(key)) => key,
+        _ => return false,
+    };
+
+    !first.is_empty()
+        && !first.starts_with("ERROR")
+        && keys.iter().all(|key| key.as_ref() == Some(first))
+}
+
+// Extract the cluster key from the response of the `statistics` info command, which is a list of
+// `KEY=VALUE` pairs separated by semicolons.
+fn parse_cluster_key(statistics: &str) -> Option<String> {
+    statistics
+        .split(';')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(key, _)| *key == "cluster_key")
+        .map(|(_, value)| value.to_owned())
+        .filter(|value| !value.is_empty())
+}
+
+// Resolve all hosts that are given by name into their addresses. Hosts with an IP address are
+// skipped, as they can't change.
+async fn resolve_host_names(hosts: &[Host]) -> Vec<Host> {
+    let mut resolved = Vec::new();
+
+    for host in hosts {
+        if host.name.parse::<IpAddr>().is_ok() {
+            continue;
+        }
+
+        match host.to_socket_addrs().await {
+            Ok(addrs) => {
+                for addr in addrs {
+                    let addr = Host::new(addr.ip().to_string(), addr.port());
+                    if !resolved.contains(&addr) {
+                        resolved.push(addr);
+                    }
+                }
+            }
+            Err(err) => warn!(%host, %err, "failed to resolve seed host"),
+        }
+    }
+
+    resolved
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::IpAddr;
+
+    use super::{is_same_cluster_key, parse_cluster_key, resolve_host_names};
+    use crate::net::Host;
+
+    #[tokio::test]
+    async fn resolve_seed_names() {
+        let resolved = resolve_host_names(&[
+            Host::new("localhost", 3000),
+            Host::new("10.0.0.1", 3000),
+            Host::new("invalid.", 3000),
+        ])
+        .await;
+
+        assert!(!resolved.is_empty());
+        assert!(resolved.iter().all(|host| {
+            host.port == 3000
+                && host
+                    .name
+                    .parse::<IpAddr>()
+                    .map_or(false, |ip| ip.is_loopback())
+        }));
+    }
+
+    #[test]
+    fn cluster_key_agreement() {
+        let key = |k: &str| Some(k.to_owned());
+
+        assert!(is_same_cluster_key(&[key("A1B2C3"), key("A1B2C3")]));
+        assert!(!is_same_cluster_key(&[key("A1B2C3"), key("D4E5F6")]));
+        assert!(!is_same_cluster_key(&[key("A1B2C3"), None]));
+        assert!(!is_same_cluster_key(&[
+            key("ERROR::unstable-cluster"),
+            key("ERROR::unstable-cluster"),
+        ]));
+        assert!(!is_same_cluster_key(&[]));
+    }
+
+    #[test]
+    fn cluster_key_from_statistics() {
+        assert_eq!(
+            Some("9AB83ED5E3D5".to_owned()),
+            parse_cluster_key("cluster_size=3;cluster_key=9AB83ED5E3D5;cluster_integrity=true")
+        );
+        assert_eq!(None, parse_cluster_key("cluster_size=3;cluster_key="));
+        assert_eq!(None, parse_cluster_key("cluster_size=3"));
+    }
+}
@@ -0,0 +1,106 @@
+use std::{borrow::Cow, collections::HashMap, sync::RwLock};
+
+use crate::policies::{BasePolicy, NamespaceDefaults, WritePolicy};
+
+/// Default policies that were registered for specific namespaces.
+///
+/// The policies passed to a command are only replaced if they equal the global defaults, so
+/// explicitly configured policies always take precedence over the namespace defaults.
+#[derive(Debug, Default)]
+pub(crate) struct NamespaceDefaultsMap {
+    namespaces: RwLock<HashMap<String, NamespaceDefaults>>,
+}
+
+impl NamespaceDefaultsMap {
+    /// Register the defaults for a namespace, replacing any previous ones. Passing [`None`]
+    /// removes the defaults instead.
+    pub fn set(&self, namespace: String, defaults: Option<NamespaceDefaults>) {
+        let mut namespaces = self
+            .namespaces
+            .write()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+
+        match defaults {
+            Some(defaults) => namespaces.insert(namespace, defaults),
+            None => namespaces.remove(&namespace),
+        };
+    }
+
+    /// Resolve the policy for read commands on the given namespace.
+    pub fn read_policy<'a>(&self, namespace: &str, policy: &'a BasePolicy) -> Cow<'a, BasePolicy> {
+        self.resolve(namespace, policy, |defaults| defaults.read.as_ref())
+    }
+
+    /// Resolve the policy for write commands on the given namespace.
+    pub fn write_policy<'a>(
+        &self,
+        namespace: &str,
+        policy: &'a WritePolicy,
+    ) -> Cow<'a, WritePolicy> {
+        self.resolve(namespace, policy, |defaults| defaults.write.as_ref())
+    }
+
+    fn resolve<'a, P>(
+        &self,
+        namespace: &str,
+        policy: &'a P,
+        select: impl FnOnce(&NamespaceDefaults) -> Option<&P>,
+    ) -> Cow<'a, P>
+    where
+        P: Clone + Default + PartialEq,
+    {
+        let namespaces = self
+            .namespaces
+            .read()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+
+        match namespaces.get(namespace).and_then(select) {
+            Some(default) if *policy == P::default() => Cow::Owned(default.clone()),
+            _ => Cow::Borrowed(policy),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+
+    use super::NamespaceDefaultsMap;
+    use crate::policies::{CommitLevel, Expiration, NamespaceDefaults, WritePolicy};
+
+    #[test]
+    fn replaces_default_policies() {
+        let map = NamespaceDefaultsMap::default();
+        let cache = WritePolicy::builder()
+            .commit_master()
+            .expiration(Expiration::Seconds(3600))
+            .build();
+        map.set(
+            "cache".to_owned(),
+            Some(NamespaceDefaults {
+                write: Some(cache.clone()),
+                ..NamespaceDefaults::default()
+            }),
+        );
+
+        let default = WritePolicy::default();
+        assert_eq!(cache, *map.write_policy("cache", &default));
+        assert!(matches!(
+            map.write_policy("test", &default),
+            Cow::Borrowed(_)
+        ));
+        assert!(matches!(
+            map.read_policy("cache", &default.base_policy),
+            Cow::Borrowed(_)
+        ));
+
+        let explicit = WritePolicy::new(0, Expiration::Never);
+        assert_eq!(explicit, *map.write_policy("cache", &explicit));
+
+        map.set("cache".to_owned(), None);
+        assert_eq!(
+            CommitLevel::All,
+            map.write_policy("cache", &default).commit_level
+        );
+    }
+}
@@ -1,28 +1,40 @@
 use std::{
     collections::{HashMap, HashSet},
     sync::{
-        atomic::{AtomicBool, AtomicIsize, AtomicUsize, Ordering},
+        atomic::{AtomicBool, AtomicIsize, AtomicU64, AtomicUsize, Ordering},
         Arc,
     },
+    time::Duration,
 };
 
-use tokio::sync::{RwLock, RwLockReadGuard};
+use tokio::{
+    sync::{Mutex, RwLock, RwLockReadGuard},
+    time::Instant,
+};
+use tracing::warn;
 
-use super::{ClusterError, NodeError, NodeRefreshError, Result};
+use super::{rate_limiter::RateLimiter, ClusterError, NodeError, NodeRefreshError, Result};
 use crate::{
     commands::{
-        self,
-        info_cmds::{CLUSTER_NAME, NODE, PARTITION_GENERATION, SERVICES, SERVICES_ALTERNATE},
+        self, buffer,
+        info_cmds::{
+            CLUSTER_NAME, NODE, PARTITIONS as PARTITION_COUNT, PARTITION_GENERATION, SERVICES,
+            SERVICES_ALTERNATE,
+        },
         Info,
     },
-    net::{Host, NetError, Pool, PooledConnection},
-    policies::ClientPolicy,
+    net::{Host, NetError, Pipeline, Pool, PoolStats, PooledConnection, Session},
+    policies::{ClientPolicy, InfoPolicy},
+    value::ReadOptions,
 };
 
 pub const PARTITIONS: u32 = 4096;
 
-/// The node instance holding connections and node settings.
-/// Exposed for usage in the sync client interface.
+/// A single server node of the cluster, holding the connections and settings for it.
+///
+/// Nodes can be looked up through [`Client::nodes`](crate::Client::nodes) or
+/// [`Client::node_for_key`](crate::Client::node_for_key), which allows to implement custom routing
+/// or per-node health checks. Connection handling and cluster tending stay internal.
 #[derive(Debug)]
 pub struct Node {
     client_policy: Arc<ClientPolicy>,
@@ -30,16 +42,27 @@ pub struct Node {
     aliases: RwLock<Vec<Host>>,
 
     connection_pool: Pool,
+    scan_pool: Option<Pool>,
+    pipeline: Option<Mutex<Option<Arc<Pipeline>>>>,
     failures: AtomicUsize,
+    // Moving average of the command latency in microseconds, or zero if nothing was measured yet.
+    latency: AtomicU64,
+
+    cluster_rate_limiter: Option<Arc<RateLimiter>>,
+    rate_limiter: Option<RateLimiter>,
+
+    session: Option<Arc<Session>>,
 
     partition_generation: AtomicIsize,
+    partition_count: AtomicUsize,
     reference_count: AtomicUsize,
     active: AtomicBool,
 
-    _features: FeatureSupport,
+    features: FeatureSupport,
 }
 
 bitflags::bitflags! {
+    /// Optional server features, as announced by a node through the `features` info command.
     #[derive(Clone, Copy, Debug, Default)]
     pub struct FeatureSupport: u32 {
         const BATCH_ANY = 1 << 0;
@@ -63,6 +86,9 @@ bitflags::bitflags! {
         const REPLICAS_MAX = 1 << 18;
         const TRUNCATE_NAMESPACE = 1 << 19;
         const UDF = 1 << 20;
+        const BOOLEAN = 1 << 21;
+        /// Bin names of up to 255 bytes, instead of 15.
+        const LONG_BIN_NAMES = 1 << 22;
     }
 }
 
@@ -92,6 +118,8 @@ impl From<&str> for FeatureSupport {
                 "replicas-max" => Self::REPLICAS_MAX,
                 "truncate-namespace" => Self::TRUNCATE_NAMESPACE,
                 "udf" => Self::UDF,
+                "boolean" => Self::BOOLEAN,
+                "long-bin-names" => Self::LONG_BIN_NAMES,
                 _ => continue,
             };
         }
@@ -101,52 +129,111 @@ impl From<&str> for FeatureSupport {
 }
 
 impl Node {
-    pub async fn new(
+    pub(crate) async fn new(
         client_policy: Arc<ClientPolicy>,
+        cluster_rate_limiter: Option<Arc<RateLimiter>>,
+        session: Option<Arc<Session>>,
         name: String,
         features: FeatureSupport,
         aliases: Vec<Host>,
     ) -> Result<Self, NetError> {
         Ok(Self {
-            connection_pool: Pool::new(aliases[0].clone(), Arc::clone(&client_policy)).await?,
+            connection_pool: Pool::new(
+                aliases[0].clone(),
+                Arc::clone(&client_policy),
+                session.clone(),
+                client_policy.max_conns_per_node,
+            )
+            .await?,
+            scan_pool: match client_policy.max_scan_conns_per_node {
+                Some(max_size) => Some(
+                    Pool::new(
+                        aliases[0].clone(),
+                        Arc::clone(&client_policy),
+                        session.clone(),
+                        max_size,
+                    )
+                    .await?,
+                ),
+                None => None,
+            },
+            pipeline: (client_policy.pipelining && features.contains(FeatureSupport::PIPELINING))
+                .then(|| Mutex::new(None)),
+            cluster_rate_limiter,
+            rate_limiter: RateLimiter::new(client_policy.max_commands_per_second_per_node),
             client_policy,
+            session,
             name,
             aliases: RwLock::new(aliases),
             failures: AtomicUsize::new(0),
+            latency: AtomicU64::new(0),
             partition_generation: AtomicIsize::new(-1),
+            partition_count: AtomicUsize::new(PARTITIONS as usize),
             reference_count: AtomicUsize::new(0),
             active: AtomicBool::new(true),
-            _features: features,
+            features,
         })
     }
 
-    // Returns the Node name
+    /// Returns the name of the node, which is unique within the cluster.
     pub fn name(&self) -> &str {
         &self.name
     }
 
+    /// Returns the address of the host that the node is connected through.
+    pub async fn address(&self) -> Host {
+        self.aliases.read().await[0].clone()
+    }
+
+    /// Returns all features that the node announced support for.
+    pub fn features(&self) -> FeatureSupport {
+        self.features
+    }
+
+    /// Returns true if the node announced support for all the given features.
+    pub fn supports(&self, features: FeatureSupport) -> bool {
+        self.features.contains(features)
+    }
+
+    // Returns the maximum length of bin names that the node accepts
+    pub(crate) fn max_bin_name_len(&self) -> usize {
+        if self.supports(FeatureSupport::LONG_BIN_NAMES) {
+            buffer::MAX_LONG_BIN_NAME_LEN
+        } else {
+            buffer::MAX_BIN_NAME_LEN
+        }
+    }
+
+    // Returns the options for deserializing values out of responses
+    pub(crate) fn read_options(&self) -> ReadOptions {
+        ReadOptions {
+            keep_unknown: self.client_policy.keep_unknown_particles,
+            limits: self.client_policy.decode_limits,
+            max_bin_size: self.client_policy.max_bin_size,
+            map_pairs: false,
+        }
+    }
+
     // Returns the reference count
-    pub fn reference_count(&self) -> usize {
+    pub(crate) fn reference_count(&self) -> usize {
         self.reference_count.load(Ordering::Relaxed)
     }
 
     // Refresh the node
-    pub async fn refresh(
+    pub(crate) async fn refresh(
         &self,
         current_aliases: &HashMap<Host, Arc<Self>>,
     ) -> Result<HashSet<Host>, NodeRefreshError> {
         self.reference_count.store(0, Ordering::Relaxed);
 
-        let commands = vec![
-            NODE,
-            CLUSTER_NAME,
-            PARTITION_GENERATION,
-            if self.client_policy.use_services_alternate {
+        let mut commands = vec![NODE, CLUSTER_NAME, PARTITION_GENERATION, PARTITION_COUNT];
+        if !self.client_policy.single_node_mode {
+            commands.push(if self.client_policy.use_services_alternate {
                 SERVICES_ALTERNATE
             } else {
                 SERVICES
-            },
-        ];
+            });
+        }
 
         let mut conn = self
             .get_connection()
@@ -176,6 +263,8 @@ impl Node {
     fn validate_node(&self, info_map: &mut Info) -> Result<(), NodeError> {
         match info_map.node.take() {
             None => return Err(NodeError::MissingNodeName),
+            // Behind a load balancer, each connection may end up on a different node.
+            Some(_) if self.client_policy.single_node_mode => {}
             Some(info_name) if info_name == self.name => {}
             Some(info_name) => {
                 self.inactivate();
@@ -192,9 +281,9 @@ impl Node {
                 Some(info_name) if info_name == expected => {}
                 Some(info_name) => {
                     self.inactivate();
-                    return Err(NodeError::NameMismatch {
+                    return Err(NodeError::ClusterNameMismatch {
                         expected: expected.to_owned(),
-                        got: info_name.clone(),
+                        got: info_name,
                     });
                 }
             }
@@ -208,6 +297,10 @@ impl Node {
         current_aliases: &HashMap<Host, Arc<Self>>,
         info_map: &mut Info,
     ) -> Result<HashSet<Host>> {
+        if self.client_policy.single_node_mode {
+            return Ok(HashSet::new());
+        }
+
         let friends = if self.client_policy.use_services_alternate {
             info_map.services_alternate.take()
         } else {
@@ -244,16 +337,68 @@ impl Node {
             .ok_or(ClusterError::MissingPartitionGeneration)?;
         self.partition_generation.store(gen, Ordering::Relaxed);
 
+        // Older servers may not report the partition count, so the default is kept.
+        if let Some(count) = info_map.partitions {
+            let previous = self.partition_count.swap(count, Ordering::Relaxed);
+            if count != previous && count != PARTITIONS as usize {
+                warn!(
+                    node = self.name(),
+                    count, "node reports an unsupported amount of partitions"
+                );
+            }
+        }
+
         Ok(())
     }
 
+    // Wait until the configured rate limits allow sending another command to the node
+    pub(crate) async fn acquire_permit(&self) {
+        if let Some(limiter) = &self.cluster_rate_limiter {
+            limiter.acquire().await;
+        }
+        if let Some(limiter) = &self.rate_limiter {
+            limiter.acquire().await;
+        }
+    }
+
     // Get a connection to the node from the connection pool
-    pub async fn get_connection(&self) -> Result<PooledConnection<'_>, NetError> {
+    pub(crate) async fn get_connection(&self) -> Result<PooledConnection<'_>, NetError> {
         self.connection_pool.get().await
     }
 
+    // Get a connection for a scan, from the separate scan pool if it's enabled
+    pub(crate) async fn get_scan_connection(&self) -> Result<PooledConnection<'_>, NetError> {
+        self.scan_pool
+            .as_ref()
+            .unwrap_or(&self.connection_pool)
+            .get()
+            .await
+    }
+
+    // Get the shared pipelined connection of the node, if pipelining is enabled and supported.
+    // A new pipeline is created if the previous one broke.
+    pub(crate) async fn get_pipeline(&self) -> Option<Arc<Pipeline>> {
+        if self.connection_pool.is_draining() {
+            return None;
+        }
+
+        let mut pipeline = self.pipeline.as_ref()?.lock().await;
+        if let Some(pipeline) = pipeline.as_ref().filter(|pipeline| pipeline.active()) {
+            return Some(Arc::clone(pipeline));
+        }
+
+        let address = self.aliases.read().await[0].address();
+        match Pipeline::new(&address, &self.client_policy, self.session.as_deref()).await {
+            Ok(new) => Some(Arc::clone(pipeline.insert(Arc::new(new)))),
+            Err(err) => {
+                warn!(node = %self.name, %err, "failed to open pipelined connection");
+                None
+            }
+        }
+    }
+
     // Amount of failures
-    pub fn failures(&self) -> usize {
+    pub(crate) fn failures(&self) -> usize {
         self.failures.load(Ordering::Relaxed)
     }
 
@@ -266,40 +411,191 @@ impl Node {
         self.failures.fetch_add(1, Ordering::Relaxed)
     }
 
+    /// Returns the recent latency of commands on this node, as a moving average over the round
+    /// trip times, or [`None`] if no command finished yet.
+    #[must_use]
+    pub fn recent_latency(&self) -> Option<Duration> {
+        match self.latency.load(Ordering::Relaxed) {
+            0 => None,
+            micros => Some(Duration::from_micros(micros)),
+        }
+    }
+
+    // Add the round trip time of a command to the moving average, weighting the new sample with
+    // an eighth.
+    pub(crate) fn record_latency(&self, elapsed: Duration) {
+        let sample = u64::try_from(elapsed.as_micros())
+            .unwrap_or(u64::MAX)
+            .max(1);
+        self.latency
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |average| {
+                Some(match average {
+                    0 => sample,
+                    average => (average - average / 8 + sample / 8).max(1),
+                })
+            })
+            .ok();
+    }
+
+    // Amount of connections that are currently in use
+    pub(crate) fn connections_in_use(&self) -> u32 {
+        let stats = self.pool_stats();
+        stats.connections.saturating_sub(stats.idle_connections)
+    }
+
     fn inactivate(&self) {
         self.active.store(false, Ordering::Relaxed);
     }
 
-    // Returns true if the node is active
+    // Gracefully shut down the node after it was removed from the cluster. No new connections are
+    // handed out, while commands that are still running get the chance to finish within the
+    // configured drain timeout, before all sockets are closed.
+    pub(crate) async fn drain(&self) {
+        self.inactivate();
+
+        if let Some(pipeline) = &self.pipeline {
+            pipeline.lock().await.take();
+        }
+
+        // Both pools share the drain timeout, so the main pool only waits for what's left of it
+        // after the scan pool is drained. Its commands keep finishing in the meantime.
+        let deadline = Instant::now() + self.client_policy.drain_timeout;
+        let scans_drained = match &self.scan_pool {
+            Some(pool) => pool.drain(self.client_policy.drain_timeout).await,
+            None => true,
+        };
+
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if !self.connection_pool.drain(remaining).await || !scans_drained {
+            let stats = self.pool_stats();
+            warn!(
+                node = %self.name,
+                in_use = stats.connections - stats.idle_connections,
+                "drain timeout elapsed with connections still in use"
+            );
+        }
+    }
+
+    /// Returns true if the node is active. A node becomes inactive when a different node or
+    /// cluster answers on its address, and is removed from the cluster afterwards.
     pub fn is_active(&self) -> bool {
         self.active.load(Ordering::Relaxed)
     }
 
+    /// Returns the current state of the node's connection pool, including the amount of
+    /// connections that were abandoned while a command was still running.
+    #[must_use]
+    pub fn pool_stats(&self) -> PoolStats {
+        self.connection_pool.stats()
+    }
+
+    /// Returns the current state of the node's separate connection pool for scans, if enabled with
+    /// [`ClientPolicy::max_scan_conns_per_node`].
+    #[must_use]
+    pub fn scan_pool_stats(&self) -> Option<PoolStats> {
+        self.scan_pool.as_ref().map(Pool::stats)
+    }
+
     // Get a list of aliases to the node
-    pub async fn aliases(&self) -> RwLockReadGuard<'_, Vec<Host>> {
+    pub(crate) async fn aliases(&self) -> RwLockReadGuard<'_, Vec<Host>> {
         self.aliases.read().await
     }
 
     // Add an alias to the node
-    pub async fn add_alias(&self, alias: Host) {
+    pub(crate) async fn add_alias(&self, alias: Host) {
         self.aliases.write().await.push(alias);
         self.reference_count.fetch_add(1, Ordering::Relaxed);
     }
 
-    // Send info commands to this node
-    pub async fn info(&self, commands: &[&str]) -> Result<HashMap<String, String>> {
-        let mut conn = self.get_connection().await?;
-        match commands::info_raw(&mut conn, commands).await {
-            Ok(info) => Ok(info),
-            Err(e) => {
-                conn.close().await;
-                Err(e.into())
+    /// Send raw info commands to this node and return the responses by command name. See
+    /// <https://docs.aerospike.com/reference/info> for the list of available commands.
+    pub async fn info(
+        &self,
+        policy: &InfoPolicy,
+        commands: &[&str],
+    ) -> Result<HashMap<String, String>> {
+        let request = async {
+            let mut conn = self.get_connection().await?;
+            conn.start_command("info");
+            let start = Instant::now();
+            match commands::info_raw(&mut conn, commands).await {
+                Ok(info) => {
+                    conn.finish_command();
+                    self.record_latency(start.elapsed());
+                    Ok(info)
+                }
+                Err(e) => {
+                    conn.close().await;
+                    Err(e.into())
+                }
             }
+        };
+
+        if policy.timeout.is_zero() {
+            request.await
+        } else {
+            // A connection that is still waiting for the response is discarded on timeout.
+            tokio::time::timeout(policy.timeout, request)
+                .await
+                .unwrap_or(Err(ClusterError::Timeout))
         }
     }
 
+    /// Abort the scan or query with the given transaction ID on this node. Returns whether the
+    /// node knew about the job.
+    pub(crate) async fn abort_job(&self, policy: &InfoPolicy, trans_id: u64) -> Result<bool> {
+        let cmd = if self.supports(FeatureSupport::QUERY_SHOW) {
+            format!("query-abort:trid={trans_id}")
+        } else {
+            format!("scan-abort:id={trans_id}")
+        };
+
+        let response = self.info(policy, &[&cmd]).await?;
+        Ok(response
+            .values()
+            .next()
+            .map_or(false, |v| v.eq_ignore_ascii_case("OK")))
+    }
+
     // Get the partition generation
-    pub fn partition_generation(&self) -> isize {
+    pub(crate) fn partition_generation(&self) -> isize {
         self.partition_generation.load(Ordering::Relaxed)
     }
+
+    // Get the amount of partitions per namespace, as last reported by the node
+    pub(crate) fn partition_count(&self) -> usize {
+        self.partition_count.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, time::Duration};
+
+    use super::{FeatureSupport, Node};
+    use crate::{net::Host, policies::ClientPolicy};
+
+    #[tokio::test]
+    async fn latency_average() {
+        let node = Node::new(
+            Arc::new(ClientPolicy::default()),
+            None,
+            None,
+            "A".to_owned(),
+            FeatureSupport::empty(),
+            vec![Host::new("127.0.0.1", 3000)],
+        )
+        .await
+        .unwrap();
+        assert_eq!(node.recent_latency(), None);
+
+        node.record_latency(Duration::from_millis(8));
+        assert_eq!(node.recent_latency(), Some(Duration::from_millis(8)));
+
+        node.record_latency(Duration::from_millis(16));
+        assert_eq!(node.recent_latency(), Some(Duration::from_millis(9)));
+
+        node.record_latency(Duration::ZERO);
+        assert!(node.recent_latency().is_some());
+    }
 }
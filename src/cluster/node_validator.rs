@@ -1,4 +1,7 @@
-use tracing::debug;
+use std::sync::Arc;
+
+use tokio::task::JoinSet;
+use tracing::{debug, error};
 
 use super::{node::FeatureSupport, Cluster, NodeError, Result};
 use crate::{
@@ -6,19 +9,75 @@ use crate::{
         self,
         info_cmds::{CLUSTER_NAME, FEATURES, NODE},
     },
-    net::{Connection, Host},
+    net::{Connection, Host, Session},
     policies::ClientPolicy,
 };
 
-pub async fn validate(
-    cluster: &Cluster,
+/// Maximum amount of hosts that are validated at the same time.
+const MAX_CONCURRENT_VALIDATIONS: usize = 16;
+
+type Validation = Result<(String, FeatureSupport, Vec<Host>), NodeError>;
+
+pub async fn validate(cluster: &Cluster, host: &Host) -> Validation {
+    validate_host(cluster.client_policy(), cluster.session(), host).await
+}
+
+/// Validate all hosts concurrently, so unreachable hosts don't hold up the others. Each
+/// validation is limited by the [`ClientPolicy::timeout`], if set.
+///
+/// The results are returned in the same order as the hosts.
+pub async fn validate_all(
+    policy: Arc<ClientPolicy>,
+    session: Option<Arc<Session>>,
+    hosts: Vec<Host>,
+) -> Vec<(Host, Validation)> {
+    let mut results = Vec::with_capacity(hosts.len());
+    results.resize_with(hosts.len(), || None);
+
+    let mut hosts = hosts.into_iter().enumerate();
+    let mut tasks = JoinSet::new();
+
+    loop {
+        while tasks.len() < MAX_CONCURRENT_VALIDATIONS {
+            let (index, host) = match hosts.next() {
+                Some(next) => next,
+                None => break,
+            };
+            let policy = Arc::clone(&policy);
+            let session = session.clone();
+
+            tasks.spawn(async move {
+                let validation = validate_host(&policy, session.as_deref(), &host);
+                let result = match policy.timeout {
+                    Some(timeout) => tokio::time::timeout(timeout, validation)
+                        .await
+                        .unwrap_or_else(|_| Err(NodeError::Timeout { host: host.clone() })),
+                    None => validation.await,
+                };
+                (index, host, result)
+            });
+        }
+
+        match tasks.join_next().await {
+            Some(Ok((index, host, result))) => results[index] = Some((host, result)),
+            Some(Err(err)) => error!(?err, "host validation task failed"),
+            None => break,
+        }
+    }
+
+    results.into_iter().flatten().collect()
+}
+
+async fn validate_host(
+    policy: &ClientPolicy,
+    session: Option<&Session>,
     host: &Host,
-) -> Result<(String, FeatureSupport, Vec<Host>), NodeError> {
+) -> Validation {
     let aliases = resolve_aliases(host).await?;
     let mut last_err = None;
 
     for alias in &aliases {
-        match validate_alias(cluster.client_policy(), cluster.name(), alias).await {
+        match validate_alias(policy, session, policy.cluster_name.as_deref(), alias).await {
             Ok((name, features)) => return Ok((name, features, aliases)),
             Err(err) => {
                 debug!(%alias, ?err, "alias validation failed");
@@ -47,10 +106,11 @@ async fn resolve_aliases(host: &Host) -> Result<Vec<Host>, NodeError> {
 
 async fn validate_alias(
     policy: &ClientPolicy,
+    session: Option<&Session>,
     cluster_name: Option<&str>,
     alias: &Host,
 ) -> Result<(String, FeatureSupport), NodeError> {
-    let mut conn = Connection::new(&alias.address(), policy).await?;
+    let mut conn = Connection::new(&alias.address(), policy, session).await?;
     let info_map = commands::info_typed(&mut conn, &[NODE, CLUSTER_NAME, FEATURES]).await?;
 
     if let Some(cluster_name) = cluster_name {
@@ -58,7 +118,7 @@ async fn validate_alias(
             None => return Err(NodeError::MissingClusterName),
             Some(info_name) if info_name == cluster_name => {}
             Some(info_name) => {
-                return Err(NodeError::NameMismatch {
+                return Err(NodeError::ClusterNameMismatch {
                     expected: cluster_name.to_owned(),
                     got: info_name,
                 })
@@ -75,3 +135,42 @@ async fn validate_alias(
 
     Ok((node_name, features))
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, time::Duration};
+
+    use tokio::{net::TcpListener, time::Instant};
+
+    use super::validate_all;
+    use crate::{cluster::NodeError, net::Host, policies::ClientPolicy};
+
+    #[tokio::test]
+    async fn validate_concurrently() {
+        // The listeners accept connections, but never answer the info commands.
+        let mut listeners = Vec::new();
+        let mut hosts = Vec::new();
+        for _ in 0..4 {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            hosts.push(Host::new(
+                "127.0.0.1",
+                listener.local_addr().unwrap().port(),
+            ));
+            listeners.push(listener);
+        }
+
+        let policy = ClientPolicy {
+            timeout: Some(Duration::from_millis(200)),
+            ..ClientPolicy::default()
+        };
+        let start = Instant::now();
+        let results = validate_all(Arc::new(policy), None, hosts.clone()).await;
+
+        assert!(start.elapsed() < Duration::from_millis(600));
+        assert_eq!(hosts.len(), results.len());
+        for ((host, result), expected) in results.iter().zip(&hosts) {
+            assert_eq!(expected, host);
+            assert!(matches!(result, Err(NodeError::Timeout { .. })));
+        }
+    }
+}
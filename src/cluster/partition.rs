@@ -1,4 +1,14 @@
-use crate::{cluster::node, Key};
+use std::{
+    collections::{BTreeMap, HashMap},
+    ops::RangeInclusive,
+    sync::Arc,
+};
+
+use super::{node, Node};
+use crate::{policies::ReadModeSc, Key};
+
+/// Partition tables of all known namespaces.
+pub(crate) type PartitionMap = HashMap<String, PartitionTable>;
 
 // Validates a Database server node
 #[derive(Clone, Debug, Eq, PartialEq)]
@@ -11,11 +21,521 @@ impl<'a> From<&'a Key> for Partition<'a> {
     fn from(value: &'a Key) -> Self {
         Self {
             namespace: &value.namespace,
-            id: {
-                let mut buf = [0; 4];
-                buf.copy_from_slice(&value.digest()[0..4]);
-                u32::from_le_bytes(buf) % node::PARTITIONS
-            },
+            id: value.partition_id(),
+        }
+    }
+}
+
+/// Ownership state of the partitions of a single namespace, as known to the client.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PartitionHealth {
+    /// Name of the namespace.
+    pub namespace: String,
+    /// Total amount of partitions in the namespace.
+    pub partitions: usize,
+    /// Amount of partitions that are still assigned to a node that left the cluster or became
+    /// inactive. Commands for these partitions fail until the partition map is refreshed.
+    pub unowned: usize,
+}
+
+impl PartitionHealth {
+    /// Whether all partitions are owned by an active node.
+    #[must_use]
+    pub const fn is_healthy(&self) -> bool {
+        self.unowned == 0
+    }
+}
+
+/// Nodes that hold a single partition of a namespace, as known to the client.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct PartitionOwners {
+    /// Name of the master node, if it's known.
+    pub master: Option<String>,
+    /// Names of the replica nodes, in replica order. Replicas whose owner isn't known are left
+    /// out.
+    pub replicas: Vec<String>,
+}
+
+/// Master changes of a single namespace, between two versions of the partition map.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub(crate) struct PartitionDiff {
+    /// Name of the namespace.
+    pub namespace: String,
+    /// Ranges of partition IDs that moved to a different master.
+    pub moved: Vec<RangeInclusive<usize>>,
+    /// Amount of moved partitions that each node became the master of.
+    pub gained: BTreeMap<String, usize>,
+    /// Amount of moved partitions that each node isn't the master of anymore.
+    pub lost: BTreeMap<String, usize>,
+}
+
+impl PartitionDiff {
+    /// Total amount of partitions that moved to a different master.
+    pub fn changes(&self) -> usize {
+        self.moved
+            .iter()
+            .map(|range| range.end() - range.start() + 1)
+            .sum()
+    }
+}
+
+/// Compare the partition masters of two partition maps, returning the changes of each namespace
+/// that has any, sorted by namespace.
+pub(crate) fn diff(old: &PartitionMap, new: &PartitionMap) -> Vec<PartitionDiff> {
+    fn name(owners: &[Option<Arc<Node>>], id: usize) -> Option<&str> {
+        owners.get(id)?.as_ref().map(|node| node.name())
+    }
+
+    let mut namespaces = old.keys().chain(new.keys()).collect::<Vec<_>>();
+    namespaces.sort_unstable();
+    namespaces.dedup();
+
+    namespaces
+        .into_iter()
+        .filter_map(|namespace| {
+            let old = old.get(namespace).map_or(&[][..], PartitionTable::masters);
+            let new = new.get(namespace).map_or(&[][..], PartitionTable::masters);
+            let mut diff = PartitionDiff {
+                namespace: namespace.clone(),
+                ..PartitionDiff::default()
+            };
+
+            for id in 0..old.len().max(new.len()) {
+                let (from, to) = (name(old, id), name(new, id));
+                if from == to {
+                    continue;
+                }
+
+                match diff.moved.last_mut() {
+                    Some(range) if range.end() + 1 == id => *range = *range.start()..=id,
+                    _ => diff.moved.push(id..=id),
+                }
+                if let Some(from) = from {
+                    *diff.lost.entry(from.to_owned()).or_default() += 1;
+                }
+                if let Some(to) = to {
+                    *diff.gained.entry(to.to_owned()).or_default() += 1;
+                }
+            }
+
+            (!diff.moved.is_empty()).then_some(diff)
+        })
+        .collect()
+}
+
+/// Owners of all partitions of a single namespace.
+#[derive(Clone, Debug)]
+pub(crate) struct PartitionTable {
+    /// Nodes holding each partition, first indexed by replica and then by partition ID. The first
+    /// replica is the master, and the amount of replicas follows the replication factor reported
+    /// by the nodes.
+    pub replicas: Vec<Vec<Option<Arc<Node>>>>,
+    /// Regime of each partition, as reported by its master. Only strong consistency namespaces
+    /// use regimes, otherwise they're always _zero_.
+    pub regimes: Vec<u32>,
+}
+
+impl PartitionTable {
+    /// Create a table for a newly discovered namespace, where no partitions are owned yet.
+    pub fn new() -> Self {
+        Self {
+            replicas: Vec::new(),
+            regimes: vec![0; node::PARTITIONS as usize],
+        }
+    }
+
+    /// Total amount of partitions in the namespace.
+    pub fn len(&self) -> usize {
+        self.regimes.len()
+    }
+
+    /// Master node of each partition.
+    pub fn masters(&self) -> &[Option<Arc<Node>>] {
+        self.replicas.first().map_or(&[], Vec::as_slice)
+    }
+
+    /// Master node of the given partition, if known.
+    pub fn master(&self, id: usize) -> Option<&Arc<Node>> {
+        self.masters().get(id)?.as_ref()
+    }
+
+    /// All known nodes that hold the given partition, in replica order starting with the master.
+    /// Replicas whose owner isn't known are left out.
+    pub fn owners(&self, id: usize) -> Vec<&Arc<Node>> {
+        self.replicas
+            .iter()
+            .filter_map(|owners| owners.get(id)?.as_ref())
+            .collect()
+    }
+
+    /// Names of the master and replica nodes of the given partition.
+    pub fn owner_names(&self, id: usize) -> PartitionOwners {
+        PartitionOwners {
+            master: self.master(id).map(|node| node.name().to_owned()),
+            replicas: self
+                .replicas
+                .iter()
+                .skip(1)
+                .filter_map(|owners| owners.get(id)?.as_ref())
+                .map(|node| node.name().to_owned())
+                .collect(),
+        }
+    }
+
+    /// Candidate nodes to read the given partition from. Reads go to the master, which holds the
+    /// partition in its current regime. Only if relaxed reads are allowed and the master is
+    /// unknown, all known replicas are candidates instead, and the caller picks one of them.
+    pub fn read_nodes(&self, id: usize, read_mode_sc: ReadModeSc) -> Vec<Arc<Node>> {
+        if let Some(master) = self.master(id) {
+            return vec![Arc::clone(master)];
+        }
+        if !read_mode_sc.allows_replica() {
+            return Vec::new();
+        }
+
+        self.replicas
+            .iter()
+            .skip(1)
+            .filter_map(|owners| owners.get(id)?.clone())
+            .collect()
+    }
+
+    /// Node to retry a read of the given partition on, for the given attempt, counting from _zero_
+    /// for the first try. Attempts go through the known replicas in order, starting with the
+    /// master. Strong consistency namespaces stay with the master, unless the read mode allows
+    /// replicas.
+    pub fn retry_node(
+        &self,
+        id: usize,
+        attempt: usize,
+        read_mode_sc: ReadModeSc,
+    ) -> Option<&Arc<Node>> {
+        let strong_consistency = self.regimes.get(id).map_or(false, |regime| *regime != 0);
+        if strong_consistency && !read_mode_sc.allows_replica() {
+            return self.master(id);
+        }
+
+        let owners = self.owners(id);
+        if owners.is_empty() {
+            return None;
+        }
+
+        Some(owners[attempt % owners.len()])
+    }
+
+    /// Assign all partitions in the bitmaps to the node, one bitmap for each replica, unless the
+    /// partition is already known with a newer regime. During ownership changes in strong
+    /// consistency namespaces, nodes can briefly report partitions that already moved on, which
+    /// must not replace the new owner.
+    ///
+    /// Replicas beyond the reported ones are released by the node, which happens when the
+    /// replication factor is lowered.
+    pub fn update(&mut self, node: &Arc<Node>, regime: u32, bitmaps: &[Vec<u8>]) {
+        let partitions = self.len();
+        if self.replicas.len() < bitmaps.len() {
+            self.replicas.resize(bitmaps.len(), vec![None; partitions]);
         }
+
+        for (replica, bitmap) in bitmaps.iter().enumerate() {
+            let owners = self.replicas[replica].iter_mut().zip(&mut self.regimes);
+
+            for (idx, (owner, current)) in owners.enumerate() {
+                let owned = bitmap
+                    .get(idx >> 3)
+                    .map_or(false, |byte| byte & (0x80 >> (idx & 7)) != 0);
+
+                if owned && regime >= *current {
+                    *owner = Some(Arc::clone(node));
+                    if replica == 0 {
+                        *current = regime;
+                    }
+                }
+            }
+        }
+
+        self.release(node, bitmaps.len());
+    }
+
+    /// Release all partitions of the node, starting from the given replica. Replicas that aren't
+    /// held by any node anymore are removed.
+    ///
+    /// Returns `true` if no partitions are owned anymore, which means the namespace doesn't exist
+    /// on the cluster.
+    pub fn release(&mut self, node: &Node, from_replica: usize) -> bool {
+        for owners in self.replicas.iter_mut().skip(from_replica) {
+            for owner in owners {
+                if owner
+                    .as_ref()
+                    .map_or(false, |owner| owner.name() == node.name())
+                {
+                    *owner = None;
+                }
+            }
+        }
+
+        while self
+            .replicas
+            .last()
+            .map_or(false, |owners| owners.iter().all(Option::is_none))
+        {
+            self.replicas.pop();
+        }
+
+        self.replicas.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::{BTreeMap, HashMap},
+        sync::Arc,
+    };
+
+    use super::{diff, PartitionDiff, PartitionOwners, PartitionTable};
+    use crate::{
+        cluster::Node,
+        net::Host,
+        policies::{ClientPolicy, ReadModeSc},
+        FeatureSupport,
+    };
+
+    async fn node(name: &str) -> Arc<Node> {
+        let node = Node::new(
+            Arc::new(ClientPolicy::default()),
+            None,
+            None,
+            name.to_owned(),
+            FeatureSupport::empty(),
+            vec![Host::new("127.0.0.1", 3000)],
+        )
+        .await
+        .unwrap();
+        Arc::new(node)
+    }
+
+    fn names(owners: &[Option<Arc<Node>>]) -> Vec<&str> {
+        owners
+            .iter()
+            .map(|owner| owner.as_ref().map_or("-", |node| node.name()))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn update_by_regime() {
+        let (a, b) = (node("A").await, node("B").await);
+        let mut table = PartitionTable::new();
+        table.update(&a, 0, &[vec![0xff]]);
+
+        // B takes over partitions 0 and 2 in a newer regime.
+        table.update(&b, 2, &[vec![0b1010_0000]]);
+        assert_eq!(["B", "A", "B"], names(&table.masters()[..3])[..]);
+        assert_eq!([2, 0, 2], table.regimes[..3]);
+
+        // A still reports partition 0 with an outdated regime.
+        table.update(&a, 1, &[vec![0b1100_0000]]);
+        assert_eq!(["B", "A", "B"], names(&table.masters()[..3])[..]);
+        assert_eq!([2, 1, 2], table.regimes[..3]);
+    }
+
+    #[tokio::test]
+    async fn read_node_by_mode() {
+        let (a, b, c) = (node("A").await, node("B").await, node("C").await);
+        let mut table = PartitionTable::new();
+        table.update(&a, 0, &[vec![0b1000_0000]]);
+        table.update(&b, 0, &[vec![0], vec![0b1100_0000]]);
+        table.update(&c, 0, &[vec![0], vec![0], vec![0b0100_0000]]);
+
+        let read = |id, mode| {
+            table
+                .read_nodes(id, mode)
+                .iter()
+                .map(|node| node.name().to_owned())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(["A"], read(0, ReadModeSc::AllowReplica)[..]);
+        assert!(read(1, ReadModeSc::Session).is_empty());
+        assert!(read(1, ReadModeSc::Linearize).is_empty());
+        assert_eq!(["B", "C"], read(1, ReadModeSc::AllowReplica)[..]);
+        assert_eq!(["B", "C"], read(1, ReadModeSc::AllowUnavailable)[..]);
+    }
+
+    #[tokio::test]
+    async fn owners_in_replica_order() {
+        let (a, b) = (node("A").await, node("B").await);
+        let mut table = PartitionTable::new();
+        table.update(&a, 0, &[vec![0b1000_0000], vec![0b0100_0000]]);
+        table.update(&b, 0, &[vec![0b0100_0000], vec![0b1010_0000]]);
+
+        let owners = |id| {
+            table
+                .owners(id)
+                .iter()
+                .map(|node| node.name().to_owned())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(["A", "B"], owners(0)[..]);
+        assert_eq!(["B", "A"], owners(1)[..]);
+        assert_eq!(["B"], owners(2)[..]);
+        assert!(owners(3).is_empty());
+    }
+
+    #[tokio::test]
+    async fn owner_names_without_master() {
+        let (a, b) = (node("A").await, node("B").await);
+        let mut table = PartitionTable::new();
+        table.update(&a, 0, &[vec![0b1000_0000], vec![0b0100_0000]]);
+        table.update(&b, 0, &[vec![0b0100_0000], vec![0b1010_0000]]);
+
+        assert_eq!(
+            PartitionOwners {
+                master: Some("A".to_owned()),
+                replicas: vec!["B".to_owned()],
+            },
+            table.owner_names(0)
+        );
+        assert_eq!(
+            PartitionOwners {
+                master: None,
+                replicas: vec!["B".to_owned()],
+            },
+            table.owner_names(2)
+        );
+        assert_eq!(PartitionOwners::default(), table.owner_names(3));
+    }
+
+    #[tokio::test]
+    async fn retry_node_by_attempt() {
+        let (a, b) = (node("A").await, node("B").await);
+        let mut table = PartitionTable::new();
+        table.update(&a, 0, &[vec![0b1000_0000], vec![0b0100_0000]]);
+        table.update(&b, 0, &[vec![0b0100_0000], vec![0b1000_0000]]);
+
+        let retry = |table: &PartitionTable, id, attempt, mode| {
+            table
+                .retry_node(id, attempt, mode)
+                .map(|node| node.name().to_owned())
+        };
+        assert_eq!(
+            Some("A"),
+            retry(&table, 0, 0, ReadModeSc::Session).as_deref()
+        );
+        assert_eq!(
+            Some("B"),
+            retry(&table, 0, 1, ReadModeSc::Session).as_deref()
+        );
+        assert_eq!(
+            Some("A"),
+            retry(&table, 0, 2, ReadModeSc::Session).as_deref()
+        );
+        assert_eq!(None, retry(&table, 2, 1, ReadModeSc::Session));
+
+        // Strong consistency namespaces only retry on replicas if the read mode allows it.
+        table.update(&b, 1, &[vec![0b0100_0000], vec![0b1000_0000]]);
+        assert_eq!(
+            Some("B"),
+            retry(&table, 1, 1, ReadModeSc::Session).as_deref()
+        );
+        assert_eq!(
+            Some("A"),
+            retry(&table, 1, 1, ReadModeSc::AllowReplica).as_deref()
+        );
+    }
+
+    #[tokio::test]
+    async fn update_short_bitmap() {
+        let (a, b) = (node("A").await, node("B").await);
+        let mut table = PartitionTable::new();
+
+        table.update(&a, 0, &[vec![0xff; 512]]);
+        table.update(&b, 0, &[vec![0xff]]);
+        assert!(table.masters()[..8]
+            .iter()
+            .all(|n| n.as_ref().unwrap().name() == "B"));
+        assert!(table.masters()[8..]
+            .iter()
+            .all(|n| n.as_ref().unwrap().name() == "A"));
+    }
+
+    #[tokio::test]
+    async fn update_replicas() {
+        let (a, b, c) = (node("A").await, node("B").await, node("C").await);
+        let mut table = PartitionTable::new();
+
+        // Replication factor 3, where each node holds a different replica of the partitions.
+        table.update(
+            &a,
+            0,
+            &[vec![0b1000_0000], vec![0b0100_0000], vec![0b0010_0000]],
+        );
+        table.update(
+            &b,
+            0,
+            &[vec![0b0100_0000], vec![0b0010_0000], vec![0b1000_0000]],
+        );
+        table.update(
+            &c,
+            0,
+            &[vec![0b0010_0000], vec![0b1000_0000], vec![0b0100_0000]],
+        );
+
+        assert_eq!(3, table.replicas.len());
+        assert_eq!(["A", "B", "C", "-"], names(&table.replicas[0][..4])[..]);
+        assert_eq!(["C", "A", "B", "-"], names(&table.replicas[1][..4])[..]);
+        assert_eq!(["B", "C", "A", "-"], names(&table.replicas[2][..4])[..]);
+
+        // The replication factor drops to 2.
+        table.update(&a, 0, &[vec![0b1000_0000], vec![0b0100_0000]]);
+        table.update(&b, 0, &[vec![0b0100_0000], vec![0b0010_0000]]);
+        table.update(&c, 0, &[vec![0b0010_0000], vec![0b1000_0000]]);
+        assert_eq!(2, table.replicas.len());
+    }
+
+    #[tokio::test]
+    async fn release_namespace() {
+        let (a, b) = (node("A").await, node("B").await);
+        let mut table = PartitionTable::new();
+        table.update(&a, 0, &[vec![0b1000_0000], vec![0b0100_0000]]);
+        table.update(&b, 0, &[vec![0b0100_0000], vec![0b1000_0000]]);
+
+        assert!(!table.release(&a, 0));
+        assert_eq!(["-", "B"], names(&table.masters()[..2])[..]);
+        assert_eq!(["B", "-"], names(&table.replicas[1][..2])[..]);
+
+        assert!(table.release(&b, 0));
+        assert!(table.replicas.is_empty());
+        assert!(table.master(1).is_none());
+    }
+
+    #[tokio::test]
+    async fn diff_masters() {
+        let (a, b) = (node("A").await, node("B").await);
+        let mut table = PartitionTable::new();
+        table.update(&a, 0, &[vec![0xff; 512]]);
+
+        let old = HashMap::from([("test".to_owned(), table.clone())]);
+        assert!(diff(&old, &old).is_empty());
+
+        // B takes over partitions 0-1 and 4.
+        table.update(&b, 1, &[vec![0b1100_1000]]);
+        let new = HashMap::from([("test".to_owned(), table)]);
+
+        let diffs = diff(&old, &new);
+        assert_eq!(
+            vec![PartitionDiff {
+                namespace: "test".to_owned(),
+                moved: vec![0..=1, 4..=4],
+                gained: BTreeMap::from([("B".to_owned(), 3)]),
+                lost: BTreeMap::from([("A".to_owned(), 3)]),
+            }],
+            diffs
+        );
+        assert_eq!(3, diffs[0].changes());
+
+        // A removed namespace loses all its partitions.
+        let diffs = diff(&new, &HashMap::new());
+        assert_eq!(4096, diffs[0].changes());
+        assert!(diffs[0].gained.is_empty());
+        assert_eq!(Some(&3), diffs[0].lost.get("B"));
     }
 }
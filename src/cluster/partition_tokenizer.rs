@@ -1,46 +1,132 @@
-use std::{
-    collections::{
-        hash_map::Entry::{Occupied, Vacant},
-        HashMap,
-    },
-    sync::Arc,
-    vec::Vec,
-};
+use std::{collections::HashMap, sync::Arc};
 
 use tokio::sync::RwLock;
 
-use super::{node, ClusterError, Node, Result};
+use super::{
+    node::FeatureSupport,
+    partition::{PartitionMap, PartitionTable},
+    ClusterError, Node, Result,
+};
 use crate::{
-    commands::{self, info_cmds::REPLICAS_MASTER},
+    commands::{
+        self,
+        info_cmds::{REPLICAS, REPLICAS_ALL, REPLICAS_MASTER},
+        Replicas,
+    },
     net::Connection,
 };
 
 pub async fn update(
     conn: &mut Connection,
-    nmap: Arc<RwLock<HashMap<String, Vec<Arc<Node>>>>>,
+    nmap: Arc<RwLock<PartitionMap>>,
     node: Arc<Node>,
-) -> Result<HashMap<String, Vec<Arc<Node>>>> {
-    let replicas = commands::info_typed(conn, &[REPLICAS_MASTER])
-        .await?
-        .replicas_master
-        .ok_or(ClusterError::MissingReplicas)?;
+) -> Result<PartitionMap> {
+    // Prefer the commands that report all replicas, and only the newest one reports the regime
+    // of each namespace as well.
+    let replicas = if node.supports(FeatureSupport::REPLICAS) {
+        commands::info_typed(conn, &[REPLICAS]).await?.replicas
+    } else if node.supports(FeatureSupport::REPLICAS_ALL) {
+        commands::info_typed(conn, &[REPLICAS_ALL]).await?.replicas
+    } else {
+        commands::info_typed(conn, &[REPLICAS_MASTER])
+            .await?
+            .replicas_master
+            .map(|masters| {
+                masters
+                    .into_iter()
+                    .map(|(ns, bitmap)| {
+                        let replicas = Replicas {
+                            regime: 0,
+                            bitmaps: vec![bitmap],
+                        };
+                        (ns, replicas)
+                    })
+                    .collect()
+            })
+    }
+    .ok_or(ClusterError::MissingReplicas)?;
 
     let mut amap = nmap.read().await.clone();
+    apply(&mut amap, &node, &replicas);
+
+    Ok(amap)
+}
+
+// Update the partition map with the replicas reported by the node. Namespaces that the node
+// doesn't report anymore are released, and removed once no node owns any of their partitions.
+fn apply(amap: &mut PartitionMap, node: &Arc<Node>, replicas: &HashMap<String, Replicas>) {
+    amap.retain(|ns, table| replicas.contains_key(ns) || !table.release(node, 0));
 
-    for (ns, buffer) in replicas {
-        match amap.entry(ns) {
-            Vacant(entry) => {
-                entry.insert(vec![Arc::clone(&node); node::PARTITIONS as usize]);
-            }
-            Occupied(mut entry) => {
-                for (idx, item) in entry.get_mut().iter_mut().enumerate() {
-                    if buffer[idx >> 3] & (0x80 >> (idx & 7) as u8) != 0 {
-                        *item = Arc::clone(&node);
-                    }
-                }
-            }
-        }
+    for (ns, replicas) in replicas {
+        amap.entry(ns.clone())
+            .or_insert_with(PartitionTable::new)
+            .update(node, replicas.regime, &replicas.bitmaps);
     }
+}
 
-    Ok(amap)
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, sync::Arc};
+
+    use super::apply;
+    use crate::{
+        cluster::{partition::PartitionMap, Node},
+        commands::Replicas,
+        net::Host,
+        policies::ClientPolicy,
+        FeatureSupport,
+    };
+
+    async fn node(name: &str) -> Arc<Node> {
+        let node = Node::new(
+            Arc::new(ClientPolicy::default()),
+            None,
+            None,
+            name.to_owned(),
+            FeatureSupport::empty(),
+            vec![Host::new("127.0.0.1", 3000)],
+        )
+        .await
+        .unwrap();
+        Arc::new(node)
+    }
+
+    fn replicas(namespaces: &[(&str, &[u8])]) -> HashMap<String, Replicas> {
+        namespaces
+            .iter()
+            .map(|(ns, bitmaps)| {
+                let replicas = Replicas {
+                    regime: 0,
+                    bitmaps: bitmaps.iter().map(|b| vec![*b]).collect(),
+                };
+                ((*ns).to_owned(), replicas)
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn namespaces_added_and_removed() {
+        let (a, b) = (node("A").await, node("B").await);
+        let mut amap = PartitionMap::new();
+
+        apply(&mut amap, &a, &replicas(&[("test", &[0x80, 0x40, 0x20])]));
+        apply(&mut amap, &b, &replicas(&[("test", &[0x40, 0x20, 0x80])]));
+        assert_eq!(3, amap["test"].replicas.len());
+
+        // A new namespace shows up on one node only.
+        let a_replicas = replicas(&[("test", &[0x80, 0x40, 0x20]), ("bar", &[0xff])]);
+        apply(&mut amap, &a, &a_replicas);
+        assert_eq!(1, amap["bar"].replicas.len());
+
+        // The namespace is removed again, one node after another.
+        apply(&mut amap, &a, &replicas(&[("test", &[0x80, 0x40, 0x20])]));
+        assert!(!amap.contains_key("bar"));
+
+        apply(&mut amap, &a, &replicas(&[]));
+        assert_eq!("B", amap["test"].master(1).unwrap().name());
+        assert!(amap["test"].master(0).is_none());
+
+        apply(&mut amap, &b, &replicas(&[]));
+        assert!(amap.is_empty());
+    }
 }
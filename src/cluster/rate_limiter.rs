@@ -0,0 +1,87 @@
+use std::sync::Mutex;
+
+use tokio::time::{Duration, Instant};
+
+/// Token bucket that limits the amount of commands sent per second.
+///
+/// The bucket holds up to one second worth of tokens, allowing short bursts. Once it is depleted,
+/// callers reserve future tokens and wait until these become available, which keeps the order in
+/// which commands are let through fair.
+#[derive(Debug)]
+pub(crate) struct RateLimiter {
+    rate: f64,
+    state: Mutex<State>,
+}
+
+#[derive(Debug)]
+struct State {
+    tokens: f64,
+    last: Instant,
+}
+
+impl RateLimiter {
+    /// Create a new limiter, or [`None`] if the rate is not set or zero.
+    pub fn new(rate: Option<u32>) -> Option<Self> {
+        let rate = f64::from(rate.filter(|&rate| rate > 0)?);
+        Some(Self {
+            rate,
+            state: Mutex::new(State {
+                tokens: rate,
+                last: Instant::now(),
+            }),
+        })
+    }
+
+    /// Take a single token out of the bucket, waiting until one is available.
+    pub async fn acquire(&self) {
+        let wait = {
+            let mut state = self
+                .state
+                .lock()
+                .unwrap_or_else(std::sync::PoisonError::into_inner);
+            let now = Instant::now();
+            let refill = now.duration_since(state.last).as_secs_f64() * self.rate;
+
+            state.tokens = (state.tokens + refill).min(self.rate) - 1.0;
+            state.last = now;
+
+            if state.tokens >= 0.0 {
+                return;
+            }
+
+            Duration::from_secs_f64(-state.tokens / self.rate)
+        };
+
+        tokio::time::sleep(wait).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::time::{Duration, Instant};
+
+    use super::RateLimiter;
+
+    #[test]
+    fn disabled() {
+        assert!(RateLimiter::new(None).is_none());
+        assert!(RateLimiter::new(Some(0)).is_none());
+    }
+
+    #[tokio::test]
+    async fn limits_after_burst() {
+        let limiter = RateLimiter::new(Some(100)).unwrap();
+
+        let start = Instant::now();
+        for _ in 0..100 {
+            limiter.acquire().await;
+        }
+        assert!(start.elapsed() < Duration::from_millis(50));
+
+        let start = Instant::now();
+        for _ in 0..10 {
+            limiter.acquire().await;
+        }
+        assert!(start.elapsed() >= Duration::from_millis(50));
+    }
+}
@@ -1,6 +1,6 @@
 #![allow(dead_code)]
 
-use std::str;
+use std::{str, time::Duration};
 
 use super::{buffer::Buffer, CommandError, Result};
 use crate::{
@@ -10,6 +10,15 @@ use crate::{
     ResultCode,
 };
 
+/// User account as known to the server, together with the roles granted to it.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct User {
+    /// Name of the user.
+    pub name: String,
+    /// Roles granted to the user.
+    pub roles: Vec<String>,
+}
+
 #[derive(Clone, Copy)]
 enum Command {
     Authenticate,
@@ -30,6 +39,8 @@ enum FieldId {
     Password,
     OldPassword,
     Credential,
+    SessionToken = 5,
+    SessionTtl,
     Roles = 10,
 }
 
@@ -72,7 +83,13 @@ impl AdminCommand {
         Ok(())
     }
 
-    pub async fn authenticate(conn: &mut Connection, user: &str, password: &str) -> Result<()> {
+    /// Log in with the user's credentials. Returns the session token and its time-to-live, if
+    /// the server handed one out.
+    pub async fn login(
+        conn: &mut Connection,
+        user: &str,
+        password: &str,
+    ) -> Result<Option<(Vec<u8>, Option<Duration>)>> {
         let buf = conn.buffer();
         buf.clear(1024)?;
         write_size(
@@ -92,11 +109,50 @@ impl AdminCommand {
 
         buf.advance(1);
         let result_code = ResultCode::from(buf.read_u8());
+        buf.advance(1);
+        let field_count = buf.read_u8();
 
         if ResultCode::SecurityNotEnabled != result_code && ResultCode::Ok != result_code {
             return Err(CommandError::ServerError(result_code));
         }
 
+        // consume the rest of the buffer
+        buf.advance(HEADER_REMAINING - 4);
+        conn.read_buffer(size as usize).await?;
+
+        if result_code == ResultCode::SecurityNotEnabled {
+            return Ok(None);
+        }
+
+        Ok(read_session(conn.buffer(), field_count))
+    }
+
+    /// Authenticate with a session token from a previous [login](Self::login).
+    pub async fn authenticate(conn: &mut Connection, user: &str, token: &[u8]) -> Result<()> {
+        let buf = conn.buffer();
+        buf.clear(1024)?;
+        write_size(
+            buf,
+            HEADER_SIZE + estimate_field_size(user) + estimate_field_size(token),
+        );
+        write_header(buf, Command::Authenticate, 2);
+        write_field_str(buf, FieldId::User, user);
+        write_field_bytes(buf, FieldId::SessionToken, token);
+
+        conn.flush().await?;
+        conn.read_buffer(HEADER_SIZE).await?;
+
+        let buf = conn.buffer();
+        let size = buf.read_u64();
+        let size = (size & 0xffff_ffff_ffff) - HEADER_REMAINING as u64;
+
+        buf.advance(1);
+        let result_code = ResultCode::from(buf.read_u8());
+
+        if ResultCode::Ok != result_code {
+            return Err(CommandError::ServerError(result_code));
+        }
+
         // consume the rest of the buffer
         buf.advance(HEADER_REMAINING - 2);
         conn.read_buffer(size as usize).await?;
@@ -245,6 +301,57 @@ impl AdminCommand {
 
         Self::execute(conn).await
     }
+
+    /// Look up a single user and its roles. Returns [`None`] if the user doesn't exist.
+    pub async fn query_user(cluster: &Cluster, user: &str) -> Result<Option<User>> {
+        let node = cluster
+            .get_random_node()
+            .await
+            .ok_or(CommandError::NoConnection)?;
+        let mut conn = node.get_connection().await?;
+
+        let buf = conn.buffer();
+        buf.clear(1024)?;
+        write_size(buf, HEADER_SIZE + estimate_field_size(user));
+        write_header(buf, Command::QueryUsers, 1);
+        write_field_str(buf, FieldId::User, user);
+
+        conn.start_command("query_user");
+        if let Err(err) = conn.flush().await {
+            conn.close().await;
+            return Err(err.into());
+        }
+
+        let mut users = Vec::new();
+        loop {
+            if let Err(err) = conn.read_buffer(8).await {
+                conn.close().await;
+                return Err(err.into());
+            }
+
+            let size = (conn.buffer().read_u64() & 0xffff_ffff_ffff) as usize;
+            if size == 0 {
+                break;
+            }
+
+            if let Err(err) = conn.read_buffer(size).await {
+                conn.close().await;
+                return Err(err.into());
+            }
+
+            match read_users(conn.buffer(), &mut users) {
+                Ok(true) => {}
+                Ok(false) => break,
+                Err(err) => {
+                    conn.close().await;
+                    return Err(err);
+                }
+            }
+        }
+
+        conn.finish_command();
+        Ok(users.into_iter().next())
+    }
 }
 
 fn write_size(buf: &mut Buffer, size: usize) {
@@ -283,6 +390,67 @@ fn write_field_bytes(buf: &mut Buffer, id: FieldId, b: impl AsRef<[u8]>) {
     buf.write_bytes(b);
 }
 
+/// Read the session token and its time-to-live from the fields of a login response.
+fn read_session(buf: &mut Buffer, field_count: u8) -> Option<(Vec<u8>, Option<Duration>)> {
+    let mut token = None;
+    let mut ttl = None;
+
+    for _ in 0..field_count {
+        let len = buf.read_u32() as usize - 1;
+        let id = buf.read_u8();
+
+        if id == FieldId::SessionToken as u8 {
+            token = Some(buf.read_bytes(len));
+        } else if id == FieldId::SessionTtl as u8 && len == 4 {
+            ttl = Some(Duration::from_secs(buf.read_u32().into()));
+        } else {
+            buf.advance(len);
+        }
+    }
+
+    token.map(|token| (token, ttl))
+}
+
+/// Read all user records of a single response block. Returns `false` once the end of the
+/// response was reached.
+fn read_users(buf: &mut Buffer, users: &mut Vec<User>) -> Result<bool> {
+    while !buf.as_ref().is_empty() {
+        buf.advance(1);
+        match ResultCode::from(buf.read_u8()) {
+            ResultCode::Ok => {}
+            ResultCode::QueryEnd => return Ok(false),
+            code => return Err(CommandError::ServerError(code)),
+        }
+        buf.advance(1);
+        let field_count = buf.read_u8();
+        buf.advance(HEADER_REMAINING - 4);
+
+        let mut user = User::default();
+        for _ in 0..field_count {
+            let len = buf.read_u32() as usize - 1;
+            let id = buf.read_u8();
+
+            if id == FieldId::User as u8 {
+                user.name = buf.read_str(len)?;
+            } else if id == FieldId::Roles as u8 {
+                let count = buf.read_u8();
+                user.roles = (0..count)
+                    .map(|_| {
+                        let len = buf.read_u8();
+                        buf.read_str(len.into())
+                    })
+                    .collect::<Result<_, _>>()?;
+            } else {
+                buf.advance(len);
+            }
+        }
+
+        users.push(user);
+    }
+
+    Ok(true)
+}
+
 fn estimate_roles_size(roles: &[&str]) -> usize {
     FIELD_HEADER_SIZE + roles.iter().map(|role| 1 + role.len()).sum::<usize>()
 }
@@ -311,3 +479,79 @@ pub fn hash_password(password: &str) -> Result<String> {
 
     Ok(bcrypt::hash_with_salt(password, COST, SALT)?.format_for_version(VERSION))
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{
+        read_session, read_users, write_field_bytes, write_field_str, write_roles, Buffer, FieldId,
+        User,
+    };
+    use crate::{errors::CommandError, msgpack::Write, ResultCode};
+
+    #[test]
+    fn read_login_session() {
+        let mut buf = Buffer::new(0);
+        write_field_str(&mut buf, FieldId::User, "admin");
+        write_field_bytes(&mut buf, FieldId::SessionToken, [1, 2, 3]);
+        write_field_bytes(&mut buf, FieldId::SessionTtl, 86400_u32.to_be_bytes());
+        buf.write_u8(0);
+
+        assert_eq!(
+            Some((vec![1, 2, 3], Some(Duration::from_secs(86400)))),
+            read_session(&mut buf, 3)
+        );
+        assert_eq!(1, buf.as_ref().len());
+    }
+
+    #[test]
+    fn read_login_without_session() {
+        let mut buf = Buffer::new(0);
+        write_field_bytes(&mut buf, FieldId::SessionTtl, 86400_u32.to_be_bytes());
+
+        assert_eq!(None, read_session(&mut buf, 1));
+    }
+
+    fn write_user_header(buf: &mut Buffer, result_code: ResultCode, field_count: u8) {
+        buf.write_u8(0);
+        buf.write_u8(result_code.into());
+        buf.write_u8(0);
+        buf.write_u8(field_count);
+        buf.write_bytes(&[0; 12]);
+    }
+
+    #[test]
+    fn read_user_with_roles() {
+        let mut buf = Buffer::new(0);
+        write_user_header(&mut buf, ResultCode::Ok, 3);
+        write_field_str(&mut buf, FieldId::User, "admin");
+        write_field_bytes(&mut buf, FieldId::Credential, [1, 2, 3]);
+        write_roles(&mut buf, &["read", "user-admin"]);
+
+        let mut users = Vec::new();
+        assert!(read_users(&mut buf, &mut users).unwrap());
+        assert_eq!(
+            vec![User {
+                name: "admin".to_owned(),
+                roles: vec!["read".to_owned(), "user-admin".to_owned()],
+            }],
+            users
+        );
+
+        write_user_header(&mut buf, ResultCode::QueryEnd, 0);
+        assert!(!read_users(&mut buf, &mut users).unwrap());
+        assert_eq!(1, users.len());
+    }
+
+    #[test]
+    fn read_users_error() {
+        let mut buf = Buffer::new(0);
+        write_user_header(&mut buf, ResultCode::InvalidUser, 0);
+
+        assert!(matches!(
+            read_users(&mut buf, &mut Vec::new()),
+            Err(CommandError::ServerError(ResultCode::InvalidUser))
+        ));
+    }
+}
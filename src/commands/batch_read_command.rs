@@ -6,125 +6,118 @@ use tracing::warn;
 
 use super::{
     buffer::{InfoAttr, MessageHeader},
-    Command, CommandError, Result,
+    until_deadline, Command, CommandError, Result,
 };
 use crate::{
-    cluster::Node, msgpack::Read, net::Connection, policies::BatchPolicy, BatchRead, Record,
-    ResultCode, Value,
+    cluster::Node, msgpack::Read, net::Connection, policies::BatchPolicy, record::BinNames,
+    BatchRead, Record, ResultCode, Value,
 };
 
 struct BatchRecord {
     batch_index: usize,
+    result: ResultCode,
     record: Option<Record>,
 }
 
 #[derive(Clone, Debug)]
 pub struct BatchReadCommand {
     policy: BatchPolicy,
+    deadline: Option<Instant>,
     pub node: Arc<Node>,
     pub batch_reads: Vec<BatchRead>,
+    bin_names: BinNames,
 }
 
 impl BatchReadCommand {
-    pub fn new(policy: &BatchPolicy, node: Arc<Node>, batch_reads: Vec<BatchRead>) -> Self {
+    /// Create a new command for the given node. The deadline is shared by all commands of the
+    /// same batch, so retries don't extend the overall timeout.
+    pub fn new(
+        policy: &BatchPolicy,
+        deadline: Option<Instant>,
+        node: Arc<Node>,
+        batch_reads: Vec<BatchRead>,
+    ) -> Self {
         Self {
             policy: policy.clone(),
+            deadline,
             node,
             batch_reads,
+            bin_names: BinNames::default(),
         }
     }
 
+    /// Send the request to its node once. Retries are up to the batch executor, which sends the
+    /// keys of failed requests to other replicas of their partitions.
     pub async fn execute(&mut self) -> Result<()> {
-        let mut iterations = 0;
-        let base_policy = self.policy.as_ref().clone();
-
-        // set timeout outside the loop
-        let deadline = base_policy.deadline();
-
-        // Execute command until successful, timed out or maximum iterations have been reached.
-        loop {
-            iterations += 1;
+        // every step that awaits is bounded by the batch's deadline
+        let deadline = self.deadline;
+        if deadline.map_or(false, |deadline| Instant::now() > deadline) {
+            return Err(CommandError::Timeout);
+        }
 
-            // too many retries
-            if let Some(max_retries) = base_policy.max_retries {
-                if iterations > max_retries + 1 {
-                    return Err(CommandError::Timeout);
-                }
-            }
+        let node = Arc::clone(&self.node);
+        until_deadline(deadline, node.acquire_permit()).await?;
 
-            // Sleep before trying again, after the first iteration
-            if iterations > 1 {
-                if base_policy.sleep_between_retries.is_zero() {
-                    // yield to free space for the runtime to execute other futures between runs
-                    // because the loop would block the thread
-                    tokio::task::yield_now().await;
-                } else {
-                    tokio::time::sleep(base_policy.sleep_between_retries).await;
-                }
+        let mut conn = match until_deadline(deadline, node.get_connection()).await? {
+            Ok(conn) => conn,
+            Err(err) => {
+                warn!(?node, %err, "failed to get a fresh connection");
+                return Err(err.into());
             }
+        };
 
-            // check for command timeout
-            if let Some(deadline) = deadline {
-                if Instant::now() > deadline {
-                    break;
-                }
-            }
-
-            // set command node, so when you return a record it has the node
-            let node = match self.get_node().await {
-                Some(node) => node,
-                None => continue, // Node is currently inactive. Retry.
-            };
-
-            let mut conn = match node.get_connection().await {
-                Ok(conn) => conn,
-                Err(err) => {
-                    warn!(?node, %err, "failed to get a fresh connection");
-                    continue;
-                }
-            };
-
-            self.prepare_buffer(&mut conn)
-                .map_err(|e| CommandError::PrepareBuffer(Box::new(e)))?;
+        self.prepare_buffer(&mut conn, &node)
+            .map_err(|e| CommandError::PrepareBuffer(Box::new(e)))?;
+
+        // Send command.
+        conn.start_command(self.name());
+        let flushed = until_deadline(deadline, conn.flush())
+            .await
+            .and_then(|flushed| {
+                flushed.map_err(|err| {
+                    warn!(?node, %err, "failed to flush remaining data to connection");
+                    err.into()
+                })
+            });
+        if let Err(err) = flushed {
+            // The request may have been sent partially, so the connection can't be reused.
+            conn.close().await;
+            return Err(err);
+        }
 
-            // Send command.
-            if let Err(err) = conn.flush().await {
-                // IO errors are considered temporary anomalies. Retry.
-                // Close socket to flush out possible garbage. Do not put back in pool.
+        // Parse results.
+        let parsed = until_deadline(deadline, self.parse_result(&mut conn))
+            .await
+            .and_then(|result| result);
+        if let Err(err) = parsed {
+            // close the connection
+            // cancelling/closing the batch/multi commands will return an error, which will
+            // close the connection to throw away its data and signal the server about the
+            // situation. We will not put back the connection in the buffer.
+            if super::keep_connection(&err) {
+                conn.finish_command();
+            } else {
                 conn.close().await;
-                warn!(?node, %err, "failed to flush remaining data to connection");
-                continue;
             }
-
-            // Parse results.
-            if let Err(err) = self.parse_result(&mut conn).await {
-                // close the connection
-                // cancelling/closing the batch/multi commands will return an error, which will
-                // close the connection to throw away its data and signal the server about the
-                // situation. We will not put back the connection in the buffer.
-                if !super::keep_connection(&err) {
-                    conn.close().await;
-                }
-                return Err(err);
-            }
-
-            // command has completed successfully.  Exit method.
-            return Ok(());
+            return Err(err);
         }
 
-        Err(CommandError::Timeout)
+        // command has completed successfully.  Exit method.
+        conn.finish_command();
+        Ok(())
     }
 
     async fn parse_group(&mut self, conn: &mut Connection, size: usize) -> Result<bool> {
         while conn.bytes_read() < size {
             conn.read_buffer(MessageHeader::SIZE).await?;
-            match self.parse_record(conn).await? {
+            match self.parse_record(conn, size).await? {
                 None => return Ok(false),
                 Some(batch_record) => {
                     let batch_read = self
                         .batch_reads
                         .get_mut(batch_record.batch_index)
                         .expect("invalid batch index");
+                    batch_read.result = batch_record.result;
                     batch_read.record = batch_record.record;
                 }
             }
@@ -132,20 +125,23 @@ impl BatchReadCommand {
         Ok(true)
     }
 
-    async fn parse_record(&mut self, conn: &mut Connection) -> Result<Option<BatchRecord>> {
+    async fn parse_record(
+        &mut self,
+        conn: &mut Connection,
+        size: usize,
+    ) -> Result<Option<BatchRecord>> {
         conn.buffer().advance(3);
         let info3 = InfoAttr::from_bits_truncate(conn.buffer().read_u8());
 
         conn.buffer().advance(1);
-        let found_key = match ResultCode::from(conn.buffer().read_u8()) {
-            ResultCode::Ok => true,
-            ResultCode::KeyNotFoundError => false,
-            rc => return Err(CommandError::ServerError(rc)),
-        };
+        let result = ResultCode::from(conn.buffer().read_u8());
 
         // if cmd is the end marker of the response, do not proceed further
         if info3.contains(InfoAttr::LAST) {
-            return Ok(None);
+            return match result {
+                ResultCode::Ok | ResultCode::KeyNotFoundError => Ok(None),
+                rc => Err(CommandError::ServerError(rc)),
+            };
         }
 
         let generation = conn.buffer().read_u32();
@@ -154,10 +150,13 @@ impl BatchReadCommand {
         let field_count = conn.buffer().read_u16(); // almost certainly 0
         let op_count = conn.buffer().read_u16() as usize;
 
-        let key = super::StreamCommand::parse_key(conn, field_count).await?;
+        let key = super::stream_command::parse_key(conn, size, field_count).await?;
 
-        let record = if found_key {
-            let mut bins: HashMap<String, Value> = HashMap::with_capacity(op_count);
+        // Any other result code only applies to this record, and not the whole batch.
+        let record = if result == ResultCode::Ok {
+            let mut bins = HashMap::with_capacity(op_count);
+            let mut skipped_bins = Vec::new();
+            let options = self.node.read_options();
 
             for _ in 0..op_count {
                 conn.read_buffer(8).await?;
@@ -166,20 +165,32 @@ impl BatchReadCommand {
                 let particle_type = conn.buffer().read_u8();
                 conn.buffer().advance(1);
                 let name_size = conn.buffer().read_u8() as usize;
-                conn.read_buffer(name_size).await?;
-                let name = conn.buffer().read_str(name_size)?;
+                conn.read_proto_buffer(name_size, size).await?;
+                let name = conn
+                    .buffer()
+                    .read_bin_name(name_size, &mut self.bin_names)?;
                 let particle_bytes_size = op_size - (4 + name_size);
-                conn.read_buffer(particle_bytes_size).await?;
-                let value = Value::read_from(conn.buffer(), particle_type, particle_bytes_size)?;
+                if options.skip_bin(particle_bytes_size) {
+                    conn.skip(particle_bytes_size).await?;
+                    skipped_bins.push((name, particle_bytes_size));
+                    continue;
+                }
+
+                conn.read_proto_buffer(particle_bytes_size, size).await?;
+                let value =
+                    Value::read_from(conn.buffer(), particle_type, particle_bytes_size, options)?;
                 bins.insert(name, value);
             }
 
-            Some(Record::new(Some(key), bins, generation, expiration))
+            let mut record = Record::new(Some(key), bins, generation, expiration);
+            record.skipped_bins = skipped_bins;
+            Some(record)
         } else {
             None
         };
         Ok(Some(BatchRecord {
             batch_index: batch_index as usize,
+            result,
             record,
         }))
     }
@@ -187,9 +198,15 @@ impl BatchReadCommand {
 
 #[async_trait]
 impl Command for BatchReadCommand {
-    fn prepare_buffer(&mut self, conn: &mut Connection) -> Result<()> {
+    const NAME: &'static str = "BatchReadCommand";
+
+    fn prepare_buffer(&mut self, conn: &mut Connection, _node: &Node) -> Result<()> {
         conn.buffer()
-            .set_batch_read(&self.policy, &self.batch_reads)
+            .set_batch_read(
+                &self.policy,
+                &self.batch_reads,
+                self.node.max_bin_name_len(),
+            )
             .map_err(Into::into)
     }
 
@@ -4,20 +4,22 @@
     clippy::cast_sign_loss
 )]
 
-use std::{mem, str, time::Duration};
+use std::{borrow::Cow, mem, str, time::Duration};
 
 use bitflags::bitflags;
 use bytes::{Buf, BufMut, BytesMut};
 
 use crate::{
     commands::field_type::FieldType,
-    msgpack::{Read, Write},
+    expressions::Expression,
+    msgpack::{Checkpoint, Limited, Read, Write},
     operations::{Operation, OperationBin, OperationData, OperationType},
     policies::{
         BasePolicy, BatchPolicy, CommitLevel, ConsistencyLevel, Expiration, GenerationPolicy,
-        RecordExistsAction, ScanPolicy, WritePolicy,
+        ReadModeSc, RecordExistsAction, ScanPolicy, WritePolicy,
     },
-    BatchRead, Bin, Bins, Key, ResultCode, UserKey,
+    record::BinNames,
+    BatchRead, Bin, BinName, Bins, Key, ResultCode, UserKey, Value,
 };
 
 bitflags! {
@@ -88,16 +90,63 @@ bitflags! {
     }
 }
 
+bitflags! {
+    /// Flags of a batch request, that apply to all of its keys.
+    #[derive(Clone, Copy)]
+    struct BatchFlags: u8 {
+        /// Allow processing on the receiving thread for in-memory namespaces.
+        const ALLOW_INLINE = 1;
+        /// Allow processing on the receiving thread for SSD namespaces.
+        const ALLOW_INLINE_SSD = 1 << 1;
+        /// Attempt all keys, regardless of errors on individual ones.
+        const RESPOND_ALL_KEYS = 1 << 2;
+    }
+}
+
+impl BatchFlags {
+    fn new(policy: &BatchPolicy, short_query: bool) -> Self {
+        let mut flags = Self::empty();
+        flags.set(Self::ALLOW_INLINE, policy.allow_inline || short_query);
+        flags.set(Self::ALLOW_INLINE_SSD, policy.allow_inline_ssd);
+        flags.set(Self::RESPOND_ALL_KEYS, policy.respond_all_keys);
+        flags
+    }
+}
+
+impl From<ReadModeSc> for InfoAttr {
+    fn from(value: ReadModeSc) -> Self {
+        match value {
+            ReadModeSc::Session => Self::empty(),
+            ReadModeSc::Linearize => Self::SC_READ_TYPE,
+            ReadModeSc::AllowReplica => Self::SC_READ_RELAX,
+            ReadModeSc::AllowUnavailable => Self::SC_READ_TYPE | Self::SC_READ_RELAX,
+        }
+    }
+}
+
 pub const TOTAL_HEADER_SIZE: usize = ProtoHeader::SIZE + MessageHeader::SIZE;
 
 const FIELD_HEADER_SIZE: usize = mem::size_of::<u32>() + mem::size_of::<u8>();
-const OPERATION_HEADER_SIZE: usize = mem::size_of::<i32>() + mem::size_of::<[u8; 4]>();
+pub(crate) const OPERATION_HEADER_SIZE: usize = mem::size_of::<i32>() + mem::size_of::<[u8; 4]>();
 const DIGEST_SIZE: usize = 20;
 
+/// Size of a batch read request without any of its keys. See [`batch_read_entry_size`] for the
+/// additional size of each key.
+pub const BATCH_READ_HEADER_SIZE: usize = TOTAL_HEADER_SIZE + FIELD_HEADER_SIZE + 5;
+
 // MAX_BUFFER_SIZE protects against allocating massive memory blocks
 // for buffers. Tweak this number if you are returning a lot of
-// LDT elements in your queries.
-const MAX_BUFFER_SIZE: usize = 1024 * 1024 + 8; // 1 MB + header
+// LDT elements in your queries. Stream responses are read record by
+// record instead, and are only bounded by the size of each proto.
+pub const MAX_BUFFER_SIZE: usize = 1024 * 1024 + 8; // 1 MB + header
+
+/// Maximum length of bin names in bytes, unless the server announced support for longer names.
+pub const MAX_BIN_NAME_LEN: usize = 15;
+/// Maximum length of bin names in bytes for servers that support long names, which is the most the
+/// wire format can carry.
+pub const MAX_LONG_BIN_NAME_LEN: usize = u8::MAX as usize;
+/// Maximum amount of operations in a single command.
+pub const MAX_OPERATIONS: usize = u16::MAX as usize;
 
 pub type Result<T, E = BufferError> = std::result::Result<T, E>;
 
@@ -107,6 +156,16 @@ pub enum BufferError {
     SizeExceeded { size: usize, max: usize },
     #[error("invalid UTF-8 content encountered")]
     InvalidUtf8(#[from] std::string::FromUtf8Error),
+    #[error("bin name `{name}` is {} bytes long (max {max})", .name.len())]
+    BinNameTooLong { name: String, max: usize },
+    #[error("too many operations in a single command: {count} (max {max})")]
+    TooManyOperations { count: usize, max: usize },
+    #[error("unsupported message version {0}")]
+    InvalidVersion(u8),
+    #[error("unexpected message type {0}")]
+    InvalidMessageType(u8),
+    #[error("invalid message length {size} (min {min})")]
+    InvalidMessageLength { size: usize, min: usize },
 }
 
 // Holds data buffer for the command
@@ -142,13 +201,16 @@ impl Buffer {
     }
 
     pub fn resize(&mut self, size: usize) -> Result<()> {
+        self.resize_within(size, MAX_BUFFER_SIZE)
+    }
+
+    /// Same as [`Self::resize`], but with a custom upper bound for the size, instead of the
+    /// [`MAX_BUFFER_SIZE`].
+    pub fn resize_within(&mut self, size: usize, max: usize) -> Result<()> {
         // Corrupted data streams can result in a huge length.
         // Do a sanity check here.
-        if size > MAX_BUFFER_SIZE {
-            return Err(BufferError::SizeExceeded {
-                size,
-                max: MAX_BUFFER_SIZE,
-            });
+        if size > max {
+            return Err(BufferError::SizeExceeded { size, max });
         }
 
         let capacity = self.buffer.capacity();
@@ -161,39 +223,61 @@ impl Buffer {
         Ok(())
     }
 
-    // Writes the command for write operations
+    // Writes the command for write operations.
+    //
+    // The bins are encoded in a single pass and the operation and message sizes are patched
+    // afterwards, as calculating the size of large list or map values upfront would mean encoding
+    // them twice.
+    //
+    // Boolean values are written as integers, unless `bool_bins` is set, as older servers don't
+    // support them natively.
     pub(crate) fn set_write(
         &mut self,
         policy: &WritePolicy,
         op_type: OperationType,
         key: &Key,
         bins: &[Bin<'_>],
+        bool_bins: bool,
+        max_bin_name_len: usize,
     ) -> Result<()> {
+        let op_count = operation_count(bins.len())?;
+        check_bin_names(bins.iter().map(|bin| bin.name), max_bin_name_len)?;
+
         let (key_size, field_count) = estimate_key_size(key, policy.as_ref().send_key);
-        let op_size = bins
+
+        // The size of lists and maps is only known once they're encoded, but all other values can
+        // be checked against the size limit before anything is allocated for them. Encoding lists
+        // and maps stops writing to the buffer once they reach the limit instead.
+        let min_op_size = bins
             .iter()
-            .map(estimate_operation_size_for_bin)
+            .map(|bin| {
+                let value_size = match bin.value {
+                    Value::List(_) | Value::HashMap(_) => 0,
+                    ref value => value.estimate_size(),
+                };
+                OPERATION_HEADER_SIZE + bin.name.len() + value_size
+            })
             .sum::<usize>();
 
-        self.clear(TOTAL_HEADER_SIZE + key_size + op_size)?;
+        self.clear(TOTAL_HEADER_SIZE + key_size + min_op_size)?;
 
         MessageHeader::for_write(
-            key_size + op_size,
+            key_size,
             policy,
             ReadAttr::empty(),
             WriteAttr::WRITE,
             field_count,
-            bins.len() as u16,
+            op_count,
         )
         .write_to(&mut self.buffer);
 
         self.write_key(key, policy.as_ref().send_key);
 
         for bin in bins {
-            self.write_operation_for_bin(bin, op_type);
+            self.write_operation_for_bin(bin, op_type, bool_bins)?;
         }
 
-        Ok(())
+        self.patch_proto_size()
     }
 
     // Writes the command for write operations
@@ -262,11 +346,20 @@ impl Buffer {
     }
 
     // Writes the command for get operations
-    pub fn set_read(&mut self, policy: &BasePolicy, key: &Key, bins: &Bins) -> Result<()> {
+    pub fn set_read(
+        &mut self,
+        policy: &BasePolicy,
+        key: &Key,
+        bins: &Bins,
+        max_bin_name_len: usize,
+    ) -> Result<()> {
         match bins {
             Bins::None => self.set_read_header(policy, key),
             Bins::All => self.set_read_for_key_only(policy, key),
             Bins::Some(bin_names) => {
+                let op_count = operation_count(bin_names.len())?;
+                check_bin_names(bin_names.iter().map(AsRef::as_ref), max_bin_name_len)?;
+
                 let (key_size, field_count) = estimate_key_size(key, policy.send_key);
                 let op_size = bin_names
                     .iter()
@@ -281,7 +374,7 @@ impl Buffer {
                     ReadAttr::READ,
                     WriteAttr::empty(),
                     field_count,
-                    bin_names.len() as u16,
+                    op_count,
                 )
                 .write_to(&mut self.buffer);
 
@@ -345,42 +438,39 @@ impl Buffer {
         &mut self,
         policy: &BatchPolicy,
         batch_reads: &[BatchRead],
+        max_bin_name_len: usize,
     ) -> Result<()> {
+        for batch_read in batch_reads {
+            if let Bins::Some(bin_names) = &batch_read.bins {
+                operation_count(bin_names.len())?;
+                check_bin_names(bin_names.iter().map(AsRef::as_ref), max_bin_name_len)?;
+            }
+        }
+
         let field_count_row = if policy.send_set_name { 2 } else { 1 };
 
         let field_count = 1;
-        let mut field_size = FIELD_HEADER_SIZE + 5;
+        let mut field_size = BATCH_READ_HEADER_SIZE - TOTAL_HEADER_SIZE;
 
         let mut prev: Option<&BatchRead> = None;
         for batch_read in batch_reads {
-            field_size += batch_read.key.digest.len() + 4;
-            match prev {
-                Some(prev) if batch_read.match_header(prev, policy.send_set_name) => {
-                    field_size += 1;
-                }
-                _ => {
-                    let key = &batch_read.key;
-                    field_size += FIELD_HEADER_SIZE + 6 + key.namespace.len();
-                    if policy.send_set_name {
-                        field_size += FIELD_HEADER_SIZE + key.set_name.len();
-                    }
-                    if let Bins::Some(bin_names) = &batch_read.bins {
-                        field_size += bin_names
-                            .iter()
-                            .map(|name| estimate_operation_size_for_bin_name(name))
-                            .sum::<usize>();
-                    }
-                }
-            }
+            field_size += batch_read_entry_size(policy, batch_read, prev);
             prev = Some(batch_read);
         }
 
         self.clear(TOTAL_HEADER_SIZE + field_size)?;
 
+        let short_query =
+            policy.short_query_threshold > 0 && batch_reads.len() <= policy.short_query_threshold;
+        let mut read_attr = ReadAttr::READ | ReadAttr::BATCH;
+        if short_query {
+            read_attr |= ReadAttr::SHORT_QUERY;
+        }
+
         MessageHeader::for_read(
             field_size,
             policy.as_ref(),
-            ReadAttr::READ | ReadAttr::BATCH,
+            read_attr,
             WriteAttr::empty(),
             field_count,
             0,
@@ -396,7 +486,7 @@ impl Buffer {
             },
         );
         self.write_u32(batch_reads.len() as u32);
-        self.write_u8(u8::from(policy.allow_inline));
+        self.write_u8(BatchFlags::new(policy, short_query).bits());
 
         prev = None;
         for (idx, batch_read) in batch_reads.iter().enumerate() {
@@ -449,16 +539,48 @@ impl Buffer {
         Ok(())
     }
 
-    // Writes the command for getting metadata operations
+    // Writes the command for getting metadata operations. Booleans are handled the same as in
+    // `set_write`.
     pub fn set_operate<'a>(
         &mut self,
         policy: &WritePolicy,
         key: &Key,
         operations: &'a [Operation<'a>],
+        bool_bins: bool,
+        max_bin_name_len: usize,
     ) -> Result<()> {
+        let op_count = operation_count(operations.len())?;
+        check_bin_names(
+            operations.iter().filter_map(Operation::bin_name),
+            max_bin_name_len,
+        )?;
+
         let mut read_attr = ReadAttr::empty();
         let mut write_attr = WriteAttr::empty();
 
+        let downgrade = !bool_bins && operations.iter().any(Operation::writes_bool);
+        let adjusted;
+        let operations = if downgrade || policy.continue_on_error {
+            adjusted = operations
+                .iter()
+                .map(|op| {
+                    let op = if downgrade {
+                        op.without_bool()
+                    } else {
+                        op.clone()
+                    };
+                    if policy.continue_on_error {
+                        op.with_no_fail()
+                    } else {
+                        op
+                    }
+                })
+                .collect::<Vec<_>>();
+            &adjusted
+        } else {
+            operations
+        };
+
         let op_size = operations
             .iter()
             .map(|operation| {
@@ -511,7 +633,7 @@ impl Buffer {
                 read_attr,
                 write_attr,
                 field_count,
-                operations.len() as u16,
+                op_count,
             )
         } else {
             MessageHeader::for_write(
@@ -520,7 +642,7 @@ impl Buffer {
                 read_attr,
                 write_attr,
                 field_count,
-                operations.len() as u16,
+                op_count,
             )
         }
         .write_to(&mut self.buffer);
@@ -534,15 +656,27 @@ impl Buffer {
         Ok(())
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn set_scan(
         &mut self,
         policy: &ScanPolicy,
         namespace: &str,
         set_name: &str,
         bins: &Bins,
+        operations: &[Operation<'_>],
         task_id: u64,
         partitions: &[u16],
+        max_records: u64,
+        max_bin_name_len: usize,
     ) -> Result<()> {
+        // Without bin data, the selected bins are irrelevant and not sent at all.
+        let no_bins = Bins::None;
+        let bins = if policy.include_bin_data {
+            bins
+        } else {
+            &no_bins
+        };
+
         let mut field_size = 0;
         let mut field_count = 0;
 
@@ -565,21 +699,58 @@ impl Buffer {
             + 8;
         field_count += 3;
 
+        let sample_percent = (policy.sample_percent < 100).then_some(policy.sample_percent);
+        if sample_percent.is_some() {
+            field_size += FIELD_HEADER_SIZE + 2;
+            field_count += 1;
+        }
+
+        if max_records > 0 {
+            field_size += FIELD_HEADER_SIZE + 8;
+            field_count += 1;
+        }
+
+        let filter_size = policy
+            .filter_expression
+            .as_ref()
+            .map_or(0, Expression::estimate_size);
+        if filter_size > 0 {
+            field_size += FIELD_HEADER_SIZE + filter_size;
+            field_count += 1;
+        }
+
+        // Read operations replace the bin selection, returning their results instead.
         let (bin_size, bin_count) = match bins {
+            _ if !operations.is_empty() => {
+                check_bin_names(
+                    operations.iter().filter_map(Operation::bin_name),
+                    max_bin_name_len,
+                )?;
+                (
+                    operations
+                        .iter()
+                        .map(|operation| OPERATION_HEADER_SIZE + operation.estimate_size())
+                        .sum::<usize>(),
+                    operation_count(operations.len())?,
+                )
+            }
             Bins::All | Bins::None => (0, 0),
-            Bins::Some(bin_names) => (
-                bin_names
-                    .iter()
-                    .map(|name| estimate_operation_size_for_bin_name(name))
-                    .sum::<usize>(),
-                bin_names.len(),
-            ),
+            Bins::Some(bin_names) => {
+                check_bin_names(bin_names.iter().map(AsRef::as_ref), max_bin_name_len)?;
+                (
+                    bin_names
+                        .iter()
+                        .map(|name| estimate_operation_size_for_bin_name(name))
+                        .sum::<usize>(),
+                    operation_count(bin_names.len())?,
+                )
+            }
         };
 
         self.clear(TOTAL_HEADER_SIZE + field_size + bin_size)?;
 
         let mut read_attr = ReadAttr::READ;
-        if *bins == Bins::None {
+        if *bins == Bins::None && operations.is_empty() {
             read_attr |= ReadAttr::GET_NO_BINS;
         }
 
@@ -589,7 +760,7 @@ impl Buffer {
             read_attr,
             WriteAttr::empty(),
             field_count,
-            bin_count as u16,
+            bin_count,
         )
         .write_to(&mut self.buffer);
 
@@ -615,7 +786,28 @@ impl Buffer {
         self.write_field_header(8, FieldType::TranId);
         self.write_u64(task_id);
 
-        if let Bins::Some(bin_names) = bins {
+        if let Some(percent) = sample_percent {
+            // The first byte holds the (no longer used) scan priority and flags.
+            self.write_field_header(2, FieldType::ScanOptions);
+            self.write_u8(0);
+            self.write_u8(percent);
+        }
+
+        if max_records > 0 {
+            self.write_field_header(8, FieldType::MaxRecords);
+            self.write_u64(max_records);
+        }
+
+        if let Some(filter) = &policy.filter_expression {
+            self.write_field_header(filter_size, FieldType::FilterExp);
+            filter.write_to(&mut self.buffer);
+        }
+
+        if !operations.is_empty() {
+            for operation in operations {
+                operation.write_to(self);
+            }
+        } else if let Bins::Some(bin_names) = bins {
             for bin_name in bin_names {
                 self.write_operation_for_bin_name(bin_name, OperationType::Read);
             }
@@ -644,6 +836,22 @@ impl Buffer {
         Ok(())
     }
 
+    /// Write an arbitrary message body, only framed by a proto header of the given type.
+    #[cfg(feature = "raw")]
+    pub fn set_raw(&mut self, ty: ProtoType, body: &[u8]) -> Result<()> {
+        self.clear(ProtoHeader::SIZE + body.len())?;
+
+        ProtoHeader {
+            version: Version::V2,
+            ty,
+            size: body.len(),
+        }
+        .write_to(&mut self.buffer);
+        self.buffer.put_slice(body);
+
+        Ok(())
+    }
+
     // Header write for write operations.
 
     fn write_key(&mut self, key: &Key, send_key: bool) {
@@ -665,6 +873,23 @@ impl Buffer {
         }
     }
 
+    /// Update the size in the proto header to the current buffer content, for messages whose size
+    /// isn't known before writing them.
+    fn patch_proto_size(&mut self) -> Result<()> {
+        let size = self.buffer.len();
+        if size > MAX_BUFFER_SIZE {
+            return Err(BufferError::SizeExceeded {
+                size,
+                max: MAX_BUFFER_SIZE,
+            });
+        }
+
+        let proto_size = ((size - ProtoHeader::SIZE) as u64).to_be_bytes();
+        self.buffer[2..ProtoHeader::SIZE].copy_from_slice(&proto_size[2..]);
+
+        Ok(())
+    }
+
     fn write_field_header(&mut self, size: usize, ftype: FieldType) {
         self.write_i32(size as i32 + 1);
         self.write_u8(ftype as u8);
@@ -686,17 +911,36 @@ impl Buffer {
         value.write_to(self);
     }
 
-    fn write_operation_for_bin(&mut self, bin: &Bin<'_>, op_type: OperationType) {
-        let name_length = bin.name.len();
-        let value_length = bin.value.estimate_size();
+    fn write_operation_for_bin(
+        &mut self,
+        bin: &Bin<'_>,
+        op_type: OperationType,
+        bool_bins: bool,
+    ) -> Result<()> {
+        let size = Checkpoint::reserve(&mut self.buffer);
+        let value = match bin.value {
+            Value::Bool(value) if !bool_bins => Cow::Owned(Value::Int(value.into())),
+            ref value => Cow::Borrowed(value),
+        };
 
-        self.write_i32((name_length + value_length + 4) as i32);
         self.write_u8(op_type as u8);
-        self.write_u8(bin.value.particle_type() as u8);
+        self.write_u8(value.particle_type());
         self.write_u8(0);
-        self.write_u8(name_length as u8);
+        self.write_u8(bin.name.len() as u8);
         self.write_str(bin.name);
-        bin.value.write_to(self);
+
+        let offset = self.buffer.len();
+        let mut limited = Limited::new(self, MAX_BUFFER_SIZE.saturating_sub(offset));
+        let value_size = value.write_to(&mut limited);
+        if limited.exceeded() {
+            return Err(BufferError::SizeExceeded {
+                size: offset + value_size,
+                max: MAX_BUFFER_SIZE,
+            });
+        }
+
+        size.patch(&mut self.buffer);
+        Ok(())
     }
 
     fn write_operation_for_bin_name(&mut self, name: &str, op_type: OperationType) {
@@ -735,14 +979,29 @@ impl Buffer {
         ProtoHeader::read_from(&mut self.buffer)
     }
 
-    pub fn read_stream_message_header(&mut self, proto: ProtoHeader) -> StreamMessageHeader {
+    pub fn read_stream_message_header(
+        &mut self,
+        proto: ProtoHeader,
+    ) -> Result<StreamMessageHeader> {
         StreamMessageHeader::read_from(&mut self.buffer, proto)
     }
 
-    pub fn read_header(&mut self) -> MessageHeader {
+    pub fn read_header(&mut self) -> Result<MessageHeader> {
         let proto = ProtoHeader::read_from(&mut self.buffer);
         MessageHeader::read_from(&mut self.buffer, proto)
     }
+
+    /// Read a bin name, re-using the allocation of a previously read equal name.
+    pub(crate) fn read_bin_name(&mut self, len: usize, names: &mut BinNames) -> Result<BinName> {
+        if let Ok(name) = str::from_utf8(&self.buffer[..len]) {
+            let name = names.intern(name);
+            self.buffer.advance(len);
+            Ok(name)
+        } else {
+            // Fall back to regular reading, for the sake of a descriptive error.
+            self.read_str(len).map(BinName::from)
+        }
+    }
 }
 
 impl AsRef<[u8]> for Buffer {
@@ -894,11 +1153,6 @@ impl Write for Buffer {
         self.buffer.write_i64(v)
     }
 
-    #[inline]
-    fn write_f32(&mut self, v: f32) -> usize {
-        self.buffer.write_f32(v)
-    }
-
     #[inline]
     fn write_f64(&mut self, v: f64) -> usize {
         self.buffer.write_f64(v)
@@ -951,14 +1205,60 @@ fn estimate_key_size(key: &Key, send_user_key: bool) -> (usize, u16) {
     (size, count)
 }
 
-fn estimate_operation_size_for_bin(bin: &Bin<'_>) -> usize {
-    OPERATION_HEADER_SIZE + bin.name.len() + bin.value.estimate_size()
+/// Size that a single key adds to a batch read request. Keys that share the namespace, set and bins
+/// with the previous key are encoded in a shorter form.
+pub fn batch_read_entry_size(
+    policy: &BatchPolicy,
+    batch_read: &BatchRead,
+    prev: Option<&BatchRead>,
+) -> usize {
+    let mut size = batch_read.key.digest.len() + 4;
+    match prev {
+        Some(prev) if batch_read.match_header(prev, policy.send_set_name) => {
+            size += 1;
+        }
+        _ => {
+            let key = &batch_read.key;
+            size += FIELD_HEADER_SIZE + 6 + key.namespace.len();
+            if policy.send_set_name {
+                size += FIELD_HEADER_SIZE + key.set_name.len();
+            }
+            if let Bins::Some(bin_names) = &batch_read.bins {
+                size += bin_names
+                    .iter()
+                    .map(|name| estimate_operation_size_for_bin_name(name))
+                    .sum::<usize>();
+            }
+        }
+    }
+    size
 }
 
 fn estimate_operation_size_for_bin_name(bin_name: &str) -> usize {
     OPERATION_HEADER_SIZE + bin_name.len()
 }
 
+// Make sure all bin names fit the server's limit, as the server otherwise rejects the whole command
+// with a generic parameter error.
+fn check_bin_names<'a>(names: impl IntoIterator<Item = &'a str>, max: usize) -> Result<()> {
+    match names.into_iter().find(|name| name.len() > max) {
+        Some(name) => Err(BufferError::BinNameTooLong {
+            name: name.to_owned(),
+            max,
+        }),
+        None => Ok(()),
+    }
+}
+
+// Convert the amount of operations into the `u16` of the message header, without silently
+// truncating it.
+fn operation_count(count: usize) -> Result<u16> {
+    u16::try_from(count).map_err(|_| BufferError::TooManyOperations {
+        count,
+        max: MAX_OPERATIONS,
+    })
+}
+
 /// A protocol header that is present at the beginning of each message sent to or received from an
 /// Aerospike instance.
 ///
@@ -987,6 +1287,21 @@ impl ProtoHeader {
         );
     }
 
+    /// Ensure that the header announces a supported message, that is at least as long as the
+    /// given size.
+    pub fn validate(&self, min: usize) -> Result<()> {
+        if !matches!(self.version, Version::V2) {
+            return Err(BufferError::InvalidVersion(self.version.into()));
+        }
+        if self.size < min {
+            return Err(BufferError::InvalidMessageLength {
+                size: self.size,
+                min,
+            });
+        }
+        Ok(())
+    }
+
     fn read_from(buf: &mut impl Buf) -> Self {
         let value = buf.get_u64();
 
@@ -1150,20 +1465,15 @@ impl MessageHeader {
         buf.put_u16(self.operation_count);
     }
 
-    fn read_from(buf: &mut impl Buf, proto: ProtoHeader) -> Self {
-        let ProtoHeader { version, ty, size } = proto;
+    fn read_from(buf: &mut impl Buf, proto: ProtoHeader) -> Result<Self> {
+        let ProtoHeader { ty, size, .. } = proto;
 
-        assert!(
-            matches!(version, Version::V2),
-            "invalid message version {version:?}",
-        );
-        assert!(
-            matches!(ty, ProtoType::Info | ProtoType::Message),
-            "invalid message type {ty:?}",
-        );
-        assert!(size >= Self::SIZE, "invalid message length {size}");
+        proto.validate(Self::SIZE)?;
+        if !matches!(ty, ProtoType::Info | ProtoType::Message) {
+            return Err(BufferError::InvalidMessageType(ty.into()));
+        }
 
-        Self {
+        Ok(Self {
             size: size - Self::SIZE,
             header_length: buf.get_u8(),
             read_attr: ReadAttr::from_bits_truncate(buf.get_u8()),
@@ -1176,7 +1486,7 @@ impl MessageHeader {
             timeout: Duration::from_secs(buf.get_u32().into()),
             field_count: buf.get_u16(),
             operation_count: buf.get_u16(),
-        }
+        })
     }
 
     /// Create a new header for a read operation.
@@ -1197,7 +1507,7 @@ impl MessageHeader {
             header_length: Self::SIZE as u8,
             read_attr,
             write_attr,
-            info_attr: InfoAttr::empty(),
+            info_attr: InfoAttr::from(policy.read_mode_sc),
             _unused: 0,
             result_code: ResultCode::Ok,
             generation: 0,
@@ -1218,7 +1528,7 @@ impl MessageHeader {
         operation_count: u16,
     ) -> Self {
         let mut generation: u32 = 0;
-        let mut info_attr = InfoAttr::empty();
+        let mut info_attr = InfoAttr::from(policy.base_policy.read_mode_sc);
 
         match policy.record_exists_action {
             RecordExistsAction::Update => (),
@@ -1286,23 +1596,16 @@ pub struct StreamMessageHeader {
 impl StreamMessageHeader {
     pub const SIZE: usize = 22;
 
-    fn read_from(buf: &mut impl Buf, proto: ProtoHeader) -> Self {
-        let ProtoHeader { version, ty, size } = proto;
-
-        assert!(
-            matches!(version, Version::V2),
-            "invalid message version {version:?}",
-        );
-        assert!(
-            matches!(ty, ProtoType::Message),
-            "invalid message type {ty:?}",
-        );
-        assert!(size >= Self::SIZE, "invalid message length {size}");
+    fn read_from(buf: &mut impl Buf, proto: ProtoHeader) -> Result<Self> {
+        proto.validate(Self::SIZE)?;
+        if !matches!(proto.ty, ProtoType::Message) {
+            return Err(BufferError::InvalidMessageType(proto.ty.into()));
+        }
 
         // skip header length, read attrs and write attrs
         buf.advance(3);
 
-        Self {
+        Ok(Self {
             info_attr: InfoAttr::from_bits_truncate(buf.get_u8()),
             _unused: buf.get_u8(),
             result_code: buf.get_u8().into(),
@@ -1311,13 +1614,14 @@ impl StreamMessageHeader {
             value: buf.get_u32(),
             field_count: buf.get_u16(),
             operation_count: buf.get_u16(),
-        }
+        })
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::operations::{list, scalar, Ops};
 
     #[test]
     fn resize_reclaim() {
@@ -1335,4 +1639,514 @@ mod tests {
         assert_eq!(&[1; 10], &buf.buffer[..10]);
         assert_eq!(&[0; 5], &buf.buffer[10..]);
     }
+
+    /// Encode a write command the way it was done before single-pass encoding, by calculating the
+    /// size of all values upfront.
+    fn set_write_two_pass(buf: &mut Buffer, policy: &WritePolicy, key: &Key, bins: &[Bin<'_>]) {
+        let (key_size, field_count) = estimate_key_size(key, policy.as_ref().send_key);
+        let op_size = bins
+            .iter()
+            .map(|bin| OPERATION_HEADER_SIZE + bin.name.len() + bin.value.estimate_size())
+            .sum::<usize>();
+
+        buf.clear(TOTAL_HEADER_SIZE + key_size + op_size).unwrap();
+
+        MessageHeader::for_write(
+            key_size + op_size,
+            policy,
+            ReadAttr::empty(),
+            WriteAttr::WRITE,
+            field_count,
+            bins.len() as u16,
+        )
+        .write_to(&mut buf.buffer);
+
+        buf.write_key(key, policy.as_ref().send_key);
+
+        for bin in bins {
+            buf.write_i32((bin.name.len() + bin.value.estimate_size() + 4) as i32);
+            buf.write_u8(OperationType::Write as u8);
+            buf.write_u8(bin.value.particle_type());
+            buf.write_u8(0);
+            buf.write_u8(bin.name.len() as u8);
+            buf.write_str(bin.name);
+            bin.value.write_to(buf);
+        }
+    }
+
+    fn large_bins(len: usize) -> Vec<Bin<'static>> {
+        let list = (0..len as i64)
+            .map(|i| crate::list!(i, format!("item-{i}"), crate::values![1.5, vec![0_u8; 16]]))
+            .collect::<Vec<_>>();
+        let map = (0..len as i64)
+            .map(|i| {
+                (
+                    crate::MapKey::from(i),
+                    crate::map!("name" => format!("entry-{i}")),
+                )
+            })
+            .collect::<std::collections::HashMap<_, _>>();
+
+        vec![
+            Bin::new("int", 5),
+            Bin::new("list", list),
+            Bin::new("map", map),
+            Bin::new("string", "value"),
+        ]
+    }
+
+    #[test]
+    fn set_write_single_pass() {
+        let policy = WritePolicy::default();
+        let key = Key::new("test", "demo", "key");
+        let bins = large_bins(100);
+
+        let mut single = Buffer::new(0);
+        single
+            .set_write(
+                &policy,
+                OperationType::Write,
+                &key,
+                &bins,
+                true,
+                MAX_BIN_NAME_LEN,
+            )
+            .unwrap();
+
+        let mut double = Buffer::new(0);
+        set_write_two_pass(&mut double, &policy, &key, &bins);
+
+        assert_eq!(double.as_ref(), single.as_ref());
+    }
+
+    #[test]
+    fn downgrade_bool_bins() {
+        let policy = WritePolicy::default();
+        let key = Key::new("test", "demo", "key");
+
+        let mut native = Buffer::new(0);
+        native
+            .set_write(
+                &policy,
+                OperationType::Write,
+                &key,
+                &[Bin::new("b", 1)],
+                true,
+                MAX_BIN_NAME_LEN,
+            )
+            .unwrap();
+
+        let mut downgraded = Buffer::new(0);
+        downgraded
+            .set_write(
+                &policy,
+                OperationType::Write,
+                &key,
+                &[Bin::new("b", true)],
+                false,
+                MAX_BIN_NAME_LEN,
+            )
+            .unwrap();
+
+        assert_eq!(native.as_ref(), downgraded.as_ref());
+
+        let int_bin = Bin::new("b", 1);
+        let bool_bin = Bin::new("b", true);
+
+        native
+            .set_operate(
+                &policy,
+                &key,
+                &[scalar::put(&int_bin)],
+                true,
+                MAX_BIN_NAME_LEN,
+            )
+            .unwrap();
+        downgraded
+            .set_operate(
+                &policy,
+                &key,
+                &[scalar::put(&bool_bin)],
+                false,
+                MAX_BIN_NAME_LEN,
+            )
+            .unwrap();
+
+        assert_eq!(native.as_ref(), downgraded.as_ref());
+    }
+
+    #[test]
+    fn continue_on_error_write_flags() {
+        let key = Key::new("test", "demo", "key");
+        let value = Value::from(1);
+        let no_fail = list::Policy::new(
+            list::OrderType::Unordered,
+            list::WriteFlags::NO_FAIL | list::WriteFlags::PARTIAL,
+        );
+
+        let mut flagged = Buffer::new(0);
+        flagged
+            .set_operate(
+                &WritePolicy::default(),
+                &key,
+                &[list::append(no_fail, "l", &value)],
+                true,
+                MAX_BIN_NAME_LEN,
+            )
+            .unwrap();
+
+        let mut continued = Buffer::new(0);
+        continued
+            .set_operate(
+                &WritePolicy::builder().continue_on_error(true).build(),
+                &key,
+                &[list::append(list::Policy::default(), "l", &value)],
+                true,
+                MAX_BIN_NAME_LEN,
+            )
+            .unwrap();
+
+        assert_eq!(flagged.as_ref(), continued.as_ref());
+    }
+
+    #[test]
+    fn read_mode_sc_attrs() {
+        let key = Key::new("test", "demo", "key");
+        let info_attr = |read_mode_sc| {
+            let policy = BasePolicy {
+                read_mode_sc,
+                ..BasePolicy::default()
+            };
+            let mut buf = Buffer::new(0);
+            buf.set_read(&policy, &key, &Bins::All, MAX_BIN_NAME_LEN)
+                .unwrap();
+            InfoAttr::from_bits_truncate(buf.buffer[ProtoHeader::SIZE + 3])
+        };
+
+        assert!(info_attr(ReadModeSc::Session).is_empty());
+        assert_eq!(
+            InfoAttr::SC_READ_TYPE.bits(),
+            info_attr(ReadModeSc::Linearize).bits()
+        );
+        assert_eq!(
+            InfoAttr::SC_READ_RELAX.bits(),
+            info_attr(ReadModeSc::AllowReplica).bits()
+        );
+        assert_eq!(
+            (InfoAttr::SC_READ_TYPE | InfoAttr::SC_READ_RELAX).bits(),
+            info_attr(ReadModeSc::AllowUnavailable).bits()
+        );
+    }
+
+    #[test]
+    fn scan_projection() {
+        let projection = |include_bin_data, bins: &Bins| {
+            let policy = ScanPolicy {
+                include_bin_data,
+                ..ScanPolicy::default()
+            };
+            let mut buf = Buffer::new(0);
+            buf.set_scan(
+                &policy,
+                "test",
+                "demo",
+                bins,
+                &[],
+                1,
+                &[0, 1],
+                0,
+                MAX_BIN_NAME_LEN,
+            )
+            .unwrap();
+            let header = &buf.buffer[ProtoHeader::SIZE..];
+            (header[1], u16::from_be_bytes([header[20], header[21]]))
+        };
+
+        let read = ReadAttr::READ.bits();
+        let no_bins = (ReadAttr::READ | ReadAttr::GET_NO_BINS).bits();
+        let selected = Bins::from(["a", "b"]);
+        assert_eq!((read, 0), projection(true, &Bins::All));
+        assert_eq!((read, 2), projection(true, &selected));
+        assert_eq!((no_bins, 0), projection(true, &Bins::None));
+        assert_eq!((no_bins, 0), projection(false, &Bins::All));
+        assert_eq!((no_bins, 0), projection(false, &selected));
+    }
+
+    #[test]
+    fn batch_flags() {
+        let flags_of = |policy: &BatchPolicy| {
+            let mut buf = Buffer::new(0);
+            let reads = [BatchRead::new(Key::new("test", "demo", 1), Bins::All)];
+            buf.set_batch_read(policy, &reads, MAX_BIN_NAME_LEN)
+                .unwrap();
+            buf.buffer[TOTAL_HEADER_SIZE + FIELD_HEADER_SIZE + 4]
+        };
+
+        assert_eq!(0b101, flags_of(&BatchPolicy::default()));
+        assert_eq!(
+            0b010,
+            flags_of(
+                &BatchPolicy::builder()
+                    .allow_inline(false)
+                    .allow_inline_ssd(true)
+                    .respond_all_keys(false)
+                    .build()
+            )
+        );
+        assert_eq!(
+            0b001,
+            flags_of(
+                &BatchPolicy::builder()
+                    .allow_inline(false)
+                    .respond_all_keys(false)
+                    .short_query_threshold(1)
+                    .build()
+            )
+        );
+    }
+
+    #[test]
+    fn scan_operations() {
+        let ops = Ops::new().list_size("l").read("a").build();
+        let mut buf = Buffer::new(0);
+        buf.set_scan(
+            &ScanPolicy::default(),
+            "test",
+            "demo",
+            &Bins::None,
+            &ops,
+            1,
+            &[0, 1],
+            0,
+            MAX_BIN_NAME_LEN,
+        )
+        .unwrap();
+
+        let header = &buf.buffer[ProtoHeader::SIZE..];
+        assert_eq!(ReadAttr::READ.bits(), header[1]);
+        assert_eq!(2, u16::from_be_bytes([header[20], header[21]]));
+
+        let long_name = Ops::new().read("a".repeat(16)).build();
+        assert!(Buffer::new(0)
+            .set_scan(
+                &ScanPolicy::default(),
+                "test",
+                "demo",
+                &Bins::All,
+                &long_name,
+                1,
+                &[0],
+                0,
+                15,
+            )
+            .is_err());
+    }
+
+    #[cfg(feature = "raw")]
+    #[test]
+    fn raw_framing() {
+        let mut buf = Buffer::new(0);
+        buf.set_raw(ProtoType::Info, b"build\n").unwrap();
+        assert_eq!(b"\x02\x01\0\0\0\0\0\x06build\n", &buf.buffer[..]);
+
+        let header = buf.read_proto_header();
+        assert!(matches!(header.ty, ProtoType::Info));
+        assert_eq!(6, header.size);
+        header.validate(6).unwrap();
+    }
+
+    #[test]
+    fn set_write_size_exceeded() {
+        let key = Key::new("test", "demo", "key");
+        let bins = [Bin::new("blob", vec![0_u8; MAX_BUFFER_SIZE])];
+
+        let result = Buffer::new(0).set_write(
+            &WritePolicy::default(),
+            OperationType::Write,
+            &key,
+            &bins,
+            true,
+            MAX_BIN_NAME_LEN,
+        );
+
+        assert!(matches!(result, Err(BufferError::SizeExceeded { .. })));
+    }
+
+    #[test]
+    fn set_write_size_checked_upfront() {
+        let key = Key::new("test", "demo", "key");
+        let bins = [Bin::new("blob", vec![0_u8; MAX_BUFFER_SIZE])];
+
+        let mut buf = Buffer::new(0);
+        let result = buf.set_write(
+            &WritePolicy::default(),
+            OperationType::Write,
+            &key,
+            &bins,
+            true,
+            MAX_BIN_NAME_LEN,
+        );
+
+        assert!(matches!(result, Err(BufferError::SizeExceeded { .. })));
+        assert!(buf.buffer.capacity() < MAX_BUFFER_SIZE);
+    }
+
+    #[test]
+    fn set_write_container_size_limited() {
+        let key = Key::new("test", "demo", "key");
+        let list = vec![Value::from(vec![0_u8; 1024]); 2048];
+        let bins = [Bin::new("small", 1), Bin::new("list", list)];
+
+        let mut buf = Buffer::new(0);
+        let result = buf.set_write(
+            &WritePolicy::default(),
+            OperationType::Write,
+            &key,
+            &bins,
+            true,
+            MAX_BIN_NAME_LEN,
+        );
+
+        assert!(matches!(
+            result,
+            Err(BufferError::SizeExceeded { size, max: MAX_BUFFER_SIZE }) if size > 2 * 1024 * 1024
+        ));
+        assert!(buf.buffer.len() <= MAX_BUFFER_SIZE);
+    }
+
+    #[test]
+    fn bin_name_too_long() {
+        let policy = WritePolicy::default();
+        let key = Key::new("test", "demo", "key");
+        let name = "a_very_long_bin_name";
+        let bin = Bin::new(name, 1);
+
+        let mut buf = Buffer::new(0);
+        let result = buf.set_write(
+            &policy,
+            OperationType::Write,
+            &key,
+            &[Bin::new("short", 1), bin.clone()],
+            true,
+            MAX_BIN_NAME_LEN,
+        );
+        assert!(matches!(
+            result,
+            Err(BufferError::BinNameTooLong { name: n, max: MAX_BIN_NAME_LEN }) if n == name
+        ));
+
+        let result = buf.set_operate(&policy, &key, &[scalar::get_bin(name)], true, 15);
+        assert!(matches!(result, Err(BufferError::BinNameTooLong { .. })));
+
+        let result = buf.set_read(&policy.base_policy, &key, &Bins::from([name]), 15);
+        assert!(matches!(result, Err(BufferError::BinNameTooLong { .. })));
+
+        buf.set_write(
+            &policy,
+            OperationType::Write,
+            &key,
+            &[bin],
+            true,
+            MAX_LONG_BIN_NAME_LEN,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn too_many_operations() {
+        let key = Key::new("test", "demo", "key");
+        let bins = vec![Bin::new("b", 1); MAX_OPERATIONS + 1];
+
+        let result = Buffer::new(0).set_write(
+            &WritePolicy::default(),
+            OperationType::Write,
+            &key,
+            &bins,
+            true,
+            MAX_BIN_NAME_LEN,
+        );
+        assert!(matches!(
+            result,
+            Err(BufferError::TooManyOperations {
+                count,
+                max: MAX_OPERATIONS,
+            }) if count == MAX_OPERATIONS + 1
+        ));
+    }
+
+    /// Compare single-pass encoding against calculating the value sizes upfront, for large list and
+    /// map bins. Run with `cargo test --release -- --ignored --nocapture bench_set_write`.
+    #[test]
+    #[ignore = "benchmark"]
+    fn bench_set_write() {
+        use std::time::Instant;
+
+        const ROUNDS: u32 = 200;
+
+        let policy = WritePolicy::default();
+        let key = Key::new("test", "demo", "key");
+        let bins = large_bins(2000);
+        let mut buf = Buffer::new(usize::MAX);
+
+        let start = Instant::now();
+        for _ in 0..ROUNDS {
+            set_write_two_pass(&mut buf, &policy, &key, &bins);
+        }
+        let two_pass = start.elapsed() / ROUNDS;
+
+        let start = Instant::now();
+        for _ in 0..ROUNDS {
+            buf.set_write(
+                &policy,
+                OperationType::Write,
+                &key,
+                &bins,
+                true,
+                MAX_BIN_NAME_LEN,
+            )
+            .unwrap();
+        }
+        let single_pass = start.elapsed() / ROUNDS;
+
+        println!(
+            "{} bytes: two-pass {two_pass:?}, single-pass {single_pass:?}",
+            buf.as_ref().len()
+        );
+    }
+
+    #[test]
+    fn invalid_headers() {
+        fn header(version: u8, ty: u8, size: usize) -> Buffer {
+            let mut buf = Buffer::new(0);
+            ProtoHeader {
+                version: version.into(),
+                ty: ty.into(),
+                size,
+            }
+            .write_to(&mut buf.buffer);
+            buf.buffer.put_bytes(0, MessageHeader::SIZE);
+            buf
+        }
+
+        assert!(header(2, 3, MessageHeader::SIZE).read_header().is_ok());
+        assert!(matches!(
+            header(1, 3, MessageHeader::SIZE).read_header(),
+            Err(BufferError::InvalidVersion(1))
+        ));
+        assert!(matches!(
+            header(2, 2, MessageHeader::SIZE).read_header(),
+            Err(BufferError::InvalidMessageType(2))
+        ));
+        assert!(matches!(
+            header(2, 3, 4).read_header(),
+            Err(BufferError::InvalidMessageLength { size: 4, .. })
+        ));
+
+        let mut buf = header(2, 1, StreamMessageHeader::SIZE);
+        let proto = buf.read_proto_header();
+        assert!(matches!(
+            buf.read_stream_message_header(proto),
+            Err(BufferError::InvalidMessageType(1))
+        ));
+    }
 }
@@ -6,6 +6,7 @@ use tracing::warn;
 use super::{Command, CommandError, Result, SingleCommand};
 use crate::{
     cluster::{Cluster, Node},
+    metrics::CommandKind,
     net::Connection,
     policies::WritePolicy,
     Key, ResultCode,
@@ -33,7 +34,9 @@ impl<'a> DeleteCommand<'a> {
 
 #[async_trait]
 impl<'a> Command for DeleteCommand<'a> {
-    fn prepare_buffer(&mut self, conn: &mut Connection) -> Result<(), CommandError> {
+    const NAME: &'static str = "DeleteCommand";
+
+    fn prepare_buffer(&mut self, conn: &mut Connection, _node: &Node) -> Result<(), CommandError> {
         conn.buffer()
             .set_delete(self.policy, self.single_command.key)
             .map_err(Into::into)
@@ -43,6 +46,14 @@ impl<'a> Command for DeleteCommand<'a> {
         self.single_command.get_node().await
     }
 
+    fn single_command(&self) -> Option<&SingleCommand<'_>> {
+        Some(&self.single_command)
+    }
+
+    fn kind(&self) -> CommandKind {
+        CommandKind::Write
+    }
+
     async fn parse_result(&mut self, conn: &mut Connection) -> Result<()> {
         let header = conn.read_header().await.map_err(|err| {
             warn!(%err, "failed parsing message header");
@@ -60,4 +71,8 @@ impl<'a> Command for DeleteCommand<'a> {
 
         SingleCommand::empty_socket(conn, header.size).await
     }
+
+    fn pipelined(&self) -> bool {
+        true
+    }
 }
@@ -33,7 +33,9 @@ impl<'a> ExistsCommand<'a> {
 
 #[async_trait]
 impl<'a> Command for ExistsCommand<'a> {
-    fn prepare_buffer(&mut self, conn: &mut Connection) -> Result<()> {
+    const NAME: &'static str = "ExistsCommand";
+
+    fn prepare_buffer(&mut self, conn: &mut Connection, _node: &Node) -> Result<()> {
         conn.buffer()
             .set_exists(self.policy, self.single_command.key)
             .map_err(Into::into)
@@ -43,6 +45,10 @@ impl<'a> Command for ExistsCommand<'a> {
         self.single_command.get_node().await
     }
 
+    fn single_command(&self) -> Option<&SingleCommand<'_>> {
+        Some(&self.single_command)
+    }
+
     async fn parse_result(&mut self, conn: &mut Connection) -> Result<()> {
         let header = conn.read_header().await.map_err(|err| {
             warn!(%err, "failed parsing message header");
@@ -60,4 +66,8 @@ impl<'a> Command for ExistsCommand<'a> {
 
         SingleCommand::empty_socket(conn, header.size).await
     }
+
+    fn pipelined(&self) -> bool {
+        true
+    }
 }
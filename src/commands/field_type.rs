@@ -11,9 +11,10 @@ pub enum FieldType {
     // GUID  = 5,
     // DigestRipeArray = 6,
     TranId = 7, // user supplied transaction id, which is simply passed back,
-    // ScanOptions = 8,
+    ScanOptions = 8,
     ScanTimeout = 9,
     PidArray = 11,
+    MaxRecords = 13,
     // IndexName = 21,
     // IndexRange = 22,
     // IndexFilter = 23,
@@ -27,5 +28,5 @@ pub enum FieldType {
     // QueryBinList = 40,
     BatchIndex = 41,
     BatchIndexWithSet = 42,
-    // FilterExp = 43,
+    FilterExp = 43,
 }
@@ -11,17 +11,33 @@ pub(crate) mod commands {
     pub const FEATURES: &str = "features";
     pub const NODE: &str = "node";
     pub const PARTITION_GENERATION: &str = "partition-generation";
+    pub const PARTITIONS: &str = "partitions";
+    pub const REPLICAS: &str = "replicas";
+    pub const REPLICAS_ALL: &str = "replicas-all";
     pub const REPLICAS_MASTER: &str = "replicas-master";
     pub const SERVICES: &str = "services";
     pub const SERVICES_ALTERNATE: &str = "services-alternate";
 }
 
+/// Partition ownership of a single namespace, as reported by one node.
+#[derive(Debug, Default, Eq, PartialEq)]
+pub(crate) struct Replicas {
+    /// Regime of the partitions in strong consistency namespaces, which increases with each
+    /// change of ownership. Always _zero_ for other namespaces, or older servers.
+    pub regime: u32,
+    /// Bitmaps of the partitions that the node owns, one for each replica, starting with the
+    /// master.
+    pub bitmaps: Vec<Vec<u8>>,
+}
+
 #[derive(Default)]
 pub(crate) struct Info {
     pub cluster_name: Option<String>,
     pub features: Option<FeatureSupport>,
     pub node: Option<String>,
     pub partition_generation: Option<isize>,
+    pub partitions: Option<usize>,
+    pub replicas: Option<HashMap<String, Replicas>>,
     pub replicas_master: Option<HashMap<String, Vec<u8>>>,
     pub services: Option<Vec<Host>>,
     pub services_alternate: Option<Vec<Host>>,
@@ -91,6 +107,12 @@ fn parse_typed(response: &str) -> Result<Info> {
                     Ok(gen) => info.partition_generation = Some(gen),
                     Err(e) => error!(value, error = ?e, "malformed partition generation"),
                 },
+                commands::PARTITIONS => match value.parse() {
+                    Ok(count) => info.partitions = Some(count),
+                    Err(e) => error!(value, error = ?e, "malformed partition count"),
+                },
+                commands::REPLICAS => info.replicas = Some(parse_replicas_list(value, true)),
+                commands::REPLICAS_ALL => info.replicas = Some(parse_replicas_list(value, false)),
                 commands::REPLICAS_MASTER => info.replicas_master = Some(parse_replicas(value)),
                 commands::SERVICES => info.services = Some(parse_hosts(value)),
                 commands::SERVICES_ALTERNATE => info.services_alternate = Some(parse_hosts(value)),
@@ -132,3 +154,88 @@ fn parse_replicas(value: &str) -> HashMap<String, Vec<u8>> {
         })
         .collect()
 }
+
+/// Parse the response of the `replicas` or `replicas-all` command, which have the format
+/// `NS:[REGIME,]COUNT,BITMAP,...;`, with one bitmap for each replica up to the replication factor
+/// `COUNT`. Only the `replicas` command reports the regime.
+///
+/// A node that doesn't hold any partitions of a replica may report it with an empty bitmap.
+fn parse_replicas_list(value: &str, with_regime: bool) -> HashMap<String, Replicas> {
+    value
+        .split(';')
+        .filter(|s| !s.is_empty())
+        .filter_map(|pair| {
+            let replicas = pair.split_once(':').and_then(|(key, value)| {
+                let mut parts = value.split(',');
+                let regime = if with_regime {
+                    parts.next()?.parse().ok()?
+                } else {
+                    0
+                };
+                let count = parts.next()?.parse().ok()?;
+                let bitmaps = parts
+                    .map(|bitmap| general_purpose::STANDARD.decode(bitmap).ok())
+                    .collect::<Option<Vec<_>>>()
+                    .filter(|bitmaps| bitmaps.len() == count)?;
+
+                Some((key.to_owned(), Replicas { regime, bitmaps }))
+            });
+
+            if replicas.is_none() {
+                error!(got = pair, "malformed replicas response");
+            }
+
+            replicas
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_replicas_list, Replicas};
+
+    #[test]
+    fn replicas_regime() {
+        let replicas = parse_replicas_list("test:3,2,gA==,QA==;bar:0,1,/w==;broken:1", true);
+
+        assert_eq!(2, replicas.len());
+        assert_eq!(
+            Some(&Replicas {
+                regime: 3,
+                bitmaps: vec![vec![0x80], vec![0x40]],
+            }),
+            replicas.get("test")
+        );
+        assert_eq!(
+            Some(&Replicas {
+                regime: 0,
+                bitmaps: vec![vec![0xff]],
+            }),
+            replicas.get("bar")
+        );
+    }
+
+    #[test]
+    fn replicas_all() {
+        let replicas = parse_replicas_list(
+            "test:3,gA==,,IA==;bar:1,/w==;short:3,gA==,QA==;invalid:2,gA==,@@@@;",
+            false,
+        );
+
+        assert_eq!(2, replicas.len());
+        assert_eq!(
+            Some(&Replicas {
+                regime: 0,
+                bitmaps: vec![vec![0x80], vec![], vec![0x20]],
+            }),
+            replicas.get("test")
+        );
+        assert_eq!(
+            Some(&Replicas {
+                regime: 0,
+                bitmaps: vec![vec![0xff]],
+            }),
+            replicas.get("bar")
+        );
+    }
+}
@@ -6,6 +6,8 @@ mod exists_command;
 mod info_command;
 mod operate_command;
 mod particle_type;
+#[cfg(feature = "raw")]
+mod raw_command;
 mod read_command;
 mod scan_command;
 mod single_command;
@@ -15,27 +17,36 @@ mod write_command;
 
 mod field_type;
 
-use std::sync::Arc;
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
 
 use async_trait::async_trait;
+use tokio::time::Instant;
+use tokio_util::sync::CancellationToken;
 
-pub use self::particle_type::ParseParticleError;
+#[cfg(feature = "raw")]
+pub(crate) use self::raw_command::RawCommand;
+pub use self::{admin_command::User, particle_type::ParseParticleError};
 pub(crate) use self::{
     admin_command::{hash_password, AdminCommand},
     batch_read_command::BatchReadCommand,
     delete_command::DeleteCommand,
     exists_command::ExistsCommand,
-    info_command::{commands as info_cmds, raw as info_raw, typed as info_typed, Info},
+    info_command::{commands as info_cmds, raw as info_raw, typed as info_typed, Info, Replicas},
     operate_command::OperateCommand,
     particle_type::ParticleType,
     read_command::ReadCommand,
-    scan_command::ScanCommand,
+    scan_command::{partition_chunks, split_max_records, split_partitions, ScanCommand},
     single_command::SingleCommand,
-    stream_command::StreamCommand,
+    stream_command::{RecordMapper, StreamCommand},
     touch_command::TouchCommand,
     write_command::WriteCommand,
 };
-use crate::{cluster::Node, net::Connection, ResultCode};
+use crate::{cluster::Node, metrics::CommandKind, net::Connection, ResultCode};
 
 pub type Result<T, E = CommandError> = crate::errors::Result<T, E>;
 
@@ -47,8 +58,15 @@ pub enum CommandError {
     BufferSize { size: usize, max: usize },
     #[error("timeout")]
     Timeout,
-    #[error("server error: {}", .0.into_string())]
+    #[error("cancelled")]
+    Cancelled,
+    #[error("server error: {0}")]
     ServerError(ResultCode),
+    /// A single operation of an operate command was rejected by the server. This is only reported
+    /// if it's the only operation that could have caused the result code. Otherwise, the result
+    /// code is returned as [`Self::ServerError`].
+    #[error("operation {index} failed: {code}")]
+    OperationFailed { index: usize, code: ResultCode },
     #[error("invalid UTF-8 content encountered")]
     InvalidUtf8(#[from] std::str::Utf8Error),
     #[error("I/O related error")]
@@ -65,6 +83,8 @@ pub enum CommandError {
     NoConnection,
     #[error("parsing failed: {0}")]
     Parse(&'static str),
+    #[error("failed decoding record")]
+    Bin(#[from] crate::record::BinError),
     #[error("other error")]
     Other(#[source] Box<crate::errors::Error>),
 }
@@ -72,12 +92,147 @@ pub enum CommandError {
 // Command interface describes all commands available
 #[async_trait]
 trait Command {
-    fn prepare_buffer(&mut self, conn: &mut Connection) -> Result<()>;
+    /// Name of the command, to identify it in logs. Spelled out for each command, as type names
+    /// aren't stable and include the type parameters of generic commands.
+    const NAME: &'static str;
+
+    /// Write the request into the connection's buffer, adjusted to the features of the node that
+    /// it's sent to.
+    fn prepare_buffer(&mut self, conn: &mut Connection, node: &Node) -> Result<()>;
     async fn get_node(&self) -> Option<Arc<Node>>;
     async fn parse_result(&mut self, conn: &mut Connection) -> Result<()>;
+
+    /// Whether the server answers the command with exactly one message, which allows sending it
+    /// over a shared pipelined connection.
+    fn pipelined(&self) -> bool {
+        false
+    }
+
+    /// Whether the policy's timeout also bounds reading the response. Scans stream their records
+    /// for as long as the consumer keeps up, so only the steps up to sending the request are
+    /// bounded for them.
+    fn bounded_response(&self) -> bool {
+        true
+    }
+
+    /// The single record command that this command is based on, which makes the command visible
+    /// to the [`CommandInterceptor`](crate::interceptor::CommandInterceptor).
+    fn single_command(&self) -> Option<&SingleCommand<'_>> {
+        None
+    }
+
+    /// Kind of the command in the latency metrics. Only used for single record commands, as batch
+    /// reads and scans are tracked as a whole.
+    fn kind(&self) -> CommandKind {
+        CommandKind::Read
+    }
+
+    /// Name of the command, for places that only have access to the command value.
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+}
+
+/// Await the future, but give up with a [`CommandError::Timeout`] once the deadline passed. Without
+/// a deadline, the future is awaited until it completes.
+async fn until_deadline<F: Future>(deadline: Option<Instant>, future: F) -> Result<F::Output> {
+    match deadline {
+        Some(deadline) => tokio::time::timeout_at(deadline, future)
+            .await
+            .map_err(|_| CommandError::Timeout),
+        None => Ok(future.await),
+    }
+}
+
+/// Await the future, but give up once the token is cancelled, in which case `None` is returned.
+pub(crate) async fn until_cancelled<F: Future>(
+    token: &CancellationToken,
+    future: F,
+) -> Option<F::Output> {
+    until_stopped(token.cancelled(), future).await
+}
+
+/// Await the future, but give up once the `stop` future completes, in which case `None` is
+/// returned.
+pub(crate) async fn until_stopped<S, F>(stop: S, future: F) -> Option<F::Output>
+where
+    S: Future<Output = ()>,
+    F: Future,
+{
+    tokio::pin!(stop, future);
+    UntilStopped { stop, future }.await
+}
+
+/// Race between a future and a stop signal, preferring the stop signal if both are ready.
+struct UntilStopped<'a, S, F> {
+    stop: Pin<&'a mut S>,
+    future: Pin<&'a mut F>,
+}
+
+impl<S: Future<Output = ()>, F: Future> Future for UntilStopped<'_, S, F> {
+    type Output = Option<F::Output>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        if self.stop.as_mut().poll(cx).is_ready() {
+            return Poll::Ready(None);
+        }
+
+        self.future.as_mut().poll(cx).map(Some)
+    }
+}
+
+/// Whether the error was caused by the connection to the node rather than the request itself, so
+/// the request may succeed on a different node.
+#[must_use]
+pub const fn is_network_error(err: &CommandError) -> bool {
+    matches!(
+        err,
+        CommandError::Timeout
+            | CommandError::Io(_)
+            | CommandError::Network(_)
+            | CommandError::NoConnection
+    )
 }
 
 #[must_use]
 pub const fn keep_connection(err: &CommandError) -> bool {
     matches!(err, CommandError::ServerError(ResultCode::KeyNotFoundError))
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tokio::time::Instant;
+
+    use tokio_util::sync::CancellationToken;
+
+    use super::{until_cancelled, until_deadline, CommandError};
+
+    #[tokio::test]
+    async fn deadline_bounds_future() {
+        let deadline = Instant::now() + Duration::from_millis(20);
+        let result = until_deadline(Some(deadline), std::future::pending::<()>()).await;
+        assert!(matches!(result, Err(CommandError::Timeout)));
+        assert!(Instant::now() >= deadline);
+
+        let deadline = Instant::now() + Duration::from_secs(10);
+        assert_eq!(
+            1,
+            until_deadline(Some(deadline), async { 1 }).await.unwrap()
+        );
+        assert_eq!(2, until_deadline(None, async { 2 }).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn cancellation_aborts_future() {
+        let token = CancellationToken::new();
+        assert_eq!(Some(1), until_cancelled(&token, async { 1 }).await);
+
+        token.cancel();
+        assert_eq!(
+            None,
+            until_cancelled(&token, std::future::pending::<()>()).await
+        );
+    }
+}
@@ -2,11 +2,12 @@ use std::sync::Arc;
 
 use async_trait::async_trait;
 
-use super::{Command, ReadCommand, Result, SingleCommand};
+use super::{Command, CommandError, ReadCommand, Result, SingleCommand};
 use crate::{
     cluster::{Cluster, Node},
+    metrics::CommandKind,
     net::Connection,
-    operations::Operation,
+    operations::{self, Operation},
     policies::WritePolicy,
     Bins, Key,
 };
@@ -24,8 +25,11 @@ impl<'a> OperateCommand<'a> {
         key: &'a Key,
         operations: &'a [Operation<'a>],
     ) -> Self {
+        let mut read_command = ReadCommand::new(&policy.base_policy, cluster, key, Bins::All);
+        read_command.key_value_pairs(operations);
+
         OperateCommand {
-            read_command: ReadCommand::new(&policy.base_policy, cluster, key, Bins::All),
+            read_command,
             policy,
             operations,
         }
@@ -38,21 +42,57 @@ impl<'a> OperateCommand<'a> {
 
 #[async_trait]
 impl<'a> Command for OperateCommand<'a> {
-    fn prepare_buffer(&mut self, conn: &mut Connection) -> Result<()> {
+    const NAME: &'static str = "OperateCommand";
+
+    fn prepare_buffer(&mut self, conn: &mut Connection, node: &Node) -> Result<()> {
         conn.buffer()
             .set_operate(
                 self.policy,
                 self.read_command.single_command.key,
                 self.operations,
+                self.read_command.single_command.bool_bins(),
+                node.max_bin_name_len(),
             )
             .map_err(Into::into)
     }
 
     async fn get_node(&self) -> Option<Arc<Node>> {
-        self.read_command.get_node().await
+        // Writes must always go to the master.
+        if self.operations.iter().all(Operation::is_read) {
+            self.read_command.get_node().await
+        } else {
+            self.read_command.single_command.get_node().await
+        }
+    }
+
+    fn single_command(&self) -> Option<&SingleCommand<'_>> {
+        Some(&self.read_command.single_command)
+    }
+
+    fn kind(&self) -> CommandKind {
+        if self.operations.iter().all(Operation::is_read) {
+            CommandKind::Read
+        } else {
+            CommandKind::Write
+        }
     }
 
     async fn parse_result(&mut self, conn: &mut Connection) -> Result<()> {
-        self.read_command.parse_result(conn).await
+        self.read_command
+            .parse_result(conn)
+            .await
+            .map_err(|err| match err {
+                CommandError::ServerError(code) if operations::is_operation_error(code) => {
+                    match operations::failed_index(self.operations) {
+                        Some(index) => CommandError::OperationFailed { index, code },
+                        None => CommandError::ServerError(code),
+                    }
+                }
+                err => err,
+            })
+    }
+
+    fn pipelined(&self) -> bool {
+        true
     }
 }
@@ -0,0 +1,61 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+
+use super::{Command, Result, SingleCommand};
+use crate::{
+    cluster::Node,
+    net::Connection,
+    policies::BasePolicy,
+    raw::{Request, Response},
+};
+
+pub struct RawCommand<'a> {
+    policy: &'a BasePolicy,
+    node: Arc<Node>,
+    request: Request,
+    pub response: Option<Response>,
+}
+
+impl<'a> RawCommand<'a> {
+    pub fn new(policy: &'a BasePolicy, node: Arc<Node>, request: Request) -> Self {
+        Self {
+            policy,
+            node,
+            request,
+            response: None,
+        }
+    }
+
+    pub async fn execute(&mut self) -> Result<()> {
+        SingleCommand::execute(self.policy, self).await
+    }
+}
+
+#[async_trait]
+impl Command for RawCommand<'_> {
+    const NAME: &'static str = "RawCommand";
+
+    fn prepare_buffer(&mut self, conn: &mut Connection, _node: &Node) -> Result<()> {
+        conn.buffer()
+            .set_raw(self.request.message_type.into(), &self.request.body)
+            .map_err(Into::into)
+    }
+
+    async fn get_node(&self) -> Option<Arc<Node>> {
+        Some(Arc::clone(&self.node))
+    }
+
+    async fn parse_result(&mut self, conn: &mut Connection) -> Result<()> {
+        let header = conn.read_proto_header().await?;
+        header.validate(0)?;
+
+        conn.read_buffer(header.size).await?;
+        self.response = Some(Response {
+            message_type: header.ty.into(),
+            body: conn.buffer().read_slice(header.size).to_vec(),
+        });
+
+        Ok(())
+    }
+}
@@ -9,20 +9,27 @@ use std::{
 use async_trait::async_trait;
 use tracing::warn;
 
-use super::{Command, CommandError, Result, SingleCommand};
+use super::{field_type::FieldType, Command, CommandError, Result, SingleCommand};
 use crate::{
     cluster::{Cluster, Node},
     msgpack::Read,
     net::Connection,
+    operations::{self, Operation},
     policies::BasePolicy,
-    Bins, Key, Record, ResultCode, Value,
+    record::BinNames,
+    value::ReadOptions,
+    BinName, Bins, Key, Record, ResultCode, UserKey, Value,
 };
 
 pub struct ReadCommand<'a> {
     pub single_command: SingleCommand<'a>,
     pub record: Option<Record>,
+    /// Raw bin entries in the order the server returned them, instead of merging them into the
+    /// record. Only used if enabled with [`Self::keep_entries`].
+    pub entries: Option<Vec<(BinName, Value)>>,
     policy: &'a BasePolicy,
     bins: Bins,
+    operations: &'a [Operation<'a>],
 }
 
 impl<'a> ReadCommand<'a> {
@@ -32,9 +39,23 @@ impl<'a> ReadCommand<'a> {
             bins,
             policy,
             record: None,
+            entries: None,
+            operations: &[],
         }
     }
 
+    /// Keep the raw bin entries of the response in [`Self::entries`], while the record only
+    /// contains the header data.
+    pub fn keep_entries(&mut self) {
+        self.entries = Some(Vec::new());
+    }
+
+    /// Decode the results of the given operations as ordered key/value pairs, where they select
+    /// them through a typed [`MapReturn`](crate::operations::map::MapReturn).
+    pub fn key_value_pairs(&mut self, operations: &'a [Operation<'a>]) {
+        self.operations = operations;
+    }
+
     pub async fn execute(&mut self) -> Result<()> {
         SingleCommand::execute(self.policy, self).await
     }
@@ -42,30 +63,16 @@ impl<'a> ReadCommand<'a> {
     fn parse_record(
         conn: &mut Connection,
         op_count: u16,
-        field_count: u16,
         generation: u32,
         expiration: u32,
+        options: ReadOptions,
+        pairs: &[bool],
     ) -> Result<Record> {
-        let mut bins: HashMap<String, Value> = HashMap::with_capacity(op_count.into());
-
-        // There can be fields in the response (setname etc). For now, ignore them. Expose them to
-        // the API if needed in the future.
-        for _ in 0..field_count {
-            let field_size = conn.buffer().read_u32() as usize;
-            conn.buffer().advance(4 + field_size);
-        }
-
-        for _ in 0..op_count {
-            let op_size = conn.buffer().read_u32() as usize;
-            conn.buffer().advance(1);
-            let particle_type = conn.buffer().read_u8();
-            conn.buffer().advance(1);
-            let name_size = conn.buffer().read_u8() as usize;
-            let name = conn.buffer().read_str(name_size)?;
-
-            let particle_bytes_size = op_size - (4 + name_size);
-            let value = Value::read_from(conn.buffer(), particle_type, particle_bytes_size)?;
+        let mut skipped_bins = Vec::new();
+        let entries = Self::parse_entries(conn, op_count, options, pairs, &mut skipped_bins)?;
+        let mut bins = HashMap::with_capacity(entries.len());
 
+        for (name, value) in entries {
             if value != Value::Nil {
                 // list/map operations may return multiple values for the same bin.
                 match bins.entry(name) {
@@ -82,20 +89,97 @@ impl<'a> ReadCommand<'a> {
             }
         }
 
-        Ok(Record::new(None, bins, generation, expiration))
+        let mut record = Record::new(None, bins, generation, expiration);
+        record.skipped_bins = skipped_bins;
+        Ok(record)
+    }
+
+    /// Read the fields of the response, returning the stored user key if the server sent one.
+    /// Any other fields are skipped.
+    fn parse_user_key(conn: &mut Connection, field_count: u16) -> Result<Option<UserKey>> {
+        let mut user_key = None;
+
+        for _ in 0..field_count {
+            let field_size = conn.buffer().read_u32() as usize;
+            let field_type = conn.buffer().read_u8();
+
+            if field_type == FieldType::Key as u8 {
+                let particle_type = conn.buffer().read_u8();
+                user_key = Some(UserKey::read_from(
+                    conn.buffer(),
+                    particle_type,
+                    field_size - 2,
+                )?);
+            } else {
+                conn.buffer().advance(field_size - 1);
+            }
+        }
+
+        Ok(user_key)
+    }
+
+    fn parse_entries(
+        conn: &mut Connection,
+        op_count: u16,
+        options: ReadOptions,
+        pairs: &[bool],
+        skipped_bins: &mut Vec<(BinName, usize)>,
+    ) -> Result<Vec<(BinName, Value)>> {
+        let mut entries = Vec::with_capacity(op_count.into());
+        let mut bin_names = BinNames::default();
+
+        for i in 0..usize::from(op_count) {
+            let op_size = conn.buffer().read_u32() as usize;
+            conn.buffer().advance(1);
+            let particle_type = conn.buffer().read_u8();
+            conn.buffer().advance(1);
+            let name_size = conn.buffer().read_u8() as usize;
+            let name = conn.buffer().read_bin_name(name_size, &mut bin_names)?;
+
+            let particle_bytes_size = op_size - (4 + name_size);
+            if options.skip_bin(particle_bytes_size) {
+                conn.buffer().advance(particle_bytes_size);
+                skipped_bins.push((name, particle_bytes_size));
+                continue;
+            }
+
+            let options = ReadOptions {
+                map_pairs: pairs.get(i).copied().unwrap_or_default(),
+                ..options
+            };
+            let value =
+                Value::read_from(conn.buffer(), particle_type, particle_bytes_size, options)?;
+
+            entries.push((name, value));
+        }
+
+        Ok(entries)
     }
 }
 
 #[async_trait]
 impl<'a> Command for ReadCommand<'a> {
-    fn prepare_buffer(&mut self, conn: &mut Connection) -> Result<()> {
+    const NAME: &'static str = "ReadCommand";
+
+    fn prepare_buffer(&mut self, conn: &mut Connection, node: &Node) -> Result<()> {
         conn.buffer()
-            .set_read(self.policy, self.single_command.key, &self.bins)
+            .set_read(
+                self.policy,
+                self.single_command.key,
+                &self.bins,
+                node.max_bin_name_len(),
+            )
             .map_err(Into::into)
     }
 
     async fn get_node(&self) -> Option<Arc<Node>> {
-        self.single_command.get_node().await
+        self.single_command
+            .get_read_node(self.policy.read_mode_sc)
+            .await
+    }
+
+    fn single_command(&self) -> Option<&SingleCommand<'_>> {
+        Some(&self.single_command)
     }
 
     async fn parse_result(&mut self, conn: &mut Connection) -> Result<()> {
@@ -116,23 +200,125 @@ impl<'a> Command for ReadCommand<'a> {
             }
         }
 
+        let options = self.single_command.read_options();
+        let pairs = operations::pair_entries(self.operations, header.operation_count.into());
         match header.result_code {
             ResultCode::Ok => {
-                let record = if self.bins == Bins::None {
+                let user_key = Self::parse_user_key(conn, header.field_count)?;
+                let mut record = if let Some(entries) = &mut self.entries {
+                    // Entries are matched to their operations by position, so none of them may be
+                    // skipped.
+                    let options = ReadOptions {
+                        max_bin_size: None,
+                        ..options
+                    };
+                    *entries = Self::parse_entries(
+                        conn,
+                        header.operation_count,
+                        options,
+                        &pairs,
+                        &mut Vec::new(),
+                    )?;
+                    Record::new(None, HashMap::new(), header.generation, header.expiration)
+                } else if self.bins == Bins::None {
                     Record::new(None, HashMap::new(), header.generation, header.expiration)
                 } else {
                     Self::parse_record(
                         conn,
                         header.operation_count,
-                        header.field_count,
                         header.generation,
                         header.expiration,
+                        options,
+                        &pairs,
                     )?
                 };
+                if self.policy.send_key {
+                    record.key = user_key.map(|user_key| Key {
+                        user_key: Some(user_key),
+                        ..self.single_command.key.clone()
+                    });
+                }
                 self.record = Some(record);
                 Ok(())
             }
             rc => Err(CommandError::ServerError(rc)),
         }
     }
+
+    fn pipelined(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ReadCommand;
+    use crate::{net::Connection, value::ReadOptions, UserKey, Value};
+
+    fn op(name: &str, particle_type: u8, data: &[u8]) -> Vec<u8> {
+        let mut op = Vec::new();
+        op.extend_from_slice(&((4 + name.len() + data.len()) as u32).to_be_bytes());
+        op.extend_from_slice(&[1, particle_type, 0, name.len() as u8]);
+        op.extend_from_slice(name.as_bytes());
+        op.extend_from_slice(data);
+        op
+    }
+
+    fn field(ty: u8, data: &[u8]) -> Vec<u8> {
+        let mut field = Vec::new();
+        field.extend_from_slice(&((1 + data.len()) as u32).to_be_bytes());
+        field.push(ty);
+        field.extend_from_slice(data);
+        field
+    }
+
+    #[tokio::test]
+    async fn stored_user_key() {
+        let mut response = field(0, b"test");
+        response.extend(field(2, b"\x03user-1"));
+        response.extend(op("a", 1, &7_i64.to_be_bytes()));
+
+        let mut conn = Connection::detached(0);
+        let size = response.len();
+        conn.set_response(response);
+        conn.read_buffer(size).await.unwrap();
+
+        let user_key = ReadCommand::parse_user_key(&mut conn, 2).unwrap();
+        assert_eq!(Some(UserKey::String("user-1".into())), user_key);
+
+        let mut skipped = Vec::new();
+        let entries =
+            ReadCommand::parse_entries(&mut conn, 1, ReadOptions::default(), &[], &mut skipped)
+                .unwrap();
+        assert_eq!(1, entries.len());
+        assert_eq!(("a", &Value::Int(7)), (&*entries[0].0, &entries[0].1));
+    }
+
+    #[tokio::test]
+    async fn skip_large_bins() {
+        let mut response = op("a", 1, &7_i64.to_be_bytes());
+        response.extend(op("b", 4, &[0xff; 100]));
+        response.extend(op("c", 3, b"small"));
+
+        let mut conn = Connection::detached(0);
+        let size = response.len();
+        conn.set_response(response);
+        conn.read_buffer(size).await.unwrap();
+
+        let options = ReadOptions {
+            max_bin_size: Some(16),
+            ..ReadOptions::default()
+        };
+        let mut skipped = Vec::new();
+        let entries = ReadCommand::parse_entries(&mut conn, 3, options, &[], &mut skipped).unwrap();
+
+        assert_eq!(2, entries.len());
+        assert_eq!(("a", &Value::Int(7)), (&*entries[0].0, &entries[0].1));
+        assert_eq!(
+            ("c", &Value::String("small".to_owned())),
+            (&*entries[1].0, &entries[1].1)
+        );
+        assert_eq!(1, skipped.len());
+        assert_eq!(("b", 100), (&*skipped[0].0, skipped[0].1));
+    }
 }
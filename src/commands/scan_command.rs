@@ -1,21 +1,30 @@
-use std::{str, sync::Arc};
+use std::{ops::Range, str, sync::Arc};
 
 use async_trait::async_trait;
 use tokio::sync::mpsc;
 
-use super::{Command, Result, SingleCommand, StreamCommand};
-use crate::{cluster::Node, net::Connection, policies::ScanPolicy, Bins, Record};
+use super::{Command, RecordMapper, Result, SingleCommand, StreamCommand};
+use crate::{
+    cluster::{node::PARTITIONS, Node},
+    net::Connection,
+    operations::Operation,
+    policies::ScanPolicy,
+    record::NodeProgressCounters,
+    Bins,
+};
 
-pub struct ScanCommand<'a> {
-    stream_command: StreamCommand,
+pub struct ScanCommand<'a, T> {
+    stream_command: StreamCommand<T>,
     policy: &'a ScanPolicy,
     namespace: &'a str,
     set_name: &'a str,
     bins: Bins,
+    operations: Arc<[Operation<'static>]>,
     partitions: Vec<u16>,
+    max_records: u64,
 }
 
-impl<'a> ScanCommand<'a> {
+impl<'a, T: Send + 'static> ScanCommand<'a, T> {
     #[allow(clippy::too_many_arguments)]
     pub fn new(
         policy: &'a ScanPolicy,
@@ -23,36 +32,51 @@ impl<'a> ScanCommand<'a> {
         namespace: &'a str,
         set_name: &'a str,
         bins: Bins,
-        tx: mpsc::Sender<Result<Record>>,
+        operations: Arc<[Operation<'static>]>,
+        tx: mpsc::Sender<Result<T>>,
+        map: RecordMapper<T>,
         task_id: u64,
         partitions: Vec<u16>,
+        max_records: u64,
+        progress: Arc<NodeProgressCounters>,
     ) -> Self {
         ScanCommand {
-            stream_command: StreamCommand::new(node, tx, task_id),
+            stream_command: StreamCommand::new(node, tx, map, task_id, progress)
+                .include_key(policy.include_key),
             policy,
             namespace,
             set_name,
             bins,
+            operations,
             partitions,
+            max_records,
         }
     }
 
     pub async fn execute(&mut self) -> Result<()> {
-        SingleCommand::execute(self.policy, self).await
+        SingleCommand::execute(self.policy, self).await?;
+        self.stream_command
+            .complete_partitions(self.partitions.len());
+        Ok(())
     }
 }
 
 #[async_trait]
-impl<'a> Command for ScanCommand<'a> {
-    fn prepare_buffer(&mut self, conn: &mut Connection) -> Result<()> {
+impl<'a, T: Send + 'static> Command for ScanCommand<'a, T> {
+    const NAME: &'static str = "ScanCommand";
+
+    fn prepare_buffer(&mut self, conn: &mut Connection, _node: &Node) -> Result<()> {
         conn.buffer()
             .set_scan(
                 self.policy,
                 self.namespace,
                 self.set_name,
                 &self.bins,
+                &self.operations,
                 self.stream_command.task_id(),
                 &self.partitions,
+                self.max_records,
+                self.stream_command.node().max_bin_name_len(),
             )
             .map_err(Into::into)
     }
@@ -64,4 +88,114 @@ impl<'a> Command for ScanCommand<'a> {
     async fn parse_result(&mut self, conn: &mut Connection) -> Result<()> {
         StreamCommand::parse_result(&mut self.stream_command, conn).await
     }
+
+    fn bounded_response(&self) -> bool {
+        false
+    }
+}
+
+/// Split the maximum amount of records across nodes, relative to the amount of partitions that
+/// each node owns. The shares add up to exactly `max_records`, with any remainder going to the
+/// first nodes that own partitions.
+pub fn split_max_records(max_records: u64, partition_counts: &[usize]) -> Vec<u64> {
+    let total = partition_counts.iter().sum::<usize>() as u128;
+    if max_records == 0 || total == 0 {
+        return vec![0; partition_counts.len()];
+    }
+
+    let mut shares = partition_counts
+        .iter()
+        .map(|&count| (u128::from(max_records) * count as u128 / total) as u64)
+        .collect::<Vec<_>>();
+
+    let mut remainder = max_records - shares.iter().sum::<u64>();
+    for (share, _) in shares
+        .iter_mut()
+        .zip(partition_counts)
+        .filter(|(_, &count)| count > 0)
+    {
+        if remainder == 0 {
+            break;
+        }
+        *share += 1;
+        remainder -= 1;
+    }
+
+    shares
+}
+
+/// Split the partitions of a node into up to `parts` chunks of roughly equal size, which are
+/// scanned in parallel. There is always at least one chunk, even if the node owns no partitions.
+pub fn split_partitions(partitions: Vec<u16>, parts: usize) -> Vec<Vec<u16>> {
+    let parts = parts.clamp(1, partitions.len().max(1));
+    if parts == 1 {
+        return vec![partitions];
+    }
+
+    let size = (partitions.len() + parts - 1) / parts;
+    partitions.chunks(size).map(<[u16]>::to_vec).collect()
+}
+
+/// Split all partition IDs into `chunks` contiguous, disjoint ranges of roughly equal size. The
+/// amount of chunks must be between _one_ and the total amount of partitions.
+pub fn partition_chunks(chunks: usize) -> Vec<Range<u16>> {
+    let total = PARTITIONS as usize;
+    (0..chunks)
+        .map(|i| (i * total / chunks) as u16..((i + 1) * total / chunks) as u16)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{partition_chunks, split_max_records, split_partitions};
+
+    #[test]
+    fn split_unlimited() {
+        assert_eq!(vec![0, 0], split_max_records(0, &[2048, 2048]));
+    }
+
+    #[test]
+    fn split_even() {
+        assert_eq!(vec![5000, 5000], split_max_records(10_000, &[2048, 2048]));
+    }
+
+    #[test]
+    fn split_uneven() {
+        assert_eq!(vec![4, 3, 3], split_max_records(10, &[1366, 1365, 1365]));
+        assert_eq!(vec![1, 1, 0], split_max_records(2, &[1366, 1365, 1365]));
+    }
+
+    #[test]
+    fn split_skips_empty_nodes() {
+        assert_eq!(vec![0, 3], split_max_records(3, &[0, 4096]));
+    }
+
+    #[test]
+    fn split_partitions_single() {
+        assert_eq!(vec![vec![1, 2, 3]], split_partitions(vec![1, 2, 3], 0));
+        assert_eq!(vec![vec![1, 2, 3]], split_partitions(vec![1, 2, 3], 1));
+        assert_eq!(vec![Vec::<u16>::new()], split_partitions(Vec::new(), 4));
+    }
+
+    #[test]
+    fn split_partitions_chunks() {
+        assert_eq!(
+            vec![vec![1, 2], vec![3, 4], vec![5]],
+            split_partitions(vec![1, 2, 3, 4, 5], 3)
+        );
+        assert_eq!(vec![vec![1], vec![2]], split_partitions(vec![1, 2], 8));
+    }
+
+    #[test]
+    fn partition_chunk_ranges() {
+        assert_eq!(vec![0..4096], partition_chunks(1));
+        assert_eq!(vec![0..1365, 1365..2730, 2730..4096], partition_chunks(3));
+
+        let chunks = partition_chunks(4096);
+        assert_eq!(4096, chunks.len());
+        assert!(chunks
+            .iter()
+            .enumerate()
+            .all(|(i, c)| *c == (i as u16..i as u16 + 1)));
+    }
 }
@@ -3,11 +3,13 @@ use std::sync::Arc;
 use tokio::time::Instant;
 use tracing::warn;
 
-use super::{Command, CommandError, Result};
+use super::{until_deadline, Command, CommandError, Result};
 use crate::{
     cluster::{partition::Partition, Cluster, Node},
+    interceptor::CommandContext,
     net::Connection,
-    policies::BasePolicy,
+    policies::{BasePolicy, ReadModeSc},
+    value::ReadOptions,
     Key,
 };
 
@@ -30,6 +32,26 @@ impl<'a> SingleCommand<'a> {
         self.cluster.get_node(&self.partition).await
     }
 
+    pub async fn get_read_node(&self, read_mode_sc: ReadModeSc) -> Option<Arc<Node>> {
+        self.cluster
+            .get_read_node(&self.partition, read_mode_sc)
+            .await
+    }
+
+    pub fn bool_bins(&self) -> bool {
+        self.cluster.bool_bins()
+    }
+
+    pub fn read_options(&self) -> ReadOptions {
+        let policy = self.cluster.client_policy();
+        ReadOptions {
+            keep_unknown: policy.keep_unknown_particles,
+            limits: policy.decode_limits,
+            max_bin_size: policy.max_bin_size,
+            map_pairs: false,
+        }
+    }
+
     pub async fn empty_socket(conn: &mut Connection, receive_size: usize) -> Result<()> {
         // There should not be any more bytes.
         // Empty the socket to be safe.
@@ -43,12 +65,45 @@ impl<'a> SingleCommand<'a> {
     pub(super) async fn execute(
         policy: &impl AsRef<BasePolicy>,
         cmd: &mut impl Command,
+    ) -> Result<()> {
+        let cluster = match cmd.single_command() {
+            Some(single) => Arc::clone(&single.cluster),
+            None => return Self::execute_command(policy, cmd).await,
+        };
+        let interceptor = cluster.client_policy().interceptor.as_deref();
+
+        if let (Some(interceptor), Some(context)) = (interceptor, Self::context(cmd)) {
+            interceptor.before(&context)?;
+        }
+
+        let start = Instant::now();
+        let result = Self::execute_command(policy, cmd).await;
+        let elapsed = start.elapsed();
+
+        cluster.metrics().record(cmd.kind(), elapsed);
+
+        if let (Some(interceptor), Some(context)) = (interceptor, Self::context(cmd)) {
+            interceptor.after(&context, result.as_ref().copied(), elapsed);
+        }
+
+        result
+    }
+
+    fn context(cmd: &impl Command) -> Option<CommandContext<'_>> {
+        cmd.single_command()
+            .map(|single| CommandContext::new(cmd.kind(), single.key))
+    }
+
+    async fn execute_command(
+        policy: &impl AsRef<BasePolicy>,
+        cmd: &mut impl Command,
     ) -> Result<()> {
         let mut iterations = 0;
         let policy = policy.as_ref();
 
-        // set timeout outside the loop
+        // set timeout outside the loop, every step that awaits is bounded by it
         let deadline = policy.deadline();
+        let response_deadline = deadline.filter(|_| cmd.bounded_response());
 
         // Execute command until successful, timed out or maximum iterations have been reached.
         loop {
@@ -61,7 +116,8 @@ impl<'a> SingleCommand<'a> {
                     // because the loop would block the thread
                     tokio::task::yield_now().await;
                 } else {
-                    tokio::time::sleep(policy.sleep_between_retries).await;
+                    until_deadline(deadline, tokio::time::sleep(policy.sleep_between_retries))
+                        .await?;
                 }
             }
 
@@ -73,13 +129,42 @@ impl<'a> SingleCommand<'a> {
             }
 
             // set command node, so when you return a record it has the node
-            let node_future = cmd.get_node();
-            let node = match node_future.await {
+            let node = match until_deadline(deadline, cmd.get_node()).await? {
                 Some(node) => node,
                 None => continue, // Node is currently inactive. Retry.
             };
 
-            let mut conn = match node.get_connection().await {
+            until_deadline(deadline, node.acquire_permit()).await?;
+
+            if cmd.pipelined() {
+                if let Some(pipeline) = until_deadline(deadline, node.get_pipeline()).await? {
+                    let mut conn = pipeline.connection();
+                    cmd.prepare_buffer(&mut conn, &node)
+                        .map_err(|e| CommandError::PrepareBuffer(Box::new(e)))?;
+
+                    let start = Instant::now();
+                    if let Err(err) = until_deadline(deadline, pipeline.send(&mut conn)).await? {
+                        warn!(?node, %err, "failed to send command through pipeline");
+                        continue;
+                    }
+
+                    let result = cmd.parse_result(&mut conn).await;
+                    if result.is_ok() {
+                        node.record_latency(start.elapsed());
+                    }
+                    return result;
+                }
+            }
+
+            // Scans use the separate scan pool, so they can't starve other commands.
+            let conn = async {
+                if cmd.bounded_response() {
+                    node.get_connection().await
+                } else {
+                    node.get_scan_connection().await
+                }
+            };
+            let mut conn = match until_deadline(deadline, conn).await? {
                 Ok(conn) => conn,
                 Err(err) => {
                     warn!(?node, %err, "failed to get a new connection");
@@ -87,11 +172,25 @@ impl<'a> SingleCommand<'a> {
                 }
             };
 
-            cmd.prepare_buffer(&mut conn)
+            cmd.prepare_buffer(&mut conn, &node)
                 .map_err(|e| CommandError::PrepareBuffer(Box::new(e)))?;
 
             // Send command.
-            if let Err(err) = conn.flush().await {
+            if cmd.bounded_response() {
+                conn.start_command(cmd.name());
+            } else {
+                conn.start_stream(cmd.name());
+            }
+            let start = Instant::now();
+            let flushed = match until_deadline(deadline, conn.flush()).await {
+                Ok(flushed) => flushed,
+                Err(err) => {
+                    // The request may have been sent partially, so the connection can't be reused.
+                    conn.close().await;
+                    return Err(err);
+                }
+            };
+            if let Err(err) = flushed {
                 // IO errors are considered temporary anomalies. Retry.
                 // Close socket to flush out possible garbage. Do not put back in pool.
                 conn.close().await;
@@ -100,18 +199,25 @@ impl<'a> SingleCommand<'a> {
             }
 
             // Parse results.
-            if let Err(err) = cmd.parse_result(&mut conn).await {
+            let parsed = until_deadline(response_deadline, cmd.parse_result(&mut conn))
+                .await
+                .and_then(|result| result);
+            if let Err(err) = parsed {
                 // close the connection
                 // cancelling/closing the batch/multi commands will return an error, which will
                 // close the connection to throw away its data and signal the server about the
                 // situation. We will not put back the connection in the buffer.
-                if !super::keep_connection(&err) {
+                if super::keep_connection(&err) {
+                    conn.finish_command();
+                } else {
                     conn.close().await;
                 }
                 return Err(err);
             }
 
             // command has completed successfully.  Exit method.
+            conn.finish_command();
+            node.record_latency(start.elapsed());
             return Ok(());
         }
 
@@ -9,31 +9,83 @@ use super::{
     Command, CommandError, Result,
 };
 use crate::{
-    cluster::Node, msgpack::Read, net::Connection, Key, Record, ResultCode, UserKey, Value,
+    cluster::Node,
+    msgpack::Read,
+    net::Connection,
+    record::{BinNames, NodeProgressCounters},
+    value::ReadOptions,
+    Key, Record, ResultCode, UserKey, Value,
 };
 
-pub struct StreamCommand {
+/// Transformation that is applied to each record on the task that receives it, before it's
+/// queued up for the consumer.
+pub type RecordMapper<T> = Arc<dyn Fn(Record) -> T + Send + Sync>;
+
+pub struct StreamCommand<T> {
     node: Arc<Node>,
-    tx: mpsc::Sender<Result<Record>>,
+    tx: mpsc::Sender<Result<T>>,
+    map: RecordMapper<T>,
     task_id: u64,
+    bin_names: BinNames,
+    progress: Arc<NodeProgressCounters>,
+    partitions_done: usize,
+    include_key: bool,
 }
 
-impl StreamCommand {
-    pub fn new(node: Arc<Node>, tx: mpsc::Sender<Result<Record>>, task_id: u64) -> Self {
-        Self { node, tx, task_id }
+impl<T> StreamCommand<T> {
+    pub fn new(
+        node: Arc<Node>,
+        tx: mpsc::Sender<Result<T>>,
+        map: RecordMapper<T>,
+        task_id: u64,
+        progress: Arc<NodeProgressCounters>,
+    ) -> Self {
+        Self {
+            node,
+            tx,
+            map,
+            task_id,
+            bin_names: BinNames::default(),
+            progress,
+            partitions_done: 0,
+            include_key: true,
+        }
+    }
+
+    /// Set whether the key is attached to each returned record.
+    pub(super) fn include_key(mut self, include_key: bool) -> Self {
+        self.include_key = include_key;
+        self
+    }
+
+    /// Mark all partitions as done, that the node didn't report as done already, once the
+    /// stream ended successfully.
+    pub(super) fn complete_partitions(&mut self, total: usize) {
+        self.progress
+            .add_partitions(total.saturating_sub(self.partitions_done));
+        self.partitions_done = self.partitions_done.max(total);
     }
 
     async fn parse_stream(&mut self, conn: &mut Connection, header: ProtoHeader) -> Result<bool> {
         while !self.tx.is_closed() && conn.bytes_read() < header.size {
-            let res = Self::parse_record(conn, header).await;
+            let res =
+                parse_record(conn, header, &mut self.bin_names, self.node.read_options()).await;
             match res {
-                Ok((Some(rec), _)) => {
-                    if self.tx.send(Ok(rec)).await.is_err() {
+                Ok((Some(mut rec), _)) => {
+                    if !self.include_key {
+                        rec.key = None;
+                    }
+                    self.progress.add_record();
+                    if self.tx.send(Ok((self.map)(rec))).await.is_err() {
                         return Ok(false);
                     }
                 }
                 Ok((None, false)) => return Ok(false),
-                Ok((None, true)) => continue,
+                // The node finished scanning a partition.
+                Ok((None, true)) => {
+                    self.progress.add_partitions(1);
+                    self.partitions_done += 1;
+                }
                 Err(err) => {
                     self.tx.send(Err(err)).await.ok();
                     return Ok(false);
@@ -44,110 +96,131 @@ impl StreamCommand {
         Ok(true)
     }
 
-    async fn parse_record(
-        conn: &mut Connection,
-        proto: ProtoHeader,
-    ) -> Result<(Option<Record>, bool)> {
-        let header = conn.read_stream_message_header(proto).await?;
-
-        if header.result_code != ResultCode::Ok {
-            if conn.bytes_read() < proto.size {
-                let remaining = proto.size - conn.bytes_read();
-                conn.read_buffer(remaining).await?;
-            }
+    pub(super) fn node(&self) -> &Node {
+        &self.node
+    }
 
-            return match header.result_code {
-                ResultCode::KeyNotFoundError => Ok((None, false)),
-                _ => Err(CommandError::ServerError(header.result_code)),
-            };
-        }
+    pub(super) fn task_id(&self) -> u64 {
+        self.task_id
+    }
+}
 
-        // if cmd is the end marker of the response, do not proceed further
-        if header.info_attr.contains(InfoAttr::LAST) {
-            return Ok((None, false));
+async fn parse_record(
+    conn: &mut Connection,
+    proto: ProtoHeader,
+    bin_names: &mut BinNames,
+    options: ReadOptions,
+) -> Result<(Option<Record>, bool)> {
+    let header = conn.read_stream_message_header(proto).await?;
+
+    if header.result_code != ResultCode::Ok {
+        if conn.bytes_read() < proto.size {
+            let remaining = proto.size - conn.bytes_read();
+            conn.skip(remaining).await?;
         }
 
-        let key = Self::parse_key(conn, header.field_count).await?;
-
-        // Partition is done, don't go further
-        if header.info_attr.contains(InfoAttr::PARTITION_DONE) {
-            return Ok((None, true));
-        }
+        return match header.result_code {
+            ResultCode::KeyNotFoundError => Ok((None, false)),
+            _ => Err(CommandError::ServerError(header.result_code)),
+        };
+    }
 
-        let mut bins = HashMap::with_capacity(header.operation_count.into());
+    // if cmd is the end marker of the response, do not proceed further
+    if header.info_attr.contains(InfoAttr::LAST) {
+        return Ok((None, false));
+    }
 
-        for _ in 0..header.operation_count {
-            conn.read_buffer(8).await?;
-            let op_size = conn.buffer().read_u32() as usize;
-            conn.buffer().advance(1);
-            let particle_type = conn.buffer().read_u8();
-            conn.buffer().advance(1);
-            let name_size = conn.buffer().read_u8() as usize;
-            conn.read_buffer(name_size).await?;
-            let name = conn.buffer().read_str(name_size)?;
+    let key = parse_key(conn, proto.size, header.field_count).await?;
 
-            let particle_bytes_size = op_size - (4 + name_size);
-            conn.read_buffer(particle_bytes_size).await?;
-            let value = Value::read_from(conn.buffer(), particle_type, particle_bytes_size)?;
+    // Partition is done, don't go further
+    if header.info_attr.contains(InfoAttr::PARTITION_DONE) {
+        return Ok((None, true));
+    }
 
-            bins.insert(name, value);
+    let mut bins = HashMap::with_capacity(header.operation_count.into());
+    let mut skipped_bins = Vec::new();
+
+    for _ in 0..header.operation_count {
+        conn.read_buffer(8).await?;
+        let op_size = conn.buffer().read_u32() as usize;
+        conn.buffer().advance(1);
+        let particle_type = conn.buffer().read_u8();
+        conn.buffer().advance(1);
+        let name_size = conn.buffer().read_u8() as usize;
+        conn.read_proto_buffer(name_size, proto.size).await?;
+        let name = conn.buffer().read_bin_name(name_size, bin_names)?;
+
+        let particle_bytes_size = op_size - (4 + name_size);
+        if options.skip_bin(particle_bytes_size) {
+            conn.skip(particle_bytes_size).await?;
+            skipped_bins.push((name, particle_bytes_size));
+            continue;
         }
 
-        let record = Record::new(Some(key), bins, header.generation, header.expiration);
-        Ok((Some(record), true))
+        conn.read_proto_buffer(particle_bytes_size, proto.size)
+            .await?;
+        let value = Value::read_from(conn.buffer(), particle_type, particle_bytes_size, options)?;
+
+        bins.insert(name, value);
     }
 
-    pub async fn parse_key(conn: &mut Connection, field_count: u16) -> Result<Key> {
-        let mut digest = [0; 20];
-        let mut namespace = String::new();
-        let mut set_name = String::new();
-        let mut orig_key = None;
-
-        for _ in 0..field_count {
-            conn.read_buffer(4).await?;
-            let field_len = conn.buffer().read_u32() as usize;
-            conn.read_buffer(field_len).await?;
-            let field_type = conn.buffer().read_u8();
-
-            match field_type {
-                x if x == FieldType::DigestRipe as u8 => {
-                    digest.copy_from_slice(conn.buffer().read_slice(field_len - 1));
-                }
-                x if x == FieldType::Namespace as u8 => {
-                    namespace = conn.buffer().read_str(field_len - 1)?;
-                }
-                x if x == FieldType::Table as u8 => {
-                    set_name = conn.buffer().read_str(field_len - 1)?;
-                }
-                x if x == FieldType::Key as u8 => {
-                    let particle_type = conn.buffer().read_u8();
-                    let particle_bytes_size = field_len - 2;
-                    orig_key = Some(UserKey::read_from(
-                        conn.buffer(),
-                        particle_type,
-                        particle_bytes_size,
-                    )?);
-                }
-                _ => panic!("invalid field type `{field_type}`"),
+    let mut record = Record::new(Some(key), bins, header.generation, header.expiration);
+    record.skipped_bins = skipped_bins;
+    Ok((Some(record), true))
+}
+
+pub(super) async fn parse_key(
+    conn: &mut Connection,
+    proto_size: usize,
+    field_count: u16,
+) -> Result<Key> {
+    let mut digest = [0; 20];
+    let mut namespace = String::new();
+    let mut set_name = String::new();
+    let mut orig_key = None;
+
+    for _ in 0..field_count {
+        conn.read_buffer(4).await?;
+        let field_len = conn.buffer().read_u32() as usize;
+        conn.read_proto_buffer(field_len, proto_size).await?;
+        let field_type = conn.buffer().read_u8();
+
+        match field_type {
+            x if x == FieldType::DigestRipe as u8 => {
+                digest.copy_from_slice(conn.buffer().read_slice(field_len - 1));
+            }
+            x if x == FieldType::Namespace as u8 => {
+                namespace = conn.buffer().read_str(field_len - 1)?;
+            }
+            x if x == FieldType::Table as u8 => {
+                set_name = conn.buffer().read_str(field_len - 1)?;
             }
+            x if x == FieldType::Key as u8 => {
+                let particle_type = conn.buffer().read_u8();
+                let particle_bytes_size = field_len - 2;
+                orig_key = Some(UserKey::read_from(
+                    conn.buffer(),
+                    particle_type,
+                    particle_bytes_size,
+                )?);
+            }
+            _ => panic!("invalid field type `{field_type}`"),
         }
-
-        Ok(Key {
-            namespace: namespace.into(),
-            set_name: set_name.into(),
-            user_key: orig_key,
-            digest,
-        })
     }
 
-    pub(super) fn task_id(&self) -> u64 {
-        self.task_id
-    }
+    Ok(Key {
+        namespace: namespace.into(),
+        set_name: set_name.into(),
+        user_key: orig_key,
+        digest,
+    })
 }
 
 #[async_trait]
-impl Command for StreamCommand {
-    fn prepare_buffer(&mut self, _conn: &mut Connection) -> Result<()> {
+impl<T: Send + 'static> Command for StreamCommand<T> {
+    const NAME: &'static str = "StreamCommand";
+
+    fn prepare_buffer(&mut self, _conn: &mut Connection, _node: &Node) -> Result<()> {
         panic!("stream command doesn't write the buffer itself")
     }
 
@@ -6,6 +6,7 @@ use tracing::warn;
 use super::{Command, CommandError, Result, SingleCommand};
 use crate::{
     cluster::{Cluster, Node},
+    metrics::CommandKind,
     net::Connection,
     policies::WritePolicy,
     Key, ResultCode,
@@ -31,7 +32,9 @@ impl<'a> TouchCommand<'a> {
 
 #[async_trait]
 impl<'a> Command for TouchCommand<'a> {
-    fn prepare_buffer(&mut self, conn: &mut Connection) -> Result<()> {
+    const NAME: &'static str = "TouchCommand";
+
+    fn prepare_buffer(&mut self, conn: &mut Connection, _node: &Node) -> Result<()> {
         conn.buffer()
             .set_touch(self.policy, self.single_command.key)
             .map_err(Into::into)
@@ -41,6 +44,14 @@ impl<'a> Command for TouchCommand<'a> {
         self.single_command.get_node().await
     }
 
+    fn single_command(&self) -> Option<&SingleCommand<'_>> {
+        Some(&self.single_command)
+    }
+
+    fn kind(&self) -> CommandKind {
+        CommandKind::Write
+    }
+
     async fn parse_result(&mut self, conn: &mut Connection) -> Result<()> {
         let header = conn.read_header().await.map_err(|err| {
             warn!(%err, "failed parsing message header");
@@ -53,4 +64,8 @@ impl<'a> Command for TouchCommand<'a> {
 
         SingleCommand::empty_socket(conn, header.size).await
     }
+
+    fn pipelined(&self) -> bool {
+        true
+    }
 }
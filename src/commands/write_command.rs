@@ -6,6 +6,7 @@ use tracing::warn;
 use super::{Command, CommandError, Result, SingleCommand};
 use crate::{
     cluster::{Cluster, Node},
+    metrics::CommandKind,
     net::Connection,
     operations::OperationType,
     policies::WritePolicy,
@@ -42,13 +43,17 @@ impl<'a, 'b> WriteCommand<'a> {
 
 #[async_trait]
 impl<'a> Command for WriteCommand<'a> {
-    fn prepare_buffer(&mut self, conn: &mut Connection) -> Result<()> {
+    const NAME: &'static str = "WriteCommand";
+
+    fn prepare_buffer(&mut self, conn: &mut Connection, node: &Node) -> Result<()> {
         conn.buffer()
             .set_write(
                 self.policy,
                 self.operation,
                 self.single_command.key,
                 self.bins,
+                self.single_command.bool_bins(),
+                node.max_bin_name_len(),
             )
             .map_err(Into::into)
     }
@@ -57,6 +62,14 @@ impl<'a> Command for WriteCommand<'a> {
         self.single_command.get_node().await
     }
 
+    fn single_command(&self) -> Option<&SingleCommand<'_>> {
+        Some(&self.single_command)
+    }
+
+    fn kind(&self) -> CommandKind {
+        CommandKind::Write
+    }
+
     async fn parse_result(&mut self, conn: &mut Connection) -> Result<()> {
         let header = conn.read_header().await.map_err(|err| {
             warn!(%err, "parse result error");
@@ -69,4 +82,8 @@ impl<'a> Command for WriteCommand<'a> {
 
         SingleCommand::empty_socket(conn, header.size).await
     }
+
+    fn pipelined(&self) -> bool {
+        true
+    }
 }
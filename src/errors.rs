@@ -39,6 +39,7 @@ pub use crate::{
     commands::{buffer::BufferError, CommandError, ParseParticleError},
     msgpack::MsgpackError,
     net::{NetError, ParseHostError},
+    record::{BinError, UdfError},
     value::ParticleError,
 };
 
@@ -75,7 +76,7 @@ pub enum Error {
     #[error("too many connections")]
     NoMoreConnections,
     /// Server responded with a response code indicating an error condition.
-    #[error("server error: {}", .0.into_string())]
+    #[error("server error: {0}")]
     ServerError(ResultCode),
     /// Error returned when a tasked timed out before it could be completed.
     #[error("timeout: {0}")]
@@ -84,8 +85,27 @@ pub enum Error {
     NoNodes,
     #[error("failed to truncate namespace or set")]
     Truncate(#[source] Box<Self>),
+    /// The namespace doesn't exist in the cluster.
+    #[error("namespace `{0}` not found")]
+    NamespaceNotFound(String),
+    /// The set doesn't exist in the namespace.
+    #[error("set `{set_name}` not found in namespace `{namespace}`")]
+    SetNotFound {
+        /// Namespace that was searched for the set.
+        namespace: String,
+        /// Name of the missing set.
+        set_name: String,
+    },
+    /// The server refused the command, for example because the user lacks the required role.
+    #[error("command not permitted: {0}")]
+    Forbidden(ResultCode),
     #[error("error creating index")]
     CreateIndex(#[source] Box<Self>),
+    #[error("error dropping index")]
+    DropIndex(#[source] Box<Self>),
+    /// No node reported an active job with the given transaction ID.
+    #[error("no active job with transaction ID {0}")]
+    JobNotFound(u64),
     #[error("network error")]
     Net(#[from] crate::net::NetError),
     #[error("command error")]
@@ -0,0 +1,512 @@
+//! Backup and restore of records in a compact binary format.
+//!
+//! The [`Exporter`] writes records, as returned by scans, into any [`AsyncWrite`] like a file. The
+//! [`Importer`] reads them back and can write them into a cluster with `put`. Together they allow
+//! copying namespaces or sets between environments, without any external tooling.
+//!
+//! # Format
+//!
+//! Exports start with the magic bytes `WPEX` and a single format version byte, followed by the
+//! records. All integers are big-endian, and each record is written as:
+//!
+//! - Total length of the following record data (`u32`).
+//! - Namespace and set name, each prefixed with its length (`u8`).
+//! - Key digest (20 bytes).
+//! - Particle type of the user key (`u8`), where `0` means the key isn't known. Otherwise, the
+//!   length (`u32`) and content of the user key follow.
+//! - Generation and expiration in seconds since the _Citrusleaf epoch_ (both `u32`).
+//! - Amount of bins (`u16`), and for each bin its name prefixed with its length (`u8`), the
+//!   particle type (`u8`) and the value prefixed with its length (`u32`).
+//!
+//! Values are encoded the same way as on the wire protocol of the server, so future particle
+//! types are carried over as well.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use windpike::{
+//!     export::{Exporter, Importer},
+//!     policies::{ClientPolicy, ScanPolicy, WritePolicy},
+//!     Bins, Client,
+//! };
+//!
+//! # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+//! let source = Client::new(&ClientPolicy::default(), "source:3000").await?;
+//! let target = Client::new(&ClientPolicy::default(), "target:3000").await?;
+//!
+//! let mut records = source
+//!     .scan(&ScanPolicy::default(), "test", "users", Bins::All)
+//!     .await?;
+//! let mut exporter = Exporter::new(Vec::new()).await?;
+//! while let Some(record) = records.next().await {
+//!     exporter.write(&record?).await?;
+//! }
+//! let data = exporter.finish().await?;
+//!
+//! let mut importer = Importer::new(&data[..]).await?;
+//! let stats = importer.import(&target, &WritePolicy::default()).await?;
+//! println!("imported {} records", stats.imported);
+//! # Ok(())
+//! # }
+//! ```
+
+use std::{collections::HashMap, time::SystemTime};
+
+use bytes::BytesMut;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+use crate::{
+    commands::{buffer::Buffer, CommandError, ParticleType},
+    msgpack::{Checkpoint, Read, Write},
+    policies::{Expiration, WritePolicy},
+    record::BinNames,
+    value::{ParticleError, ReadOptions},
+    Bin, ClientApi, Key, Record, RecordExpiration, UserKey, Value,
+};
+
+/// Magic bytes at the start of every export.
+const MAGIC: &[u8; 4] = b"WPEX";
+/// Current version of the format.
+const VERSION: u8 = 1;
+/// Upper bound for the size of a single record, which protects from allocating huge buffers when
+/// reading corrupted data.
+const MAX_RECORD_SIZE: usize = 128 * 1024 * 1024;
+
+/// Errors that can happen while exporting or importing records.
+#[derive(Debug, thiserror::Error)]
+pub enum ExportError {
+    /// Reading or writing the underlying data failed.
+    #[error("I/O related error")]
+    Io(#[from] std::io::Error),
+    /// The data doesn't start with the expected magic bytes.
+    #[error("data is not a record export")]
+    InvalidMagic,
+    /// The data was written in a newer version of the format.
+    #[error("unsupported export format version {0}")]
+    UnsupportedVersion(u8),
+    /// Only records that carry their key can be exported, which is always the case for scans.
+    #[error("record has no key")]
+    MissingKey,
+    /// A name or value is too large to be represented in the format.
+    #[error("{0} is too large to be exported")]
+    TooLarge(&'static str),
+    /// The data ended in the middle of a record, or a record's content is inconsistent.
+    #[error("corrupted record data: {0}")]
+    Corrupted(&'static str),
+    /// Decoding a bin value or the user key failed.
+    #[error("failed decoding value")]
+    Particle(#[from] ParticleError),
+    /// Writing a record into the cluster failed.
+    #[error("failed importing record")]
+    Command(#[from] CommandError),
+}
+
+type Result<T, E = ExportError> = std::result::Result<T, E>;
+
+/// Writer for records in the export format.
+pub struct Exporter<W> {
+    writer: W,
+    buffer: BytesMut,
+    records: u64,
+}
+
+impl<W: AsyncWrite + Unpin> Exporter<W> {
+    /// Create a new exporter, which immediately writes the format header.
+    pub async fn new(mut writer: W) -> Result<Self> {
+        writer.write_all(MAGIC).await?;
+        writer.write_u8(VERSION).await?;
+
+        Ok(Self {
+            writer,
+            buffer: BytesMut::new(),
+            records: 0,
+        })
+    }
+
+    /// Write a single record, which must have its [`Record::key`] set.
+    pub async fn write(&mut self, record: &Record) -> Result<()> {
+        self.buffer.clear();
+        encode_record(&mut self.buffer, record)?;
+        self.writer.write_all(&self.buffer).await?;
+        self.records += 1;
+        Ok(())
+    }
+
+    /// Amount of records written so far.
+    #[must_use]
+    pub fn records(&self) -> u64 {
+        self.records
+    }
+
+    /// Flush all pending data and return the underlying writer.
+    pub async fn finish(mut self) -> Result<W> {
+        self.writer.flush().await?;
+        Ok(self.writer)
+    }
+}
+
+/// Reader for records in the export format.
+pub struct Importer<R> {
+    reader: R,
+    buffer: Buffer,
+    bin_names: BinNames,
+}
+
+impl<R: AsyncRead + Unpin> Importer<R> {
+    /// Create a new importer, which immediately reads and validates the format header.
+    pub async fn new(mut reader: R) -> Result<Self> {
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic).await?;
+        if &magic != MAGIC {
+            return Err(ExportError::InvalidMagic);
+        }
+
+        let version = reader.read_u8().await?;
+        if version != VERSION {
+            return Err(ExportError::UnsupportedVersion(version));
+        }
+
+        Ok(Self {
+            reader,
+            buffer: Buffer::new(0),
+            bin_names: BinNames::default(),
+        })
+    }
+
+    /// Read the next record, or [`None`] once all records were read.
+    ///
+    /// The [`Record::key`] of the returned records is always set. It contains the user key if it
+    /// was exported, or the digest only otherwise.
+    pub async fn next(&mut self) -> Result<Option<Record>> {
+        // Only a clean end of the data between two records is fine, not in the middle of one.
+        let mut len = [0; 4];
+        let read = self.reader.read(&mut len).await?;
+        if read == 0 {
+            return Ok(None);
+        }
+        self.reader.read_exact(&mut len[read..]).await?;
+
+        self.buffer
+            .resize_within(u32::from_be_bytes(len) as usize, MAX_RECORD_SIZE)
+            .map_err(|_| ExportError::Corrupted("record exceeds the size limit"))?;
+        self.reader.read_exact(self.buffer.as_mut()).await?;
+
+        decode_record(&mut self.buffer, &mut self.bin_names).map(Some)
+    }
+
+    /// Write all remaining records into the cluster.
+    ///
+    /// Each record is written with the given policy, but the expiration is replaced with the
+    /// remaining time to live of the record. Records that expired since they were exported are
+    /// skipped. The generation can't be restored, as the server manages it on its own.
+    pub async fn import(
+        &mut self,
+        client: &dyn ClientApi,
+        policy: &WritePolicy,
+    ) -> Result<ImportStats> {
+        let mut stats = ImportStats::default();
+        let mut policy = policy.clone();
+
+        while let Some(record) = self.next().await? {
+            policy.expiration = match record.expiration() {
+                RecordExpiration::Never => Expiration::Never,
+                RecordExpiration::At(time) => {
+                    if let Ok(ttl) = time.duration_since(SystemTime::now()) {
+                        Expiration::Seconds(ttl.as_secs().clamp(1, u32::MAX.into()) as u32)
+                    } else {
+                        stats.expired += 1;
+                        continue;
+                    }
+                }
+            };
+
+            let bins = record
+                .bins
+                .iter()
+                .map(|(name, value)| Bin::new(name, value.clone()))
+                .collect::<Vec<_>>();
+            // The key is always set by `next`.
+            if let Some(key) = &record.key {
+                client.put(&policy, key, &bins).await?;
+                stats.imported += 1;
+            }
+        }
+
+        Ok(stats)
+    }
+}
+
+/// Counters of an [`Importer::import`].
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct ImportStats {
+    /// Amount of records that were written into the cluster.
+    pub imported: u64,
+    /// Amount of records that were skipped, because they expired in the meantime.
+    pub expired: u64,
+}
+
+fn encode_record(buf: &mut BytesMut, record: &Record) -> Result<()> {
+    let key = record.key.as_ref().ok_or(ExportError::MissingKey)?;
+    let len = Checkpoint::reserve(buf);
+
+    write_name(buf, &key.namespace, "namespace")?;
+    write_name(buf, &key.set_name, "set name")?;
+    buf.write_bytes(&key.digest());
+
+    if let Some(user_key) = &key.user_key {
+        buf.write_u8(user_key.particle_type() as u8);
+        let value = Checkpoint::reserve(buf);
+        match user_key {
+            UserKey::Int(value) => buf.write_i64(*value),
+            UserKey::String(value) => buf.write_str(value),
+            UserKey::Blob(value) => buf.write_bytes(value),
+        };
+        value.patch(buf);
+    } else {
+        buf.write_u8(ParticleType::Null as u8);
+    }
+
+    buf.write_u32(record.generation);
+    buf.write_u32(record.citrusleaf_expiration());
+
+    let count = u16::try_from(record.bins.len()).map_err(|_| ExportError::TooLarge("bin count"))?;
+    buf.write_u16(count);
+
+    for (name, value) in &record.bins {
+        write_name(buf, name, "bin name")?;
+        buf.write_u8(value.particle_type());
+        let value_len = Checkpoint::reserve(buf);
+        value.write_to(buf);
+        value_len.patch(buf);
+    }
+
+    if len.patch(buf) > MAX_RECORD_SIZE {
+        return Err(ExportError::TooLarge("record"));
+    }
+
+    Ok(())
+}
+
+fn write_name(buf: &mut BytesMut, name: &str, what: &'static str) -> Result<()> {
+    let len = u8::try_from(name.len()).map_err(|_| ExportError::TooLarge(what))?;
+    buf.write_u8(len);
+    buf.write_str(name);
+    Ok(())
+}
+
+fn decode_record(buf: &mut Buffer, bin_names: &mut BinNames) -> Result<Record> {
+    let options = ReadOptions {
+        keep_unknown: true,
+        ..ReadOptions::default()
+    };
+
+    let namespace = read_name(buf)?;
+    let set_name = read_name(buf)?;
+    ensure(buf, 20)?;
+    let mut digest = [0; 20];
+    digest.copy_from_slice(&buf.read_bytes(20));
+
+    ensure(buf, 1)?;
+    let key = match buf.read_u8() {
+        0 => Key::from_digest(namespace, set_name, digest),
+        particle_type => {
+            let user_key = match read_value(buf, particle_type, options)? {
+                Value::Int(value) => UserKey::Int(value),
+                Value::String(value) => UserKey::String(value.into()),
+                Value::Blob(value) => UserKey::Blob(value.into()),
+                _ => return Err(ExportError::Corrupted("invalid user key type")),
+            };
+            let key = Key::new(namespace, set_name, user_key);
+            if key.digest() != digest {
+                return Err(ExportError::Corrupted("user key doesn't match the digest"));
+            }
+            key
+        }
+    };
+
+    ensure(buf, 10)?;
+    let generation = buf.read_u32();
+    let expiration = buf.read_u32();
+    let count = buf.read_u16();
+
+    let mut bins = HashMap::with_capacity(count.into());
+    for _ in 0..count {
+        ensure(buf, 1)?;
+        let name_len = buf.read_u8() as usize;
+        ensure(buf, name_len)?;
+        let name = buf
+            .read_bin_name(name_len, bin_names)
+            .map_err(|_| ExportError::Corrupted("invalid bin name"))?;
+
+        ensure(buf, 1)?;
+        let particle_type = buf.read_u8();
+        bins.insert(name, read_value(buf, particle_type, options)?);
+    }
+
+    if !buf.is_empty() {
+        return Err(ExportError::Corrupted("trailing data after the bins"));
+    }
+
+    Ok(Record::new(Some(key), bins, generation, expiration))
+}
+
+fn read_name(buf: &mut Buffer) -> Result<String> {
+    ensure(buf, 1)?;
+    let len = buf.read_u8() as usize;
+    ensure(buf, len)?;
+    buf.read_str(len)
+        .map_err(|_| ExportError::Corrupted("invalid UTF-8 in name"))
+}
+
+/// Read a value that is prefixed with its length, and make sure that it's fully contained in the
+/// buffer and decoded from exactly that many bytes.
+fn read_value(buf: &mut Buffer, particle_type: u8, options: ReadOptions) -> Result<Value> {
+    ensure(buf, 4)?;
+    let len = buf.read_u32() as usize;
+    ensure(buf, len)?;
+
+    // Fixed size particles are read regardless of the length.
+    let fixed = match ParticleType::try_from(particle_type) {
+        Ok(ParticleType::Integer | ParticleType::Float) => Some(8),
+        Ok(ParticleType::Bool) => Some(1),
+        Ok(ParticleType::Null) => Some(0),
+        _ => None,
+    };
+    if fixed.map_or(false, |fixed| fixed != len) {
+        return Err(ExportError::Corrupted("invalid value length"));
+    }
+
+    let remaining = buf.as_ref().len();
+    let value = Value::read_from(buf, particle_type, len, options)?;
+    if remaining - buf.as_ref().len() != len {
+        return Err(ExportError::Corrupted("invalid value length"));
+    }
+
+    Ok(value)
+}
+
+fn ensure(buf: &Buffer, len: usize) -> Result<()> {
+    if buf.as_ref().len() < len {
+        Err(ExportError::Corrupted("unexpected end of record"))
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::{ExportError, Exporter, Importer};
+    use crate::{Key, Record, Value};
+
+    fn record(key: Key) -> Record {
+        let bins = HashMap::from([
+            ("int".into(), Value::from(-7)),
+            ("float".into(), Value::from(1.5)),
+            ("str".into(), Value::from("hello")),
+            ("blob".into(), Value::from(vec![1_u8, 2, 3])),
+            ("bool".into(), Value::from(true)),
+            ("list".into(), crate::list![1, "a", crate::list![2.5]]),
+            ("map".into(), crate::map!("a" => 1, 2 => crate::list![])),
+            (
+                "geo".into(),
+                Value::GeoJson(r#"{"type":"Point","coordinates":[1,2]}"#.to_owned()),
+            ),
+        ]);
+        Record::new(Some(key), bins, 3, 0)
+    }
+
+    #[tokio::test]
+    async fn roundtrip() {
+        let with_user_key = record(Key::new("test", "users", "alice"));
+        let digest_only = record(Key::from_digest("test", "", [7; 20]));
+
+        let mut exporter = Exporter::new(Vec::new()).await.unwrap();
+        exporter.write(&with_user_key).await.unwrap();
+        exporter.write(&digest_only).await.unwrap();
+        assert_eq!(2, exporter.records());
+        let data = exporter.finish().await.unwrap();
+
+        let mut importer = Importer::new(&data[..]).await.unwrap();
+        for expected in [with_user_key, digest_only] {
+            let record = importer.next().await.unwrap().unwrap();
+            assert_eq!(expected.key, record.key);
+            assert_eq!(expected.bins, record.bins);
+            assert_eq!(expected.generation, record.generation);
+            assert_eq!(expected.expiration(), record.expiration());
+        }
+        assert!(importer.next().await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn invalid_data() {
+        let mut exporter = Exporter::new(Vec::new()).await.unwrap();
+        assert!(matches!(
+            exporter
+                .write(&Record::new(None, HashMap::new(), 0, 0))
+                .await,
+            Err(ExportError::MissingKey)
+        ));
+        exporter
+            .write(&record(Key::new("test", "users", 1)))
+            .await
+            .unwrap();
+        let data = exporter.finish().await.unwrap();
+
+        assert!(matches!(
+            Importer::new(&b"NOPE\x01"[..]).await,
+            Err(ExportError::InvalidMagic)
+        ));
+        assert!(matches!(
+            Importer::new(&b"WPEX\x09"[..]).await,
+            Err(ExportError::UnsupportedVersion(9))
+        ));
+
+        // Cut off in the middle of a record.
+        let mut importer = Importer::new(&data[..data.len() - 3]).await.unwrap();
+        assert!(matches!(importer.next().await, Err(ExportError::Io(_))));
+
+        // Shorter record length, that cuts off the last bin.
+        let mut truncated = data.clone();
+        let len = u32::from_be_bytes(truncated[5..9].try_into().unwrap()) - 3;
+        truncated[5..9].copy_from_slice(&len.to_be_bytes());
+        truncated.truncate(truncated.len() - 3);
+        let mut importer = Importer::new(&truncated[..]).await.unwrap();
+        assert!(matches!(
+            importer.next().await,
+            Err(ExportError::Corrupted(_))
+        ));
+    }
+
+    #[cfg(feature = "testing")]
+    #[tokio::test]
+    async fn import_into_client() {
+        use crate::{
+            policies::{BasePolicy, WritePolicy},
+            testing::MockClient,
+            Bins,
+        };
+
+        let key = Key::new("test", "users", "bob");
+        let mut exporter = Exporter::new(Vec::new()).await.unwrap();
+        exporter.write(&record(key.clone())).await.unwrap();
+        let data = exporter.finish().await.unwrap();
+
+        let client = MockClient::new();
+        let stats = Importer::new(&data[..])
+            .await
+            .unwrap()
+            .import(&client, &WritePolicy::default())
+            .await
+            .unwrap();
+        assert_eq!(1, stats.imported);
+        assert_eq!(0, stats.expired);
+
+        let imported = client
+            .get(&BasePolicy::default(), &key, Bins::All)
+            .await
+            .unwrap();
+        assert_eq!(Some(&Value::from("hello")), imported.bins.get("str"));
+    }
+}
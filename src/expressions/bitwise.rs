@@ -0,0 +1,87 @@
+//! Expressions that read the bits of blob bins.
+//!
+//! They mirror the read operations in [`operations::bitwise`](crate::operations::bitwise), but can
+//! be used within filter expressions. Offsets and sizes are given in bits and can be any integer
+//! expression. The `bin` argument is usually a [`blob_bin`](super::blob_bin).
+//!
+//! ```
+//! use windpike::expressions::{bitwise, blob_bin, eq, val};
+//!
+//! // Records that have the first 8 feature flags all set.
+//! let filter = eq(bitwise::count(val(0), val(8), blob_bin("flags")), val(8));
+//! ```
+
+use super::{ExpType, Expression};
+use crate::operations::bitwise::OpType;
+
+/// Identifier of the bitwise module in module calls.
+const MODULE: i64 = 1;
+
+fn read(return_type: ExpType, op: OpType, args: Vec<Expression>, bin: Expression) -> Expression {
+    Expression::call(return_type, MODULE, op as i64, args, bin)
+}
+
+/// Get `bit_size` bits of the blob bin, starting at `bit_offset`, as blob.
+#[must_use]
+pub fn get(bit_offset: Expression, bit_size: Expression, bin: Expression) -> Expression {
+    read(ExpType::Blob, OpType::Get, vec![bit_offset, bit_size], bin)
+}
+
+/// Count the set bits in `bit_size` bits of the blob bin, starting at `bit_offset`, as integer.
+#[must_use]
+pub fn count(bit_offset: Expression, bit_size: Expression, bin: Expression) -> Expression {
+    read(ExpType::Int, OpType::Count, vec![bit_offset, bit_size], bin)
+}
+
+/// Get the offset of the first bit that equals the boolean `value`, within `bit_size` bits of the
+/// blob bin starting at `bit_offset`, as integer. The offset is relative to `bit_offset`, and `-1`
+/// if no bit matches.
+#[must_use]
+pub fn lscan(
+    bit_offset: Expression,
+    bit_size: Expression,
+    value: Expression,
+    bin: Expression,
+) -> Expression {
+    read(
+        ExpType::Int,
+        OpType::Lscan,
+        vec![bit_offset, bit_size, value],
+        bin,
+    )
+}
+
+/// Get the offset of the last bit that equals the boolean `value`, within `bit_size` bits of the
+/// blob bin starting at `bit_offset`, as integer. The offset is relative to `bit_offset`, and `-1`
+/// if no bit matches.
+#[must_use]
+pub fn rscan(
+    bit_offset: Expression,
+    bit_size: Expression,
+    value: Expression,
+    bin: Expression,
+) -> Expression {
+    read(
+        ExpType::Int,
+        OpType::Rscan,
+        vec![bit_offset, bit_size, value],
+        bin,
+    )
+}
+
+/// Get `bit_size` bits of the blob bin, starting at `bit_offset`, as integer. The bits are treated
+/// as signed number if `signed` is set.
+#[must_use]
+pub fn get_int(
+    bit_offset: Expression,
+    bit_size: Expression,
+    signed: bool,
+    bin: Expression,
+) -> Expression {
+    let mut args = vec![bit_offset, bit_size];
+    if signed {
+        args.push(super::val(1));
+    }
+
+    read(ExpType::Int, OpType::GetInt, args, bin)
+}
@@ -0,0 +1,62 @@
+//! Expressions that read [HyperLogLog](https://docs.aerospike.com/server/guide/data-types/hll)
+//! bins, like their estimated cardinality.
+//!
+//! They mirror the read operations in [`operations::hll`](crate::operations::hll), but can be used
+//! within filter expressions. The `bin` argument is usually an [`hll_bin`](super::hll_bin), and
+//! lists of HLL values are given as literal [`val`](super::val).
+//!
+//! ```
+//! use windpike::expressions::{gt, hll, hll_bin, val};
+//!
+//! // Records with more than 1000 distinct visitors.
+//! let filter = gt(hll::get_count(hll_bin("visitors")), val(1000));
+//! ```
+
+use super::{ExpType, Expression};
+use crate::operations::hll::OpType;
+
+/// Identifier of the HLL module in module calls.
+const MODULE: i64 = 2;
+
+fn read(return_type: ExpType, op: OpType, args: Vec<Expression>, bin: Expression) -> Expression {
+    Expression::call(return_type, MODULE, op as i64, args, bin)
+}
+
+/// Get the estimated number of elements in the HLL bin, as integer.
+#[must_use]
+pub fn get_count(bin: Expression) -> Expression {
+    read(ExpType::Int, OpType::Count, Vec::new(), bin)
+}
+
+/// Get the union of the HLL bin and all HLL values in the list, as HLL.
+#[must_use]
+pub fn get_union(list: Expression, bin: Expression) -> Expression {
+    read(ExpType::Hll, OpType::Union, vec![list], bin)
+}
+
+/// Get the estimated number of elements in the union of the HLL bin and all HLL values in the
+/// list, as integer.
+#[must_use]
+pub fn get_union_count(list: Expression, bin: Expression) -> Expression {
+    read(ExpType::Int, OpType::UnionCount, vec![list], bin)
+}
+
+/// Get the estimated number of elements in the intersection of the HLL bin and all HLL values in
+/// the list, as integer.
+#[must_use]
+pub fn get_intersect_count(list: Expression, bin: Expression) -> Expression {
+    read(ExpType::Int, OpType::IntersectCount, vec![list], bin)
+}
+
+/// Get the estimated similarity of the HLL bin and all HLL values in the list, as float.
+#[must_use]
+pub fn get_similarity(list: Expression, bin: Expression) -> Expression {
+    read(ExpType::Float, OpType::Similarity, vec![list], bin)
+}
+
+/// Get the index and minhash bit counts that were used to create the HLL bin, as list of two
+/// integers.
+#[must_use]
+pub fn describe(bin: Expression) -> Expression {
+    read(ExpType::List, OpType::Describe, Vec::new(), bin)
+}
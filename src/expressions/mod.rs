@@ -0,0 +1,460 @@
+//! Filter expressions, which are evaluated by the server for each record.
+//!
+//! Expressions are built from the functions in this module and can be combined freely. Records for
+//! which the expression evaluates to `false` are skipped by the server, so they are never sent to
+//! the client.
+//!
+//! ```
+//! use windpike::{
+//!     expressions::{and, ge, int_bin, lt, string_bin, val},
+//!     policies::ScanPolicy,
+//! };
+//!
+//! let policy = ScanPolicy {
+//!     filter_expression: Some(and(vec![
+//!         ge(int_bin("age"), val(18)),
+//!         lt(int_bin("age"), val(65)),
+//!         ge(string_bin("name"), val("M")),
+//!     ])),
+//!     ..ScanPolicy::default()
+//! };
+//! ```
+
+use crate::{msgpack, Value};
+
+pub mod bitwise;
+pub mod hll;
+
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum ExpOp {
+    Eq = 1,
+    Ne,
+    Gt,
+    Ge,
+    Lt,
+    Le,
+    Regex,
+    And = 16,
+    Or,
+    Not,
+    Exclusive,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    DigestModulo = 64,
+    LastUpdate = 66,
+    SinceUpdate,
+    VoidTime,
+    Ttl,
+    SetName,
+    KeyExists,
+    IsTombstone,
+    RecordSize = 74,
+    Key = 80,
+    Bin,
+    BinType,
+    Quoted = 126,
+    Call,
+}
+
+impl From<ExpOp> for i64 {
+    fn from(value: ExpOp) -> Self {
+        value as Self
+    }
+}
+
+/// Data type of an expression value, for example the expected type of a bin.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ExpType {
+    /// No value.
+    Nil,
+    /// Boolean value.
+    Bool,
+    /// 64-bit signed integer.
+    Int,
+    /// String value.
+    String,
+    /// List of values.
+    List,
+    /// Map of values.
+    Map,
+    /// Byte array.
+    Blob,
+    /// 64-bit floating point number.
+    Float,
+    /// Geospatial value.
+    Geo,
+    /// [HyperLogLog](https://docs.aerospike.com/server/guide/data-types/hll) value.
+    Hll,
+}
+
+impl From<ExpType> for i64 {
+    fn from(value: ExpType) -> Self {
+        value as Self
+    }
+}
+
+bitflags::bitflags! {
+    /// Options for [`regex_compare`], which follow the POSIX `regcomp` flags.
+    #[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+    pub struct RegexFlags: i64 {
+        /// Use POSIX extended regular expressions.
+        const EXTENDED = 1;
+        /// Ignore the case of letters.
+        const ICASE = 2;
+        /// Don't report the position of matches.
+        const NOSUB = 4;
+        /// Match-any-character operators don't match a newline.
+        const NEWLINE = 8;
+    }
+}
+
+#[derive(Clone, Debug)]
+pub(crate) enum ExpNode {
+    /// Command with a fixed amount of arguments, packed as `[op, args...]`.
+    Cmd(ExpOp, Vec<Expression>),
+    /// Value of a bin with the expected type, packed as `[op, type, name]`.
+    Bin(ExpType, String),
+    /// Particle type of a bin, packed as `[op, name]`.
+    BinType(String),
+    /// Stored user key of the record with the expected type, packed as `[op, type]`.
+    Key(ExpType),
+    /// Regular expression match against a string, packed as `[op, flags, regex, arg]`.
+    Regex(RegexFlags, String, Box<Expression>),
+    /// Literal value. Lists are quoted, as they would be interpreted as command otherwise.
+    Value(Value),
+    /// Read operation of a server module on a bin value, like HLL or bitwise operations, packed as
+    /// `[op, return type, module, [module op, args...], bin]`.
+    Call {
+        return_type: ExpType,
+        module: i64,
+        op: i64,
+        args: Vec<Expression>,
+        bin: Box<Expression>,
+    },
+}
+
+/// Filter expression, which is evaluated by the server. See the [module](self) documentation for
+/// details.
+#[derive(Clone, Debug)]
+pub struct Expression {
+    pub(crate) node: ExpNode,
+}
+
+impl Expression {
+    const fn new(node: ExpNode) -> Self {
+        Self { node }
+    }
+
+    const fn cmd(op: ExpOp, args: Vec<Self>) -> Self {
+        Self::new(ExpNode::Cmd(op, args))
+    }
+
+    fn call(return_type: ExpType, module: i64, op: i64, args: Vec<Self>, bin: Self) -> Self {
+        Self::new(ExpNode::Call {
+            return_type,
+            module,
+            op,
+            args,
+            bin: Box::new(bin),
+        })
+    }
+
+    pub(crate) fn write_to(&self, w: &mut impl msgpack::Write) -> usize {
+        msgpack::encoder::pack_expression(w, self)
+    }
+
+    pub(crate) fn estimate_size(&self) -> usize {
+        self.write_to(&mut msgpack::Sink)
+    }
+}
+
+/// Create a literal value, like an integer, string or list.
+#[must_use]
+pub fn val(value: impl Into<Value>) -> Expression {
+    Expression::new(ExpNode::Value(value.into()))
+}
+
+/// Create a literal _nil_ value.
+#[must_use]
+pub const fn nil() -> Expression {
+    Expression::new(ExpNode::Value(Value::Nil))
+}
+
+/// Read the value of a bin, which is expected to have the given type. Prefer the typed shorthands
+/// like [`int_bin`] or [`string_bin`].
+#[must_use]
+pub fn bin(name: impl Into<String>, exp_type: ExpType) -> Expression {
+    Expression::new(ExpNode::Bin(exp_type, name.into()))
+}
+
+/// Read the value of an integer bin.
+#[must_use]
+pub fn int_bin(name: impl Into<String>) -> Expression {
+    bin(name, ExpType::Int)
+}
+
+/// Read the value of a float bin.
+#[must_use]
+pub fn float_bin(name: impl Into<String>) -> Expression {
+    bin(name, ExpType::Float)
+}
+
+/// Read the value of a string bin.
+#[must_use]
+pub fn string_bin(name: impl Into<String>) -> Expression {
+    bin(name, ExpType::String)
+}
+
+/// Read the value of a boolean bin.
+#[must_use]
+pub fn bool_bin(name: impl Into<String>) -> Expression {
+    bin(name, ExpType::Bool)
+}
+
+/// Read the value of a blob bin.
+#[must_use]
+pub fn blob_bin(name: impl Into<String>) -> Expression {
+    bin(name, ExpType::Blob)
+}
+
+/// Read the value of a list bin.
+#[must_use]
+pub fn list_bin(name: impl Into<String>) -> Expression {
+    bin(name, ExpType::List)
+}
+
+/// Read the value of a map bin.
+#[must_use]
+pub fn map_bin(name: impl Into<String>) -> Expression {
+    bin(name, ExpType::Map)
+}
+
+/// Read the value of a geospatial bin.
+#[must_use]
+pub fn geo_bin(name: impl Into<String>) -> Expression {
+    bin(name, ExpType::Geo)
+}
+
+/// Read the value of a [HyperLogLog](https://docs.aerospike.com/server/guide/data-types/hll) bin.
+#[must_use]
+pub fn hll_bin(name: impl Into<String>) -> Expression {
+    bin(name, ExpType::Hll)
+}
+
+/// Get the particle type of a bin as integer, which is `0` if the bin doesn't exist.
+#[must_use]
+pub fn bin_type(name: impl Into<String>) -> Expression {
+    Expression::new(ExpNode::BinType(name.into()))
+}
+
+/// Check whether a bin exists.
+#[must_use]
+pub fn bin_exists(name: impl Into<String>) -> Expression {
+    ne(bin_type(name), val(0))
+}
+
+/// Read the user key of the record, which is expected to have the given type. The key is only
+/// available if it was stored together with the record.
+#[must_use]
+pub const fn key(exp_type: ExpType) -> Expression {
+    Expression::new(ExpNode::Key(exp_type))
+}
+
+/// Check whether the user key of the record is stored.
+#[must_use]
+pub const fn key_exists() -> Expression {
+    Expression::cmd(ExpOp::KeyExists, Vec::new())
+}
+
+/// Get the set name of the record.
+#[must_use]
+pub const fn set_name() -> Expression {
+    Expression::cmd(ExpOp::SetName, Vec::new())
+}
+
+/// Get the last update time of the record, in nanoseconds since the Unix epoch.
+#[must_use]
+pub const fn last_update() -> Expression {
+    Expression::cmd(ExpOp::LastUpdate, Vec::new())
+}
+
+/// Get the time since the last update of the record, in milliseconds.
+#[must_use]
+pub const fn since_update() -> Expression {
+    Expression::cmd(ExpOp::SinceUpdate, Vec::new())
+}
+
+/// Get the expiration time of the record, in nanoseconds since the Unix epoch, or `0` if it never
+/// expires.
+#[must_use]
+pub const fn void_time() -> Expression {
+    Expression::cmd(ExpOp::VoidTime, Vec::new())
+}
+
+/// Get the remaining time-to-live of the record, in seconds.
+#[must_use]
+pub const fn ttl() -> Expression {
+    Expression::cmd(ExpOp::Ttl, Vec::new())
+}
+
+/// Check whether the record is a tombstone of a deleted record.
+#[must_use]
+pub const fn is_tombstone() -> Expression {
+    Expression::cmd(ExpOp::IsTombstone, Vec::new())
+}
+
+/// Get the storage size of the record in bytes. Requires Aerospike Server 7.0 or later.
+#[must_use]
+pub const fn record_size() -> Expression {
+    Expression::cmd(ExpOp::RecordSize, Vec::new())
+}
+
+/// Get the remainder of the record's digest divided by `modulo`, which allows to select a stable
+/// sample of records.
+#[must_use]
+pub fn digest_modulo(modulo: i64) -> Expression {
+    Expression::cmd(ExpOp::DigestModulo, vec![val(modulo)])
+}
+
+/// Check whether the string expression matches the regular expression.
+#[must_use]
+pub fn regex_compare(regex: impl Into<String>, flags: RegexFlags, exp: Expression) -> Expression {
+    Expression::new(ExpNode::Regex(flags, regex.into(), Box::new(exp)))
+}
+
+macro_rules! binary {
+    ($($(#[$doc:meta])* $name:ident => $op:ident,)+) => {
+        $(
+            $(#[$doc])*
+            #[must_use]
+            pub fn $name(left: Expression, right: Expression) -> Expression {
+                Expression::cmd(ExpOp::$op, vec![left, right])
+            }
+        )+
+    };
+}
+
+binary! {
+    /// Check whether both values are equal.
+    eq => Eq,
+    /// Check whether both values are not equal.
+    ne => Ne,
+    /// Check whether the left value is greater than the right one.
+    gt => Gt,
+    /// Check whether the left value is greater than or equal to the right one.
+    ge => Ge,
+    /// Check whether the left value is less than the right one.
+    lt => Lt,
+    /// Check whether the left value is less than or equal to the right one.
+    le => Le,
+}
+
+macro_rules! variadic {
+    ($($(#[$doc:meta])* $name:ident => $op:ident,)+) => {
+        $(
+            $(#[$doc])*
+            #[must_use]
+            pub fn $name(exps: Vec<Expression>) -> Expression {
+                Expression::cmd(ExpOp::$op, exps)
+            }
+        )+
+    };
+}
+
+variadic! {
+    /// Check whether all expressions are `true`.
+    and => And,
+    /// Check whether any of the expressions is `true`.
+    or => Or,
+    /// Check whether exactly one of the expressions is `true`.
+    exclusive => Exclusive,
+    /// Sum up all numbers, which must be of the same type.
+    add => Add,
+    /// Subtract all following numbers from the first one.
+    sub => Sub,
+    /// Multiply all numbers, which must be of the same type.
+    mul => Mul,
+    /// Divide the first number by all following ones.
+    div => Div,
+}
+
+/// Negate the boolean expression.
+#[must_use]
+pub fn not(exp: Expression) -> Expression {
+    Expression::cmd(ExpOp::Not, vec![exp])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        and, bin_exists, bitwise, blob_bin, hll, hll_bin, int_bin, key, key_exists, list_bin, not,
+        regex_compare, string_bin, val, ExpType, Expression, RegexFlags,
+    };
+    use crate::Value;
+
+    fn bytes(exp: &Expression) -> Vec<u8> {
+        let mut buf = Vec::new();
+        let size = exp.write_to(&mut buf);
+        assert_eq!(size, buf.len());
+        assert_eq!(size, exp.estimate_size());
+        buf
+    }
+
+    #[test]
+    fn pack_comparison() {
+        assert_eq!(
+            bytes(&super::eq(int_bin("a"), val(1))),
+            [0x93, 0x01, 0x93, 0x51, 0x02, 0xa1, b'a', 0x01]
+        );
+        assert_eq!(
+            bytes(&super::eq(string_bin("s"), val("x"))),
+            [0x93, 0x01, 0x93, 0x51, 0x03, 0xa1, b's', 0xa2, 0x03, b'x']
+        );
+    }
+
+    #[test]
+    fn pack_logical() {
+        assert_eq!(
+            bytes(&and(vec![key_exists(), not(bin_exists("a"))])),
+            [0x93, 0x10, 0x91, 0x47, 0x92, 0x12, 0x93, 0x02, 0x92, 0x52, 0xa1, b'a', 0x00]
+        );
+    }
+
+    #[test]
+    fn pack_quoted_list() {
+        assert_eq!(
+            bytes(&super::eq(
+                list_bin("l"),
+                val(vec![Value::from(1), Value::from(2)])
+            )),
+            [0x93, 0x01, 0x93, 0x51, 0x04, 0xa1, b'l', 0x92, 0x7e, 0x92, 0x01, 0x02]
+        );
+    }
+
+    #[test]
+    fn pack_key_and_regex() {
+        assert_eq!(bytes(&key(ExpType::String)), [0x92, 0x50, 0x03]);
+        assert_eq!(
+            bytes(&regex_compare("^a", RegexFlags::ICASE, string_bin("s"))),
+            [0x94, 0x07, 0x02, 0xa2, b'^', b'a', 0x93, 0x51, 0x03, 0xa1, b's']
+        );
+    }
+
+    #[test]
+    fn pack_module_calls() {
+        assert_eq!(
+            bytes(&hll::get_count(hll_bin("h"))),
+            [0x95, 0x7f, 0x02, 0x02, 0x91, 0x32, 0x93, 0x51, 0x09, 0xa1, b'h']
+        );
+        assert_eq!(
+            bytes(&bitwise::count(val(0), val(8), blob_bin("b"))),
+            [0x95, 0x7f, 0x02, 0x01, 0x93, 0x33, 0x00, 0x08, 0x93, 0x51, 0x06, 0xa1, b'b']
+        );
+        assert_eq!(
+            bytes(&bitwise::get_int(val(0), val(8), true, blob_bin("b")))[..9],
+            [0x95, 0x7f, 0x02, 0x01, 0x94, 0x36, 0x00, 0x08, 0x01]
+        );
+    }
+}
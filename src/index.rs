@@ -1,16 +1,27 @@
 use std::{
-    fmt::{self, Display},
+    fmt::{self, Display, Write},
+    future::Future,
     sync::Arc,
     time::{Duration, Instant},
 };
 
+use base64::{engine::general_purpose, Engine};
+use bytes::BytesMut;
+
 use crate::{
     cluster::Cluster,
+    commands::buffer::MAX_LONG_BIN_NAME_LEN,
     errors::{Error, Result},
+    msgpack::encoder,
+    operations::cdt::Context,
+    policies::InfoPolicy,
 };
 
 /// Current status of an indexing task, as reported by the [`CreateIndex::query_status`] and
-/// [`CreateIndex::wait_till_complete`] methods.
+/// [`DropIndex::query_status`] methods.
+///
+/// For a [`DropIndex`] task, the status is [`Complete`](Self::Complete) once no node knows the
+/// index anymore, and [`InProgress`](Self::InProgress) while any node still lists it.
 #[derive(Clone, Copy, Debug)]
 pub enum Status {
     /// Task for the index operation not found.
@@ -25,14 +36,21 @@ pub enum Status {
 #[derive(Clone, Debug)]
 pub struct CreateIndex {
     cluster: Arc<Cluster>,
+    policy: InfoPolicy,
     namespace: String,
     index_name: String,
 }
 
 impl CreateIndex {
-    pub(crate) fn new(cluster: Arc<Cluster>, namespace: String, index_name: String) -> Self {
+    pub(crate) fn new(
+        cluster: Arc<Cluster>,
+        policy: InfoPolicy,
+        namespace: String,
+        index_name: String,
+    ) -> Self {
         Self {
             cluster,
+            policy,
             namespace,
             index_name,
         }
@@ -85,7 +103,7 @@ impl CreateIndex {
 
         for node in nodes {
             let response = node
-                .info(&[&command])
+                .info(&self.policy, &[&command])
                 .await?
                 .get(&command)
                 .map(|r| Self::parse_response(r));
@@ -101,27 +119,94 @@ impl CreateIndex {
     }
 
     pub async fn wait_till_complete(&self, timeout: Option<Duration>) -> Result<()> {
-        const POLL_INTERVAL: Duration = Duration::from_secs(1);
-
-        let now = Instant::now();
-        let timeout_reached = |deadline| now.elapsed() + POLL_INTERVAL > deadline;
-
-        loop {
-            // Sleep first to give task a chance to complete
-            tokio::time::sleep(POLL_INTERVAL).await;
-
-            match self.query_status().await {
-                Ok(Status::NotFound) => {
-                    return Err(Error::BadResponse("task status not found".to_owned()))
-                }
-                Ok(Status::InProgress) => {} // do nothing and wait
-                Ok(Status::Complete) => return Ok(()),
-                Err(e) => return Err(e),
+        wait_till_complete(timeout, || self.query_status()).await
+    }
+}
+
+/// Struct for querying the status of an index removal, which the nodes process asynchronously.
+///
+/// Waiting for the removal to complete allows to safely recreate an index with the same name,
+/// without racing against the nodes that still hold the old one.
+#[derive(Clone, Debug)]
+pub struct DropIndex {
+    cluster: Arc<Cluster>,
+    policy: InfoPolicy,
+    namespace: String,
+    index_name: String,
+}
+
+impl DropIndex {
+    pub(crate) fn new(
+        cluster: Arc<Cluster>,
+        policy: InfoPolicy,
+        namespace: String,
+        index_name: String,
+    ) -> Self {
+        Self {
+            cluster,
+            policy,
+            namespace,
+            index_name,
+        }
+    }
+
+    pub async fn query_status(&self) -> Result<Status> {
+        let nodes = self.cluster.nodes().await;
+
+        if nodes.is_empty() {
+            return Err(Error::Connection("No connected node".to_owned()));
+        }
+
+        let command = CreateIndex::build_command(&self.namespace, &self.index_name);
+
+        for node in nodes {
+            let response = node
+                .info(&self.policy, &[&command])
+                .await?
+                .get(&command)
+                .map(|r| CreateIndex::parse_response(r));
+
+            match response {
+                Some(Ok(Status::NotFound)) | None => {}
+                Some(Ok(_)) => return Ok(Status::InProgress),
+                Some(Err(e)) => return Err(e),
             }
+        }
 
-            if timeout.map_or(false, timeout_reached) {
-                return Err(Error::Timeout("task timeout reached".to_owned()));
+        Ok(Status::Complete)
+    }
+
+    pub async fn wait_till_complete(&self, timeout: Option<Duration>) -> Result<()> {
+        wait_till_complete(timeout, || self.query_status()).await
+    }
+}
+
+/// Poll the status of an index task until it completed, failed or the timeout was reached.
+async fn wait_till_complete<F, Fut>(timeout: Option<Duration>, query_status: F) -> Result<()>
+where
+    F: Fn() -> Fut,
+    Fut: Future<Output = Result<Status>>,
+{
+    const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+    let now = Instant::now();
+    let timeout_reached = |deadline| now.elapsed() + POLL_INTERVAL > deadline;
+
+    loop {
+        // Sleep first to give task a chance to complete
+        tokio::time::sleep(POLL_INTERVAL).await;
+
+        match query_status().await {
+            Ok(Status::NotFound) => {
+                return Err(Error::BadResponse("task status not found".to_owned()))
             }
+            Ok(Status::InProgress) => {} // do nothing and wait
+            Ok(Status::Complete) => return Ok(()),
+            Err(e) => return Err(e),
+        }
+
+        if timeout.map_or(false, timeout_reached) {
+            return Err(Error::Timeout("task timeout reached".to_owned()));
         }
     }
 }
@@ -167,3 +252,251 @@ impl Display for CollectionIndexType {
         })
     }
 }
+
+/// Definition of a secondary index, to be created with
+/// [`Client::create_index_with`](crate::Client::create_index_with).
+///
+/// All names are validated before the index creation is sent to the server, as malformed info
+/// commands are often silently ignored or only answered with a generic error.
+///
+/// ```
+/// use windpike::{
+///     index::{CollectionIndexType, IndexBuilder},
+///     operations::cdt::Context,
+///     Value,
+/// };
+///
+/// let index = IndexBuilder::numeric("test", "scores", "idx_test_demo_scores")
+///     .set_name("demo")
+///     .collection(CollectionIndexType::List)
+///     .context(&[Context::map_key(Value::from("points"))]);
+///
+/// assert!(index.validate().is_ok());
+/// ```
+#[derive(Clone, Debug)]
+pub struct IndexBuilder {
+    namespace: String,
+    set_name: String,
+    bin_name: String,
+    index_name: String,
+    index_type: IndexType,
+    collection_type: Option<CollectionIndexType>,
+    context: Vec<Context>,
+}
+
+impl IndexBuilder {
+    /// Maximum length of an index name.
+    pub const MAX_INDEX_NAME_LEN: usize = 255;
+    /// Maximum length of a namespace name.
+    pub const MAX_NAMESPACE_LEN: usize = 31;
+    /// Maximum length of a set name.
+    pub const MAX_SET_NAME_LEN: usize = 63;
+
+    /// Create a new index definition on the bin of the given namespace. The index covers all sets
+    /// unless limited with [`Self::set_name`].
+    #[must_use]
+    pub fn new(
+        namespace: impl Into<String>,
+        bin_name: impl Into<String>,
+        index_name: impl Into<String>,
+        index_type: IndexType,
+    ) -> Self {
+        Self {
+            namespace: namespace.into(),
+            set_name: String::new(),
+            bin_name: bin_name.into(),
+            index_name: index_name.into(),
+            index_type,
+            collection_type: None,
+            context: Vec::new(),
+        }
+    }
+
+    /// Create a new definition of a [numeric](IndexType::Numeric) index.
+    #[must_use]
+    pub fn numeric(
+        namespace: impl Into<String>,
+        bin_name: impl Into<String>,
+        index_name: impl Into<String>,
+    ) -> Self {
+        Self::new(namespace, bin_name, index_name, IndexType::Numeric)
+    }
+
+    /// Create a new definition of a [string](IndexType::String) index.
+    #[must_use]
+    pub fn string(
+        namespace: impl Into<String>,
+        bin_name: impl Into<String>,
+        index_name: impl Into<String>,
+    ) -> Self {
+        Self::new(namespace, bin_name, index_name, IndexType::String)
+    }
+
+    /// Create a new definition of a [geospatial](IndexType::Geo2DSphere) index.
+    #[must_use]
+    pub fn geo(
+        namespace: impl Into<String>,
+        bin_name: impl Into<String>,
+        index_name: impl Into<String>,
+    ) -> Self {
+        Self::new(namespace, bin_name, index_name, IndexType::Geo2DSphere)
+    }
+
+    /// Limit the index to the records of a single set.
+    #[must_use]
+    pub fn set_name(mut self, set_name: impl Into<String>) -> Self {
+        self.set_name = set_name.into();
+        self
+    }
+
+    /// Index the elements of a list, or the keys or values of a map, instead of a scalar bin
+    /// value.
+    #[must_use]
+    pub fn collection(mut self, collection_type: CollectionIndexType) -> Self {
+        self.collection_type = Some(collection_type);
+        self
+    }
+
+    /// Index a value that is nested inside of the bin's list or map, at the given location.
+    #[must_use]
+    pub fn context(mut self, context: &[Context]) -> Self {
+        self.context = context.to_vec();
+        self
+    }
+
+    /// Namespace that the index is created in.
+    #[must_use]
+    pub fn namespace(&self) -> &str {
+        &self.namespace
+    }
+
+    /// Name of the index.
+    #[must_use]
+    pub fn index_name(&self) -> &str {
+        &self.index_name
+    }
+
+    /// Check that all names are present, don't exceed the server limits and don't contain any
+    /// characters that would break the info command.
+    ///
+    /// The bin name is only checked against the longest name that any server accepts, as the
+    /// actual limit depends on the node. It's checked against the limit of the node that receives
+    /// the command once the index is created.
+    pub fn validate(&self) -> Result<()> {
+        check("namespace", &self.namespace, Self::MAX_NAMESPACE_LEN, false)?;
+        check("set name", &self.set_name, Self::MAX_SET_NAME_LEN, true)?;
+        self.validate_bin_name(MAX_LONG_BIN_NAME_LEN)?;
+        check(
+            "index name",
+            &self.index_name,
+            Self::MAX_INDEX_NAME_LEN,
+            false,
+        )
+    }
+
+    /// Check the bin name against the limit of a specific node.
+    pub(crate) fn validate_bin_name(&self, max_bin_name_len: usize) -> Result<()> {
+        check("bin name", &self.bin_name, max_bin_name_len, false)
+    }
+
+    /// Validate the definition and render the `sindex-create` info command for it.
+    pub fn to_command(&self) -> Result<String> {
+        self.validate()?;
+
+        let mut cmd = format!("sindex-create:ns={}", self.namespace);
+        if !self.set_name.is_empty() {
+            write!(cmd, ";set={}", self.set_name).ok();
+        }
+        write!(cmd, ";indexname={}", self.index_name).ok();
+
+        if !self.context.is_empty() {
+            let mut buf = BytesMut::new();
+            encoder::pack_context(&mut buf, &self.context);
+            write!(cmd, ";context={}", general_purpose::STANDARD.encode(buf)).ok();
+        }
+
+        cmd.push_str(";numbins=1");
+        if let Some(collection_type) = self.collection_type {
+            write!(cmd, ";indextype={collection_type}").ok();
+        }
+        write!(
+            cmd,
+            ";indexdata={},{};priority=normal",
+            self.bin_name, self.index_type
+        )
+        .ok();
+
+        Ok(cmd)
+    }
+}
+
+fn check(kind: &str, value: &str, max: usize, optional: bool) -> Result<()> {
+    if value.is_empty() && !optional {
+        Err(Error::InvalidArgument(format!("{kind} must not be empty")))
+    } else if value.len() > max {
+        Err(Error::InvalidArgument(format!(
+            "{kind} `{value}` is longer than {max} bytes"
+        )))
+    } else if value.contains([';', ':', '=', ',']) {
+        Err(Error::InvalidArgument(format!(
+            "{kind} `{value}` contains reserved characters"
+        )))
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CollectionIndexType, IndexBuilder};
+    use crate::{errors::Error, operations::cdt::Context};
+
+    #[test]
+    fn render_command() {
+        let cmd = IndexBuilder::string("test", "name", "idx_name")
+            .to_command()
+            .unwrap();
+        assert_eq!(
+            cmd,
+            "sindex-create:ns=test;indexname=idx_name;numbins=1;indexdata=name,STRING;\
+             priority=normal"
+        );
+
+        let cmd = IndexBuilder::numeric("test", "scores", "idx_scores")
+            .set_name("demo")
+            .collection(CollectionIndexType::List)
+            .context(&[Context::list_index(-1)])
+            .to_command()
+            .unwrap();
+        assert_eq!(
+            cmd,
+            "sindex-create:ns=test;set=demo;indexname=idx_scores;context=khD/;numbins=1;\
+             indextype=LIST;indexdata=scores,NUMERIC;priority=normal"
+        );
+    }
+
+    #[test]
+    fn validate_names() {
+        let invalid = [
+            IndexBuilder::geo("", "loc", "idx"),
+            IndexBuilder::geo("test", "b".repeat(256), "idx"),
+            IndexBuilder::geo("test", "loc", "idx;drop"),
+            IndexBuilder::geo("test", "loc", "idx").set_name("a=b"),
+        ];
+
+        for index in invalid {
+            assert!(matches!(index.validate(), Err(Error::InvalidArgument(_))));
+        }
+    }
+
+    #[test]
+    fn validate_bin_name_for_node() {
+        let index = IndexBuilder::geo("test", "location_of_the_user", "idx");
+        assert!(index.validate().is_ok());
+        assert!(index.validate_bin_name(255).is_ok());
+        assert!(matches!(
+            index.validate_bin_name(15),
+            Err(Error::InvalidArgument(_))
+        ));
+    }
+}
@@ -0,0 +1,83 @@
+//! Helpers for processing the responses of info commands.
+
+use crate::ResultCode;
+
+/// Look up a single value in an info response that consists of `name=value` pairs, which are
+/// separated by either `:` or `;`, depending on the command.
+pub(crate) fn field<'a>(response: &'a str, name: &str) -> Option<&'a str> {
+    response
+        .split([':', ';'])
+        .filter_map(|pair| pair.split_once('='))
+        .find_map(|(key, value)| (key == name).then_some(value))
+}
+
+/// Same as [`field`], but parses the value as integer.
+pub(crate) fn field_u64(response: &str, name: &str) -> Option<u64> {
+    field(response, name).and_then(|value| value.parse().ok())
+}
+
+/// Check whether the response of the `sets/<namespace>` command lists the given set.
+pub(crate) fn has_set(response: &str, set_name: &str) -> bool {
+    response
+        .split(';')
+        .any(|entry| field(entry, "set") == Some(set_name))
+}
+
+/// Extract the result code of a failed command, which is reported as `FAIL:<code>` or
+/// `ERROR:<code>`, optionally followed by a message.
+pub(crate) fn error_code(response: &str) -> Option<ResultCode> {
+    let (status, rest) = response.split_once(':')?;
+    if !status.eq_ignore_ascii_case("FAIL") && !status.eq_ignore_ascii_case("ERROR") {
+        return None;
+    }
+
+    rest.split(':')
+        .next()
+        .and_then(|code| code.trim().parse::<u8>().ok())
+        .map(ResultCode::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{error_code, field, field_u64, has_set};
+    use crate::ResultCode;
+
+    #[test]
+    fn parse_sets() {
+        let response = "objects=120:tombstones=0:memory_data_bytes=0:truncate_lut=0:\
+                        stop-writes-count=0:disable-eviction=false;";
+        assert_eq!(field_u64(response, "objects"), Some(120));
+        assert_eq!(field(response, "disable-eviction"), Some("false"));
+        assert_eq!(field(response, "missing"), None);
+    }
+
+    #[test]
+    fn parse_namespace() {
+        let response =
+            "ns_cluster_size=3;effective_replication_factor=2;objects=36;replication-factor=2";
+        assert_eq!(field_u64(response, "effective_replication_factor"), Some(2));
+        assert_eq!(field_u64(response, "replication-factor"), Some(2));
+        assert_eq!(field_u64(response, "ns_cluster_size"), Some(3));
+    }
+
+    #[test]
+    fn find_set() {
+        let response =
+            "ns=test:set=demo:objects=1:tombstones=0;ns=test:set=demo2:objects=0:tombstones=0;";
+        assert!(has_set(response, "demo"));
+        assert!(has_set(response, "demo2"));
+        assert!(!has_set(response, "dem"));
+        assert!(!has_set("", "demo"));
+    }
+
+    #[test]
+    fn parse_error_code() {
+        assert_eq!(error_code("FAIL:20"), Some(ResultCode::InvalidNamespace));
+        assert_eq!(
+            error_code("ERROR:81:role violation"),
+            Some(ResultCode::RoleViolation)
+        );
+        assert_eq!(error_code("ok"), None);
+        assert_eq!(error_code("ns=test:set=demo"), None);
+    }
+}
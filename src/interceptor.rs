@@ -0,0 +1,83 @@
+//! Hooks that run around each single record command.
+//!
+//! An interceptor is configured with
+//! [`ClientPolicy::interceptor`](crate::policies::ClientPolicy::interceptor) and is called for
+//! every record-level command, like reads, writes, deletes or operations. This allows audit
+//! logging, collecting metrics, or injecting failures in tests, without wrapping the whole client.
+//!
+//! ```
+//! use std::{sync::Arc, time::Duration};
+//!
+//! use windpike::{
+//!     errors::CommandError,
+//!     interceptor::{CommandContext, CommandInterceptor},
+//!     policies::ClientPolicy,
+//! };
+//!
+//! #[derive(Debug)]
+//! struct AuditLog;
+//!
+//! impl CommandInterceptor for AuditLog {
+//!     fn after(
+//!         &self,
+//!         context: &CommandContext<'_>,
+//!         outcome: Result<(), &CommandError>,
+//!         elapsed: Duration,
+//!     ) {
+//!         println!(
+//!             "{:?} on {:?} took {elapsed:?}, success: {}",
+//!             context.kind,
+//!             context.key,
+//!             outcome.is_ok(),
+//!         );
+//!     }
+//! }
+//!
+//! let policy = ClientPolicy {
+//!     interceptor: Some(Arc::new(AuditLog)),
+//!     ..ClientPolicy::default()
+//! };
+//! ```
+
+use std::{fmt::Debug, time::Duration};
+
+use crate::{errors::CommandError, metrics::CommandKind, Key};
+
+/// Details about the command that is being intercepted.
+#[derive(Clone, Copy, Debug)]
+#[non_exhaustive]
+pub struct CommandContext<'a> {
+    /// Whether the command reads or writes the record. Operations count as a write if any of
+    /// them modifies the record.
+    pub kind: CommandKind,
+    /// Key of the record that the command operates on.
+    pub key: &'a Key,
+}
+
+impl<'a> CommandContext<'a> {
+    pub(crate) fn new(kind: CommandKind, key: &'a Key) -> Self {
+        Self { kind, key }
+    }
+}
+
+/// Hooks that are called before and after each single record command.
+///
+/// Both hooks are called once per command, regardless of how many retries the command needed.
+/// They run on the task that executes the command, so they should return quickly.
+pub trait CommandInterceptor: Debug + Send + Sync {
+    /// Called before the command is sent to the cluster. Returning an error aborts the command
+    /// with that error, without contacting any node, and [`Self::after`] isn't called.
+    fn before(&self, _context: &CommandContext<'_>) -> Result<(), CommandError> {
+        Ok(())
+    }
+
+    /// Called after the command finished, with its outcome and the total time it took,
+    /// including all retries.
+    fn after(
+        &self,
+        _context: &CommandContext<'_>,
+        _outcome: Result<(), &CommandError>,
+        _elapsed: Duration,
+    ) {
+    }
+}
@@ -0,0 +1,170 @@
+//! Monitoring and management of long-running scans and queries on the server side.
+//!
+//! Jobs are listed with [`Client::list_jobs`](crate::Client::list_jobs) and can be aborted with
+//! [`Client::kill_job`](crate::Client::kill_job).
+
+use std::{collections::HashMap, time::Duration};
+
+use tracing::warn;
+
+/// A scan or query job, as reported by a single cluster node.
+///
+/// Each node runs its own part of a scan or query, so the same transaction ID is usually reported
+/// by every node in the cluster.
+#[derive(Clone, Debug)]
+pub struct Job {
+    /// Transaction ID of the job, which can be used to abort it.
+    pub trans_id: u64,
+    /// Name of the node that reported the job.
+    pub node: String,
+    /// Kind of job, like `basic` for regular scans and queries, or `background-udf`.
+    pub job_type: String,
+    /// Namespace that the job operates on.
+    pub namespace: String,
+    /// Set that the job operates on, which is empty if the job covers the whole namespace.
+    pub set_name: String,
+    /// Current state of the job.
+    pub status: JobStatus,
+    /// Progress of the job on this node, in percent.
+    pub progress: f64,
+    /// Duration that the job has been running for.
+    pub run_time: Duration,
+    /// Amount of records that were successfully processed so far.
+    pub records: u64,
+    /// All raw values reported by the server, including the ones that were parsed into the fields
+    /// above.
+    pub fields: HashMap<String, String>,
+}
+
+/// State of a [`Job`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum JobStatus {
+    /// The job is still running.
+    Active,
+    /// The job finished, either successfully or by being aborted. The reason is reported by the
+    /// server, like `ok` or `user-aborted`.
+    Done(String),
+    /// Any other status that is not known to the client.
+    Unknown(String),
+}
+
+impl From<&str> for JobStatus {
+    fn from(value: &str) -> Self {
+        if value == "active" {
+            Self::Active
+        } else if let Some(reason) = value
+            .strip_prefix("done(")
+            .and_then(|reason| reason.strip_suffix(')'))
+        {
+            Self::Done(reason.to_owned())
+        } else {
+            Self::Unknown(value.to_owned())
+        }
+    }
+}
+
+/// Parse the response of the `query-show` or `scan-show` info command of a single node.
+///
+/// The response is a list of jobs, separated by `;`. Each job consists of `key=value` pairs,
+/// separated by `:`.
+pub(crate) fn parse_jobs(node: &str, response: &str) -> Vec<Job> {
+    response
+        .split(';')
+        .filter(|job| !job.is_empty())
+        .filter_map(|job| {
+            let fields = job
+                .split(':')
+                .filter_map(|pair| pair.split_once('='))
+                .map(|(key, value)| (key.to_owned(), value.to_owned()))
+                .collect::<HashMap<_, _>>();
+
+            let trans_id = if let Some(id) = fields.get("trid").and_then(|id| id.parse().ok()) {
+                id
+            } else {
+                warn!(node, job, "job without valid transaction ID");
+                return None;
+            };
+
+            let get = |keys: &[&str]| keys.iter().find_map(|&key| fields.get(key));
+            let parse = |keys: &[&str]| {
+                get(keys)
+                    .and_then(|value| value.parse::<u64>().ok())
+                    .unwrap_or_default()
+            };
+
+            Some(Job {
+                trans_id,
+                node: node.to_owned(),
+                job_type: get(&["job-type"]).cloned().unwrap_or_default(),
+                namespace: get(&["ns"]).cloned().unwrap_or_default(),
+                set_name: get(&["set"]).cloned().unwrap_or_default(),
+                status: get(&["status"]).map_or_else(
+                    || JobStatus::Unknown(String::new()),
+                    |status| status.as_str().into(),
+                ),
+                progress: get(&["job-progress", "job-progress(%)"])
+                    .and_then(|value| value.parse().ok())
+                    .unwrap_or_default(),
+                run_time: Duration::from_millis(parse(&["run-time"])),
+                records: parse(&["recs-succeeded", "recs-read"]),
+                fields,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{parse_jobs, JobStatus};
+
+    #[test]
+    fn parse_query_show() {
+        let response = concat!(
+            "trid=123:job-type=basic:ns=test:set=demo:status=active:job-progress=12.50:",
+            "run-time=1500:recs-succeeded=42;",
+            "trid=456:job-type=background-udf:ns=test:status=done(user-aborted):",
+            "job-progress=100.00:run-time=10;",
+        );
+        let jobs = parse_jobs("BB9", response);
+
+        assert_eq!(jobs.len(), 2);
+
+        let job = &jobs[0];
+        assert_eq!(job.trans_id, 123);
+        assert_eq!(job.node, "BB9");
+        assert_eq!(job.job_type, "basic");
+        assert_eq!(job.namespace, "test");
+        assert_eq!(job.set_name, "demo");
+        assert_eq!(job.status, JobStatus::Active);
+        assert!((job.progress - 12.5).abs() < f64::EPSILON);
+        assert_eq!(job.run_time, Duration::from_millis(1500));
+        assert_eq!(job.records, 42);
+
+        let job = &jobs[1];
+        assert_eq!(job.trans_id, 456);
+        assert_eq!(job.set_name, "");
+        assert_eq!(job.status, JobStatus::Done("user-aborted".to_owned()));
+    }
+
+    #[test]
+    fn parse_legacy_scan_show() {
+        let response = concat!(
+            "trid=7:job-type=basic:ns=test:set=demo:status=done(ok):",
+            "job-progress(%)=100.00:run-time=20:recs-read=1000",
+        );
+        let jobs = parse_jobs("BB9", response);
+
+        assert_eq!(jobs.len(), 1);
+        assert_eq!(jobs[0].status, JobStatus::Done("ok".to_owned()));
+        assert!((jobs[0].progress - 100.0).abs() < f64::EPSILON);
+        assert_eq!(jobs[0].records, 1000);
+    }
+
+    #[test]
+    fn skip_invalid_jobs() {
+        assert!(parse_jobs("BB9", "").is_empty());
+        assert!(parse_jobs("BB9", "job-type=basic:ns=test").is_empty());
+    }
+}
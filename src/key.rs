@@ -2,7 +2,7 @@ use std::borrow::Cow;
 
 use ripemd::{Digest, Ripemd160};
 
-use crate::{commands::ParticleType, msgpack, value::ParticleError};
+use crate::{cluster::node, commands::ParticleType, msgpack, value::ParticleError};
 
 macro_rules! from {
     ($to:ty, $variant:ident, $($from:ty),+) => {
@@ -54,11 +54,41 @@ impl Key {
         }
     }
 
+    /// Construct a new key from an already computed digest, without the original user key.
+    ///
+    /// This is useful when only the digest of a record is known, for example after it was
+    /// returned by a scan without the user key being stored on the server. The set name isn't
+    /// checked against the digest and is only sent along as additional information.
+    #[must_use]
+    pub fn from_digest<N, S>(namespace: N, set_name: S, digest: [u8; 20]) -> Self
+    where
+        N: Into<Cow<'static, str>>,
+        S: Into<Cow<'static, str>>,
+    {
+        Self {
+            namespace: namespace.into(),
+            set_name: set_name.into(),
+            user_key: None,
+            digest,
+        }
+    }
+
     #[must_use]
     pub fn digest(&self) -> [u8; 20] {
         self.digest
     }
 
+    /// Partition that the record belongs to within its namespace, in the range of `0..4096`.
+    ///
+    /// The partition is derived from the digest only, so it is stable for the lifetime of the
+    /// record and the same for every client.
+    #[must_use]
+    pub fn partition_id(&self) -> u32 {
+        let mut buf = [0; 4];
+        buf.copy_from_slice(&self.digest[0..4]);
+        u32::from_le_bytes(buf) % node::PARTITIONS
+    }
+
     fn compute_digest(set_name: &str, user_key: &UserKey) -> [u8; 20] {
         let mut hash = Ripemd160::new();
         hash.update(set_name.as_bytes());
@@ -71,6 +101,12 @@ impl Key {
 
 /// The user key, which is a subset of the [`Value`](crate::Value) type, as only a few of its
 /// variants are allowed to be used in Aerospike keys.
+///
+/// Integer keys of any width are widened to an `i64` and hashed as its 8-byte big-endian
+/// representation, the same way other Aerospike clients hash their `long` or `int64` keys. Equal
+/// values therefore always produce the same digest, regardless of the integer type they were
+/// created from. Unsigned 64-bit values can only be converted with [`TryFrom`], as anything above
+/// [`i64::MAX`] can't be represented by other clients.
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub enum UserKey {
     /// 64-bit signed integer.
@@ -225,11 +261,27 @@ from!(UserKey, Int, i8, i16, i32, i64, u8, u16, u32);
 from!(UserKey, String, &'static str, String, Cow<'static, str>);
 from!(UserKey, Blob, &'static [u8], Vec<u8>, Cow<'static, [u8]>);
 
+impl TryFrom<u64> for UserKey {
+    type Error = std::num::TryFromIntError;
+
+    fn try_from(value: u64) -> Result<Self, Self::Error> {
+        i64::try_from(value).map(Self::Int)
+    }
+}
+
+impl TryFrom<usize> for UserKey {
+    type Error = std::num::TryFromIntError;
+
+    fn try_from(value: usize) -> Result<Self, Self::Error> {
+        i64::try_from(value).map(Self::Int)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::str;
 
-    use crate::Key;
+    use crate::{Key, UserKey};
 
     macro_rules! digest {
         ($x:expr) => {
@@ -298,6 +350,40 @@ mod tests {
         assert_eq!(digest!(u8::MAX), "5a7dd3ea237c30c8735b051524e66fd401a10f6a");
     }
 
+    #[test]
+    fn unsigned_keys() {
+        assert_eq!(
+            digest!(UserKey::try_from(0u64).unwrap()),
+            "93d943aae37b017ad7e011b0c1d2e2143c2fb37d"
+        );
+        assert_eq!(
+            digest!(UserKey::try_from(1u64).unwrap()),
+            "82d7213b469812947c109a6d341e3b5b1dedec1f"
+        );
+        assert_eq!(
+            digest!(UserKey::try_from(i64::MAX as u64).unwrap()),
+            "1698328974afa62c8e069860c1516f780d63dbb8"
+        );
+        assert_eq!(
+            digest!(UserKey::try_from(1usize).unwrap()),
+            "82d7213b469812947c109a6d341e3b5b1dedec1f"
+        );
+        assert_eq!(
+            digest!(UserKey::try_from(usize::try_from(i64::MAX).unwrap()).unwrap()),
+            "1698328974afa62c8e069860c1516f780d63dbb8"
+        );
+
+        // Values beyond `i64::MAX` would wrap around to negative keys, like `i64::MIN`, which
+        // address a different record.
+        assert_eq!(
+            digest!(UserKey::from(i64::MIN)),
+            "7185c2a47fb02c996daed26b4e01b83240aee9d4"
+        );
+        assert!(UserKey::try_from(1u64 << 63).is_err());
+        assert!(UserKey::try_from(u64::MAX).is_err());
+        assert!(UserKey::try_from(usize::MAX).is_err());
+    }
+
     #[test]
     fn string_keys() {
         assert_eq!(digest!(""), "2819b1ff6e346a43b4f5f6b77a88bc3eaac22a83");
@@ -364,4 +450,21 @@ mod tests {
             "fe19770c371774ba1a1532438d4851b8a773a9e6"
         );
     }
+
+    #[test]
+    fn from_digest() {
+        let key = Key::new("namespace", "set", "haha");
+        let digest_key = Key::from_digest("namespace", "set", key.digest());
+
+        assert_eq!(key.digest(), digest_key.digest());
+        assert_eq!(None, digest_key.user_key);
+    }
+
+    #[test]
+    fn partition_id() {
+        // digest 93d943aa...
+        assert_eq!(Key::new("namespace", "set", 0).partition_id(), 0x993);
+        // digest 22116d25...
+        assert_eq!(Key::new("namespace", "set", -1).partition_id(), 0x122);
+    }
 }
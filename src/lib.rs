@@ -112,32 +112,58 @@
 //! ```
 
 pub use ordered_float;
+pub use tokio_util::sync::CancellationToken;
+#[cfg(feature = "derive")]
+pub use windpike_derive::{FromRecord, ToBins};
 
+#[cfg(feature = "blocking")]
+pub use crate::record::BlockingRecords;
 pub use crate::{
-    batch::BatchRead,
-    bin::{Bin, Bins},
+    api::ClientApi,
+    batch::{BatchBuilder, BatchRead},
+    bin::{Bin, Bins, ToBins},
     client::Client,
+    cluster::{node::FeatureSupport, ClusterInfo, Node, PartitionHealth, PartitionOwners},
+    commands::User,
     key::{Key, UserKey},
-    net::{Host, ToHosts},
-    record::{Record, RecordSet},
+    net::{Host, PoolStats, ToHosts},
+    record::{
+        BinName, FromRecord, NodeProgress, Record, RecordBins, RecordExpiration, RecordSet,
+        ScanProgress,
+    },
     result_code::ResultCode,
-    value::{FloatValue, MapKey, Value},
+    value::{FloatValue, FromValue, MapKey, Value},
 };
 
+mod api;
 mod batch;
 mod bin;
+pub mod cache;
 mod client;
 mod cluster;
 mod commands;
 pub mod errors;
+pub mod export;
+pub mod expressions;
 mod key;
 #[macro_use]
 mod macros;
 pub mod index;
+mod info;
+pub mod interceptor;
+pub mod job;
+pub mod metrics;
 mod msgpack;
 mod net;
 pub mod operations;
 pub mod policies;
+#[cfg(feature = "raw")]
+pub mod raw;
 mod record;
 mod result_code;
+#[cfg(feature = "testing")]
+pub mod testing;
+#[cfg(any(feature = "chrono", feature = "time"))]
+pub mod timestamp;
 mod value;
+pub mod writer;
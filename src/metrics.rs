@@ -0,0 +1,298 @@
+//! Client-side latency metrics.
+//!
+//! The client can track the latency of its commands in histograms, separately for each
+//! [`CommandKind`]. Tracking is disabled by default and can be enabled with
+//! [`ClientPolicy::latency_metrics`](crate::policies::ClientPolicy::latency_metrics), or toggled
+//! at runtime with [`Client::set_latency_metrics`](crate::Client::set_latency_metrics).
+//!
+//! The histograms use logarithmic buckets with linear sub-buckets, similar to HDR histograms.
+//! Recorded values are accurate to about 12.5%, regardless of their magnitude, while recording
+//! stays a cheap lock-free operation.
+//!
+//! ```rust,no_run
+//! # use windpike::{metrics::CommandKind, Client};
+//! # fn run(client: &Client) {
+//! client.set_latency_metrics(true);
+//!
+//! // ... run some commands ...
+//!
+//! let reads = client.latency_snapshot(CommandKind::Read);
+//! println!(
+//!     "p99 of {} reads: {:?}",
+//!     reads.count(),
+//!     reads.percentile(99.0)
+//! );
+//! # }
+//! ```
+
+use std::{
+    sync::atomic::{AtomicBool, AtomicU64, Ordering},
+    time::Duration,
+};
+
+/// Kind of command that latencies are tracked for.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum CommandKind {
+    /// Single record reads, like gets, exists checks or operations that only read.
+    Read,
+    /// Single record writes, like puts, deletes, touches or operations that write.
+    Write,
+    /// Batch reads, measured for the whole batch including all nodes.
+    Batch,
+    /// Scans, measured separately for each node that is scanned.
+    Scan,
+}
+
+impl CommandKind {
+    const ALL: [Self; 4] = [Self::Read, Self::Write, Self::Batch, Self::Scan];
+
+    const fn index(self) -> usize {
+        match self {
+            Self::Read => 0,
+            Self::Write => 1,
+            Self::Batch => 2,
+            Self::Scan => 3,
+        }
+    }
+}
+
+/// Amount of bits for the linear sub-buckets within each power of two.
+const SUB_BUCKET_BITS: u32 = 3;
+const SUB_BUCKETS: usize = 1 << SUB_BUCKET_BITS;
+/// Enough buckets to hold any `u64` value.
+const BUCKETS: usize = (u64::BITS - SUB_BUCKET_BITS + 1) as usize * SUB_BUCKETS;
+
+/// Index of the bucket that holds the given value.
+fn bucket_index(value: u64) -> usize {
+    if value < SUB_BUCKETS as u64 {
+        return value as usize;
+    }
+
+    let shift = u64::BITS - 1 - value.leading_zeros() - SUB_BUCKET_BITS;
+    let sub_bucket = (value >> shift) as usize & (SUB_BUCKETS - 1);
+    (shift as usize + 1) * SUB_BUCKETS + sub_bucket
+}
+
+/// Highest value that is stored in the given bucket.
+fn bucket_upper_bound(index: usize) -> u64 {
+    if index < SUB_BUCKETS {
+        return index as u64;
+    }
+
+    let shift = index / SUB_BUCKETS - 1;
+    let lower = ((SUB_BUCKETS + index % SUB_BUCKETS) as u64) << shift;
+    lower + ((1 << shift) - 1)
+}
+
+/// Lock-free histogram of latencies in microseconds.
+#[derive(Debug)]
+struct LatencyHistogram {
+    buckets: Box<[AtomicU64]>,
+    count: AtomicU64,
+    sum: AtomicU64,
+    max: AtomicU64,
+}
+
+impl LatencyHistogram {
+    fn new() -> Self {
+        Self {
+            buckets: (0..BUCKETS).map(|_| AtomicU64::new(0)).collect(),
+            count: AtomicU64::new(0),
+            sum: AtomicU64::new(0),
+            max: AtomicU64::new(0),
+        }
+    }
+
+    fn record(&self, latency: Duration) {
+        let micros = u64::try_from(latency.as_micros()).unwrap_or(u64::MAX);
+
+        self.buckets[bucket_index(micros)].fetch_add(1, Ordering::Relaxed);
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.sum.fetch_add(micros, Ordering::Relaxed);
+        self.max.fetch_max(micros, Ordering::Relaxed);
+    }
+
+    fn snapshot(&self) -> LatencySnapshot {
+        LatencySnapshot {
+            buckets: self
+                .buckets
+                .iter()
+                .map(|bucket| bucket.load(Ordering::Relaxed))
+                .collect(),
+            count: self.count.load(Ordering::Relaxed),
+            sum: self.sum.load(Ordering::Relaxed),
+            max: self.max.load(Ordering::Relaxed),
+        }
+    }
+
+    fn reset(&self) {
+        for bucket in self.buckets.iter() {
+            bucket.store(0, Ordering::Relaxed);
+        }
+        self.count.store(0, Ordering::Relaxed);
+        self.sum.store(0, Ordering::Relaxed);
+        self.max.store(0, Ordering::Relaxed);
+    }
+}
+
+/// Point-in-time copy of the latencies that were recorded for one [`CommandKind`].
+///
+/// Values are recorded concurrently with taking the snapshot, so the fields might be off by the
+/// few commands that finished in the meantime.
+#[derive(Clone, Debug)]
+pub struct LatencySnapshot {
+    buckets: Vec<u64>,
+    count: u64,
+    sum: u64,
+    max: u64,
+}
+
+impl LatencySnapshot {
+    /// Amount of recorded commands.
+    #[must_use]
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// Average latency of all recorded commands.
+    #[must_use]
+    pub fn mean(&self) -> Duration {
+        Duration::from_micros(self.sum.checked_div(self.count).unwrap_or_default())
+    }
+
+    /// Highest recorded latency.
+    #[must_use]
+    pub fn max(&self) -> Duration {
+        Duration::from_micros(self.max)
+    }
+
+    /// Latency that the given percentage of commands stayed below or at, like `99.0` for the p99
+    /// latency. The percentile is clamped to the range `0.0..=100.0`, and a _zero_ duration is
+    /// returned if no commands were recorded.
+    #[must_use]
+    pub fn percentile(&self, percentile: f64) -> Duration {
+        if self.count == 0 {
+            return Duration::ZERO;
+        }
+
+        let fraction = percentile.clamp(0.0, 100.0) / 100.0;
+        #[allow(
+            clippy::cast_possible_truncation,
+            clippy::cast_precision_loss,
+            clippy::cast_sign_loss
+        )]
+        let rank = ((fraction * self.count as f64).ceil() as u64).max(1);
+
+        let mut seen = 0;
+        for (index, &amount) in self.buckets.iter().enumerate() {
+            seen += amount;
+            if seen >= rank {
+                return Duration::from_micros(bucket_upper_bound(index).min(self.max));
+            }
+        }
+
+        self.max()
+    }
+}
+
+/// Latency histograms for all command kinds, shared by the whole cluster.
+#[derive(Debug)]
+pub(crate) struct Metrics {
+    enabled: AtomicBool,
+    histograms: [LatencyHistogram; CommandKind::ALL.len()],
+}
+
+impl Metrics {
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            enabled: AtomicBool::new(enabled),
+            histograms: CommandKind::ALL.map(|_| LatencyHistogram::new()),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::Relaxed);
+    }
+
+    /// Record the latency of a command, if tracking is enabled.
+    pub fn record(&self, kind: CommandKind, latency: Duration) {
+        if self.is_enabled() {
+            self.histograms[kind.index()].record(latency);
+        }
+    }
+
+    pub fn snapshot(&self, kind: CommandKind) -> LatencySnapshot {
+        self.histograms[kind.index()].snapshot()
+    }
+
+    pub fn reset(&self) {
+        for histogram in &self.histograms {
+            histogram.reset();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{bucket_index, bucket_upper_bound, CommandKind, Metrics, BUCKETS};
+
+    #[test]
+    fn buckets_are_contiguous() {
+        for value in 0..100_000 {
+            let index = bucket_index(value);
+            assert!(value <= bucket_upper_bound(index), "value {value}");
+            if index > 0 {
+                assert!(value > bucket_upper_bound(index - 1), "value {value}");
+            }
+        }
+
+        assert_eq!(BUCKETS - 1, bucket_index(u64::MAX));
+        assert_eq!(u64::MAX, bucket_upper_bound(BUCKETS - 1));
+    }
+
+    #[test]
+    fn percentiles() {
+        let metrics = Metrics::new(true);
+        for millis in 1..=100 {
+            metrics.record(CommandKind::Read, Duration::from_millis(millis));
+        }
+
+        let snapshot = metrics.snapshot(CommandKind::Read);
+        assert_eq!(100, snapshot.count());
+        assert_eq!(Duration::from_micros(50_500), snapshot.mean());
+        assert_eq!(Duration::from_millis(100), snapshot.max());
+
+        for (percentile, expected) in [(50.0, 50), (90.0, 90), (99.0, 99), (100.0, 100)] {
+            let actual = snapshot.percentile(percentile).as_secs_f64() * 1000.0;
+            let error = (actual - f64::from(expected)) / f64::from(expected);
+            assert!((0.0..0.125).contains(&error), "p{percentile}: {actual}");
+        }
+
+        assert_eq!(0, metrics.snapshot(CommandKind::Write).count());
+    }
+
+    #[test]
+    fn toggle_and_reset() {
+        let metrics = Metrics::new(false);
+        metrics.record(CommandKind::Scan, Duration::from_millis(1));
+        assert_eq!(0, metrics.snapshot(CommandKind::Scan).count());
+        assert_eq!(
+            Duration::ZERO,
+            metrics.snapshot(CommandKind::Scan).percentile(99.0)
+        );
+
+        metrics.set_enabled(true);
+        metrics.record(CommandKind::Scan, Duration::from_millis(1));
+        assert_eq!(1, metrics.snapshot(CommandKind::Scan).count());
+
+        metrics.reset();
+        assert_eq!(0, metrics.snapshot(CommandKind::Scan).count());
+        assert_eq!(Duration::ZERO, metrics.snapshot(CommandKind::Scan).max());
+    }
+}
@@ -5,66 +5,147 @@ use tracing::warn;
 use super::{Marker, MsgpackError, Read, Result};
 use crate::{
     commands::ParticleType,
+    policies::DecodeLimits,
     value::{MapKey, Value},
 };
 
-pub(crate) fn unpack_value_list(buf: &mut impl Read) -> Result<Value> {
+pub(crate) fn unpack_value_list(buf: &mut impl Read, limits: &DecodeLimits) -> Result<Value> {
     if buf.is_empty() {
         return Ok(Value::List(Vec::new()));
     }
 
-    let value = unpack_value(buf)?;
-    assert!(matches!(value, Value::List(_)));
-
-    Ok(value)
+    match unpack_value(buf, limits, 0)? {
+        value @ Value::List(_) => Ok(value),
+        _ => Err(MsgpackError::UnexpectedType("list")),
+    }
 }
 
-pub(crate) fn unpack_value_map(buf: &mut impl Read) -> Result<Value> {
+pub(crate) fn unpack_value_map(buf: &mut impl Read, limits: &DecodeLimits) -> Result<Value> {
     if buf.is_empty() {
         return Ok(Value::from(HashMap::new()));
     }
 
-    let value = unpack_value(buf)?;
-    assert!(matches!(value, Value::HashMap(_)));
+    match unpack_value(buf, limits, 0)? {
+        value @ Value::HashMap(_) => Ok(value),
+        _ => Err(MsgpackError::UnexpectedType("map")),
+    }
+}
+
+/// Decode a map in the order of its entries, as a [`Value::List`] that holds a two element list of
+/// key and value for each entry.
+pub(crate) fn unpack_value_map_pairs(buf: &mut impl Read, limits: &DecodeLimits) -> Result<Value> {
+    if buf.is_empty() {
+        return Ok(Value::List(Vec::new()));
+    }
+
+    let marker = buf.read_u8();
+    let count = match Marker::from(marker) {
+        Marker::FixMap(len) => len as usize,
+        Marker::Map16 => buf.read_u16() as usize,
+        Marker::Map32 => buf.read_u32() as usize,
+        _ => return Err(MsgpackError::InvalidMarker(marker)),
+    };
+
+    let pairs = unpack_map_entries(buf, limits, 1, count)?
+        .into_iter()
+        .map(|(key, value)| Value::List(vec![key.into(), value]))
+        .collect();
+
+    Ok(Value::List(pairs))
+}
+
+/// Ensure that a container with the given amount of elements can be decoded at the given nesting
+/// depth, before any memory is allocated for it.
+fn check_container(limits: &DecodeLimits, depth: usize, count: usize) -> Result<()> {
+    if depth > limits.max_depth {
+        return Err(MsgpackError::DepthExceeded(limits.max_depth));
+    }
+    if count > limits.max_elements {
+        return Err(MsgpackError::TooManyElements {
+            count,
+            max: limits.max_elements,
+        });
+    }
+    Ok(())
+}
 
-    Ok(value)
+fn check_bytes(limits: &DecodeLimits, len: usize) -> Result<()> {
+    if len > limits.max_bytes {
+        return Err(MsgpackError::TooLarge {
+            len,
+            max: limits.max_bytes,
+        });
+    }
+    Ok(())
 }
 
-fn unpack_array(buf: &mut impl Read, mut count: usize) -> Result<Value> {
+fn unpack_array(
+    buf: &mut impl Read,
+    limits: &DecodeLimits,
+    depth: usize,
+    mut count: usize,
+) -> Result<Value> {
+    check_container(limits, depth, count)?;
+
     if count > 0 && is_ext(buf.peek()) {
-        unpack_value(buf).ok();
+        unpack_value(buf, limits, depth).ok();
         count -= 1;
     }
 
     let mut list: Vec<Value> = Vec::with_capacity(count);
     for _ in 0..count {
-        let val = unpack_value(buf)?;
+        let val = unpack_value(buf, limits, depth)?;
         list.push(val);
     }
 
     Ok(Value::from(list))
 }
 
-fn unpack_map(buf: &mut impl Read, mut count: usize) -> Result<Value> {
+fn unpack_map(
+    buf: &mut impl Read,
+    limits: &DecodeLimits,
+    depth: usize,
+    count: usize,
+) -> Result<Value> {
+    let entries = unpack_map_entries(buf, limits, depth, count)?;
+    Ok(Value::from(entries.into_iter().collect::<HashMap<_, _>>()))
+}
+
+/// Decode the entries of a map in the order they were encoded, skipping over the extension that
+/// ordered maps start with.
+fn unpack_map_entries(
+    buf: &mut impl Read,
+    limits: &DecodeLimits,
+    depth: usize,
+    mut count: usize,
+) -> Result<Vec<(MapKey, Value)>> {
+    check_container(limits, depth, count)?;
+
     if count > 0 && is_ext(buf.peek()) {
-        unpack_value(buf).ok();
-        unpack_value(buf).ok();
+        unpack_value(buf, limits, depth).ok();
+        unpack_value(buf, limits, depth).ok();
         count -= 1;
     }
 
-    let mut map = HashMap::with_capacity(count);
+    let mut entries = Vec::with_capacity(count);
     for _ in 0..count {
-        let key = unpack_map_key(buf)?;
-        let val = unpack_value(buf)?;
-        map.insert(key, val);
+        let key = unpack_map_key(buf, limits)?;
+        let val = unpack_value(buf, limits, depth)?;
+        entries.push((key, val));
     }
 
-    Ok(Value::from(map))
+    Ok(entries)
 }
 
-fn unpack_blob(buf: &mut impl Read, count: usize) -> Result<Value> {
+fn unpack_blob(buf: &mut impl Read, limits: &DecodeLimits, count: usize) -> Result<Value> {
+    // Without the particle type prefix, the only valid content is an empty string.
+    if count == 0 {
+        return Ok(Value::String(String::new()));
+    }
+
     let vtype = buf.read_u8();
     let count = count - 1;
+    check_bytes(limits, count)?;
 
     match ParticleType::try_from(vtype)? {
         ParticleType::String => {
@@ -80,9 +161,14 @@ fn unpack_blob(buf: &mut impl Read, count: usize) -> Result<Value> {
     }
 }
 
-fn unpack_string(buf: &mut impl Read, count: usize) -> Result<String> {
+fn unpack_string(buf: &mut impl Read, limits: &DecodeLimits, count: usize) -> Result<String> {
+    if count == 0 {
+        return Ok(String::new());
+    }
+
     let vtype = buf.read_u8();
     let count = count - 1;
+    check_bytes(limits, count)?;
 
     match ParticleType::try_from(vtype)? {
         ParticleType::String => buf.read_str(count).map_err(Into::into),
@@ -90,25 +176,25 @@ fn unpack_string(buf: &mut impl Read, count: usize) -> Result<String> {
     }
 }
 
-fn unpack_map_key(buf: &mut impl Read) -> Result<MapKey> {
+fn unpack_map_key(buf: &mut impl Read, limits: &DecodeLimits) -> Result<MapKey> {
     let marker = buf.read_u8();
 
     match Marker::from(marker) {
         Marker::Pfix(value) => Ok(MapKey::from(value)),
-        Marker::FixStr(len) => unpack_string(buf, len as usize).map(Into::into),
+        Marker::FixStr(len) => unpack_string(buf, limits, len as usize).map(Into::into),
         Marker::Bin8 | Marker::Str8 => {
             let count = buf.read_u8();
-            unpack_string(buf, count as usize).map(Into::into)
+            unpack_string(buf, limits, count as usize).map(Into::into)
         }
         Marker::Bin16 | Marker::Str16 => {
             let count = buf.read_u16();
-            unpack_string(buf, count as usize).map(Into::into)
+            unpack_string(buf, limits, count as usize).map(Into::into)
         }
         Marker::Bin32 | Marker::Str32 => {
             let count = buf.read_u32();
-            unpack_string(buf, count as usize).map(Into::into)
+            unpack_string(buf, limits, count as usize).map(Into::into)
         }
-        Marker::F32 => Ok(MapKey::from(buf.read_f32())),
+        Marker::F32 => Ok(MapKey::from(f64::from(buf.read_f32()))),
         Marker::F64 => Ok(MapKey::from(buf.read_f64())),
         Marker::U8 => Ok(MapKey::from(buf.read_u8())),
         Marker::U16 => Ok(MapKey::from(buf.read_u16())),
@@ -123,14 +209,15 @@ fn unpack_map_key(buf: &mut impl Read) -> Result<MapKey> {
     }
 }
 
-fn unpack_value(buf: &mut impl Read) -> Result<Value> {
+/// Decode the next value, where `depth` is the amount of containers that it's nested in.
+fn unpack_value(buf: &mut impl Read, limits: &DecodeLimits, depth: usize) -> Result<Value> {
     let marker = Marker::from(buf.read_u8());
 
     match marker {
         Marker::Pfix(value) => Ok(Value::from(value)),
-        Marker::FixMap(len) => unpack_map(buf, len as usize),
-        Marker::FixArray(len) => unpack_array(buf, len as usize),
-        Marker::FixStr(len) => unpack_blob(buf, len as usize),
+        Marker::FixMap(len) => unpack_map(buf, limits, depth + 1, len as usize),
+        Marker::FixArray(len) => unpack_array(buf, limits, depth + 1, len as usize),
+        Marker::FixStr(len) => unpack_blob(buf, limits, len as usize),
         Marker::Nil => Ok(Value::Nil),
         Marker::Reserved => {
             warn!("skipping over reserved type");
@@ -140,15 +227,15 @@ fn unpack_value(buf: &mut impl Read) -> Result<Value> {
         Marker::True => Ok(Value::from(true)),
         Marker::Bin8 | Marker::Str8 => {
             let count = buf.read_u8();
-            unpack_blob(buf, count as usize)
+            unpack_blob(buf, limits, count as usize)
         }
         Marker::Bin16 | Marker::Str16 => {
             let count = buf.read_u16();
-            unpack_blob(buf, count as usize)
+            unpack_blob(buf, limits, count as usize)
         }
         Marker::Bin32 | Marker::Str32 => {
             let count = buf.read_u32();
-            unpack_blob(buf, count as usize)
+            unpack_blob(buf, limits, count as usize)
         }
         Marker::Ext8 => {
             warn!("skipping over type extension with 8 bit header and bytes");
@@ -168,7 +255,7 @@ fn unpack_value(buf: &mut impl Read) -> Result<Value> {
             buf.advance(count);
             Ok(Value::Nil)
         }
-        Marker::F32 => Ok(Value::from(buf.read_f32())),
+        Marker::F32 => Ok(Value::from(f64::from(buf.read_f32()))),
         Marker::F64 => Ok(Value::from(buf.read_f64())),
         Marker::U8 => Ok(Value::from(buf.read_u8())),
         Marker::U16 => Ok(Value::from(buf.read_u16())),
@@ -205,19 +292,19 @@ fn unpack_value(buf: &mut impl Read) -> Result<Value> {
         }
         Marker::Array16 => {
             let count = buf.read_u16();
-            unpack_array(buf, count as usize)
+            unpack_array(buf, limits, depth + 1, count as usize)
         }
         Marker::Array32 => {
             let count = buf.read_u32();
-            unpack_array(buf, count as usize)
+            unpack_array(buf, limits, depth + 1, count as usize)
         }
         Marker::Map16 => {
             let count = buf.read_u16();
-            unpack_map(buf, count as usize)
+            unpack_map(buf, limits, depth + 1, count as usize)
         }
         Marker::Map32 => {
             let count = buf.read_u32();
-            unpack_map(buf, count as usize)
+            unpack_map(buf, limits, depth + 1, count as usize)
         }
         Marker::Nfix(value) => Ok(Value::from(value)),
     }
@@ -238,3 +325,133 @@ fn is_ext(marker: Option<u8>) -> bool {
         )
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::{unpack_value_list, unpack_value_map, unpack_value_map_pairs};
+    use crate::{
+        commands::buffer::Buffer,
+        msgpack::{MsgpackError, Write},
+        policies::DecodeLimits,
+        value::MapKey,
+        Value,
+    };
+
+    fn buffer(bytes: &[u8]) -> Buffer {
+        let mut buf = Buffer::new(0);
+        buf.write_bytes(bytes);
+        buf
+    }
+
+    #[test]
+    fn nesting_depth() {
+        let limits = DecodeLimits {
+            max_depth: 2,
+            ..DecodeLimits::default()
+        };
+
+        // [[1]]
+        let value = unpack_value_list(&mut buffer(&[0x91, 0x91, 0x01]), &limits).unwrap();
+        assert_eq!(value, Value::List(vec![Value::List(vec![Value::Int(1)])]));
+
+        // [[[1]]]
+        assert!(matches!(
+            unpack_value_list(&mut buffer(&[0x91, 0x91, 0x91, 0x01]), &limits),
+            Err(MsgpackError::DepthExceeded(2))
+        ));
+    }
+
+    #[test]
+    fn element_count() {
+        let limits = DecodeLimits {
+            max_elements: 2,
+            ..DecodeLimits::default()
+        };
+
+        // A list that announces 2^32 - 1 elements, without any of them being present.
+        assert!(matches!(
+            unpack_value_list(&mut buffer(&[0xdd, 0xff, 0xff, 0xff, 0xff]), &limits),
+            Err(MsgpackError::TooManyElements {
+                count: 0xffff_ffff,
+                max: 2
+            })
+        ));
+
+        // {1: 1, 2: 2, 3: 3}
+        assert!(matches!(
+            unpack_value_map(
+                &mut buffer(&[0x83, 0x01, 0x01, 0x02, 0x02, 0x03, 0x03]),
+                &limits
+            ),
+            Err(MsgpackError::TooManyElements { count: 3, max: 2 })
+        ));
+    }
+
+    #[test]
+    fn string_length() {
+        let limits = DecodeLimits {
+            max_bytes: 2,
+            ..DecodeLimits::default()
+        };
+
+        // ["ab"] and ["abc"], with the particle type prefix in each string.
+        let value = unpack_value_list(&mut buffer(&[0x91, 0xa3, 3, b'a', b'b']), &limits).unwrap();
+        assert_eq!(value, Value::List(vec![Value::from("ab")]));
+
+        assert!(matches!(
+            unpack_value_list(&mut buffer(&[0x91, 0xa4, 3, b'a', b'b', b'c']), &limits),
+            Err(MsgpackError::TooLarge { len: 3, max: 2 })
+        ));
+    }
+
+    #[test]
+    fn unexpected_type() {
+        let limits = DecodeLimits::default();
+
+        assert!(matches!(
+            unpack_value_list(&mut buffer(&[0x80]), &limits),
+            Err(MsgpackError::UnexpectedType("list"))
+        ));
+        assert!(matches!(
+            unpack_value_map(&mut buffer(&[0x90]), &limits),
+            Err(MsgpackError::UnexpectedType("map"))
+        ));
+    }
+
+    #[test]
+    fn empty_string() {
+        // ["", 1] and {"": 1}, without the particle type prefix in the strings.
+        let value =
+            unpack_value_list(&mut buffer(&[0x92, 0xa0, 0x01]), &DecodeLimits::default()).unwrap();
+        assert_eq!(value, Value::List(vec![Value::from(""), Value::Int(1)]));
+
+        let value =
+            unpack_value_map(&mut buffer(&[0x81, 0xa0, 0x01]), &DecodeLimits::default()).unwrap();
+        assert_eq!(
+            value,
+            Value::from(HashMap::from([(MapKey::from(""), Value::Int(1))]))
+        );
+    }
+
+    #[test]
+    fn map_pairs_order() {
+        // {3: 1, 1: 2, 2: 3}, with the extension of a key ordered map in front.
+        let value = unpack_value_map_pairs(
+            &mut buffer(&[
+                0x84, 0xc7, 0x00, 0x01, 0xc0, 0x03, 0x01, 0x01, 0x02, 0x02, 0x03,
+            ]),
+            &DecodeLimits::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            value,
+            Value::List(vec![
+                Value::List(vec![Value::Int(3), Value::Int(1)]),
+                Value::List(vec![Value::Int(1), Value::Int(2)]),
+                Value::List(vec![Value::Int(2), Value::Int(3)]),
+            ])
+        );
+    }
+}
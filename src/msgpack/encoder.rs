@@ -3,18 +3,16 @@ use std::collections::HashMap;
 use super::{Marker, Write};
 use crate::{
     commands::ParticleType,
+    expressions::{ExpNode, ExpOp, Expression},
     operations::cdt,
-    value::{FloatValue, MapKey, Value},
+    value::{MapKey, Value},
 };
 
 pub(crate) fn pack_map_key(w: &mut impl Write, val: &MapKey) -> usize {
     match val {
         MapKey::Int(val) => pack_integer(w, *val),
         MapKey::Uint(val) => pack_u64(w, *val),
-        MapKey::Float(val) => match val {
-            FloatValue::F64(val) => pack_f64(w, val.0),
-            FloatValue::F32(val) => pack_f32(w, val.0),
-        },
+        MapKey::Float(val) => pack_f64(w, val.to_f64()),
         MapKey::String(val) => pack_string(w, val),
     }
 }
@@ -26,17 +24,35 @@ pub(crate) fn pack_value(w: &mut impl Write, val: &Value) -> usize {
         Value::Uint(val) => pack_u64(w, *val),
         Value::Bool(val) => pack_bool(w, *val),
         Value::String(val) => pack_string(w, val),
-        Value::Float(val) => match val {
-            FloatValue::F64(val) => pack_f64(w, val.0),
-            FloatValue::F32(val) => pack_f32(w, val.0),
-        },
+        // The server only stores doubles, so 32-bit floats are widened upfront to keep them
+        // comparable with the values that are read back.
+        Value::Float(val) => pack_f64(w, val.to_f64()),
         Value::Blob(val) | Value::Hll(val) => pack_blob(w, val),
         Value::List(val) => pack_array(w, val),
         Value::HashMap(val) => pack_map(w, val),
         Value::GeoJson(val) => pack_geo_json(w, val),
+        // Unknown particles only appear as top-level bin values, so there is no matching
+        // MessagePack representation. Keep the content as plain bytes at least.
+        Value::Unknown { raw, .. } => pack_blob(w, raw),
     }
 }
 
+/// Pack the CDT context as flat list of alternating context types and values.
+pub(crate) fn pack_context(w: &mut impl Write, ctx: &[cdt::Context]) -> usize {
+    let mut size = pack_array_begin(w, ctx.len() * 2);
+
+    for c in ctx {
+        if c.id == 0 {
+            size += pack_integer(w, i64::from(c.id));
+        } else {
+            size += pack_integer(w, i64::from(c.id | c.flags));
+        }
+        size += pack_value(w, &c.value);
+    }
+
+    size
+}
+
 pub(crate) fn pack_cdt_op(
     w: &mut impl Write,
     op: &cdt::Operation<'_>,
@@ -52,16 +68,7 @@ pub(crate) fn pack_cdt_op(
     } else {
         size += pack_array_begin(w, 3);
         size += pack_integer(w, 0xff);
-        size += pack_array_begin(w, ctx.len() * 2);
-
-        for c in ctx {
-            if c.id == 0 {
-                size += pack_integer(w, i64::from(c.id));
-            } else {
-                size += pack_integer(w, i64::from(c.id | c.flags));
-            }
-            size += pack_value(w, &c.value);
-        }
+        size += pack_context(w, ctx);
 
         size += pack_array_begin(w, op.args.len() + 1);
         size += pack_integer(w, i64::from(op.op));
@@ -69,13 +76,17 @@ pub(crate) fn pack_cdt_op(
 
     if !op.args.is_empty() {
         for arg in &op.args {
-            size += match *arg {
-                cdt::Argument::Byte(byte) => pack_value(w, &Value::from(byte)),
-                cdt::Argument::Int(int) => pack_value(w, &Value::from(int)),
+            size += match arg {
+                cdt::Argument::Byte(byte) | cdt::Argument::WriteFlags(byte) => {
+                    pack_value(w, &Value::from(*byte))
+                }
+                cdt::Argument::Int(int) | cdt::Argument::PairsReturnType(int) => {
+                    pack_value(w, &Value::from(*int))
+                }
                 cdt::Argument::Value(value) => pack_value(w, value),
                 cdt::Argument::List(list) => pack_array(w, list),
                 cdt::Argument::Map(map) => pack_map(w, map),
-                cdt::Argument::Bool(bool_val) => pack_value(w, &Value::from(bool_val)),
+                cdt::Argument::Bool(bool_val) => pack_value(w, &Value::from(*bool_val)),
             }
         }
     }
@@ -93,13 +104,17 @@ pub(crate) fn pack_hll_op(
     size += pack_integer(w, i64::from(op.op));
     if !op.args.is_empty() {
         for arg in &op.args {
-            size += match *arg {
-                cdt::Argument::Byte(byte) => pack_value(w, &Value::from(byte)),
-                cdt::Argument::Int(int) => pack_value(w, &Value::from(int)),
+            size += match arg {
+                cdt::Argument::Byte(byte) | cdt::Argument::WriteFlags(byte) => {
+                    pack_value(w, &Value::from(*byte))
+                }
+                cdt::Argument::Int(int) | cdt::Argument::PairsReturnType(int) => {
+                    pack_value(w, &Value::from(*int))
+                }
                 cdt::Argument::Value(value) => pack_value(w, value),
                 cdt::Argument::List(list) => pack_array(w, list),
                 cdt::Argument::Map(map) => pack_map(w, map),
-                cdt::Argument::Bool(bool_val) => pack_value(w, &Value::from(bool_val)),
+                cdt::Argument::Bool(bool_val) => pack_value(w, &Value::from(*bool_val)),
             }
         }
     }
@@ -115,16 +130,7 @@ pub(crate) fn pack_cdt_bit_op(
     if !ctx.is_empty() {
         size += pack_array_begin(w, 3);
         size += pack_integer(w, 0xff);
-        size += pack_array_begin(w, ctx.len() * 2);
-
-        for c in ctx {
-            if c.id == 0 {
-                size += pack_integer(w, i64::from(c.id));
-            } else {
-                size += pack_integer(w, i64::from(c.id | c.flags));
-            }
-            size += pack_value(w, &c.value);
-        }
+        size += pack_context(w, ctx);
     }
 
     size += pack_array_begin(w, op.args.len() + 1);
@@ -132,19 +138,82 @@ pub(crate) fn pack_cdt_bit_op(
 
     if !op.args.is_empty() {
         for arg in &op.args {
-            size += match *arg {
-                cdt::Argument::Byte(byte) => pack_value(w, &Value::from(byte)),
-                cdt::Argument::Int(int) => pack_value(w, &Value::from(int)),
+            size += match arg {
+                cdt::Argument::Byte(byte) | cdt::Argument::WriteFlags(byte) => {
+                    pack_value(w, &Value::from(*byte))
+                }
+                cdt::Argument::Int(int) | cdt::Argument::PairsReturnType(int) => {
+                    pack_value(w, &Value::from(*int))
+                }
                 cdt::Argument::Value(value) => pack_value(w, value),
                 cdt::Argument::List(list) => pack_array(w, list),
                 cdt::Argument::Map(map) => pack_map(w, map),
-                cdt::Argument::Bool(bool_val) => pack_value(w, &Value::from(bool_val)),
+                cdt::Argument::Bool(bool_val) => pack_value(w, &Value::from(*bool_val)),
             }
         }
     }
     size
 }
 
+pub(crate) fn pack_expression(w: &mut impl Write, exp: &Expression) -> usize {
+    match &exp.node {
+        ExpNode::Cmd(op, args) => {
+            pack_array_begin(w, args.len() + 1)
+                + pack_integer(w, (*op).into())
+                + args
+                    .iter()
+                    .map(|arg| pack_expression(w, arg))
+                    .sum::<usize>()
+        }
+        ExpNode::Bin(exp_type, name) => {
+            pack_array_begin(w, 3)
+                + pack_integer(w, ExpOp::Bin.into())
+                + pack_integer(w, (*exp_type).into())
+                + pack_raw_string(w, name)
+        }
+        ExpNode::BinType(name) => {
+            pack_array_begin(w, 2)
+                + pack_integer(w, ExpOp::BinType.into())
+                + pack_raw_string(w, name)
+        }
+        ExpNode::Key(exp_type) => {
+            pack_array_begin(w, 2)
+                + pack_integer(w, ExpOp::Key.into())
+                + pack_integer(w, (*exp_type).into())
+        }
+        ExpNode::Regex(flags, regex, arg) => {
+            pack_array_begin(w, 4)
+                + pack_integer(w, ExpOp::Regex.into())
+                + pack_integer(w, flags.bits())
+                + pack_raw_string(w, regex)
+                + pack_expression(w, arg)
+        }
+        ExpNode::Call {
+            return_type,
+            module,
+            op,
+            args,
+            bin,
+        } => {
+            pack_array_begin(w, 5)
+                + pack_integer(w, ExpOp::Call.into())
+                + pack_integer(w, (*return_type).into())
+                + pack_integer(w, *module)
+                + pack_array_begin(w, args.len() + 1)
+                + pack_integer(w, *op)
+                + args
+                    .iter()
+                    .map(|arg| pack_expression(w, arg))
+                    .sum::<usize>()
+                + pack_expression(w, bin)
+        }
+        ExpNode::Value(value @ Value::List(_)) => {
+            pack_array_begin(w, 2) + pack_integer(w, ExpOp::Quoted.into()) + pack_value(w, value)
+        }
+        ExpNode::Value(value) => pack_value(w, value),
+    }
+}
+
 fn pack_array(w: &mut impl Write, values: &[Value]) -> usize {
     pack_array_begin(w, values.len()) + values.iter().map(|val| pack_value(w, val)).sum::<usize>()
 }
@@ -177,6 +246,14 @@ fn pack_string(w: &mut impl Write, value: &str) -> usize {
     size
 }
 
+/// Pack a plain string, without the particle type prefix used for values.
+fn pack_raw_string(w: &mut impl Write, value: &str) -> usize {
+    let size = pack_bytes_begin(w, value.len());
+    w.write_str(value);
+
+    size + value.len()
+}
+
 fn pack_geo_json(w: &mut impl Write, value: &str) -> usize {
     let mut size = value.len() + 1;
 
@@ -364,13 +441,6 @@ fn pack_u64(w: &mut impl Write, value: u64) -> usize {
     }
 }
 
-#[inline]
-fn pack_f32(w: &mut impl Write, value: f32) -> usize {
-    w.write_u8(Marker::F32.into());
-    w.write_f32(value);
-    5
-}
-
 #[inline]
 fn pack_f64(w: &mut impl Write, value: f64) -> usize {
     w.write_u8(Marker::F64.into());
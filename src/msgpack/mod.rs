@@ -4,7 +4,7 @@
     clippy::cast_sign_loss
 )]
 
-use bytes::BufMut;
+use bytes::{BufMut, BytesMut};
 
 use crate::commands::{buffer::BufferError, ParseParticleError};
 
@@ -23,6 +23,14 @@ pub enum MsgpackError {
     Buffer(#[from] crate::commands::buffer::BufferError),
     #[error("the marker `{0}` isn't valid for the data type")]
     InvalidMarker(u8),
+    #[error("lists and maps are nested deeper than the limit of {0}")]
+    DepthExceeded(usize),
+    #[error("container with {count} elements exceeds the limit of {max}")]
+    TooManyElements { count: usize, max: usize },
+    #[error("string or blob of {len} bytes exceeds the limit of {max}")]
+    TooLarge { len: usize, max: usize },
+    #[error("expected a {0}, but found a value of a different type")]
+    UnexpectedType(&'static str),
 }
 
 pub(crate) trait Read {
@@ -57,7 +65,6 @@ pub(crate) trait Write {
     fn write_i16(&mut self, v: i16) -> usize;
     fn write_i32(&mut self, v: i32) -> usize;
     fn write_i64(&mut self, v: i64) -> usize;
-    fn write_f32(&mut self, v: f32) -> usize;
     fn write_f64(&mut self, v: f64) -> usize;
 
     fn write_bytes(&mut self, v: &[u8]) -> usize;
@@ -110,11 +117,6 @@ impl Write for Sink {
         std::mem::size_of::<i64>()
     }
 
-    #[inline]
-    fn write_f32(&mut self, _: f32) -> usize {
-        std::mem::size_of::<f32>()
-    }
-
     #[inline]
     fn write_f64(&mut self, _: f64) -> usize {
         std::mem::size_of::<f64>()
@@ -141,6 +143,65 @@ impl Write for Sink {
     }
 }
 
+/// Writer that only forwards data to the inner writer up to a limit, so encoding an oversized value
+/// doesn't keep growing the underlying buffer. Everything after the limit is only counted, like
+/// with the [`Sink`].
+pub(crate) struct Limited<'a, W> {
+    inner: &'a mut W,
+    remaining: usize,
+    exceeded: bool,
+}
+
+impl<'a, W: Write> Limited<'a, W> {
+    pub(crate) fn new(inner: &'a mut W, limit: usize) -> Self {
+        Self {
+            inner,
+            remaining: limit,
+            exceeded: false,
+        }
+    }
+
+    /// Whether any data was dropped because it didn't fit within the limit.
+    pub(crate) const fn exceeded(&self) -> bool {
+        self.exceeded
+    }
+}
+
+macro_rules! limited_write {
+    ($($name:ident($ty:ty)),* $(,)?) => {
+        $(
+            fn $name(&mut self, v: $ty) -> usize {
+                let size = Sink.$name(v);
+                if !self.exceeded && size <= self.remaining {
+                    self.remaining -= size;
+                    self.inner.$name(v)
+                } else {
+                    self.exceeded = true;
+                    size
+                }
+            }
+        )*
+    };
+}
+
+impl<W: Write> Write for Limited<'_, W> {
+    limited_write!(
+        write_u8(u8),
+        write_u16(u16),
+        write_u32(u32),
+        write_u64(u64),
+        write_i8(i8),
+        write_i16(i16),
+        write_i32(i32),
+        write_i64(i64),
+        write_f64(f64),
+        write_bytes(&[u8]),
+        write_str(&str),
+        write_bool(bool),
+        write_geo(&str),
+    );
+}
+
 impl<T: BufMut> Write for T {
     fn write_u8(&mut self, v: u8) -> usize {
         self.put_u8(v);
@@ -182,11 +243,6 @@ impl<T: BufMut> Write for T {
         Sink.write_i64(v)
     }
 
-    fn write_f32(&mut self, v: f32) -> usize {
-        self.put_f32(v);
-        Sink.write_f32(v)
-    }
-
     fn write_f64(&mut self, v: f64) -> usize {
         self.put_f64(v);
         Sink.write_f64(v)
@@ -214,6 +270,37 @@ impl<T: BufMut> Write for T {
     }
 }
 
+/// Placeholder for a big-endian length prefix in a growable buffer.
+///
+/// The prefix is written as zero first and patched with the actual length once all content that
+/// follows it was written. This allows encoding nested values in a single pass, instead of walking
+/// them once with a [`Sink`] to calculate their size upfront and then a second time to write them.
+#[must_use = "the placeholder must be patched with the final length"]
+pub(crate) struct Checkpoint {
+    position: usize,
+}
+
+impl Checkpoint {
+    const WIDTH: usize = std::mem::size_of::<u32>();
+
+    /// Reserve a `u32` length prefix at the current end of the buffer.
+    pub(crate) fn reserve(buf: &mut BytesMut) -> Self {
+        let position = buf.len();
+        buf.put_u32(0);
+        Self { position }
+    }
+
+    /// Patch the placeholder with the amount of bytes written after it, and return that amount.
+    pub(crate) fn patch(self, buf: &mut BytesMut) -> usize {
+        let written = buf.len() - self.position - Self::WIDTH;
+
+        buf[self.position..self.position + Self::WIDTH]
+            .copy_from_slice(&(written as u32).to_be_bytes());
+
+        written
+    }
+}
+
 #[derive(Clone, Copy)]
 enum Marker {
     Pfix(u8),
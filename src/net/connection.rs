@@ -1,16 +1,24 @@
-use std::ops::Add;
+use std::{
+    io::{self, Cursor},
+    net::SocketAddr,
+    ops::Add,
+};
 
+use rand::Rng;
+use socket2::{SockRef, TcpKeepalive};
 use tokio::{
     io::{AsyncReadExt, AsyncWriteExt},
     net::TcpStream,
+    sync::mpsc,
+    task::JoinHandle,
     time::{Duration, Instant},
 };
+use tracing::debug;
 
-use super::{NetError, Result};
+use super::{trace, NetError, Result, Session};
 use crate::{
-    commands::{
-        buffer::{Buffer, MessageHeader, ProtoHeader, StreamMessageHeader, TOTAL_HEADER_SIZE},
-        AdminCommand,
+    commands::buffer::{
+        Buffer, MessageHeader, ProtoHeader, StreamMessageHeader, MAX_BUFFER_SIZE, TOTAL_HEADER_SIZE,
     },
     policies::ClientPolicy,
 };
@@ -22,8 +30,16 @@ pub struct Connection {
     idle_deadline: Option<Instant>,
 
     // connection object
-    conn: TcpStream,
+    conn: Stream,
     active: bool,
+    // set to the command that waits for its response, so abandoned connections aren't reused
+    in_flight: Option<&'static str>,
+    // whether the command in flight streams its response, and may be stopped by the consumer
+    streaming: bool,
+
+    // percentage of commands whose payloads are logged, and whether the current one is
+    trace_wire: u8,
+    traced: bool,
 
     bytes_read: usize,
 
@@ -31,50 +47,207 @@ pub struct Connection {
 }
 
 impl Connection {
-    pub async fn new(addr: &str, policy: &ClientPolicy) -> Result<Self> {
-        let stream = tokio::time::timeout(Duration::from_secs(10), TcpStream::connect(addr)).await;
-        if stream.is_err() {
-            return Err(NetError::FailedOpening);
+    pub async fn new(addr: &str, policy: &ClientPolicy, session: Option<&Session>) -> Result<Self> {
+        let mut conn = Self::open(addr, policy).await?;
+        if let Some(session) = session {
+            if !conn.authenticate(session).await? {
+                // The server closes the connection after rejecting the session token, so a fresh
+                // one logs in with the credentials instead.
+                conn.close().await;
+                conn = Self::open(addr, policy).await?;
+                conn.login(session).await?;
+            }
+        }
+        conn.refresh();
+        Ok(conn)
+    }
+
+    async fn open(addr: &str, policy: &ClientPolicy) -> Result<Self> {
+        let stream = match policy.connect_timeout {
+            Some(timeout) => tokio::time::timeout(timeout, connect(addr, policy))
+                .await
+                .unwrap_or_else(|_| Err(io::ErrorKind::TimedOut.into())),
+            None => connect(addr, policy).await,
         }
-        let mut conn = Self {
+        .map_err(|source| NetError::FailedOpening {
+            address: addr.to_owned(),
+            source,
+        })?;
+        configure_socket(&stream, policy)?;
+
+        Ok(Self {
             buffer: Buffer::new(policy.buffer_reclaim_threshold),
             bytes_read: 0,
-            conn: stream.unwrap()?,
+            conn: Stream::Tcp(stream),
             active: true,
+            in_flight: None,
+            streaming: false,
+            trace_wire: policy.trace_wire.unwrap_or_default(),
+            traced: false,
             idle_timeout: policy.idle_timeout,
             idle_deadline: policy.idle_timeout.map(|timeout| Instant::now() + timeout),
-        };
-        conn.authenticate(&policy.user_password).await?;
-        conn.refresh();
-        Ok(conn)
+        })
+    }
+
+    /// Create a connection that isn't backed by a socket, but instead receives its response data
+    /// from a [`Pipeline`](super::Pipeline).
+    pub(crate) fn detached(buffer_reclaim_threshold: usize) -> Self {
+        Self {
+            buffer: Buffer::new(buffer_reclaim_threshold),
+            bytes_read: 0,
+            conn: Stream::Memory(Cursor::default()),
+            active: true,
+            in_flight: None,
+            streaming: false,
+            trace_wire: 0,
+            traced: false,
+            idle_timeout: None,
+            idle_deadline: None,
+        }
+    }
+
+    pub(super) fn into_tcp_stream(self) -> Option<TcpStream> {
+        match self.conn {
+            Stream::Tcp(stream) => Some(stream),
+            Stream::Memory(_) => None,
+        }
     }
 
+    pub(crate) fn set_response(&mut self, response: Vec<u8>) {
+        self.conn = Stream::Memory(Cursor::new(response));
+    }
+
+    /// Whether the connection can be reused. Connections that still wait for the response of a
+    /// cancelled command contain unread data and must be discarded.
     pub(super) fn active(&self) -> bool {
-        self.active
+        self.active && self.in_flight.is_none()
+    }
+
+    /// Name of the command that was started on this connection, but not finished yet.
+    pub(super) fn in_flight(&self) -> Option<&'static str> {
+        self.in_flight.filter(|_| self.active)
+    }
+
+    /// Mark the start of a command. Until it is [finished](Self::finish_command), the connection
+    /// is considered broken, in case the command future is dropped halfway through.
+    pub fn start_command(&mut self, command: &'static str) {
+        self.in_flight = Some(command);
+        self.streaming = false;
+        self.traced = self.trace_wire > 0 && rand::thread_rng().gen_range(0..100) < self.trace_wire;
+    }
+
+    /// Like [`Self::start_command`], but for commands that stream their response, like scans.
+    /// Consumers may stop these at any time, so dropping the connection halfway through is
+    /// expected and not reported as leak.
+    pub fn start_stream(&mut self, command: &'static str) {
+        self.start_command(command);
+        self.streaming = true;
+    }
+
+    /// Mark the command as finished, after its response was fully read.
+    pub fn finish_command(&mut self) {
+        self.in_flight = None;
+        self.streaming = false;
+        self.traced = false;
+    }
+
+    /// Whether the command in flight streams its response. See [`Self::start_stream`].
+    pub(super) fn streaming(&self) -> bool {
+        self.streaming
+    }
+
+    /// Name of the current command, if its payloads are logged.
+    fn traced_command(&self) -> Option<&'static str> {
+        self.in_flight.filter(|_| self.traced)
+    }
+
+    /// Mark the connection as unusable, so it's discarded instead of being returned to the pool.
+    /// The socket itself is closed once the connection is dropped.
+    pub(super) fn invalidate(&mut self) {
+        self.active = false;
     }
 
     pub async fn close(&mut self) {
         self.active = false;
-        self.conn.shutdown().await.ok();
+        if let Stream::Tcp(stream) = &mut self.conn {
+            stream.shutdown().await.ok();
+        }
     }
 
     pub async fn flush(&mut self) -> Result<()> {
-        self.conn.write_all(self.buffer.as_ref()).await?;
+        if let Some(command) = self.traced_command() {
+            let data = self.buffer.as_ref();
+            debug!(
+                command,
+                "sending request{}{}",
+                trace::annotate(data),
+                trace::hex_dump(data)
+            );
+        }
+
+        // Detached connections hand their request over to the pipeline instead.
+        if let Stream::Tcp(stream) = &mut self.conn {
+            stream.write_all(self.buffer.as_ref()).await?;
+        }
         self.refresh();
         Ok(())
     }
 
     pub async fn read_buffer(&mut self, size: usize) -> Result<()> {
         self.buffer.resize(size)?;
-        self.conn.read_exact(self.buffer.as_mut()).await?;
+        self.fill_buffer(size).await
+    }
+
+    /// Read a part of a proto message of the given total size, after [bookmarking](Self::bookmark)
+    /// its start.
+    ///
+    /// Records in stream responses can exceed the [`MAX_BUFFER_SIZE`], so instead of the fixed
+    /// limit, the size is checked to not go beyond the end of the current proto message.
+    pub async fn read_proto_buffer(&mut self, size: usize, proto_size: usize) -> Result<()> {
+        let remaining = proto_size.saturating_sub(self.bytes_read);
+        self.buffer.resize_within(size, remaining)?;
+        self.fill_buffer(size).await
+    }
+
+    /// Read and discard the given amount of bytes, without buffering all of them at once.
+    pub async fn skip(&mut self, mut size: usize) -> Result<()> {
+        while size > 0 {
+            let chunk = size.min(MAX_BUFFER_SIZE);
+            self.read_buffer(chunk).await?;
+            size -= chunk;
+        }
+        Ok(())
+    }
+
+    async fn fill_buffer(&mut self, size: usize) -> Result<()> {
+        match &mut self.conn {
+            Stream::Tcp(stream) => stream.read_exact(self.buffer.as_mut()).await?,
+            Stream::Memory(response) => response.read_exact(self.buffer.as_mut()).await?,
+        };
         self.bytes_read += size;
         self.refresh();
+
+        if let Some(command) = self.traced_command() {
+            debug!(
+                command,
+                size,
+                "received data{}",
+                trace::hex_dump(self.buffer.as_ref())
+            );
+        }
+
         Ok(())
     }
 
     pub async fn read_proto_header(&mut self) -> Result<ProtoHeader> {
         self.read_buffer(ProtoHeader::SIZE).await?;
-        Ok(self.buffer.read_proto_header())
+        let header = self.buffer.read_proto_header();
+
+        if let Some(command) = self.traced_command() {
+            debug!(command, ?header, "received proto header");
+        }
+
+        Ok(header)
     }
 
     pub async fn read_stream_message_header(
@@ -82,12 +255,29 @@ impl Connection {
         proto: ProtoHeader,
     ) -> Result<StreamMessageHeader> {
         self.read_buffer(StreamMessageHeader::SIZE).await?;
-        Ok(self.buffer.read_stream_message_header(proto))
+        self.buffer
+            .read_stream_message_header(proto)
+            .map_err(Into::into)
     }
 
     pub async fn read_header(&mut self) -> Result<MessageHeader> {
         self.read_buffer(TOTAL_HEADER_SIZE).await?;
-        Ok(self.buffer.read_header())
+        let header = self.buffer.read_header()?;
+
+        if let Some(command) = self.traced_command() {
+            debug!(
+                command,
+                size = header.size,
+                result_code = ?header.result_code,
+                generation = header.generation,
+                expiration = header.expiration,
+                fields = header.field_count,
+                operations = header.operation_count,
+                "received message header"
+            );
+        }
+
+        Ok(header)
     }
 
     fn refresh(&mut self) {
@@ -97,18 +287,25 @@ impl Connection {
         };
     }
 
-    async fn authenticate(&mut self, user_password: &Option<(String, String)>) -> Result<()> {
-        if let Some((user, password)) = user_password {
-            return match AdminCommand::authenticate(self, user, password).await {
-                Ok(()) => Ok(()),
-                Err(err) => {
-                    self.close().await;
-                    Err(NetError::Authenticate(Box::new(err)))
-                }
-            };
+    /// Authenticate with the session, returning `false` if its token was rejected.
+    async fn authenticate(&mut self, session: &Session) -> Result<bool> {
+        match session.authenticate(self).await {
+            Ok(accepted) => Ok(accepted),
+            Err(err) => {
+                self.close().await;
+                Err(NetError::Authenticate(Box::new(err)))
+            }
         }
+    }
 
-        Ok(())
+    async fn login(&mut self, session: &Session) -> Result<()> {
+        match session.login(self).await {
+            Ok(()) => Ok(()),
+            Err(err) => {
+                self.close().await;
+                Err(NetError::Authenticate(Box::new(err)))
+            }
+        }
     }
 
     pub fn bookmark(&mut self) {
@@ -123,3 +320,237 @@ impl Connection {
         &mut self.buffer
     }
 }
+
+#[derive(Debug)]
+enum Stream {
+    Tcp(TcpStream),
+    /// Already received response of a pipelined command.
+    Memory(Cursor<Vec<u8>>),
+}
+
+/// Resolve the address and connect to any of the resulting IPs.
+async fn connect(addr: &str, policy: &ClientPolicy) -> io::Result<TcpStream> {
+    let addrs = tokio::net::lookup_host(addr).await?.collect::<Vec<_>>();
+    connect_any(interleave_families(addrs), policy.connect_attempt_delay).await
+}
+
+/// Connect to the first address that accepts the connection, following the _Happy Eyeballs_
+/// algorithm (RFC 8305).
+///
+/// The attempts are started in the given order. Whenever an attempt fails or doesn't complete
+/// within the `delay`, the next one is started while the previous ones keep running, so
+/// unreachable addresses don't hold up the connection setup. The first established connection
+/// wins and all other attempts are cancelled.
+async fn connect_any(addrs: Vec<SocketAddr>, delay: Duration) -> io::Result<TcpStream> {
+    /// Cancels the pending attempts, once the winner was found or the caller gave up.
+    struct Attempts(Vec<JoinHandle<()>>);
+
+    impl Drop for Attempts {
+        fn drop(&mut self) {
+            for attempt in &self.0 {
+                attempt.abort();
+            }
+        }
+    }
+
+    if addrs.len() == 1 {
+        return TcpStream::connect(addrs[0]).await;
+    }
+
+    let (tx, mut rx) = mpsc::channel(addrs.len().max(1));
+    let mut attempts = Attempts(Vec::with_capacity(addrs.len()));
+    let mut addrs = addrs.into_iter().peekable();
+    let mut running = 0;
+    let mut last_err = None;
+
+    loop {
+        if let Some(addr) = addrs.next() {
+            let tx = tx.clone();
+            attempts.0.push(tokio::spawn(async move {
+                tx.send(TcpStream::connect(addr).await).await.ok();
+            }));
+            running += 1;
+        }
+
+        if running == 0 {
+            break;
+        }
+
+        let result = if addrs.peek().is_some() {
+            match tokio::time::timeout(delay, rx.recv()).await {
+                Ok(result) => result,
+                // Give the next address a chance, without aborting the slow attempts.
+                Err(_) => continue,
+            }
+        } else {
+            rx.recv().await
+        };
+
+        match result {
+            Some(Ok(stream)) => return Ok(stream),
+            Some(Err(err)) => {
+                running -= 1;
+                last_err = Some(err);
+            }
+            None => break,
+        }
+    }
+
+    Err(last_err.unwrap_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "could not resolve to any address",
+        )
+    }))
+}
+
+/// Reorder the addresses to alternate between IPv6 and IPv4, starting with the family of the
+/// first address. Otherwise, the order within each family is kept.
+fn interleave_families(addrs: Vec<SocketAddr>) -> Vec<SocketAddr> {
+    let first_v6 = addrs.first().map_or(false, SocketAddr::is_ipv6);
+    let (mut first, mut second): (Vec<_>, Vec<_>) = addrs
+        .into_iter()
+        .partition(|addr| addr.is_ipv6() == first_v6);
+
+    let mut result = Vec::with_capacity(first.len() + second.len());
+    let mut first = first.drain(..);
+    let mut second = second.drain(..);
+    loop {
+        match (first.next(), second.next()) {
+            (None, None) => break,
+            (a, b) => result.extend(a.into_iter().chain(b)),
+        }
+    }
+
+    result
+}
+
+/// Apply the TCP options of the client policy to a freshly opened socket.
+fn configure_socket(stream: &TcpStream, policy: &ClientPolicy) -> Result<()> {
+    stream.set_nodelay(policy.tcp_nodelay)?;
+
+    if let Some(time) = policy.tcp_keepalive {
+        #[allow(unused_mut)]
+        let mut keepalive = TcpKeepalive::new().with_time(time);
+        // Not all platforms allow to configure the interval, like socket2 itself.
+        #[cfg(any(
+            target_os = "android",
+            target_os = "dragonfly",
+            target_os = "freebsd",
+            target_os = "fuchsia",
+            target_os = "illumos",
+            target_os = "ios",
+            target_os = "linux",
+            target_os = "macos",
+            target_os = "netbsd",
+            target_os = "tvos",
+            target_os = "watchos",
+            target_os = "windows",
+        ))]
+        if let Some(interval) = policy.tcp_keepalive_interval {
+            keepalive = keepalive.with_interval(interval);
+        }
+
+        SockRef::from(stream).set_tcp_keepalive(&keepalive)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io, net::SocketAddr};
+
+    use tokio::{net::TcpListener, time::Duration};
+
+    use super::{connect_any, interleave_families, Connection, NetError, MAX_BUFFER_SIZE};
+    use crate::policies::ClientPolicy;
+
+    #[tokio::test]
+    async fn read_large_proto() {
+        let size = 2 * MAX_BUFFER_SIZE;
+        let mut conn = Connection::detached(0);
+        conn.set_response(vec![1; size + 4]);
+        conn.bookmark();
+
+        assert!(conn.read_buffer(size).await.is_err());
+        conn.read_proto_buffer(size, size + 2).await.unwrap();
+        assert_eq!(size, conn.bytes_read());
+        assert!(conn.read_proto_buffer(4, size + 2).await.is_err());
+        conn.read_proto_buffer(2, size + 2).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn skip_in_chunks() {
+        let size = 3 * MAX_BUFFER_SIZE + 1;
+        let mut conn = Connection::detached(0);
+        conn.set_response(vec![0; size + 1]);
+        conn.bookmark();
+
+        conn.skip(size).await.unwrap();
+        assert_eq!(size, conn.bytes_read());
+        assert!(conn.skip(2).await.is_err());
+    }
+
+    #[test]
+    fn interleave() {
+        let addrs = [
+            "[::1]:1",
+            "[::1]:2",
+            "[::1]:3",
+            "127.0.0.1:4",
+            "127.0.0.1:5",
+        ]
+        .iter()
+        .map(|addr| addr.parse::<SocketAddr>().unwrap())
+        .collect::<Vec<_>>();
+        let ports = interleave_families(addrs)
+            .iter()
+            .map(SocketAddr::port)
+            .collect::<Vec<_>>();
+        assert_eq!(ports, [1, 4, 2, 5, 3]);
+    }
+
+    #[tokio::test]
+    async fn connect_skips_failing_addresses() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let closed = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let closed_addr = closed.local_addr().unwrap();
+        drop(closed);
+
+        let stream = connect_any(
+            vec![closed_addr, listener.local_addr().unwrap()],
+            Duration::from_secs(60),
+        )
+        .await
+        .unwrap();
+        assert_eq!(listener.local_addr().unwrap(), stream.peer_addr().unwrap());
+
+        assert!(
+            connect_any(vec![closed_addr, closed_addr], Duration::from_secs(60))
+                .await
+                .is_err()
+        );
+        assert!(connect_any(Vec::new(), Duration::from_secs(60))
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn failed_opening_context() {
+        let closed = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = closed.local_addr().unwrap().to_string();
+        drop(closed);
+
+        let err = Connection::new(&addr, &ClientPolicy::default(), None)
+            .await
+            .unwrap_err();
+        match err {
+            NetError::FailedOpening { address, source } => {
+                assert_eq!(addr, address);
+                assert_eq!(io::ErrorKind::ConnectionRefused, source.kind());
+            }
+            err => panic!("unexpected error: {err}"),
+        }
+    }
+}
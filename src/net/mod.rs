@@ -1,28 +1,57 @@
 pub use self::{
     connection::Connection,
     host::{Host, ToHosts},
-    pool::{Pool, PooledConnection},
+    pipeline::Pipeline,
+    pool::{Pool, PoolStats, PooledConnection},
+    session::Session,
 };
 
 mod connection;
 mod host;
 mod parser;
+mod pipeline;
 mod pool;
+mod session;
+mod trace;
+
+use std::time::Duration;
 
 type Result<T, E = NetError> = std::result::Result<T, E>;
 
 #[derive(Debug, thiserror::Error)]
 pub enum NetError {
-    #[error("no more connections available in the pool")]
-    NoMoreConnections,
-    #[error("could not open network connection")]
-    FailedOpening,
+    #[error(
+        "no more connections available in the pool for `{address}` after waiting {waited:?} \
+         ({connections}/{max_size} connections open)"
+    )]
+    NoMoreConnections {
+        /// Address of the node that the pool connects to.
+        address: String,
+        /// Amount of open connections at the time of giving up.
+        connections: u32,
+        /// Maximum amount of connections that the pool can hold.
+        max_size: u32,
+        /// Time spent waiting for a connection to become available.
+        waited: Duration,
+    },
+    #[error("could not open network connection to `{address}`: {source}")]
+    FailedOpening {
+        /// Address that the connection was opened to.
+        address: String,
+        /// Cause of the failure, including the OS error code if available.
+        #[source]
+        source: std::io::Error,
+    },
     #[error("I/O related error")]
     Io(#[from] std::io::Error),
     #[error("buffer error")]
     Buffer(#[from] crate::commands::buffer::BufferError),
     #[error("authentication error")]
     Authenticate(#[source] Box<crate::commands::CommandError>),
+    #[error("pipelined connection was closed")]
+    PipelineClosed,
+    #[error("node is being removed from the cluster and doesn't hand out connections anymore")]
+    Draining,
 }
 
 #[derive(Debug, thiserror::Error)]
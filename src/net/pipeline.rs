@@ -0,0 +1,136 @@
+use std::io;
+
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::tcp::{OwnedReadHalf, OwnedWriteHalf},
+    sync::{mpsc, oneshot},
+};
+
+use super::{Connection, NetError, Result, Session};
+use crate::{
+    commands::buffer::{Buffer, ProtoHeader},
+    policies::ClientPolicy,
+};
+
+type Responder = oneshot::Sender<Result<Vec<u8>>>;
+
+/// Single socket that is shared between many in-flight commands.
+///
+/// Requests are written to the socket one after another, without waiting for the response of the
+/// previous one. The server answers pipelined requests strictly in the order it received them, so
+/// responses are handed back to the waiting commands in that same order.
+///
+/// Only commands that receive exactly one response message can be pipelined. Streaming commands
+/// like scans and batch reads must use a dedicated connection.
+#[derive(Debug)]
+pub struct Pipeline {
+    requests: mpsc::Sender<(Vec<u8>, Responder)>,
+    buffer_reclaim_threshold: usize,
+}
+
+impl Pipeline {
+    /// Maximum amount of requests that can be queued for writing before senders have to wait.
+    const QUEUE_SIZE: usize = 1024;
+
+    pub async fn new(addr: &str, policy: &ClientPolicy, session: Option<&Session>) -> Result<Self> {
+        let (reader, writer) = Connection::new(addr, policy, session)
+            .await?
+            .into_tcp_stream()
+            .ok_or_else(|| NetError::FailedOpening {
+                address: addr.to_owned(),
+                source: io::ErrorKind::Unsupported.into(),
+            })?
+            .into_split();
+        let (requests_tx, requests_rx) = mpsc::channel(Self::QUEUE_SIZE);
+        let (pending_tx, pending_rx) = mpsc::unbounded_channel();
+
+        tokio::spawn(write_requests(writer, requests_rx, pending_tx));
+        tokio::spawn(read_responses(
+            reader,
+            pending_rx,
+            policy.buffer_reclaim_threshold,
+        ));
+
+        Ok(Self {
+            requests: requests_tx,
+            buffer_reclaim_threshold: policy.buffer_reclaim_threshold,
+        })
+    }
+
+    /// Whether the underlying socket is still usable. A pipeline is never revived after it broke
+    /// and has to be replaced by a new one.
+    pub fn active(&self) -> bool {
+        !self.requests.is_closed()
+    }
+
+    /// Create a new connection to prepare a command in, before [sending](Self::send) it.
+    pub fn connection(&self) -> Connection {
+        Connection::detached(self.buffer_reclaim_threshold)
+    }
+
+    /// Send the request that was prepared in the given connection's buffer, and wait for its
+    /// response. Afterwards, the response can be read from the connection as usual.
+    pub async fn send(&self, conn: &mut Connection) -> Result<()> {
+        let (tx, rx) = oneshot::channel();
+        self.requests
+            .send((conn.buffer().as_ref().to_vec(), tx))
+            .await
+            .map_err(|_| NetError::PipelineClosed)?;
+
+        let response = rx.await.map_err(|_| NetError::PipelineClosed)??;
+        conn.set_response(response);
+        Ok(())
+    }
+}
+
+async fn write_requests(
+    mut writer: OwnedWriteHalf,
+    mut requests: mpsc::Receiver<(Vec<u8>, Responder)>,
+    pending: mpsc::UnboundedSender<Responder>,
+) {
+    while let Some((request, responder)) = requests.recv().await {
+        // The responder must be queued before writing, as the response might arrive before this
+        // task is scheduled again.
+        if pending.send(responder).is_err() || writer.write_all(&request).await.is_err() {
+            break;
+        }
+    }
+
+    // Signal the server to close the connection, which in turn stops the reading side.
+    writer.shutdown().await.ok();
+}
+
+async fn read_responses(
+    mut reader: OwnedReadHalf,
+    mut pending: mpsc::UnboundedReceiver<Responder>,
+    buffer_reclaim_threshold: usize,
+) {
+    let mut buffer = Buffer::new(buffer_reclaim_threshold);
+
+    while let Some(responder) = pending.recv().await {
+        let response = read_response(&mut reader, &mut buffer).await;
+        let failed = response.is_err();
+
+        // The command might have been cancelled in the meantime, but the response still has to be
+        // consumed to keep the stream in sync.
+        responder.send(response).ok();
+
+        if failed {
+            break;
+        }
+    }
+}
+
+async fn read_response(reader: &mut OwnedReadHalf, buffer: &mut Buffer) -> Result<Vec<u8>> {
+    buffer.resize(ProtoHeader::SIZE)?;
+    reader.read_exact(buffer.as_mut()).await?;
+
+    let mut response = buffer.as_ref().to_vec();
+    let proto = buffer.read_proto_header();
+
+    buffer.resize(proto.size)?;
+    reader.read_exact(buffer.as_mut()).await?;
+    response.extend_from_slice(buffer.as_ref());
+
+    Ok(response)
+}
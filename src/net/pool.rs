@@ -1,18 +1,25 @@
 use std::{
+    io,
     ops::{Deref, DerefMut},
-    sync::Arc,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
     time::Duration,
 };
 
 use async_trait::async_trait;
 use bb8::{ManageConnection, RunError};
+use tokio::time::Instant;
+use tracing::{debug, warn};
 
-use super::{Connection, Host, NetError, Result};
+use super::{Connection, Host, NetError, Result, Session};
 use crate::policies::ClientPolicy;
 
 struct NodeConnectionManager {
     host: Host,
     policy: Arc<ClientPolicy>,
+    session: Option<Arc<Session>>,
 }
 
 #[async_trait]
@@ -21,14 +28,14 @@ impl ManageConnection for NodeConnectionManager {
     type Error = NetError;
 
     async fn connect(&self) -> Result<Self::Connection, Self::Error> {
-        Connection::new(&self.host.address(), &self.policy).await
+        Connection::new(&self.host.address(), &self.policy, self.session.as_deref()).await
     }
 
     async fn is_valid(&self, conn: &mut Self::Connection) -> Result<(), Self::Error> {
         if conn.active() {
             Ok(())
         } else {
-            Err(NetError::NoMoreConnections)
+            Err(io::Error::from(io::ErrorKind::NotConnected).into())
         }
     }
 
@@ -37,44 +44,289 @@ impl ManageConnection for NodeConnectionManager {
     }
 }
 
+/// Snapshot of the connection pool of a single node.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct PoolStats {
+    /// Amount of open connections, both idle and in use.
+    pub connections: u32,
+    /// Amount of open connections that are currently not in use.
+    pub idle_connections: u32,
+    /// Amount of connections that were given up while a command was still waiting for its
+    /// response, for example because the command future was dropped or panicked. These
+    /// connections are closed instead of being returned to the pool. Scans that are stopped early,
+    /// like with [`RecordSet::close`](crate::RecordSet::close), don't count as leaks.
+    pub leaked: usize,
+}
+
 #[derive(Debug)]
-pub struct Pool(bb8::Pool<NodeConnectionManager>);
+pub struct Pool {
+    inner: bb8::Pool<NodeConnectionManager>,
+    address: String,
+    max_size: u32,
+    leaked: AtomicUsize,
+    draining: AtomicBool,
+}
 
 impl Pool {
-    pub async fn new(host: Host, policy: Arc<ClientPolicy>) -> Result<Self> {
+    pub async fn new(
+        host: Host,
+        policy: Arc<ClientPolicy>,
+        session: Option<Arc<Session>>,
+        max_size: u32,
+    ) -> Result<Self> {
+        let address = host.address();
         bb8::Builder::new()
-            .max_size(policy.max_conns_per_node)
+            .max_size(max_size)
             .idle_timeout(policy.idle_timeout)
             .connection_timeout(policy.timeout.unwrap_or(Duration::from_secs(5)))
-            .build(NodeConnectionManager { host, policy })
+            .build(NodeConnectionManager {
+                host,
+                policy,
+                session,
+            })
             .await
-            .map(Self)
+            .map(|inner| Self {
+                inner,
+                address,
+                max_size,
+                leaked: AtomicUsize::new(0),
+                draining: AtomicBool::new(false),
+            })
     }
 
     pub async fn get(&self) -> Result<PooledConnection<'_>> {
-        self.0
+        if self.is_draining() {
+            return Err(NetError::Draining);
+        }
+
+        let start = Instant::now();
+        self.inner
             .get()
             .await
-            .map(PooledConnection)
+            .map(|conn| PooledConnection {
+                conn,
+                leaked: &self.leaked,
+                draining: &self.draining,
+            })
             .map_err(|e| match e {
                 RunError::User(e) => e,
-                RunError::TimedOut => NetError::NoMoreConnections,
+                RunError::TimedOut => NetError::NoMoreConnections {
+                    address: self.address.clone(),
+                    connections: self.inner.state().connections,
+                    max_size: self.max_size,
+                    waited: start.elapsed(),
+                },
             })
     }
+
+    pub fn stats(&self) -> PoolStats {
+        let state = self.inner.state();
+        PoolStats {
+            connections: state.connections,
+            idle_connections: state.idle_connections,
+            leaked: self.leaked.load(Ordering::Relaxed),
+        }
+    }
+
+    pub fn is_draining(&self) -> bool {
+        self.draining.load(Ordering::Relaxed)
+    }
+
+    /// Stop handing out connections and wait up to the given timeout for all connections that are
+    /// currently in use to be returned. Returned connections are closed instead of being kept
+    /// around, and all idle connections are closed once draining finished.
+    ///
+    /// Returns `false` if connections were still in use when the timeout elapsed.
+    pub async fn drain(&self, timeout: Duration) -> bool {
+        self.draining.store(true, Ordering::Relaxed);
+
+        let deadline = Instant::now() + timeout;
+        let drained = loop {
+            let state = self.inner.state();
+            if state.connections <= state.idle_connections {
+                break true;
+            }
+            if Instant::now() >= deadline {
+                break false;
+            }
+            tokio::time::sleep(DRAIN_POLL_INTERVAL).await;
+        };
+
+        // Check out the remaining idle connections, which makes the pool discard them as they're
+        // marked as broken when being returned.
+        while self.inner.state().idle_connections > 0 {
+            match tokio::time::timeout(DRAIN_POLL_INTERVAL, self.inner.get()).await {
+                Ok(Ok(mut conn)) => conn.close().await,
+                _ => break,
+            }
+        }
+
+        drained
+    }
 }
 
-pub struct PooledConnection<'a>(bb8::PooledConnection<'a, NodeConnectionManager>);
+const DRAIN_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+pub struct PooledConnection<'a> {
+    conn: bb8::PooledConnection<'a, NodeConnectionManager>,
+    leaked: &'a AtomicUsize,
+    draining: &'a AtomicBool,
+}
 
 impl<'a> Deref for PooledConnection<'a> {
     type Target = Connection;
 
     fn deref(&self) -> &Connection {
-        &self.0
+        &self.conn
     }
 }
 
 impl<'a> DerefMut for PooledConnection<'a> {
     fn deref_mut(&mut self) -> &mut Connection {
-        &mut self.0
+        &mut self.conn
+    }
+}
+
+impl Drop for PooledConnection<'_> {
+    fn drop(&mut self) {
+        // The connection itself is discarded by the pool, as it isn't considered active anymore.
+        if let Some(command) = self.conn.in_flight() {
+            if self.conn.streaming() {
+                debug!(command, "stream stopped early, discarding its connection");
+            } else {
+                warn!(
+                    command,
+                    "connection dropped while waiting for a response, discarding it"
+                );
+                self.leaked.fetch_add(1, Ordering::Relaxed);
+            }
+        } else if self.draining.load(Ordering::Relaxed) {
+            // Don't return connections to the pool of a node that is being removed.
+            self.conn.invalidate();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, time::Duration};
+
+    use tokio::net::TcpListener;
+
+    use super::{Pool, PoolStats};
+    use crate::{
+        net::{Host, NetError},
+        policies::ClientPolicy,
+    };
+
+    async fn pool(listener: &TcpListener) -> Pool {
+        let port = listener.local_addr().unwrap().port();
+        Pool::new(
+            Host::new("127.0.0.1", port),
+            Arc::new(ClientPolicy::default()),
+            None,
+            ClientPolicy::DEFAULT_MAX_CONNS_PER_NODE,
+        )
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn discard_abandoned_connections() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let pool = pool(&listener).await;
+
+        let mut conn = pool.get().await.unwrap();
+        conn.start_command("TestCommand");
+        conn.finish_command();
+        drop(conn);
+        assert_eq!(
+            PoolStats {
+                connections: 1,
+                idle_connections: 1,
+                leaked: 0,
+            },
+            pool.stats()
+        );
+
+        let mut conn = pool.get().await.unwrap();
+        conn.start_command("TestCommand");
+        drop(conn);
+        assert_eq!(
+            PoolStats {
+                connections: 0,
+                idle_connections: 0,
+                leaked: 1,
+            },
+            pool.stats()
+        );
+
+        // Streams that are stopped early are discarded as well, but aren't leaks.
+        let mut conn = pool.get().await.unwrap();
+        conn.start_stream("TestStream");
+        drop(conn);
+        assert_eq!(
+            PoolStats {
+                connections: 0,
+                idle_connections: 0,
+                leaked: 1,
+            },
+            pool.stats()
+        );
+    }
+
+    #[tokio::test]
+    async fn drain_waits_for_connections() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let pool = pool(&listener).await;
+
+        let idle = pool.get().await.unwrap();
+        let busy = pool.get().await.unwrap();
+        drop(idle);
+
+        let (drained, ()) = tokio::join!(pool.drain(Duration::from_secs(5)), async {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            assert!(matches!(pool.get().await, Err(NetError::Draining)));
+            drop(busy);
+        });
+
+        assert!(drained);
+        assert_eq!(PoolStats::default(), pool.stats());
+    }
+
+    #[tokio::test]
+    async fn drain_timeout() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let pool = pool(&listener).await;
+
+        let busy = pool.get().await.unwrap();
+        assert!(!pool.drain(Duration::from_millis(20)).await);
+        assert_eq!(1, pool.stats().connections);
+
+        drop(busy);
+        assert_eq!(PoolStats::default(), pool.stats());
+    }
+
+    #[tokio::test]
+    async fn separate_max_size() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let pool = Pool::new(
+            Host::new("127.0.0.1", port),
+            Arc::new(ClientPolicy {
+                timeout: Some(Duration::from_millis(20)),
+                ..ClientPolicy::default()
+            }),
+            None,
+            1,
+        )
+        .await
+        .unwrap();
+
+        let _busy = pool.get().await.unwrap();
+        assert!(matches!(
+            pool.get().await,
+            Err(NetError::NoMoreConnections { max_size: 1, .. })
+        ));
     }
 }
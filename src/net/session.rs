@@ -0,0 +1,129 @@
+use std::sync::{Mutex, PoisonError};
+
+use tokio::time::{Duration, Instant};
+use tracing::debug;
+
+use super::Connection;
+use crate::{
+    commands::{AdminCommand, CommandError},
+    policies::ClientPolicy,
+};
+
+/// Login state of a client, shared by all connections to the cluster.
+///
+/// The first connection logs in with the user's credentials, for which the server has to verify
+/// the password's bcrypt hash. The server then hands out a session token, that all further
+/// connections use to authenticate instead, which is much cheaper for both sides.
+#[derive(Debug)]
+pub struct Session {
+    user: String,
+    credential: String,
+    token: Mutex<Option<Token>>,
+}
+
+#[derive(Clone, Debug)]
+struct Token {
+    value: Vec<u8>,
+    expires_at: Option<Instant>,
+}
+
+impl Session {
+    /// Safety margin to the expiration time reported by the server, so the token isn't used
+    /// anymore shortly before the server would reject it.
+    const EXPIRATION_MARGIN: Duration = Duration::from_secs(60);
+
+    /// Create a new session for the configured credentials, if authentication is enabled.
+    pub fn new(policy: &ClientPolicy) -> Option<Self> {
+        policy
+            .user_password
+            .as_ref()
+            .map(|(user, credential)| Self {
+                user: user.clone(),
+                credential: credential.clone(),
+                token: Mutex::default(),
+            })
+    }
+
+    /// Authenticate a new connection, preferably with the cached session token.
+    ///
+    /// Returns `false` if the server rejected the token. It's dropped then, and as the server
+    /// closes the connection after a failed authentication, the caller has to [log
+    /// in](Self::login) on a new connection.
+    pub(super) async fn authenticate(&self, conn: &mut Connection) -> Result<bool, CommandError> {
+        match self.token() {
+            Some(token) => match AdminCommand::authenticate(conn, &self.user, &token).await {
+                Ok(()) => Ok(true),
+                Err(err) => {
+                    debug!(user = %self.user, %err, "session token rejected");
+                    self.set_token(None);
+                    Ok(false)
+                }
+            },
+            None => self.login(conn).await.map(|()| true),
+        }
+    }
+
+    /// Log in with the user's credentials, and cache the session token that the server returns.
+    pub(super) async fn login(&self, conn: &mut Connection) -> Result<(), CommandError> {
+        let token = AdminCommand::login(conn, &self.user, &self.credential).await?;
+        self.set_token(token.and_then(|(value, ttl)| {
+            let expires_at = match ttl {
+                Some(ttl) => Some(Instant::now() + ttl.checked_sub(Self::EXPIRATION_MARGIN)?),
+                None => None,
+            };
+            Some(Token { value, expires_at })
+        }));
+
+        Ok(())
+    }
+
+    /// The cached session token, if there is one that didn't expire yet.
+    fn token(&self) -> Option<Vec<u8>> {
+        self.token
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .as_ref()
+            .filter(|token| {
+                token
+                    .expires_at
+                    .map_or(true, |expires_at| expires_at > Instant::now())
+            })
+            .map(|token| token.value.clone())
+    }
+
+    fn set_token(&self, token: Option<Token>) {
+        *self.token.lock().unwrap_or_else(PoisonError::into_inner) = token;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::time::{Duration, Instant};
+
+    use super::{Session, Token};
+    use crate::policies::ClientPolicy;
+
+    #[test]
+    fn cached_token() {
+        assert!(Session::new(&ClientPolicy::default()).is_none());
+
+        let policy = ClientPolicy {
+            user_password: Some(("admin".to_owned(), "hash".to_owned())),
+            ..ClientPolicy::default()
+        };
+        let session = Session::new(&policy).unwrap();
+        assert_eq!(None, session.token());
+
+        session.set_token(Some(Token {
+            value: vec![1, 2, 3],
+            expires_at: None,
+        }));
+        assert_eq!(Some(vec![1, 2, 3]), session.token());
+
+        session.set_token(Some(Token {
+            value: vec![1, 2, 3],
+            expires_at: Some(Instant::now() - Duration::from_secs(1)),
+        }));
+        assert_eq!(None, session.token());
+    }
+}
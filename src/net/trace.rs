@@ -0,0 +1,283 @@
+//! Human readable dumps of the wire protocol, to debug mismatches between the client and server.
+//!
+//! Requests are fully decoded into their proto header, message header, fields and operations.
+//! Responses are read in many small pieces, so they're only dumped piece by piece, with the
+//! headers decoded by the connection itself.
+
+use std::fmt::Write;
+
+/// Format the data as hex dump, with 16 bytes per line and the printable ASCII characters next to
+/// them.
+pub(super) fn hex_dump(data: &[u8]) -> String {
+    let mut out = String::with_capacity(data.len() * 4);
+
+    for (i, chunk) in data.chunks(16).enumerate() {
+        write!(out, "\n{:08x} ", i * 16).ok();
+        for byte in chunk {
+            write!(out, " {byte:02x}").ok();
+        }
+        for _ in chunk.len()..16 {
+            out.push_str("   ");
+        }
+
+        out.push_str("  ");
+        out.extend(chunk.iter().map(|&b| {
+            if b.is_ascii_graphic() || b == b' ' {
+                char::from(b)
+            } else {
+                '.'
+            }
+        }));
+    }
+
+    out
+}
+
+/// Decode a request into a line for each of its parts. Truncated or unknown data is reported as
+/// such, instead of failing the whole annotation.
+pub(super) fn annotate(data: &[u8]) -> String {
+    let mut out = String::new();
+    let mut reader = Reader { data, pos: 0 };
+
+    while let Some(header) = reader.u64() {
+        let version = header >> 56;
+        let ty = (header >> 48) & 0xff;
+        let size = usize::try_from(header & 0xffff_ffff_ffff).unwrap_or(usize::MAX);
+        write!(
+            out,
+            "\nproto: version {version}, type {}, size {size}",
+            proto_type_name(ty)
+        )
+        .ok();
+
+        let body = reader.take(size);
+        if body.len() < size {
+            write!(out, "\n  truncated, only {} bytes present", body.len()).ok();
+        }
+
+        match ty {
+            1 => {
+                write!(out, "\n  info: {:?}", String::from_utf8_lossy(body)).ok();
+            }
+            3 => annotate_message(body, &mut out),
+            _ => {
+                write!(out, "\n  payload: {} bytes", body.len()).ok();
+            }
+        }
+    }
+
+    if reader.remaining() > 0 {
+        write!(out, "\ntrailing data: {} bytes", reader.remaining()).ok();
+    }
+
+    out
+}
+
+fn annotate_message(data: &[u8], out: &mut String) {
+    const HEADER_SIZE: usize = 22;
+
+    let mut reader = Reader { data, pos: 0 };
+    let header = reader.take(HEADER_SIZE);
+    if header.len() < HEADER_SIZE {
+        write!(out, "\n  truncated message header: {} bytes", header.len()).ok();
+        return;
+    }
+
+    let u32_at = |pos: usize| {
+        u32::from_be_bytes([
+            header[pos],
+            header[pos + 1],
+            header[pos + 2],
+            header[pos + 3],
+        ])
+    };
+    let field_count = u16::from_be_bytes([header[18], header[19]]);
+    let op_count = u16::from_be_bytes([header[20], header[21]]);
+    write!(
+        out,
+        "\n  message: info1 {:#04x}, info2 {:#04x}, info3 {:#04x}, result code {}, generation {}, \
+         expiration {}, timeout {}ms, {field_count} fields, {op_count} operations",
+        header[1],
+        header[2],
+        header[3],
+        header[5],
+        u32_at(6),
+        u32_at(10),
+        u32_at(14),
+    )
+    .ok();
+
+    for _ in 0..field_count {
+        let size = match reader.u32() {
+            Some(size) => size as usize,
+            None => break,
+        };
+        let ty = reader.take(1).first().copied().unwrap_or_default();
+        let data = reader.take(size.saturating_sub(1));
+        write!(
+            out,
+            "\n  field {}: {} bytes",
+            field_type_name(ty),
+            data.len()
+        )
+        .ok();
+        if ty <= 1 {
+            write!(out, " {:?}", String::from_utf8_lossy(data)).ok();
+        }
+    }
+
+    for _ in 0..op_count {
+        let size = match reader.u32() {
+            Some(size) => size as usize,
+            None => break,
+        };
+        let meta = reader.take(4);
+        if meta.len() < 4 {
+            break;
+        }
+        let name = reader.take(meta[3].into());
+        let value = reader.take(size.saturating_sub(4 + name.len()));
+        write!(
+            out,
+            "\n  operation {}: bin {:?}, particle type {}, {} bytes",
+            operation_type_name(meta[0]),
+            String::from_utf8_lossy(name),
+            meta[1],
+            value.len()
+        )
+        .ok();
+    }
+
+    if reader.remaining() > 0 {
+        write!(out, "\n  trailing data: {} bytes", reader.remaining()).ok();
+    }
+}
+
+const fn proto_type_name(ty: u64) -> &'static str {
+    match ty {
+        1 => "info",
+        2 => "security",
+        3 => "message",
+        4 => "compressed message",
+        5 => "xdr",
+        _ => "unknown",
+    }
+}
+
+const fn field_type_name(ty: u8) -> &'static str {
+    match ty {
+        0 => "namespace",
+        1 => "set name",
+        2 => "user key",
+        4 => "digest",
+        7 => "transaction id",
+        8 => "scan options",
+        9 => "scan timeout",
+        11 => "partition ids",
+        12 => "digest array",
+        13 => "max records",
+        41 => "batch index",
+        42 => "batch index with set",
+        43 => "filter expression",
+        _ => "unknown",
+    }
+}
+
+const fn operation_type_name(ty: u8) -> &'static str {
+    match ty {
+        1 => "read",
+        2 => "write",
+        3 => "cdt read",
+        4 => "cdt write",
+        5 => "incr",
+        9 => "append",
+        10 => "prepend",
+        11 => "touch",
+        12 => "bit read",
+        13 => "bit write",
+        14 => "delete",
+        15 => "hll read",
+        16 => "hll write",
+        _ => "unknown",
+    }
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn remaining(&self) -> usize {
+        self.data.len() - self.pos
+    }
+
+    /// Take up to the given amount of bytes, less if the data ends before.
+    fn take(&mut self, size: usize) -> &'a [u8] {
+        let end = self.pos.saturating_add(size).min(self.data.len());
+        let data = &self.data[self.pos..end];
+        self.pos = end;
+        data
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        self.take(4).try_into().ok().map(u32::from_be_bytes)
+    }
+
+    fn u64(&mut self) -> Option<u64> {
+        let pos = self.pos;
+        let value = self.take(8).try_into().ok().map(u64::from_be_bytes);
+        if value.is_none() {
+            self.pos = pos;
+        }
+        value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{annotate, hex_dump};
+    use crate::{commands::buffer::Buffer, policies::BasePolicy, Bins, Key};
+
+    #[test]
+    fn dump_lines() {
+        let dump = hex_dump(b"0123456789abcdef\x00\x01");
+        assert_eq!(
+            "\n00000000  30 31 32 33 34 35 36 37 38 39 61 62 63 64 65 66  \
+             0123456789abcdef\n00000010  00 01                                            ..",
+            dump
+        );
+    }
+
+    #[test]
+    fn annotate_read() {
+        let mut buf = Buffer::new(0);
+        buf.set_read(
+            &BasePolicy::default(),
+            &Key::new("test", "demo", 1),
+            &Bins::from(["a"]),
+            15,
+        )
+        .unwrap();
+
+        let annotation = annotate(buf.as_ref());
+        let lines = annotation.lines().skip(1).collect::<Vec<_>>();
+        assert_eq!(6, lines.len(), "{annotation}");
+        assert!(lines[0].starts_with("proto: version 2, type message"));
+        assert!(lines[1].contains("3 fields, 1 operations"));
+        assert_eq!(r#"  field namespace: 4 bytes "test""#, lines[2]);
+        assert_eq!(r#"  field set name: 4 bytes "demo""#, lines[3]);
+        assert_eq!("  field digest: 20 bytes", lines[4]);
+        assert_eq!(
+            r#"  operation read: bin "a", particle type 0, 0 bytes"#,
+            lines[5]
+        );
+    }
+
+    #[test]
+    fn annotate_truncated() {
+        let annotation = annotate(&[2, 3, 0, 0, 0, 0, 0, 30, 22]);
+        assert!(annotation.contains("truncated, only 1 bytes present"));
+        assert!(annotation.contains("truncated message header: 1 bytes"));
+    }
+}
@@ -13,6 +13,8 @@
 //! // bin result = [[0b00000001, 0b01000010, 0b00000000], [0b01011010]]
 //! ```
 
+use std::borrow::Cow;
+
 use bitflags::bitflags;
 
 use super::cdt::{self, Encoder};
@@ -22,7 +24,7 @@ use crate::{
 };
 
 #[derive(Clone, Copy, Debug)]
-enum OpType {
+pub(crate) enum OpType {
     Resize = 0,
     Insert,
     Remove,
@@ -117,13 +119,14 @@ impl Default for Policy {
 const fn write<'a>(bin: &'a str, op: OpType, args: Vec<cdt::Argument<'a>>) -> Operation<'a> {
     Operation {
         op: OperationType::BitWrite,
-        ctx: &[],
-        bin: OperationBin::Name(bin),
+        ctx: Cow::Borrowed(&[]),
+        bin: OperationBin::Name(Cow::Borrowed(bin)),
         data: OperationData::CdtBitOp(cdt::Operation {
             op: op as u8,
             encoder: Encoder::CdtBit,
             args,
         }),
+        encoded: None,
     }
 }
 
@@ -131,13 +134,14 @@ const fn write<'a>(bin: &'a str, op: OpType, args: Vec<cdt::Argument<'a>>) -> Op
 const fn read<'a>(bin: &'a str, op: OpType, args: Vec<cdt::Argument<'a>>) -> Operation<'a> {
     Operation {
         op: OperationType::BitRead,
-        ctx: &[],
-        bin: OperationBin::Name(bin),
+        ctx: Cow::Borrowed(&[]),
+        bin: OperationBin::Name(Cow::Borrowed(bin)),
         data: OperationData::CdtBitOp(cdt::Operation {
             op: op as u8,
             encoder: Encoder::CdtBit,
             args,
         }),
+        encoded: None,
     }
 }
 
@@ -161,7 +165,7 @@ pub fn resize(
 ) -> Operation<'_> {
     let mut args = vec![
         cdt::Argument::Int(byte_size),
-        cdt::Argument::Byte(policy.flags.bits()),
+        cdt::Argument::WriteFlags(policy.flags.bits()),
     ];
     if !resize_flags.is_empty() {
         args.push(cdt::Argument::Byte(resize_flags.bits()));
@@ -193,8 +197,8 @@ pub fn insert<'a>(
         OpType::Insert,
         vec![
             cdt::Argument::Int(byte_offset),
-            cdt::Argument::Value(value),
-            cdt::Argument::Byte(policy.flags.bits()),
+            cdt::Argument::Value(Cow::Borrowed(value)),
+            cdt::Argument::WriteFlags(policy.flags.bits()),
         ],
     )
 }
@@ -218,7 +222,7 @@ pub fn remove(bin: &str, byte_offset: i64, byte_size: i64, policy: Policy) -> Op
         vec![
             cdt::Argument::Int(byte_offset),
             cdt::Argument::Int(byte_size),
-            cdt::Argument::Byte(policy.flags.bits()),
+            cdt::Argument::WriteFlags(policy.flags.bits()),
         ],
     )
 }
@@ -249,8 +253,8 @@ pub fn set<'a>(
         vec![
             cdt::Argument::Int(bit_offset),
             cdt::Argument::Int(bit_size),
-            cdt::Argument::Value(value),
-            cdt::Argument::Byte(policy.flags.bits()),
+            cdt::Argument::Value(Cow::Borrowed(value)),
+            cdt::Argument::WriteFlags(policy.flags.bits()),
         ],
     )
 }
@@ -281,8 +285,8 @@ pub fn or<'a>(
         vec![
             cdt::Argument::Int(bit_offset),
             cdt::Argument::Int(bit_size),
-            cdt::Argument::Value(value),
-            cdt::Argument::Byte(policy.flags.bits()),
+            cdt::Argument::Value(Cow::Borrowed(value)),
+            cdt::Argument::WriteFlags(policy.flags.bits()),
         ],
     )
 }
@@ -313,8 +317,8 @@ pub fn xor<'a>(
         vec![
             cdt::Argument::Int(bit_offset),
             cdt::Argument::Int(bit_size),
-            cdt::Argument::Value(value),
-            cdt::Argument::Byte(policy.flags.bits()),
+            cdt::Argument::Value(Cow::Borrowed(value)),
+            cdt::Argument::WriteFlags(policy.flags.bits()),
         ],
     )
 }
@@ -345,8 +349,8 @@ pub fn and<'a>(
         vec![
             cdt::Argument::Int(bit_offset),
             cdt::Argument::Int(bit_size),
-            cdt::Argument::Value(value),
-            cdt::Argument::Byte(policy.flags.bits()),
+            cdt::Argument::Value(Cow::Borrowed(value)),
+            cdt::Argument::WriteFlags(policy.flags.bits()),
         ],
     )
 }
@@ -370,7 +374,7 @@ pub fn not(bin: &str, bit_offset: i64, bit_size: i64, policy: Policy) -> Operati
         vec![
             cdt::Argument::Int(bit_offset),
             cdt::Argument::Int(bit_size),
-            cdt::Argument::Byte(policy.flags.bits()),
+            cdt::Argument::WriteFlags(policy.flags.bits()),
         ],
     )
 }
@@ -402,7 +406,7 @@ pub fn lshift(
             cdt::Argument::Int(bit_offset),
             cdt::Argument::Int(bit_size),
             cdt::Argument::Int(shift),
-            cdt::Argument::Byte(policy.flags.bits()),
+            cdt::Argument::WriteFlags(policy.flags.bits()),
         ],
     )
 }
@@ -434,7 +438,7 @@ pub fn rshift(
             cdt::Argument::Int(bit_offset),
             cdt::Argument::Int(bit_size),
             cdt::Argument::Int(shift),
-            cdt::Argument::Byte(policy.flags.bits()),
+            cdt::Argument::WriteFlags(policy.flags.bits()),
         ],
     )
 }
@@ -476,7 +480,7 @@ pub fn add(
             cdt::Argument::Int(bit_offset),
             cdt::Argument::Int(bit_size),
             cdt::Argument::Int(value),
-            cdt::Argument::Byte(policy.flags.bits()),
+            cdt::Argument::WriteFlags(policy.flags.bits()),
             cdt::Argument::Byte(action_flags),
         ],
     )
@@ -519,7 +523,7 @@ pub fn subtract(
             cdt::Argument::Int(bit_offset),
             cdt::Argument::Int(bit_size),
             cdt::Argument::Int(value),
-            cdt::Argument::Byte(policy.flags.bits()),
+            cdt::Argument::WriteFlags(policy.flags.bits()),
             cdt::Argument::Byte(action_flags),
         ],
     )
@@ -552,7 +556,7 @@ pub fn set_int(
             cdt::Argument::Int(bit_offset),
             cdt::Argument::Int(bit_size),
             cdt::Argument::Int(value),
-            cdt::Argument::Byte(policy.flags.bits()),
+            cdt::Argument::WriteFlags(policy.flags.bits()),
         ],
     )
 }
@@ -1,15 +1,36 @@
-use std::collections::HashMap;
+use std::{borrow::Cow, collections::HashMap};
 
-use super::{list, map};
+use super::{hll, list, map};
 use crate::{commands::ParticleType, msgpack, value::MapKey, Value};
 
+#[derive(Clone)]
 pub(crate) enum Argument<'a> {
     Byte(u8),
+    /// Write flags of the operation's policy, which are encoded like a [`Self::Byte`].
+    WriteFlags(u8),
     Int(i64),
+    /// Return type flags of a typed [`map::MapReturn`] that selects key/value pairs, which are
+    /// encoded like a [`Self::Int`].
+    PairsReturnType(i64),
     Bool(bool),
-    Value(&'a Value),
-    List(&'a [Value]),
-    Map(&'a HashMap<MapKey, Value>),
+    Value(Cow<'a, Value>),
+    List(Cow<'a, [Value]>),
+    Map(Cow<'a, HashMap<MapKey, Value>>),
+}
+
+impl Argument<'_> {
+    fn into_owned(self) -> Argument<'static> {
+        match self {
+            Self::Byte(v) => Argument::Byte(v),
+            Self::WriteFlags(v) => Argument::WriteFlags(v),
+            Self::Int(v) => Argument::Int(v),
+            Self::PairsReturnType(v) => Argument::PairsReturnType(v),
+            Self::Bool(v) => Argument::Bool(v),
+            Self::Value(v) => Argument::Value(Cow::Owned(v.into_owned())),
+            Self::List(v) => Argument::List(Cow::Owned(v.into_owned())),
+            Self::Map(v) => Argument::Map(Cow::Owned(v.into_owned())),
+        }
+    }
 }
 
 #[derive(Clone, Copy)]
@@ -29,6 +50,7 @@ impl Encoder {
     }
 }
 
+#[derive(Clone)]
 pub(crate) struct Operation<'a> {
     pub op: u8,
     pub(super) encoder: Encoder,
@@ -41,6 +63,49 @@ impl<'a> Operation<'a> {
         ParticleType::Blob
     }
 
+    /// Whether the operation was created with the `NO_FAIL` write flag, which has the same value
+    /// for list, bitwise and HLL operations.
+    pub fn no_fail(&self) -> bool {
+        self.args.iter().any(|arg| {
+            matches!(arg, Argument::WriteFlags(flags) if flags & list::WriteFlags::NO_FAIL.bits() != 0)
+        })
+    }
+
+    /// Whether the operation selects key/value pairs through a typed [`map::MapReturn`], so its
+    /// result is decoded as ordered pairs.
+    pub fn key_value_pairs(&self) -> bool {
+        self.args
+            .iter()
+            .any(|arg| matches!(arg, Argument::PairsReturnType(_)))
+    }
+
+    /// Copy of the operation with the `NO_FAIL` write flag added, as well as the `PARTIAL` flag for
+    /// list and bitwise operations. Operations without write flags are copied as is.
+    pub fn with_no_fail(&self) -> Self {
+        let added = match self.encoder {
+            Encoder::Cdt | Encoder::CdtBit => {
+                (list::WriteFlags::NO_FAIL | list::WriteFlags::PARTIAL).bits()
+            }
+            Encoder::Hll => hll::WriteFlags::NO_FAIL.bits(),
+        };
+
+        let mut op = self.clone();
+        for arg in &mut op.args {
+            if let Argument::WriteFlags(flags) = arg {
+                *flags |= added;
+            }
+        }
+        op
+    }
+
+    pub fn into_owned(self) -> Operation<'static> {
+        Operation {
+            op: self.op,
+            encoder: self.encoder,
+            args: self.args.into_iter().map(Argument::into_owned).collect(),
+        }
+    }
+
     pub fn estimate_size(&self, ctx: &[Context]) -> usize {
         self.encoder.encode(&mut msgpack::Sink, self, ctx)
     }
@@ -92,7 +157,12 @@ impl Context {
         }
     }
 
-    /// list with given type at index offset, given an order and pad.
+    /// Like [`Self::list_index`], but creates a list with the given order at the index offset, if
+    /// there is no value yet. This allows to build nested structures implicitly during an
+    /// operation, instead of failing when an intermediate level doesn't exist.
+    ///
+    /// If `pad` is set, an unordered list is padded with nil values to reach an index beyond its
+    /// end. Otherwise, the index must be within or right after the end of the list.
     #[must_use]
     pub const fn list_index_create(index: i64, order: list::OrderType, pad: bool) -> Self {
         Self {
@@ -165,7 +235,9 @@ impl Context {
         }
     }
 
-    /// Create map with given type at map key.
+    /// Like [`Self::map_key`], but creates a map with the given order at the key, if the key
+    /// doesn't exist yet. This allows to build nested structures implicitly during an operation,
+    /// instead of failing when an intermediate level doesn't exist.
     #[must_use]
     pub const fn map_key_create(key: Value, order: map::OrderType) -> Self {
         Self {
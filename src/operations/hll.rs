@@ -1,12 +1,14 @@
 //! `HyperLogLog` operations on HLL items nested in lists/maps are not currently
 //! supported by the server.
 
+use std::borrow::Cow;
+
 use bitflags::bitflags;
 
 use super::cdt::{self, Encoder};
 use crate::{
     operations::{Operation, OperationBin, OperationData, OperationType},
-    Value,
+    FromValue, Value,
 };
 
 bitflags! {
@@ -51,8 +53,132 @@ impl Default for Policy {
     }
 }
 
+/// Content of an HLL bin, as returned in a [`Value::Hll`].
+///
+/// The cardinality can't be computed locally, but the configuration of the HLL can be read from
+/// its header, without another round trip to the server with [`describe`]. The value can be
+/// turned back into a [`Value`] to be passed to operations like [`set_union`] or [`get_union`].
+///
+/// ```
+/// # use windpike::{operations::hll::HllValue, Value};
+/// # let value = Value::Hll(vec![0, 12, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+/// let hll = HllValue::try_from(value).unwrap();
+/// assert_eq!(12, hll.index_bits());
+/// assert_eq!(0, hll.minhash_bits());
+/// assert_eq!(4096, hll.register_count());
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct HllValue(Vec<u8>);
+
+impl HllValue {
+    /// Size of the header, that precedes the registers. It consists of a flags byte, the index bit
+    /// count, the minhash bit count and a cached cardinality.
+    pub const HEADER_SIZE: usize = 11;
+    /// Largest supported amount of index bits.
+    pub const MAX_INDEX_BITS: u8 = 16;
+    /// Largest supported amount of minhash bits.
+    pub const MAX_MINHASH_BITS: u8 = 51;
+    /// Smallest supported amount of index bits.
+    pub const MIN_INDEX_BITS: u8 = 4;
+    /// Smallest supported amount of minhash bits, besides _zero_ which disables them.
+    pub const MIN_MINHASH_BITS: u8 = 4;
+
+    /// Wrap the raw content of an HLL bin, after validating its header.
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<Self, HllError> {
+        if bytes.len() < Self::HEADER_SIZE {
+            return Err(HllError::Truncated(bytes.len()));
+        }
+
+        let (index_bits, minhash_bits) = (bytes[1], bytes[2]);
+        if !(Self::MIN_INDEX_BITS..=Self::MAX_INDEX_BITS).contains(&index_bits) {
+            return Err(HllError::InvalidIndexBits(index_bits));
+        }
+        if minhash_bits != 0
+            && (!(Self::MIN_MINHASH_BITS..=Self::MAX_MINHASH_BITS).contains(&minhash_bits)
+                || index_bits + minhash_bits > 64)
+        {
+            return Err(HllError::InvalidMinhashBits(minhash_bits));
+        }
+
+        Ok(Self(bytes))
+    }
+
+    /// Amount of bits of the hash that select the register, which defines the precision.
+    #[must_use]
+    pub fn index_bits(&self) -> u8 {
+        self.0[1]
+    }
+
+    /// Amount of bits of the hash that are stored in each register for similarity and
+    /// intersection estimates. A value of _zero_ means that minhash bits are disabled.
+    #[must_use]
+    pub fn minhash_bits(&self) -> u8 {
+        self.0[2]
+    }
+
+    /// Amount of registers, which is `2^index_bits`.
+    #[must_use]
+    pub fn register_count(&self) -> usize {
+        1 << self.index_bits()
+    }
+
+    /// Raw content of the HLL.
+    #[must_use]
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// Unwrap the raw content of the HLL.
+    #[must_use]
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.0
+    }
+}
+
+impl TryFrom<Value> for HllValue {
+    type Error = HllError;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Hll(bytes) => Self::from_bytes(bytes),
+            other => Err(HllError::NotHll(other.type_name())),
+        }
+    }
+}
+
+impl From<HllValue> for Value {
+    fn from(value: HllValue) -> Self {
+        Self::Hll(value.0)
+    }
+}
+
+impl FromValue for HllValue {
+    const TYPE_NAME: &'static str = "HyperLogLog";
+
+    fn from_value(value: Value) -> Option<Self> {
+        value.try_into().ok()
+    }
+}
+
+/// Errors that can happen when converting a value into an [`HllValue`].
+#[derive(Debug, thiserror::Error)]
+pub enum HllError {
+    /// The value is of a different type.
+    #[error("expected a HyperLogLog value, but got a `{0}`")]
+    NotHll(&'static str),
+    /// The value is too short to contain the HLL header.
+    #[error("HyperLogLog value of {0} bytes is too short")]
+    Truncated(usize),
+    /// The index bit count is outside the supported range.
+    #[error("invalid HyperLogLog index bit count `{0}`")]
+    InvalidIndexBits(u8),
+    /// The minhash bit count is outside the supported range.
+    #[error("invalid HyperLogLog minhash bit count `{0}`")]
+    InvalidMinhashBits(u8),
+}
+
 #[derive(Clone, Copy, Debug)]
-enum OpType {
+pub(crate) enum OpType {
     Init = 0,
     Add,
     SetUnion,
@@ -66,17 +192,24 @@ enum OpType {
     Describe,
 }
 
+/// Whether the HLL operation always returns a value if it was applied, so a missing value means
+/// that the server skipped it.
+pub(super) fn returns_value(op: u8) -> bool {
+    op == OpType::Add as u8
+}
+
 #[inline]
 const fn write<'a>(bin: &'a str, op: OpType, args: Vec<cdt::Argument<'a>>) -> Operation<'a> {
     Operation {
         op: OperationType::HllWrite,
-        ctx: &[],
-        bin: OperationBin::Name(bin),
+        ctx: Cow::Borrowed(&[]),
+        bin: OperationBin::Name(Cow::Borrowed(bin)),
         data: OperationData::HllOp(cdt::Operation {
             op: op as u8,
             encoder: Encoder::Hll,
             args,
         }),
+        encoded: None,
     }
 }
 
@@ -84,13 +217,14 @@ const fn write<'a>(bin: &'a str, op: OpType, args: Vec<cdt::Argument<'a>>) -> Op
 const fn read<'a>(bin: &'a str, op: OpType, args: Vec<cdt::Argument<'a>>) -> Operation<'a> {
     Operation {
         op: OperationType::HllRead,
-        ctx: &[],
-        bin: OperationBin::Name(bin),
+        ctx: Cow::Borrowed(&[]),
+        bin: OperationBin::Name(Cow::Borrowed(bin)),
         data: OperationData::HllOp(cdt::Operation {
             op: op as u8,
             encoder: Encoder::Hll,
             args,
         }),
+        encoded: None,
     }
 }
 
@@ -118,7 +252,7 @@ pub fn init_with_min_hash(
         vec![
             cdt::Argument::Int(index_bit_count),
             cdt::Argument::Int(min_hash_bit_count),
-            cdt::Argument::Byte(policy.flags.bits()),
+            cdt::Argument::WriteFlags(policy.flags.bits()),
         ],
     )
 }
@@ -160,10 +294,10 @@ pub fn add_with_index_and_min_hash<'a>(
         bin,
         OpType::Add,
         vec![
-            cdt::Argument::List(list),
+            cdt::Argument::List(Cow::Borrowed(list)),
             cdt::Argument::Int(index_bit_count),
             cdt::Argument::Int(min_hash_bit_count),
-            cdt::Argument::Byte(policy.flags.bits()),
+            cdt::Argument::WriteFlags(policy.flags.bits()),
         ],
     )
 }
@@ -177,8 +311,8 @@ pub fn set_union<'a>(policy: Policy, bin: &'a str, list: &'a [Value]) -> Operati
         bin,
         OpType::SetUnion,
         vec![
-            cdt::Argument::List(list),
-            cdt::Argument::Byte(policy.flags.bits()),
+            cdt::Argument::List(Cow::Borrowed(list)),
+            cdt::Argument::WriteFlags(policy.flags.bits()),
         ],
     )
 }
@@ -211,7 +345,11 @@ pub fn get_count(bin: &str) -> Operation<'_> {
 /// with the HLL bin.
 #[must_use]
 pub fn get_union<'a>(bin: &'a str, list: &'a [Value]) -> Operation<'a> {
-    read(bin, OpType::Union, vec![cdt::Argument::List(list)])
+    read(
+        bin,
+        OpType::Union,
+        vec![cdt::Argument::List(Cow::Borrowed(list))],
+    )
 }
 
 /// Create HLL `get_union_count` operation.
@@ -219,7 +357,11 @@ pub fn get_union<'a>(bin: &'a str, list: &'a [Value]) -> Operation<'a> {
 /// HLL objects.
 #[must_use]
 pub fn get_union_count<'a>(bin: &'a str, list: &'a [Value]) -> Operation<'a> {
-    read(bin, OpType::UnionCount, vec![cdt::Argument::List(list)])
+    read(
+        bin,
+        OpType::UnionCount,
+        vec![cdt::Argument::List(Cow::Borrowed(list))],
+    )
 }
 
 /// Create HLL `get_intersect_count` operation.
@@ -227,14 +369,22 @@ pub fn get_union_count<'a>(bin: &'a str, list: &'a [Value]) -> Operation<'a> {
 /// these HLL objects.
 #[must_use]
 pub fn get_intersect_count<'a>(bin: &'a str, list: &'a [Value]) -> Operation<'a> {
-    read(bin, OpType::IntersectCount, vec![cdt::Argument::List(list)])
+    read(
+        bin,
+        OpType::IntersectCount,
+        vec![cdt::Argument::List(Cow::Borrowed(list))],
+    )
 }
 
 /// Create HLL getSimilarity operation.
 /// Server returns estimated similarity of these HLL objects. Return type is a double.
 #[must_use]
 pub fn get_similarity<'a>(bin: &'a str, list: &'a [Value]) -> Operation<'a> {
-    read(bin, OpType::Similarity, vec![cdt::Argument::List(list)])
+    read(
+        bin,
+        OpType::Similarity,
+        vec![cdt::Argument::List(Cow::Borrowed(list))],
+    )
 }
 
 /// Create HLL describe operation.
@@ -244,3 +394,49 @@ pub fn get_similarity<'a>(bin: &'a str, list: &'a [Value]) -> Operation<'a> {
 pub fn describe(bin: &str) -> Operation<'_> {
     read(bin, OpType::Describe, vec![])
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{HllError, HllValue};
+    use crate::Value;
+
+    fn hll(index_bits: u8, minhash_bits: u8) -> Value {
+        let mut bytes = vec![0; HllValue::HEADER_SIZE];
+        bytes[1] = index_bits;
+        bytes[2] = minhash_bits;
+        Value::Hll(bytes)
+    }
+
+    #[test]
+    fn parse_header() {
+        let value = HllValue::try_from(hll(16, 48)).unwrap();
+        assert_eq!(16, value.index_bits());
+        assert_eq!(48, value.minhash_bits());
+        assert_eq!(65536, value.register_count());
+        assert_eq!(hll(16, 48), Value::from(value));
+    }
+
+    #[test]
+    fn invalid_header() {
+        assert!(matches!(
+            HllValue::try_from(Value::Blob(vec![0; 11])),
+            Err(HllError::NotHll("blob"))
+        ));
+        assert!(matches!(
+            HllValue::from_bytes(vec![0, 12, 0]),
+            Err(HllError::Truncated(3))
+        ));
+        assert!(matches!(
+            HllValue::try_from(hll(3, 0)),
+            Err(HllError::InvalidIndexBits(3))
+        ));
+        assert!(matches!(
+            HllValue::try_from(hll(4, 2)),
+            Err(HllError::InvalidMinhashBits(2))
+        ));
+        assert!(matches!(
+            HllValue::try_from(hll(16, 50)),
+            Err(HllError::InvalidMinhashBits(50))
+        ));
+    }
+}
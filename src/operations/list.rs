@@ -16,6 +16,8 @@
 //! If an index is out of bounds, a parameter error will be returned. If a range is partially out of
 //! bounds, the valid part of the range will be returned.
 
+use std::borrow::Cow;
+
 use bitflags::bitflags;
 
 use super::cdt::{self, Encoder};
@@ -25,7 +27,7 @@ use crate::{
 };
 
 #[derive(Clone, Copy, Debug)]
-enum OpType {
+pub(crate) enum OpType {
     SetType = 0,
     Append,
     AppendItems,
@@ -162,6 +164,9 @@ impl Default for Policy {
     }
 }
 
+/// List attribute that tells the server to persist the list index.
+const PERSIST_INDEX_FLAG: u8 = 0x10;
+
 #[must_use]
 pub(super) const fn order_flag(order: OrderType, pad: bool) -> u8 {
     if matches!(order, OrderType::Ordered) {
@@ -173,6 +178,20 @@ pub(super) const fn order_flag(order: OrderType, pad: bool) -> u8 {
     }
 }
 
+/// Whether the list operation always returns a value if it was applied, so a missing value
+/// means that the server skipped it.
+pub(super) fn returns_value(op: u8) -> bool {
+    [
+        OpType::Append,
+        OpType::AppendItems,
+        OpType::Insert,
+        OpType::InsertItems,
+        OpType::Increment,
+    ]
+    .iter()
+    .any(|&ty| ty as u8 == op)
+}
+
 #[inline]
 const fn write<'a>(
     ctx: &'a [cdt::Context],
@@ -182,13 +201,14 @@ const fn write<'a>(
 ) -> Operation<'a> {
     Operation {
         op: OperationType::CdtWrite,
-        ctx,
-        bin: OperationBin::Name(bin),
+        ctx: Cow::Borrowed(ctx),
+        bin: OperationBin::Name(Cow::Borrowed(bin)),
         data: OperationData::CdtListOp(cdt::Operation {
             op: op as u8,
             encoder: Encoder::Cdt,
             args,
         }),
+        encoded: None,
     }
 }
 
@@ -201,13 +221,14 @@ const fn read<'a>(
 ) -> Operation<'a> {
     Operation {
         op: OperationType::CdtRead,
-        ctx,
-        bin: OperationBin::Name(bin),
+        ctx: Cow::Borrowed(ctx),
+        bin: OperationBin::Name(Cow::Borrowed(bin)),
         data: OperationData::CdtListOp(cdt::Operation {
             op: op as u8,
             encoder: Encoder::Cdt,
             args,
         }),
+        encoded: None,
     }
 }
 
@@ -217,13 +238,33 @@ const fn read<'a>(
 /// satisfy the context position.
 #[must_use]
 pub fn create(bin: &str, order: OrderType, pad: bool) -> Operation<'_> {
+    create_with_index(bin, order, pad, false)
+}
+
+/// Creates list create operation, same as [`create`], but optionally lets the server persist the
+/// list index.
+///
+/// A persisted index speeds up the lookup of values in large ordered lists, at the cost of
+/// additional storage on the server. It is only supported for top-level lists.
+#[must_use]
+pub fn create_with_index(
+    bin: &str,
+    order: OrderType,
+    pad: bool,
+    persist_index: bool,
+) -> Operation<'_> {
+    let mut attributes = order as u8;
+    if persist_index {
+        attributes |= PERSIST_INDEX_FLAG;
+    }
+
     write(
         &[],
         bin,
         OpType::SetType,
         vec![
             cdt::Argument::Byte(order_flag(order, pad)),
-            cdt::Argument::Byte(order as u8),
+            cdt::Argument::Byte(attributes),
         ],
     )
 }
@@ -248,9 +289,9 @@ pub fn append<'a>(policy: Policy, bin: &'a str, value: &'a Value) -> Operation<'
         bin,
         OpType::Append,
         vec![
-            cdt::Argument::Value(value),
+            cdt::Argument::Value(Cow::Borrowed(value)),
             cdt::Argument::Byte(policy.attributes as u8),
-            cdt::Argument::Byte(policy.flags.bits()),
+            cdt::Argument::WriteFlags(policy.flags.bits()),
         ],
     )
 }
@@ -269,9 +310,9 @@ pub fn append_items<'a>(
             bin,
             OpType::AppendItems,
             vec![
-                cdt::Argument::List(values),
+                cdt::Argument::List(Cow::Borrowed(values)),
                 cdt::Argument::Byte(policy.attributes as u8),
-                cdt::Argument::Byte(policy.flags.bits()),
+                cdt::Argument::WriteFlags(policy.flags.bits()),
             ],
         )
     })
@@ -287,8 +328,8 @@ pub fn insert<'a>(policy: Policy, bin: &'a str, index: i64, value: &'a Value) ->
         OpType::Insert,
         vec![
             cdt::Argument::Int(index),
-            cdt::Argument::Value(value),
-            cdt::Argument::Byte(policy.flags.bits()),
+            cdt::Argument::Value(Cow::Borrowed(value)),
+            cdt::Argument::WriteFlags(policy.flags.bits()),
         ],
     )
 }
@@ -312,8 +353,8 @@ pub fn insert_items<'a>(
             OpType::InsertItems,
             vec![
                 cdt::Argument::Int(index),
-                cdt::Argument::List(values),
-                cdt::Argument::Byte(policy.flags.bits()),
+                cdt::Argument::List(Cow::Borrowed(values)),
+                cdt::Argument::WriteFlags(policy.flags.bits()),
             ],
         )
     })
@@ -390,7 +431,7 @@ pub fn remove_by_value<'a>(
         OpType::RemoveByValue,
         vec![
             cdt::Argument::Byte(return_type as u8),
-            cdt::Argument::Value(value),
+            cdt::Argument::Value(Cow::Borrowed(value)),
         ],
     )
 }
@@ -409,7 +450,7 @@ pub fn remove_by_value_list<'a>(
         OpType::RemoveByValueList,
         vec![
             cdt::Argument::Byte(return_type as u8),
-            cdt::Argument::List(values),
+            cdt::Argument::List(Cow::Borrowed(values)),
         ],
     )
 }
@@ -432,8 +473,8 @@ pub fn remove_by_value_range<'a>(
         OpType::RemoveByValueInterval,
         vec![
             cdt::Argument::Byte(return_type as u8),
-            cdt::Argument::Value(begin),
-            cdt::Argument::Value(end),
+            cdt::Argument::Value(Cow::Borrowed(begin)),
+            cdt::Argument::Value(Cow::Borrowed(end)),
         ],
     )
 }
@@ -465,7 +506,7 @@ pub fn remove_by_value_relative_rank_range<'a>(
         OpType::RemoveByValueRelRankRange,
         vec![
             cdt::Argument::Byte(return_type as u8),
-            cdt::Argument::Value(value),
+            cdt::Argument::Value(Cow::Borrowed(value)),
             cdt::Argument::Int(rank),
         ],
     )
@@ -499,7 +540,7 @@ pub fn remove_by_value_relative_rank_range_count<'a>(
         OpType::RemoveByValueRelRankRange,
         vec![
             cdt::Argument::Byte(return_type as u8),
-            cdt::Argument::Value(value),
+            cdt::Argument::Value(Cow::Borrowed(value)),
             cdt::Argument::Int(rank),
             cdt::Argument::Int(count),
         ],
@@ -621,7 +662,10 @@ pub fn set<'a>(bin: &'a str, index: i64, value: &'a Value) -> Option<Operation<'
             &[],
             bin,
             OpType::Set,
-            vec![cdt::Argument::Int(index), cdt::Argument::Value(value)],
+            vec![
+                cdt::Argument::Int(index),
+                cdt::Argument::Value(Cow::Borrowed(value)),
+            ],
         )
     })
 }
@@ -657,7 +701,7 @@ pub fn increment(policy: Policy, bin: &str, index: i64, value: i64) -> Operation
         vec![
             cdt::Argument::Int(index),
             cdt::Argument::Int(value),
-            cdt::Argument::Byte(policy.flags.bits()),
+            cdt::Argument::WriteFlags(policy.flags.bits()),
         ],
     )
 }
@@ -703,7 +747,7 @@ pub fn get_by_value<'a>(bin: &'a str, value: &'a Value, return_type: ReturnType)
         OpType::GetByValue,
         vec![
             cdt::Argument::Byte(return_type as u8),
-            cdt::Argument::Value(value),
+            cdt::Argument::Value(Cow::Borrowed(value)),
         ],
     )
 }
@@ -723,7 +767,7 @@ pub fn get_by_value_list<'a>(
         OpType::GetByValueList,
         vec![
             cdt::Argument::Byte(return_type as u8),
-            cdt::Argument::List(values),
+            cdt::Argument::List(Cow::Borrowed(values)),
         ],
     )
 }
@@ -746,8 +790,8 @@ pub fn get_by_value_range<'a>(
         OpType::GetByValueInterval,
         vec![
             cdt::Argument::Byte(return_type as u8),
-            cdt::Argument::Value(begin),
-            cdt::Argument::Value(end),
+            cdt::Argument::Value(Cow::Borrowed(begin)),
+            cdt::Argument::Value(Cow::Borrowed(end)),
         ],
     )
 }
@@ -885,7 +929,7 @@ pub fn get_by_value_relative_rank_range<'a>(
         OpType::GetByValueRelRankRange,
         vec![
             cdt::Argument::Byte(return_type as u8),
-            cdt::Argument::Value(value),
+            cdt::Argument::Value(Cow::Borrowed(value)),
             cdt::Argument::Int(rank),
         ],
     )
@@ -919,7 +963,7 @@ pub fn get_by_value_relative_rank_range_count<'a>(
         OpType::GetByValueRelRankRange,
         vec![
             cdt::Argument::Byte(return_type as u8),
-            cdt::Argument::Value(value),
+            cdt::Argument::Value(Cow::Borrowed(value)),
             cdt::Argument::Int(rank),
             cdt::Argument::Int(count),
         ],
@@ -25,7 +25,7 @@
 //! * Rank 1 Count 2: Second and third lowest ranked items in map.
 //! * Rank -3 Count 3: Top three ranked items in map.
 
-use std::collections::HashMap;
+use std::{borrow::Cow, collections::HashMap, marker::PhantomData};
 
 use super::cdt::{self, Encoder};
 use crate::{
@@ -128,6 +128,277 @@ pub enum ReturnType {
     Inverted = 0x10000,
 }
 
+/// Conversion into the raw return type flags of map operations, which allows map operations to
+/// take either a plain [`ReturnType`] or a typed [`MapReturn`].
+pub trait IntoReturnType {
+    /// Get the raw return type flags as sent to the server.
+    fn into_return_type(self) -> i64;
+
+    /// Whether the result is decoded as ordered key/value pairs, see [`KeyValues`].
+    fn key_value_pairs(&self) -> bool {
+        false
+    }
+}
+
+impl IntoReturnType for ReturnType {
+    fn into_return_type(self) -> i64 {
+        self as i64
+    }
+}
+
+/// Typed alternative to [`ReturnType`], which only allows valid flag combinations and decodes the
+/// operation result into a fitting Rust type.
+///
+/// ```
+/// # use windpike::{operations::map::{self, MapReturn}, MapKey, Value};
+/// let ret = MapReturn::key_values().inverted();
+/// let op = map::get_by_rank_range("bin", 0, 3, ret);
+///
+/// // The operation result, as it is found in the record bin.
+/// let result = Value::from(vec![
+///     Value::from(vec![Value::from("b"), Value::from(2)]),
+///     Value::from(vec![Value::from("a"), Value::from(1)]),
+/// ]);
+/// assert_eq!(
+///     ret.decode(result),
+///     Some(vec![
+///         (MapKey::from("b"), Value::from(2)),
+///         (MapKey::from("a"), Value::from(1)),
+///     ])
+/// );
+/// ```
+#[derive(Debug)]
+pub struct MapReturn<T> {
+    flags: i64,
+    kind: PhantomData<fn() -> T>,
+}
+
+impl<T> Clone for MapReturn<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for MapReturn<T> {}
+
+impl<T: MapReturnKind> MapReturn<T> {
+    const fn new(return_type: ReturnType) -> Self {
+        Self {
+            flags: return_type as i64,
+            kind: PhantomData,
+        }
+    }
+
+    /// Invert the selection of the operation, so it applies to all items that are outside of the
+    /// selected ones instead.
+    #[must_use]
+    pub const fn inverted(self) -> Self {
+        Self {
+            flags: self.flags | ReturnType::Inverted as i64,
+            kind: PhantomData,
+        }
+    }
+
+    /// Decode the result of the operation, as found in the record bin. Returns [`None`] if the
+    /// value doesn't have the expected type.
+    #[must_use]
+    pub fn decode(&self, value: Value) -> Option<T::Output> {
+        T::decode(value)
+    }
+}
+
+impl MapReturn<Count> {
+    /// Return the count of selected items.
+    #[must_use]
+    pub const fn count() -> Self {
+        Self::new(ReturnType::Count)
+    }
+}
+
+impl MapReturn<Keys> {
+    /// Return the keys of the selected items.
+    #[must_use]
+    pub const fn keys() -> Self {
+        Self::new(ReturnType::Key)
+    }
+}
+
+impl MapReturn<Values> {
+    /// Return the values of the selected items.
+    #[must_use]
+    pub const fn values() -> Self {
+        Self::new(ReturnType::Value)
+    }
+}
+
+impl MapReturn<KeyValues> {
+    /// Return the selected items as key/value pairs.
+    #[must_use]
+    pub const fn key_values() -> Self {
+        Self::new(ReturnType::KeyValue)
+    }
+}
+
+impl MapReturn<Positions> {
+    /// Return the key index of the selected items.
+    #[must_use]
+    pub const fn index() -> Self {
+        Self::new(ReturnType::Index)
+    }
+
+    /// Return the reverse key index of the selected items.
+    #[must_use]
+    pub const fn reverse_index() -> Self {
+        Self::new(ReturnType::ReverseIndex)
+    }
+
+    /// Return the value rank of the selected items.
+    #[must_use]
+    pub const fn rank() -> Self {
+        Self::new(ReturnType::Rank)
+    }
+
+    /// Return the reverse value rank of the selected items.
+    #[must_use]
+    pub const fn reverse_rank() -> Self {
+        Self::new(ReturnType::ReverseRank)
+    }
+}
+
+impl<T> IntoReturnType for MapReturn<T> {
+    fn into_return_type(self) -> i64 {
+        self.flags
+    }
+
+    fn key_value_pairs(&self) -> bool {
+        self.flags & !(ReturnType::Inverted as i64) == ReturnType::KeyValue as i64
+    }
+}
+
+fn return_type_arg(return_type: impl IntoReturnType) -> cdt::Argument<'static> {
+    if return_type.key_value_pairs() {
+        cdt::Argument::PairsReturnType(return_type.into_return_type())
+    } else {
+        cdt::Argument::Int(return_type.into_return_type())
+    }
+}
+
+/// Kind of data selected by a [`MapReturn`], which defines how the result is decoded.
+pub trait MapReturnKind {
+    /// The decoded result type.
+    type Output;
+
+    /// Decode the result of a map operation.
+    fn decode(value: Value) -> Option<Self::Output>;
+}
+
+/// Count of selected items, see [`MapReturn::count`].
+#[derive(Clone, Copy, Debug)]
+pub struct Count;
+
+impl MapReturnKind for Count {
+    type Output = i64;
+
+    fn decode(value: Value) -> Option<Self::Output> {
+        match value {
+            Value::Int(count) => Some(count),
+            Value::Nil => Some(0),
+            _ => None,
+        }
+    }
+}
+
+/// Keys of selected items, see [`MapReturn::keys`].
+#[derive(Clone, Copy, Debug)]
+pub struct Keys;
+
+impl MapReturnKind for Keys {
+    type Output = Vec<Value>;
+
+    fn decode(value: Value) -> Option<Self::Output> {
+        Some(decode_list(value))
+    }
+}
+
+/// Values of selected items, see [`MapReturn::values`].
+#[derive(Clone, Copy, Debug)]
+pub struct Values;
+
+impl MapReturnKind for Values {
+    type Output = Vec<Value>;
+
+    fn decode(value: Value) -> Option<Self::Output> {
+        Some(decode_list(value))
+    }
+}
+
+/// Key/value pairs of selected items, see [`MapReturn::key_values`].
+///
+/// Operations that use this return type have their result decoded as a [`Value::List`] of two
+/// element lists, one for each key/value pair, in the order that the server returned them. That's
+/// also the value found in the record bin. A plain [`Value::HashMap`], like the result of the same
+/// operation with [`ReturnType::KeyValue`], is decoded as well, but its pairs come in no particular
+/// order.
+#[derive(Clone, Copy, Debug)]
+pub struct KeyValues;
+
+impl MapReturnKind for KeyValues {
+    type Output = Vec<(MapKey, Value)>;
+
+    fn decode(value: Value) -> Option<Self::Output> {
+        match value {
+            Value::List(pairs) => pairs.into_iter().map(decode_pair).collect(),
+            Value::HashMap(map) => Some(map.into_iter().collect()),
+            Value::Nil => Some(Vec::new()),
+            _ => None,
+        }
+    }
+}
+
+fn decode_pair(pair: Value) -> Option<(MapKey, Value)> {
+    let [key, value] = match pair {
+        Value::List(pair) => <[Value; 2]>::try_from(pair).ok()?,
+        _ => return None,
+    };
+
+    let key = match key {
+        Value::Int(v) => MapKey::Int(v),
+        Value::Uint(v) => MapKey::Uint(v),
+        Value::Float(v) => MapKey::Float(v),
+        Value::String(v) => MapKey::String(v),
+        _ => return None,
+    };
+
+    Some((key, value))
+}
+
+/// Index or rank of selected items, see [`MapReturn::index`] and [`MapReturn::rank`].
+#[derive(Clone, Copy, Debug)]
+pub struct Positions;
+
+impl MapReturnKind for Positions {
+    type Output = Vec<i64>;
+
+    fn decode(value: Value) -> Option<Self::Output> {
+        decode_list(value)
+            .into_iter()
+            .map(|value| match value {
+                Value::Int(pos) => Some(pos),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+/// Single item selections return the plain value, while all others return a list of them.
+fn decode_list(value: Value) -> Vec<Value> {
+    match value {
+        Value::List(list) => list,
+        Value::Nil => Vec::new(),
+        value => vec![value],
+    }
+}
+
 /// Unique key map write type.
 #[derive(Clone, Copy, Debug)]
 pub enum WriteMode {
@@ -213,13 +484,14 @@ pub(super) const fn order_flag(order: OrderType) -> u8 {
 const fn write<'a>(bin: &'a str, op: OpType, args: Vec<cdt::Argument<'a>>) -> Operation<'a> {
     Operation {
         op: OperationType::CdtWrite,
-        ctx: &[],
-        bin: OperationBin::Name(bin),
+        ctx: Cow::Borrowed(&[]),
+        bin: OperationBin::Name(Cow::Borrowed(bin)),
         data: OperationData::CdtMapOp(cdt::Operation {
             op: op as u8,
             encoder: Encoder::Cdt,
             args,
         }),
+        encoded: None,
     }
 }
 
@@ -227,13 +499,14 @@ const fn write<'a>(bin: &'a str, op: OpType, args: Vec<cdt::Argument<'a>>) -> Op
 const fn read<'a>(bin: &'a str, op: OpType, args: Vec<cdt::Argument<'a>>) -> Operation<'a> {
     Operation {
         op: OperationType::CdtRead,
-        ctx: &[],
-        bin: OperationBin::Name(bin),
+        ctx: Cow::Borrowed(&[]),
+        bin: OperationBin::Name(Cow::Borrowed(bin)),
         data: OperationData::CdtMapOp(cdt::Operation {
             op: op as u8,
             encoder: Encoder::Cdt,
             args,
         }),
+        encoded: None,
     }
 }
 
@@ -257,9 +530,9 @@ pub fn set_order(bin: &str, map_order: OrderType) -> Operation<'_> {
 /// policy also specifies the mode used when writing items to the map.
 #[must_use]
 pub fn put<'a>(policy: Policy, bin: &'a str, key: &'a Value, val: &'a Value) -> Operation<'a> {
-    let mut args = vec![cdt::Argument::Value(key)];
+    let mut args = vec![cdt::Argument::Value(Cow::Borrowed(key))];
     if *val != Value::Nil {
-        args.push(cdt::Argument::Value(val));
+        args.push(cdt::Argument::Value(Cow::Borrowed(val)));
     }
     if let Some(arg) = map_order_arg(policy) {
         args.push(arg);
@@ -280,7 +553,7 @@ pub fn put_items<'a>(
     bin: &'a str,
     items: &'a HashMap<MapKey, Value>,
 ) -> Operation<'a> {
-    let mut args = vec![cdt::Argument::Map(items)];
+    let mut args = vec![cdt::Argument::Map(Cow::Borrowed(items))];
     if let Some(arg) = map_order_arg(policy) {
         args.push(arg);
     }
@@ -300,9 +573,9 @@ pub fn increment_value<'a>(
     key: &'a Value,
     incr: &'a Value,
 ) -> Operation<'a> {
-    let mut args = vec![cdt::Argument::Value(key)];
+    let mut args = vec![cdt::Argument::Value(Cow::Borrowed(key))];
     if *incr != Value::Nil {
-        args.push(cdt::Argument::Value(incr));
+        args.push(cdt::Argument::Value(Cow::Borrowed(incr)));
     }
     if let Some(arg) = map_order_arg(policy) {
         args.push(arg);
@@ -323,9 +596,9 @@ pub fn decrement_value<'a>(
     key: &'a Value,
     decr: &'a Value,
 ) -> Operation<'a> {
-    let mut args = vec![cdt::Argument::Value(key)];
+    let mut args = vec![cdt::Argument::Value(Cow::Borrowed(key))];
     if *decr != Value::Nil {
-        args.push(cdt::Argument::Value(decr));
+        args.push(cdt::Argument::Value(Cow::Borrowed(decr)));
     }
     if let Some(arg) = map_order_arg(policy) {
         args.push(arg);
@@ -344,13 +617,17 @@ pub fn clear(bin: &str) -> Operation<'_> {
 /// Create map remove operation. Server removes the map item identified by the key and returns
 /// the removed data specified by `return_type`.
 #[must_use]
-pub fn remove_by_key<'a>(bin: &'a str, key: &'a Value, return_type: ReturnType) -> Operation<'a> {
+pub fn remove_by_key<'a>(
+    bin: &'a str,
+    key: &'a Value,
+    return_type: impl IntoReturnType,
+) -> Operation<'a> {
     write(
         bin,
         OpType::RemoveByKey,
         vec![
-            cdt::Argument::Byte(return_type as u8),
-            cdt::Argument::Value(key),
+            return_type_arg(return_type),
+            cdt::Argument::Value(Cow::Borrowed(key)),
         ],
     )
 }
@@ -361,14 +638,14 @@ pub fn remove_by_key<'a>(bin: &'a str, key: &'a Value, return_type: ReturnType)
 pub fn remove_by_key_list<'a>(
     bin: &'a str,
     keys: &'a [Value],
-    return_type: ReturnType,
+    return_type: impl IntoReturnType,
 ) -> Operation<'a> {
     write(
         bin,
         OpType::RemoveKeyList,
         vec![
-            cdt::Argument::Byte(return_type as u8),
-            cdt::Argument::List(keys),
+            return_type_arg(return_type),
+            cdt::Argument::List(Cow::Borrowed(keys)),
         ],
     )
 }
@@ -382,14 +659,14 @@ pub fn remove_by_key_range<'a>(
     bin: &'a str,
     begin: &'a Value,
     end: &'a Value,
-    return_type: ReturnType,
+    return_type: impl IntoReturnType,
 ) -> Operation<'a> {
     let mut args = vec![
-        cdt::Argument::Byte(return_type as u8),
-        cdt::Argument::Value(begin),
+        return_type_arg(return_type),
+        cdt::Argument::Value(Cow::Borrowed(begin)),
     ];
     if *end != Value::Nil {
-        args.push(cdt::Argument::Value(end));
+        args.push(cdt::Argument::Value(Cow::Borrowed(end)));
     }
 
     write(bin, OpType::RemoveByKeyInterval, args)
@@ -401,14 +678,14 @@ pub fn remove_by_key_range<'a>(
 pub fn remove_by_value<'a>(
     bin: &'a str,
     value: &'a Value,
-    return_type: ReturnType,
+    return_type: impl IntoReturnType,
 ) -> Operation<'a> {
     write(
         bin,
         OpType::RemoveByValue,
         vec![
-            cdt::Argument::Byte(return_type as u8),
-            cdt::Argument::Value(value),
+            return_type_arg(return_type),
+            cdt::Argument::Value(Cow::Borrowed(value)),
         ],
     )
 }
@@ -419,14 +696,14 @@ pub fn remove_by_value<'a>(
 pub fn remove_by_value_list<'a>(
     bin: &'a str,
     values: &'a [Value],
-    return_type: ReturnType,
+    return_type: impl IntoReturnType,
 ) -> Operation<'a> {
     write(
         bin,
         OpType::RemoveValueList,
         vec![
-            cdt::Argument::Byte(return_type as u8),
-            cdt::Argument::List(values),
+            return_type_arg(return_type),
+            cdt::Argument::List(Cow::Borrowed(values)),
         ],
     )
 }
@@ -440,14 +717,14 @@ pub fn remove_by_value_range<'a>(
     bin: &'a str,
     begin: &'a Value,
     end: &'a Value,
-    return_type: ReturnType,
+    return_type: impl IntoReturnType,
 ) -> Operation<'a> {
     let mut args = vec![
-        cdt::Argument::Byte(return_type as u8),
-        cdt::Argument::Value(begin),
+        return_type_arg(return_type),
+        cdt::Argument::Value(Cow::Borrowed(begin)),
     ];
     if *end != Value::Nil {
-        args.push(cdt::Argument::Value(end));
+        args.push(cdt::Argument::Value(Cow::Borrowed(end)));
     }
 
     write(bin, OpType::RemoveByValueInterval, args)
@@ -456,14 +733,11 @@ pub fn remove_by_value_range<'a>(
 /// Create map remove operation. Server removes the map item identified by the index and return
 /// the removed data specified by `return_type`.
 #[must_use]
-pub fn remove_by_index(bin: &str, index: i64, return_type: ReturnType) -> Operation<'_> {
+pub fn remove_by_index(bin: &str, index: i64, return_type: impl IntoReturnType) -> Operation<'_> {
     write(
         bin,
         OpType::RemoveByIndex,
-        vec![
-            cdt::Argument::Byte(return_type as u8),
-            cdt::Argument::Int(index),
-        ],
+        vec![return_type_arg(return_type), cdt::Argument::Int(index)],
     )
 }
 
@@ -474,13 +748,13 @@ pub fn remove_by_index_range(
     bin: &str,
     index: i64,
     count: i64,
-    return_type: ReturnType,
+    return_type: impl IntoReturnType,
 ) -> Operation<'_> {
     write(
         bin,
         OpType::RemoveByIndexRange,
         vec![
-            cdt::Argument::Byte(return_type as u8),
+            return_type_arg(return_type),
             cdt::Argument::Int(index),
             cdt::Argument::Int(count),
         ],
@@ -490,28 +764,26 @@ pub fn remove_by_index_range(
 /// Create map remove operation. Server removes the map items starting at the specified index
 /// to the end of the map and returns the removed data specified by `return_type`.
 #[must_use]
-pub fn remove_by_index_range_from(bin: &str, index: i64, return_type: ReturnType) -> Operation<'_> {
+pub fn remove_by_index_range_from(
+    bin: &str,
+    index: i64,
+    return_type: impl IntoReturnType,
+) -> Operation<'_> {
     write(
         bin,
         OpType::RemoveByIndexRange,
-        vec![
-            cdt::Argument::Byte(return_type as u8),
-            cdt::Argument::Int(index),
-        ],
+        vec![return_type_arg(return_type), cdt::Argument::Int(index)],
     )
 }
 
 /// Create map remove operation. Server removes the map item identified by rank and returns the
 /// removed data specified by `return_type`.
 #[must_use]
-pub fn remove_by_rank(bin: &str, rank: i64, return_type: ReturnType) -> Operation<'_> {
+pub fn remove_by_rank(bin: &str, rank: i64, return_type: impl IntoReturnType) -> Operation<'_> {
     write(
         bin,
         OpType::RemoveByRank,
-        vec![
-            cdt::Argument::Byte(return_type as u8),
-            cdt::Argument::Int(rank),
-        ],
+        vec![return_type_arg(return_type), cdt::Argument::Int(rank)],
     )
 }
 
@@ -522,13 +794,13 @@ pub fn remove_by_rank_range(
     bin: &str,
     rank: i64,
     count: i64,
-    return_type: ReturnType,
+    return_type: impl IntoReturnType,
 ) -> Operation<'_> {
     write(
         bin,
         OpType::RemoveByRankRange,
         vec![
-            cdt::Argument::Byte(return_type as u8),
+            return_type_arg(return_type),
             cdt::Argument::Int(rank),
             cdt::Argument::Int(count),
         ],
@@ -538,14 +810,15 @@ pub fn remove_by_rank_range(
 /// Create map remove operation. Server removes the map items starting at the specified rank to
 /// the last ranked item and returns the removed data specified by `return_type`.
 #[must_use]
-pub fn remove_by_rank_range_from(bin: &str, rank: i64, return_type: ReturnType) -> Operation<'_> {
+pub fn remove_by_rank_range_from(
+    bin: &str,
+    rank: i64,
+    return_type: impl IntoReturnType,
+) -> Operation<'_> {
     write(
         bin,
         OpType::RemoveByRankRange,
-        vec![
-            cdt::Argument::Byte(return_type as u8),
-            cdt::Argument::Int(rank),
-        ],
+        vec![return_type_arg(return_type), cdt::Argument::Int(rank)],
     )
 }
 
@@ -558,13 +831,17 @@ pub fn size(bin: &str) -> Operation<'_> {
 /// Create map get by key operation. Server selects the map item identified by the key and
 /// returns the selected data specified by `return_type`.
 #[must_use]
-pub fn get_by_key<'a>(bin: &'a str, key: &'a Value, return_type: ReturnType) -> Operation<'a> {
+pub fn get_by_key<'a>(
+    bin: &'a str,
+    key: &'a Value,
+    return_type: impl IntoReturnType,
+) -> Operation<'a> {
     read(
         bin,
         OpType::GetByKey,
         vec![
-            cdt::Argument::Byte(return_type as u8),
-            cdt::Argument::Value(key),
+            return_type_arg(return_type),
+            cdt::Argument::Value(Cow::Borrowed(key)),
         ],
     )
 }
@@ -578,14 +855,14 @@ pub fn get_by_key_range<'a>(
     bin: &'a str,
     begin: &'a Value,
     end: &'a Value,
-    return_type: ReturnType,
+    return_type: impl IntoReturnType,
 ) -> Operation<'a> {
     let mut args = vec![
-        cdt::Argument::Byte(return_type as u8),
-        cdt::Argument::Value(begin),
+        return_type_arg(return_type),
+        cdt::Argument::Value(Cow::Borrowed(begin)),
     ];
     if *end != Value::Nil {
-        args.push(cdt::Argument::Value(end));
+        args.push(cdt::Argument::Value(Cow::Borrowed(end)));
     }
 
     read(bin, OpType::GetByKeyInterval, args)
@@ -594,13 +871,17 @@ pub fn get_by_key_range<'a>(
 /// Create map get by value operation. Server selects the map items identified by value and
 /// returns the selected data specified by `return_type`.
 #[must_use]
-pub fn get_by_value<'a>(bin: &'a str, value: &'a Value, return_type: ReturnType) -> Operation<'a> {
+pub fn get_by_value<'a>(
+    bin: &'a str,
+    value: &'a Value,
+    return_type: impl IntoReturnType,
+) -> Operation<'a> {
     read(
         bin,
         OpType::GetByValue,
         vec![
-            cdt::Argument::Byte(return_type as u8),
-            cdt::Argument::Value(value),
+            return_type_arg(return_type),
+            cdt::Argument::Value(Cow::Borrowed(value)),
         ],
     )
 }
@@ -614,14 +895,14 @@ pub fn get_by_value_range<'a>(
     bin: &'a str,
     begin: &'a Value,
     end: &'a Value,
-    return_type: ReturnType,
+    return_type: impl IntoReturnType,
 ) -> Operation<'a> {
     let mut args = vec![
-        cdt::Argument::Byte(return_type as u8),
-        cdt::Argument::Value(begin),
+        return_type_arg(return_type),
+        cdt::Argument::Value(Cow::Borrowed(begin)),
     ];
     if *end != Value::Nil {
-        args.push(cdt::Argument::Value(end));
+        args.push(cdt::Argument::Value(Cow::Borrowed(end)));
     }
 
     read(bin, OpType::GetByValueInterval, args)
@@ -630,14 +911,11 @@ pub fn get_by_value_range<'a>(
 /// Create map get by index operation. Server selects the map item identified by index and
 /// returns the selected data specified by `return_type`.
 #[must_use]
-pub fn get_by_index(bin: &str, index: i64, return_type: ReturnType) -> Operation<'_> {
+pub fn get_by_index(bin: &str, index: i64, return_type: impl IntoReturnType) -> Operation<'_> {
     read(
         bin,
         OpType::GetByIndex,
-        vec![
-            cdt::Argument::Byte(return_type as u8),
-            cdt::Argument::Int(index),
-        ],
+        vec![return_type_arg(return_type), cdt::Argument::Int(index)],
     )
 }
 
@@ -648,13 +926,13 @@ pub fn get_by_index_range(
     bin: &str,
     index: i64,
     count: i64,
-    return_type: ReturnType,
+    return_type: impl IntoReturnType,
 ) -> Operation<'_> {
     read(
         bin,
         OpType::GetByIndexRange,
         vec![
-            cdt::Argument::Byte(return_type as u8),
+            return_type_arg(return_type),
             cdt::Argument::Int(index),
             cdt::Argument::Int(count),
         ],
@@ -665,28 +943,26 @@ pub fn get_by_index_range(
 /// specified index to the end of the map and returns the selected data specified by
 /// `return_type`.
 #[must_use]
-pub fn get_by_index_range_from(bin: &str, index: i64, return_type: ReturnType) -> Operation<'_> {
+pub fn get_by_index_range_from(
+    bin: &str,
+    index: i64,
+    return_type: impl IntoReturnType,
+) -> Operation<'_> {
     read(
         bin,
         OpType::GetByIndexRange,
-        vec![
-            cdt::Argument::Byte(return_type as u8),
-            cdt::Argument::Int(index),
-        ],
+        vec![return_type_arg(return_type), cdt::Argument::Int(index)],
     )
 }
 
 /// Create map get by rank operation. Server selects the map item identified by rank and
 /// returns the selected data specified by `return_type`.
 #[must_use]
-pub fn get_by_rank(bin: &str, rank: i64, return_type: ReturnType) -> Operation<'_> {
+pub fn get_by_rank(bin: &str, rank: i64, return_type: impl IntoReturnType) -> Operation<'_> {
     read(
         bin,
         OpType::GetByRank,
-        vec![
-            cdt::Argument::Byte(return_type as u8),
-            cdt::Argument::Int(rank),
-        ],
+        vec![return_type_arg(return_type), cdt::Argument::Int(rank)],
     )
 }
 
@@ -697,13 +973,13 @@ pub fn get_by_rank_range(
     bin: &str,
     rank: i64,
     count: i64,
-    return_type: ReturnType,
+    return_type: impl IntoReturnType,
 ) -> Operation<'_> {
     read(
         bin,
         OpType::GetByRankRange,
         vec![
-            cdt::Argument::Byte(return_type as u8),
+            return_type_arg(return_type),
             cdt::Argument::Int(rank),
             cdt::Argument::Int(count),
         ],
@@ -714,14 +990,15 @@ pub fn get_by_rank_range(
 /// specified rank to the last ranked item and returns the selected data specified by
 /// `return_type`.
 #[must_use]
-pub fn get_by_rank_range_from(bin: &str, rank: i64, return_type: ReturnType) -> Operation<'_> {
+pub fn get_by_rank_range_from(
+    bin: &str,
+    rank: i64,
+    return_type: impl IntoReturnType,
+) -> Operation<'_> {
     read(
         bin,
         OpType::GetByRankRange,
-        vec![
-            cdt::Argument::Byte(return_type as u8),
-            cdt::Argument::Int(rank),
-        ],
+        vec![return_type_arg(return_type), cdt::Argument::Int(rank)],
     )
 }
 
@@ -742,14 +1019,14 @@ pub fn remove_by_key_relative_index_range<'a>(
     bin: &'a str,
     key: &'a Value,
     index: i64,
-    return_type: ReturnType,
+    return_type: impl IntoReturnType,
 ) -> Operation<'a> {
     write(
         bin,
         OpType::RemoveByKeyRelIndexRange,
         vec![
-            cdt::Argument::Byte(return_type as u8),
-            cdt::Argument::Value(key),
+            return_type_arg(return_type),
+            cdt::Argument::Value(Cow::Borrowed(key)),
             cdt::Argument::Int(index),
         ],
     )
@@ -773,14 +1050,14 @@ pub fn remove_by_key_relative_index_range_count<'a>(
     key: &'a Value,
     index: i64,
     count: i64,
-    return_type: ReturnType,
+    return_type: impl IntoReturnType,
 ) -> Operation<'a> {
     write(
         bin,
         OpType::RemoveByKeyRelIndexRange,
         vec![
-            cdt::Argument::Byte(return_type as u8),
-            cdt::Argument::Value(key),
+            return_type_arg(return_type),
+            cdt::Argument::Value(Cow::Borrowed(key)),
             cdt::Argument::Int(index),
             cdt::Argument::Int(count),
         ],
@@ -801,14 +1078,14 @@ pub fn remove_by_value_relative_rank_range<'a>(
     bin: &'a str,
     value: &'a Value,
     rank: i64,
-    return_type: ReturnType,
+    return_type: impl IntoReturnType,
 ) -> Operation<'a> {
     write(
         bin,
         OpType::RemoveByValueRelRankRange,
         vec![
-            cdt::Argument::Byte(return_type as u8),
-            cdt::Argument::Value(value),
+            return_type_arg(return_type),
+            cdt::Argument::Value(Cow::Borrowed(value)),
             cdt::Argument::Int(rank),
         ],
     )
@@ -829,14 +1106,14 @@ pub fn remove_by_value_relative_rank_range_count<'a>(
     value: &'a Value,
     rank: i64,
     count: i64,
-    return_type: ReturnType,
+    return_type: impl IntoReturnType,
 ) -> Operation<'a> {
     write(
         bin,
         OpType::RemoveByValueRelRankRange,
         vec![
-            cdt::Argument::Byte(return_type as u8),
-            cdt::Argument::Value(value),
+            return_type_arg(return_type),
+            cdt::Argument::Value(Cow::Borrowed(value)),
             cdt::Argument::Int(rank),
             cdt::Argument::Int(count),
         ],
@@ -849,14 +1126,14 @@ pub fn remove_by_value_relative_rank_range_count<'a>(
 pub fn get_by_key_list<'a>(
     bin: &'a str,
     keys: &'a [Value],
-    return_type: ReturnType,
+    return_type: impl IntoReturnType,
 ) -> Operation<'a> {
     read(
         bin,
         OpType::GetByKeyList,
         vec![
-            cdt::Argument::Byte(return_type as u8),
-            cdt::Argument::List(keys),
+            return_type_arg(return_type),
+            cdt::Argument::List(Cow::Borrowed(keys)),
         ],
     )
 }
@@ -867,14 +1144,14 @@ pub fn get_by_key_list<'a>(
 pub fn get_by_value_list<'a>(
     bin: &'a str,
     values: &'a [Value],
-    return_type: ReturnType,
+    return_type: impl IntoReturnType,
 ) -> Operation<'a> {
     read(
         bin,
         OpType::GetByValueList,
         vec![
-            cdt::Argument::Byte(return_type as u8),
-            cdt::Argument::List(values),
+            return_type_arg(return_type),
+            cdt::Argument::List(Cow::Borrowed(values)),
         ],
     )
 }
@@ -896,14 +1173,14 @@ pub fn get_by_key_relative_index_range<'a>(
     bin: &'a str,
     key: &'a Value,
     index: i64,
-    return_type: ReturnType,
+    return_type: impl IntoReturnType,
 ) -> Operation<'a> {
     read(
         bin,
         OpType::GetByKeyRelIndexRange,
         vec![
-            cdt::Argument::Byte(return_type as u8),
-            cdt::Argument::Value(key),
+            return_type_arg(return_type),
+            cdt::Argument::Value(Cow::Borrowed(key)),
             cdt::Argument::Int(index),
         ],
     )
@@ -927,14 +1204,14 @@ pub fn get_by_key_relative_index_range_count<'a>(
     key: &'a Value,
     index: i64,
     count: i64,
-    return_type: ReturnType,
+    return_type: impl IntoReturnType,
 ) -> Operation<'a> {
     read(
         bin,
         OpType::GetByKeyRelIndexRange,
         vec![
-            cdt::Argument::Byte(return_type as u8),
-            cdt::Argument::Value(key),
+            return_type_arg(return_type),
+            cdt::Argument::Value(Cow::Borrowed(key)),
             cdt::Argument::Int(index),
             cdt::Argument::Int(count),
         ],
@@ -955,14 +1232,14 @@ pub fn get_by_value_relative_rank_range<'a>(
     bin: &'a str,
     value: &'a Value,
     rank: i64,
-    return_type: ReturnType,
+    return_type: impl IntoReturnType,
 ) -> Operation<'a> {
     read(
         bin,
         OpType::GetByValueRelRankRange,
         vec![
-            cdt::Argument::Byte(return_type as u8),
-            cdt::Argument::Value(value),
+            return_type_arg(return_type),
+            cdt::Argument::Value(Cow::Borrowed(value)),
             cdt::Argument::Int(rank),
         ],
     )
@@ -983,16 +1260,68 @@ pub fn get_by_value_relative_rank_range_count<'a>(
     value: &'a Value,
     rank: i64,
     count: i64,
-    return_type: ReturnType,
+    return_type: impl IntoReturnType,
 ) -> Operation<'a> {
     read(
         bin,
         OpType::GetByValueRelRankRange,
         vec![
-            cdt::Argument::Byte(return_type as u8),
-            cdt::Argument::Value(value),
+            return_type_arg(return_type),
+            cdt::Argument::Value(Cow::Borrowed(value)),
             cdt::Argument::Int(rank),
             cdt::Argument::Int(count),
         ],
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{IntoReturnType, MapReturn, ReturnType};
+    use crate::{MapKey, Value};
+
+    #[test]
+    fn return_flags() {
+        assert_eq!(
+            MapReturn::count().into_return_type(),
+            ReturnType::Count as i64
+        );
+        assert_eq!(
+            MapReturn::key_values().inverted().into_return_type(),
+            ReturnType::KeyValue as i64 | ReturnType::Inverted as i64
+        );
+        assert_eq!(ReturnType::Inverted.into_return_type(), 0x10000);
+    }
+
+    #[test]
+    fn decode_results() {
+        assert_eq!(MapReturn::count().decode(Value::from(3)), Some(3));
+        assert_eq!(MapReturn::count().decode(Value::from("x")), None);
+        assert_eq!(
+            MapReturn::values().decode(Value::from("x")),
+            Some(vec![Value::from("x")])
+        );
+        assert_eq!(MapReturn::keys().decode(Value::Nil), Some(vec![]));
+        assert_eq!(
+            MapReturn::rank().decode(Value::from(vec![Value::from(2), Value::from(0)])),
+            Some(vec![2, 0])
+        );
+
+        let pairs = Value::from(vec![
+            Value::from(vec![Value::from("b"), Value::from(1)]),
+            Value::from(vec![Value::from(-1), Value::from(2)]),
+            Value::from(vec![Value::from("a"), Value::from(3)]),
+        ]);
+        assert_eq!(
+            MapReturn::key_values().decode(pairs),
+            Some(vec![
+                (MapKey::from("b"), Value::from(1)),
+                (MapKey::from(-1), Value::from(2)),
+                (MapKey::from("a"), Value::from(3)),
+            ])
+        );
+        assert_eq!(
+            MapReturn::key_values().decode(Value::from(vec![Value::from(1)])),
+            None
+        );
+    }
+}
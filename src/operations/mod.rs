@@ -5,9 +5,16 @@ pub mod cdt;
 pub mod hll;
 pub mod list;
 pub mod map;
+mod ops;
 pub mod scalar;
 
-use crate::{commands::ParticleType, msgpack, Value};
+use std::{borrow::Cow, iter, sync::Arc};
+
+pub use self::ops::Ops;
+use crate::{
+    commands::{buffer::OPERATION_HEADER_SIZE, ParticleType},
+    msgpack, BinName, ResultCode, Value,
+};
 
 #[derive(Clone, Copy)]
 pub(crate) enum OperationType {
@@ -28,38 +35,240 @@ pub(crate) enum OperationType {
     HllWrite,
 }
 
+#[derive(Clone)]
 pub(crate) enum OperationData<'a> {
     None,
-    Value(&'a Value),
+    Value(Cow<'a, Value>),
     CdtListOp(cdt::Operation<'a>),
     CdtMapOp(cdt::Operation<'a>),
     CdtBitOp(cdt::Operation<'a>),
     HllOp(cdt::Operation<'a>),
 }
 
+#[derive(Clone)]
 pub(crate) enum OperationBin<'a> {
     None,
     All,
-    Name(&'a str),
+    Name(Cow<'a, str>),
+}
+
+impl OperationBin<'_> {
+    fn is_name(&self) -> bool {
+        matches!(self, Self::Name(_))
+    }
+}
+
+/// Result of a single operation, as returned by
+/// [`Client::operate_ordered`](crate::Client::operate_ordered).
+#[derive(Clone, Debug, PartialEq)]
+pub struct OperateResult {
+    /// Name of the bin that the operation applied to.
+    pub bin: BinName,
+    /// Value returned by the operation, which is [`Value::Nil`] for operations that don't return
+    /// anything, like most writes.
+    pub value: Value,
+    /// Whether the server skipped a write because of its `NO_FAIL` write flag, like appending a
+    /// duplicate to a list with unique items.
+    ///
+    /// The server doesn't send a status for each operation, so this is judged by the returned
+    /// value. It's only detected for list and HLL writes that always return a value when they're
+    /// applied, like appends, inserts and HLL adds. For all other operations, a skipped write can't
+    /// be told apart from a [`Value::Nil`] that was actually returned, and reads that didn't find
+    /// anything return [`Value::Nil`] as well.
+    pub skipped: bool,
+}
+
+/// Count the bin entries of an operate response that belong to each operation, given the total
+/// amount of entries. The server sends them in the order of the operations.
+///
+/// Reading the record header doesn't return any entry, while reading all bins returns one entry
+/// per bin. All other operations return exactly one entry.
+fn entry_counts<'o, 'a>(
+    ops: &'o [Operation<'a>],
+    total: usize,
+) -> impl Iterator<Item = (&'o Operation<'a>, usize)> {
+    let mut remaining = total;
+
+    ops.iter().enumerate().map(move |(i, op)| {
+        let count = match (op.op, &op.bin) {
+            (OperationType::Read, OperationBin::None) => 0,
+            (OperationType::Read, OperationBin::All) => {
+                let single = ops[i + 1..]
+                    .iter()
+                    .filter(|op| !matches!(op.op, OperationType::Read) || op.bin.is_name())
+                    .count();
+                remaining.saturating_sub(single)
+            }
+            _ => 1,
+        };
+        remaining = remaining.saturating_sub(count);
+        (op, count)
+    })
+}
+
+/// Match the bin entries of an operate response to their operations.
+pub(crate) fn align_results(
+    ops: &[Operation<'_>],
+    entries: Vec<(BinName, Value)>,
+) -> Vec<OperateResult> {
+    let mut results = Vec::with_capacity(entries.len());
+    let total = entries.len();
+    let mut entries = entries.into_iter();
+
+    for (op, count) in entry_counts(ops, total) {
+        results.extend(entries.by_ref().take(count).map(|(bin, value)| {
+            let skipped = op.is_skipped(&value);
+            OperateResult {
+                bin,
+                value,
+                skipped,
+            }
+        }));
+    }
+
+    results
+}
+
+/// Flag the bin entries of an operate response that hold the result of a map operation, which
+/// selected key/value pairs through a typed [`map::MapReturn`]. These are decoded as ordered pairs,
+/// see [`map::KeyValues`].
+pub(crate) fn pair_entries(ops: &[Operation<'_>], total: usize) -> Vec<bool> {
+    entry_counts(ops, total)
+        .flat_map(|(op, count)| iter::repeat(op.key_value_pairs()).take(count))
+        .collect()
+}
+
+/// Whether the result code of a failed operate command was caused by a single operation, rather
+/// than the record as a whole.
+pub(crate) const fn is_operation_error(code: ResultCode) -> bool {
+    matches!(
+        code,
+        ResultCode::ParameterError
+            | ResultCode::BinExistsError
+            | ResultCode::BinTypeError
+            | ResultCode::BinNotFound
+            | ResultCode::ElementNotFound
+            | ResultCode::ElementExists
+            | ResultCode::OpNotApplicable
+    )
+}
+
+/// Locate the operation that caused an operation error, which is only possible if it's the only
+/// one that can fail. Plain bin and record header reads never fail on their own.
+pub(crate) fn failed_index(ops: &[Operation<'_>]) -> Option<usize> {
+    let mut candidates = ops
+        .iter()
+        .enumerate()
+        .filter(|(_, op)| !matches!(op.op, OperationType::Read))
+        .map(|(i, _)| i);
+
+    match (candidates.next(), candidates.next()) {
+        (Some(index), None) => Some(index),
+        _ => None,
+    }
 }
 
 /// Database operation definition. This data type is used in the client's `operate()` method.
+///
+/// Operations created by the functions in the submodules borrow their bin names, values and
+/// context. Use [`Operation::into_owned`] to detach an operation from those borrows, so it can be
+/// stored and executed later.
+#[derive(Clone)]
 pub struct Operation<'a> {
     // OpType determines type of operation.
     pub(crate) op: OperationType,
     // CDT context for nested types
-    pub(crate) ctx: &'a [cdt::Context],
+    pub(crate) ctx: Cow<'a, [cdt::Context]>,
     // BinName (Optional) determines the name of bin used in operation.
     pub(crate) bin: OperationBin<'a>,
     // BinData determines bin value used in operation.
     pub(crate) data: OperationData<'a>,
+    // Full wire encoding of the operation, if it was prepared for repeated use.
+    pub(crate) encoded: Option<Arc<[u8]>>,
 }
 
 impl<'a> Operation<'a> {
+    // Returns true if the operation only reads data
+    pub(crate) fn is_read(&self) -> bool {
+        matches!(
+            self.op,
+            OperationType::Read
+                | OperationType::CdtRead
+                | OperationType::BitRead
+                | OperationType::HllRead
+        )
+    }
+
+    // Returns true if the operation is a map operation that selects key/value pairs through a
+    // typed map return.
+    pub(crate) fn key_value_pairs(&self) -> bool {
+        matches!(&self.data, OperationData::CdtMapOp(op) if op.key_value_pairs())
+    }
+
+    // Returns true if the server skipped the operation because of its NO_FAIL write flag, judging
+    // by the value it returned. Only operations that always return a value when they're applied
+    // can be detected this way.
+    pub(crate) fn is_skipped(&self, value: &Value) -> bool {
+        if *value != Value::Nil {
+            return false;
+        }
+
+        match &self.data {
+            OperationData::CdtListOp(op) => op.no_fail() && list::returns_value(op.op),
+            OperationData::HllOp(op) => op.no_fail() && hll::returns_value(op.op),
+            _ => false,
+        }
+    }
+
+    // Returns the name of the bin that the operation targets, if any
+    pub(crate) fn bin_name(&self) -> Option<&str> {
+        match &self.bin {
+            OperationBin::Name(name) => Some(name),
+            OperationBin::None | OperationBin::All => None,
+        }
+    }
+
+    // Returns true if the operation writes a boolean value to a bin
+    pub(crate) fn writes_bool(&self) -> bool {
+        matches!(&self.data, OperationData::Value(value) if matches!(**value, Value::Bool(_)))
+    }
+
+    // Copy of the operation that writes booleans as integers, for servers without boolean support
+    pub(crate) fn without_bool(&self) -> Self {
+        let mut op = self.clone();
+        if let OperationData::Value(value) = &mut op.data {
+            if let Value::Bool(b) = **value {
+                *value = Cow::Owned(Value::Int(b.into()));
+                op.encoded = None;
+            }
+        }
+        op
+    }
+
+    // Copy of the operation that is skipped by the server instead of failing the whole command,
+    // for operations that support write flags
+    pub(crate) fn with_no_fail(&self) -> Self {
+        let mut op = self.clone();
+        match &mut op.data {
+            OperationData::CdtListOp(cdt_op)
+            | OperationData::CdtBitOp(cdt_op)
+            | OperationData::HllOp(cdt_op) => {
+                *cdt_op = cdt_op.with_no_fail();
+                op.encoded = None;
+            }
+            OperationData::None | OperationData::Value(_) | OperationData::CdtMapOp(_) => {}
+        }
+        op
+    }
+
     #[must_use]
     pub(crate) fn estimate_size(&self) -> usize {
+        if let Some(encoded) = &self.encoded {
+            return encoded.len() - OPERATION_HEADER_SIZE;
+        }
+
         let mut size: usize = 0;
-        size += match self.bin {
+        size += match &self.bin {
             OperationBin::Name(bin) => bin.len(),
             OperationBin::None | OperationBin::All => 0,
         };
@@ -69,13 +278,17 @@ impl<'a> Operation<'a> {
             OperationData::CdtListOp(cdt_op)
             | OperationData::CdtMapOp(cdt_op)
             | OperationData::CdtBitOp(cdt_op)
-            | OperationData::HllOp(cdt_op) => cdt_op.estimate_size(self.ctx),
+            | OperationData::HllOp(cdt_op) => cdt_op.estimate_size(&self.ctx),
         };
 
         size
     }
 
     pub(crate) fn write_to(&self, w: &mut impl msgpack::Write) -> usize {
+        if let Some(encoded) = &self.encoded {
+            return w.write_bytes(encoded);
+        }
+
         let mut size: usize = 0;
 
         // remove the header size from the estimate
@@ -89,7 +302,7 @@ impl<'a> Operation<'a> {
                 size += self.write_op_header_to(w, ParticleType::Null as u8);
             }
             OperationData::Value(value) => {
-                size += self.write_op_header_to(w, value.particle_type() as u8);
+                size += self.write_op_header_to(w, value.particle_type());
                 size += value.write_to(w);
             }
             OperationData::CdtListOp(cdt_op)
@@ -97,7 +310,7 @@ impl<'a> Operation<'a> {
             | OperationData::CdtBitOp(cdt_op)
             | OperationData::HllOp(cdt_op) => {
                 size += self.write_op_header_to(w, cdt::Operation::particle_type() as u8);
-                size += cdt_op.write_to(w, self.ctx);
+                size += cdt_op.write_to(w, &self.ctx);
             }
         };
 
@@ -107,7 +320,7 @@ impl<'a> Operation<'a> {
     fn write_op_header_to(&self, w: &mut impl msgpack::Write, particle_type: u8) -> usize {
         let mut size = w.write_u8(particle_type);
         size += w.write_u8(0);
-        match self.bin {
+        match &self.bin {
             OperationBin::Name(bin) => {
                 size += w.write_u8(bin.len() as u8);
                 size += w.write_str(bin);
@@ -121,8 +334,227 @@ impl<'a> Operation<'a> {
 
     /// Set the context of the operation. Required for nested structures
     #[must_use]
-    pub const fn set_context(mut self, ctx: &'a [cdt::Context]) -> Operation<'a> {
-        self.ctx = ctx;
+    pub fn set_context(mut self, ctx: impl Into<Cow<'a, [cdt::Context]>>) -> Operation<'a> {
+        self.ctx = ctx.into();
+        self.encoded = None;
         self
     }
+
+    /// Prepare this operation for repeated use, by encoding it once and keeping the result.
+    ///
+    /// Every command that includes the operation then copies the encoded bytes, instead of
+    /// estimating the size and encoding the operation again. This helps in hot paths that send
+    /// the same operation over and over, like appending the same value to a list on many
+    /// different keys. The operation is converted into an owned one with [`Self::into_owned`],
+    /// so it can be kept around.
+    ///
+    /// ```rust
+    /// use windpike::{
+    ///     operations::{list, Operation},
+    ///     Value,
+    /// };
+    ///
+    /// let value = Value::from("visited");
+    /// let ops: Vec<Operation<'static>> =
+    ///     vec![list::append(list::Policy::default(), "history", &value).prepare()];
+    /// ```
+    #[must_use]
+    pub fn prepare(self) -> Operation<'static> {
+        let mut op = self.into_owned();
+        if op.encoded.is_none() {
+            let mut encoded = Vec::with_capacity(OPERATION_HEADER_SIZE + op.estimate_size());
+            op.write_to(&mut encoded);
+            op.encoded = Some(encoded.into());
+        }
+        op
+    }
+
+    /// Convert this operation into one that owns all its data, by cloning any borrowed bin names,
+    /// values and context.
+    ///
+    /// ```rust
+    /// use windpike::{
+    ///     operations::{list, Operation},
+    ///     Value,
+    /// };
+    ///
+    /// fn append_op(bin: &str, value: i64) -> Operation<'static> {
+    ///     let value = Value::from(value);
+    ///     list::append(list::Policy::default(), bin, &value).into_owned()
+    /// }
+    ///
+    /// let ops = vec![append_op("a", 1), append_op("b", 2)];
+    /// assert_eq!(ops.len(), 2);
+    /// ```
+    #[must_use]
+    pub fn into_owned(self) -> Operation<'static> {
+        Operation {
+            op: self.op,
+            ctx: Cow::Owned(self.ctx.into_owned()),
+            bin: match self.bin {
+                OperationBin::None => OperationBin::None,
+                OperationBin::All => OperationBin::All,
+                OperationBin::Name(bin) => OperationBin::Name(Cow::Owned(bin.into_owned())),
+            },
+            data: match self.data {
+                OperationData::None => OperationData::None,
+                OperationData::Value(value) => OperationData::Value(Cow::Owned(value.into_owned())),
+                OperationData::CdtListOp(op) => OperationData::CdtListOp(op.into_owned()),
+                OperationData::CdtMapOp(op) => OperationData::CdtMapOp(op.into_owned()),
+                OperationData::CdtBitOp(op) => OperationData::CdtBitOp(op.into_owned()),
+                OperationData::HllOp(op) => OperationData::HllOp(op.into_owned()),
+            },
+            encoded: self.encoded,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{align_results, cdt, failed_index, list, map, pair_entries, scalar, OperateResult};
+    use crate::{msgpack::encoder, Bin, Value};
+
+    #[test]
+    fn context_create_flags() {
+        let ctx = [
+            cdt::Context::list_index(1),
+            cdt::Context::list_index_create(1, list::OrderType::Unordered, true),
+            cdt::Context::list_index_create(-1, list::OrderType::Ordered, false),
+            cdt::Context::map_key_create(Value::from(1), map::OrderType::KeyOrdered),
+        ];
+
+        let mut buf = Vec::new();
+        encoder::pack_context(&mut buf, &ctx);
+        assert_eq!(
+            buf,
+            [
+                0x98, // array with 8 entries
+                0x10, 0x01, // list index
+                0xcc, 0x90, 0x01, // list index, create unordered and padded
+                0xcc, 0xd0, 0xff, // list index, create ordered
+                0xcc, 0xa2, 0x01, // map key, create key-ordered
+            ]
+        );
+    }
+
+    #[test]
+    fn align_operate_results() {
+        let bin = Bin::new("a", 1);
+        let value = Value::from(1);
+        let ops = [
+            scalar::put(&bin),
+            scalar::get_header(),
+            list::append(list::Policy::default(), "l", &value),
+            scalar::get(),
+            scalar::get_bin("missing"),
+            list::get_by_index("l", 5, list::ReturnType::Values),
+        ];
+        let entries = vec![
+            ("a".into(), Value::Nil),
+            ("l".into(), Value::from(1)),
+            ("a".into(), Value::from(1)),
+            ("l".into(), Value::from(vec![Value::from(1)])),
+            ("missing".into(), Value::Nil),
+            ("l".into(), Value::Nil),
+        ];
+
+        let results = align_results(&ops, entries);
+        let bins = results
+            .iter()
+            .map(|OperateResult { bin, .. }| &**bin)
+            .collect::<Vec<_>>();
+
+        assert_eq!(bins, ["a", "l", "a", "l", "missing", "l"]);
+        assert_eq!(results[3].value, Value::from(vec![Value::from(1)]));
+        assert!(results.iter().all(|result| !result.skipped));
+    }
+
+    #[test]
+    fn skipped_no_fail_writes() {
+        let value = Value::from(1);
+        let no_fail = list::Policy::new(
+            list::OrderType::Unordered,
+            list::WriteFlags::ADD_UNIQUE | list::WriteFlags::NO_FAIL,
+        );
+        let ops = [
+            list::append(no_fail, "l", &value),
+            list::append(no_fail, "l", &value),
+            list::append(list::Policy::default(), "l", &value),
+            list::set("l", 0, &value).unwrap(),
+        ];
+        let entries = vec![
+            ("l".into(), Value::from(1)),
+            ("l".into(), Value::Nil),
+            ("l".into(), Value::Nil),
+            ("l".into(), Value::Nil),
+        ];
+
+        let skipped = align_results(&ops, entries)
+            .into_iter()
+            .map(|OperateResult { skipped, .. }| skipped)
+            .collect::<Vec<_>>();
+
+        assert_eq!(skipped, [false, true, false, false]);
+    }
+
+    #[test]
+    fn continue_on_error_flags() {
+        let bin = Bin::new("a", 1);
+        let value = Value::from(1);
+        let ops = [
+            list::append(list::Policy::default(), "l", &value).with_no_fail(),
+            scalar::put(&bin).with_no_fail(),
+        ];
+        let entries = vec![("l".into(), Value::Nil), ("a".into(), Value::Nil)];
+
+        let skipped = align_results(&ops, entries)
+            .into_iter()
+            .map(|OperateResult { skipped, .. }| skipped)
+            .collect::<Vec<_>>();
+
+        assert_eq!(skipped, [true, false]);
+    }
+
+    #[test]
+    fn locate_failed_operation() {
+        let value = Value::from(1);
+        let append = list::append(list::Policy::default(), "l", &value);
+
+        assert_eq!(
+            Some(1),
+            failed_index(&[scalar::get_bin("a"), append.clone(), scalar::get()])
+        );
+        assert_eq!(None, failed_index(&[append.clone(), append]));
+        assert_eq!(None, failed_index(&[scalar::get()]));
+    }
+
+    #[test]
+    fn key_value_pair_entries() {
+        let ops = [
+            map::get_by_index("m", 0, map::MapReturn::key_values()),
+            scalar::get(),
+            map::get_by_index("m", 0, map::ReturnType::KeyValue),
+            map::get_by_rank("m", 0, map::MapReturn::key_values().inverted()),
+        ];
+
+        assert_eq!(vec![true, false, false, false, true], pair_entries(&ops, 5));
+    }
+
+    #[test]
+    fn prepared_operation() {
+        let value = Value::from(1);
+        let op = list::append(list::Policy::default(), "l", &value);
+        let prepared = op.clone().prepare();
+        assert!(prepared.encoded.is_some());
+
+        let (mut expected, mut buf) = (Vec::new(), Vec::new());
+        op.write_to(&mut expected);
+        prepared.write_to(&mut buf);
+        assert_eq!(expected, buf);
+        assert_eq!(op.estimate_size(), prepared.estimate_size());
+
+        assert!(prepared.with_no_fail().encoded.is_none());
+        let ctx = [cdt::Context::list_index(0)];
+        assert!(prepared.set_context(&ctx).encoded.is_none());
+    }
 }
@@ -0,0 +1,238 @@
+//! Fluent builder for a list of database operations that owns all the involved data.
+
+use std::borrow::Cow;
+
+use super::{list, map, scalar, Operation, OperationBin, OperationData, OperationType};
+use crate::Value;
+
+/// Builder for a list of operations, that can be passed to the client's `operate()` method.
+///
+/// Unlike the constructor functions in the sibling modules, the builder takes ownership of all bin
+/// names and values. That makes it possible to assemble operations in helper functions and return
+/// them, without having to keep the referenced values alive somewhere else. Operations that have
+/// no dedicated builder method can be added through [`Ops::op`].
+///
+/// All list and map operations use the respective default policy.
+///
+/// # Example
+///
+/// ```rust
+/// use windpike::{operations::Ops, policies::WritePolicy, Client, Key, Value};
+///
+/// fn counter_ops(name: &str) -> Ops {
+///     Ops::new()
+///         .add(name, 1)
+///         .list_append("history", name)
+///         .read(name)
+/// }
+///
+/// # async fn run(client: Client) {
+/// let key = Key::new("test", "test", "mykey");
+/// let ops = counter_ops("visits").build();
+/// let record = client
+///     .operate(&WritePolicy::default(), &key, &ops)
+///     .await
+///     .unwrap();
+/// println!("{:?}", record.bins.get("visits"));
+/// # }
+/// ```
+#[derive(Default)]
+pub struct Ops {
+    ops: Vec<Operation<'static>>,
+}
+
+impl Ops {
+    /// Create a new, empty operation builder.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self { ops: Vec::new() }
+    }
+
+    fn push(mut self, op: Operation<'static>) -> Self {
+        self.ops.push(op);
+        self
+    }
+
+    fn scalar(self, op: OperationType, bin: &str, value: Value) -> Self {
+        self.push(Operation {
+            op,
+            ctx: Cow::Borrowed(&[]),
+            bin: OperationBin::Name(Cow::Owned(bin.to_owned())),
+            data: OperationData::Value(Cow::Owned(value)),
+            encoded: None,
+        })
+    }
+
+    /// Add any operation, converting it into an owned one with [`Operation::into_owned`].
+    #[must_use]
+    pub fn op(self, op: Operation<'_>) -> Self {
+        self.push(op.into_owned())
+    }
+
+    /// Read all record bins. See [`scalar::get`].
+    #[must_use]
+    pub fn get(self) -> Self {
+        self.push(scalar::get())
+    }
+
+    /// Read the record header only. See [`scalar::get_header`].
+    #[must_use]
+    pub fn get_header(self) -> Self {
+        self.push(scalar::get_header())
+    }
+
+    /// Read a single bin. See [`scalar::get_bin`].
+    #[must_use]
+    pub fn read(self, bin: impl AsRef<str>) -> Self {
+        self.op(scalar::get_bin(bin.as_ref()))
+    }
+
+    /// Set the value of a bin. See [`scalar::put`].
+    #[must_use]
+    pub fn put(self, bin: impl AsRef<str>, value: impl Into<Value>) -> Self {
+        self.scalar(OperationType::Write, bin.as_ref(), value.into())
+    }
+
+    /// Add an integer to the value of a bin. See [`scalar::add`].
+    #[must_use]
+    pub fn add(self, bin: impl AsRef<str>, value: impl Into<Value>) -> Self {
+        self.scalar(OperationType::Incr, bin.as_ref(), value.into())
+    }
+
+    /// Append a string or blob to the value of a bin. See [`scalar::append`].
+    #[must_use]
+    pub fn append(self, bin: impl AsRef<str>, value: impl Into<Value>) -> Self {
+        self.scalar(OperationType::Append, bin.as_ref(), value.into())
+    }
+
+    /// Prepend a string or blob to the value of a bin. See [`scalar::prepend`].
+    #[must_use]
+    pub fn prepend(self, bin: impl AsRef<str>, value: impl Into<Value>) -> Self {
+        self.scalar(OperationType::Prepend, bin.as_ref(), value.into())
+    }
+
+    /// Reset the record's time to expiration. See [`scalar::touch`].
+    #[must_use]
+    pub fn touch(self) -> Self {
+        self.push(scalar::touch())
+    }
+
+    /// Delete the record. See [`scalar::delete`].
+    #[must_use]
+    pub fn delete(self) -> Self {
+        self.push(scalar::delete())
+    }
+
+    /// Append a value to the end of a list bin. See [`list::append`].
+    #[must_use]
+    pub fn list_append(self, bin: impl AsRef<str>, value: impl Into<Value>) -> Self {
+        self.op(list::append(
+            list::Policy::default(),
+            bin.as_ref(),
+            &value.into(),
+        ))
+    }
+
+    /// Insert a value at the given index of a list bin. See [`list::insert`].
+    #[must_use]
+    pub fn list_insert(self, bin: impl AsRef<str>, index: i64, value: impl Into<Value>) -> Self {
+        self.op(list::insert(
+            list::Policy::default(),
+            bin.as_ref(),
+            index,
+            &value.into(),
+        ))
+    }
+
+    /// Remove and return the item at the given index of a list bin. See [`list::pop`].
+    #[must_use]
+    pub fn list_pop(self, bin: impl AsRef<str>, index: i64) -> Self {
+        self.op(list::pop(bin.as_ref(), index))
+    }
+
+    /// Return the item at the given index of a list bin. See [`list::get`].
+    #[must_use]
+    pub fn list_get(self, bin: impl AsRef<str>, index: i64) -> Self {
+        self.op(list::get(bin.as_ref(), index))
+    }
+
+    /// Return the size of a list bin. See [`list::size`].
+    #[must_use]
+    pub fn list_size(self, bin: impl AsRef<str>) -> Self {
+        self.op(list::size(bin.as_ref()))
+    }
+
+    /// Remove all items of a list bin. See [`list::clear`].
+    #[must_use]
+    pub fn list_clear(self, bin: impl AsRef<str>) -> Self {
+        self.op(list::clear(bin.as_ref()))
+    }
+
+    /// Write a key/value item to a map bin. See [`map::put`].
+    #[must_use]
+    pub fn map_put(
+        self,
+        bin: impl AsRef<str>,
+        key: impl Into<Value>,
+        value: impl Into<Value>,
+    ) -> Self {
+        self.op(map::put(
+            map::Policy::default(),
+            bin.as_ref(),
+            &key.into(),
+            &value.into(),
+        ))
+    }
+
+    /// Select a map item by key. See [`map::get_by_key`].
+    #[must_use]
+    pub fn map_get_by_key(
+        self,
+        bin: impl AsRef<str>,
+        key: impl Into<Value>,
+        return_type: impl map::IntoReturnType,
+    ) -> Self {
+        self.op(map::get_by_key(bin.as_ref(), &key.into(), return_type))
+    }
+
+    /// Remove a map item by key. See [`map::remove_by_key`].
+    #[must_use]
+    pub fn map_remove_by_key(
+        self,
+        bin: impl AsRef<str>,
+        key: impl Into<Value>,
+        return_type: impl map::IntoReturnType,
+    ) -> Self {
+        self.op(map::remove_by_key(bin.as_ref(), &key.into(), return_type))
+    }
+
+    /// Return the size of a map bin. See [`map::size`].
+    #[must_use]
+    pub fn map_size(self, bin: impl AsRef<str>) -> Self {
+        self.op(map::size(bin.as_ref()))
+    }
+
+    /// Remove all items of a map bin. See [`map::clear`].
+    #[must_use]
+    pub fn map_clear(self, bin: impl AsRef<str>) -> Self {
+        self.op(map::clear(bin.as_ref()))
+    }
+
+    /// Number of operations added to the builder so far.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.ops.len()
+    }
+
+    /// Whether no operations were added to the builder yet.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.ops.is_empty()
+    }
+
+    /// Create the list of operations.
+    #[must_use]
+    pub fn build(self) -> Vec<Operation<'static>> {
+        self.ops
+    }
+}
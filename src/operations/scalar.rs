@@ -1,5 +1,7 @@
 //! String/number bin operations. Create operations used by the client's `operate()` method.
 
+use std::borrow::Cow;
+
 use crate::{
     operations::{Operation, OperationBin, OperationData, OperationType},
     Bin,
@@ -10,9 +12,10 @@ use crate::{
 pub const fn get<'a>() -> Operation<'a> {
     Operation {
         op: OperationType::Read,
-        ctx: &[],
+        ctx: Cow::Borrowed(&[]),
         bin: OperationBin::All,
         data: OperationData::None,
+        encoded: None,
     }
 }
 
@@ -21,9 +24,10 @@ pub const fn get<'a>() -> Operation<'a> {
 pub const fn get_header<'a>() -> Operation<'a> {
     Operation {
         op: OperationType::Read,
-        ctx: &[],
+        ctx: Cow::Borrowed(&[]),
         bin: OperationBin::None,
         data: OperationData::None,
+        encoded: None,
     }
 }
 
@@ -32,9 +36,10 @@ pub const fn get_header<'a>() -> Operation<'a> {
 pub const fn get_bin(bin_name: &str) -> Operation<'_> {
     Operation {
         op: OperationType::Read,
-        ctx: &[],
-        bin: OperationBin::Name(bin_name),
+        ctx: Cow::Borrowed(&[]),
+        bin: OperationBin::Name(Cow::Borrowed(bin_name)),
         data: OperationData::None,
+        encoded: None,
     }
 }
 
@@ -43,31 +48,34 @@ pub const fn get_bin(bin_name: &str) -> Operation<'_> {
 pub const fn put<'a>(bin: &'a Bin<'_>) -> Operation<'a> {
     Operation {
         op: OperationType::Write,
-        ctx: &[],
-        bin: OperationBin::Name(bin.name),
-        data: OperationData::Value(&bin.value),
+        ctx: Cow::Borrowed(&[]),
+        bin: OperationBin::Name(Cow::Borrowed(bin.name)),
+        data: OperationData::Value(Cow::Borrowed(&bin.value)),
+        encoded: None,
     }
 }
 
-/// Create string append database operation.
+/// Create string or blob append database operation.
 #[must_use]
 pub const fn append<'a>(bin: &'a Bin<'_>) -> Operation<'a> {
     Operation {
         op: OperationType::Append,
-        ctx: &[],
-        bin: OperationBin::Name(bin.name),
-        data: OperationData::Value(&bin.value),
+        ctx: Cow::Borrowed(&[]),
+        bin: OperationBin::Name(Cow::Borrowed(bin.name)),
+        data: OperationData::Value(Cow::Borrowed(&bin.value)),
+        encoded: None,
     }
 }
 
-/// Create string prepend database operation.
+/// Create string or blob prepend database operation.
 #[must_use]
 pub const fn prepend<'a>(bin: &'a Bin<'_>) -> Operation<'a> {
     Operation {
         op: OperationType::Prepend,
-        ctx: &[],
-        bin: OperationBin::Name(bin.name),
-        data: OperationData::Value(&bin.value),
+        ctx: Cow::Borrowed(&[]),
+        bin: OperationBin::Name(Cow::Borrowed(bin.name)),
+        data: OperationData::Value(Cow::Borrowed(&bin.value)),
+        encoded: None,
     }
 }
 
@@ -76,9 +84,10 @@ pub const fn prepend<'a>(bin: &'a Bin<'_>) -> Operation<'a> {
 pub const fn add<'a>(bin: &'a Bin<'_>) -> Operation<'a> {
     Operation {
         op: OperationType::Incr,
-        ctx: &[],
-        bin: OperationBin::Name(bin.name),
-        data: OperationData::Value(&bin.value),
+        ctx: Cow::Borrowed(&[]),
+        bin: OperationBin::Name(Cow::Borrowed(bin.name)),
+        data: OperationData::Value(Cow::Borrowed(&bin.value)),
+        encoded: None,
     }
 }
 
@@ -87,9 +96,10 @@ pub const fn add<'a>(bin: &'a Bin<'_>) -> Operation<'a> {
 pub const fn touch<'a>() -> Operation<'a> {
     Operation {
         op: OperationType::Touch,
-        ctx: &[],
+        ctx: Cow::Borrowed(&[]),
         bin: OperationBin::None,
         data: OperationData::None,
+        encoded: None,
     }
 }
 
@@ -98,8 +108,9 @@ pub const fn touch<'a>() -> Operation<'a> {
 pub const fn delete<'a>() -> Operation<'a> {
     Operation {
         op: OperationType::Delete,
-        ctx: &[],
+        ctx: Cow::Borrowed(&[]),
         bin: OperationBin::None,
         data: OperationData::None,
+        encoded: None,
     }
 }
@@ -1,19 +1,97 @@
 //! Policies that allow to adjust the behavior of various operations.
 
-use std::{collections::HashMap, option::Option};
+use std::{collections::HashMap, option::Option, sync::Arc};
 
+use rand::Rng;
 use tokio::time::{Duration, Instant};
 
-use crate::commands::{self, CommandError};
+use crate::{
+    commands::{self, CommandError},
+    expressions::Expression,
+    interceptor::CommandInterceptor,
+};
+
+/// Generate builder methods for the fields of the [`BasePolicy`], which is accessed through the
+/// builder's `base` method.
+macro_rules! base_setters {
+    () => {
+        /// Set the [`BasePolicy::consistency_level`].
+        #[must_use]
+        pub fn consistency_level(mut self, consistency_level: ConsistencyLevel) -> Self {
+            self.base().consistency_level = consistency_level;
+            self
+        }
+
+        /// Set the [`BasePolicy::timeout`].
+        #[must_use]
+        pub fn timeout(mut self, timeout: Duration) -> Self {
+            self.base().timeout = timeout;
+            self
+        }
+
+        /// Set the [`BasePolicy::max_retries`].
+        #[must_use]
+        pub fn max_retries(mut self, max_retries: Option<usize>) -> Self {
+            self.base().max_retries = max_retries;
+            self
+        }
+
+        /// Set the [`BasePolicy::sleep_between_retries`].
+        #[must_use]
+        pub fn sleep_between_retries(mut self, sleep_between_retries: Duration) -> Self {
+            self.base().sleep_between_retries = sleep_between_retries;
+            self
+        }
+
+        /// Set the [`BasePolicy::read_mode_sc`].
+        #[must_use]
+        pub fn read_mode_sc(mut self, read_mode_sc: ReadModeSc) -> Self {
+            self.base().read_mode_sc = read_mode_sc;
+            self
+        }
+
+        /// Set the [`BasePolicy::send_key`].
+        #[must_use]
+        pub fn send_key(mut self, send_key: bool) -> Self {
+            self.base().send_key = send_key;
+            self
+        }
+    };
+}
+
+/// Generate a builder method that sets a single field of the policy.
+macro_rules! setter {
+    ($policy:ident, $field:ident: $ty:ty) => {
+        #[doc = concat!("Set the [`", stringify!($policy), "::", stringify!($field), "`].")]
+        #[must_use]
+        pub fn $field(mut self, $field: $ty) -> Self {
+            self.0.$field = $field;
+            self
+        }
+    };
+}
+
+/// Policy for read operations, which only consists of the [`BasePolicy`] parameters.
+///
+/// ```
+/// # use windpike::policies::{ReadModeSc, ReadPolicy};
+/// let policy = ReadPolicy::linearizable();
+/// assert_eq!(ReadModeSc::Linearize, policy.read_mode_sc);
+/// ```
+pub type ReadPolicy = BasePolicy;
 
 /// Common parameters used for read operations and acts as base for most of the other policies.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Eq, PartialEq)]
 pub struct BasePolicy {
     /// Level of consistency guarantee for read operations that determines how many replicas are
     /// required to contain the same data set.
     pub consistency_level: ConsistencyLevel,
     /// The duration after which the transaction is cancelled (including retries).
     ///
+    /// It bounds the whole command, including waits for rate limits and pooled connections,
+    /// sending the request and reading the response. Scans are the exception, as they stream
+    /// records for as long as the consumer keeps up, so only sending their request is bounded.
+    ///
     /// This value is sent to the server as well, so it will have an effect on both sides of the
     /// connection (in case the client doesn't properly cancel the operation).
     pub timeout: Duration,
@@ -24,6 +102,9 @@ pub struct BasePolicy {
     /// Send the user key on read and write operations. By default, only the hashed version is sent
     /// to reduce the amount of data transferred.
     pub send_key: bool,
+    /// Consistency guarantee of read operations in namespaces with strong consistency enabled.
+    /// Ignored for other namespaces.
+    pub read_mode_sc: ReadModeSc,
 }
 
 impl BasePolicy {
@@ -36,6 +117,22 @@ impl BasePolicy {
     /// Default value for the [`Self::timeout`] parameter.
     pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
 
+    /// Create a builder, starting from the default values.
+    #[must_use]
+    pub fn builder() -> BasePolicyBuilder {
+        BasePolicyBuilder::default()
+    }
+
+    /// Policy for reads that always observe the latest committed write in strong consistency
+    /// namespaces. See [`ReadModeSc::Linearize`].
+    #[must_use]
+    pub fn linearizable() -> Self {
+        Self {
+            read_mode_sc: ReadModeSc::Linearize,
+            ..Self::default()
+        }
+    }
+
     /// Deadline for current transaction based on specified timeout.
     #[must_use]
     pub(crate) fn deadline(&self) -> Option<Instant> {
@@ -51,6 +148,7 @@ impl Default for BasePolicy {
             sleep_between_retries: Self::DEFAULT_SLEEP_BETWEEN_RETRIES,
             consistency_level: ConsistencyLevel::default(),
             send_key: Self::DEFAULT_SEND_KEY,
+            read_mode_sc: ReadModeSc::default(),
         }
     }
 }
@@ -61,8 +159,39 @@ impl AsRef<Self> for BasePolicy {
     }
 }
 
+/// Builder for the [`BasePolicy`], starting from its defaults.
+///
+/// ```
+/// # use std::time::Duration;
+/// # use windpike::policies::BasePolicy;
+/// let policy = BasePolicy::builder()
+///     .timeout(Duration::from_secs(5))
+///     .send_key(true)
+///     .build();
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct BasePolicyBuilder(BasePolicy);
+
+impl BasePolicyBuilder {
+    base_setters!();
+
+    /// Create the policy.
+    #[must_use]
+    pub fn build(self) -> BasePolicy {
+        self.0
+    }
+
+    fn base(&mut self) -> &mut BasePolicy {
+        &mut self.0
+    }
+}
+
 /// Level which defines the amount of replicas to contact on read operations to ensure the
 /// consistency of the retrieved data.
+///
+/// This controls the duplicate resolution of the server. While partitions migrate, several nodes
+/// can hold diverging versions of a record, and [`Self::All`] makes the server resolve them before
+/// answering. Namespaces with strong consistency use the [`ReadModeSc`] instead.
 #[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
 pub enum ConsistencyLevel {
     /// Contact only a single replica to retrieve the data. **This is the default**.
@@ -72,8 +201,34 @@ pub enum ConsistencyLevel {
     All = 1,
 }
 
+/// Read consistency guarantee for namespaces with strong consistency (SC) enabled.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum ReadModeSc {
+    /// Reads are guaranteed to observe all writes of the same client, but not necessarily the
+    /// latest writes of other clients. **This is the default**.
+    #[default]
+    Session,
+    /// Reads always observe the latest committed write across all clients, at the cost of extra
+    /// round trips between the nodes.
+    Linearize,
+    /// Reads may be served by a replica and return stale data, but are allowed while the master
+    /// is unavailable.
+    AllowReplica,
+    /// Reads may return stale data even from unavailable partitions, for example during a
+    /// network split.
+    AllowUnavailable,
+}
+
+impl ReadModeSc {
+    /// Whether reads may be served by replicas, instead of only the master of a partition.
+    pub(crate) const fn allows_replica(self) -> bool {
+        matches!(self, Self::AllowReplica | Self::AllowUnavailable)
+    }
+}
+
 /// Parameters for all batch operations.
 #[derive(Clone, Debug)]
+#[allow(clippy::struct_excessive_bools)]
 pub struct BatchPolicy {
     /// The base policy that this one extends.
     pub base_policy: BasePolicy,
@@ -86,18 +241,64 @@ pub struct BatchPolicy {
     /// This setting can improve performance for small sized records, but can possibly introduce
     /// unfair processing of received commands.
     pub allow_inline: bool,
+    /// Allow the server to process the batch request immediately on its receiving thread, for
+    /// namespaces that store their data on SSD. Like [`Self::allow_inline`], but for namespaces
+    /// that aren't in memory only.
+    ///
+    /// Disabled by default, as inline processing of device reads can block the receiving thread
+    /// for a long time. Only enable this when the namespace is backed by very fast devices.
+    pub allow_inline_ssd: bool,
+    /// Attempt all keys of the batch, regardless of errors for individual keys. If disabled, the
+    /// server stops processing the batch on the first key error (except for records that don't
+    /// exist) and fails the whole request.
+    ///
+    /// This flag, like [`Self::allow_inline_ssd`], is understood by servers 6.0 and later. Older
+    /// servers read the whole flags byte as plain allow-inline boolean, so any set flag enables
+    /// inline processing there, even if [`Self::allow_inline`] is disabled.
+    pub respond_all_keys: bool,
     /// For every key in the batch, send the set name as well.
     ///
     /// This is only required when authentication is enabled and per-set security roles are
     /// defined.
     pub send_set_name: bool,
+    /// Maximum amount of keys in the request to a single node, for which the request is flagged as
+    /// short query. The server processes these inline, regardless of [`Self::allow_inline`], and
+    /// skips monitoring them. A value of _zero_ disables the flag.
+    ///
+    /// This setting is meant for very small batches, where the scheduling overhead outweighs the
+    /// time spent reading the records.
+    pub short_query_threshold: usize,
+    /// Maximum amount of keys in a single request to a node. Larger batches are split into
+    /// multiple requests, whose results are combined again, the same way as batches that exceed
+    /// the maximum request size. A value of _zero_ disables the limit.
+    ///
+    /// This allows to read very large batches at once, without exceeding the server's limit for
+    /// the amount of keys in a single request.
+    pub max_keys_per_request: usize,
 }
 
 impl BatchPolicy {
     /// Default value for the [`Self::allow_inline`] parameter.
     pub const DEFAULT_ALLOW_INLINE: bool = true;
+    /// Default value for the [`Self::allow_inline_ssd`] parameter.
+    pub const DEFAULT_ALLOW_INLINE_SSD: bool = false;
+    /// Default value for the [`Self::max_keys_per_request`] parameter.
+    pub const DEFAULT_MAX_KEYS_PER_REQUEST: usize = 5000;
+    /// Default value for the [`Self::respond_all_keys`] parameter.
+    ///
+    /// Enabling it by default changes the batch flags that are sent with the default policy from
+    /// `0b001` (allow inline) to `0b101` (allow inline and respond all keys).
+    pub const DEFAULT_RESPOND_ALL_KEYS: bool = true;
     /// Default value for the [`Self::send_set_name`] parameter.
     pub const DEFAULT_SEND_SET_NAME: bool = false;
+    /// Default value for the [`Self::short_query_threshold`] parameter.
+    pub const DEFAULT_SHORT_QUERY_THRESHOLD: usize = 0;
+
+    /// Create a builder, starting from the default values.
+    #[must_use]
+    pub fn builder() -> BatchPolicyBuilder {
+        BatchPolicyBuilder::default()
+    }
 }
 
 impl Default for BatchPolicy {
@@ -106,7 +307,11 @@ impl Default for BatchPolicy {
             base_policy: BasePolicy::default(),
             concurrency: Concurrency::default(),
             allow_inline: Self::DEFAULT_ALLOW_INLINE,
+            allow_inline_ssd: Self::DEFAULT_ALLOW_INLINE_SSD,
+            respond_all_keys: Self::DEFAULT_RESPOND_ALL_KEYS,
             send_set_name: Self::DEFAULT_SEND_SET_NAME,
+            short_query_threshold: Self::DEFAULT_SHORT_QUERY_THRESHOLD,
+            max_keys_per_request: Self::DEFAULT_MAX_KEYS_PER_REQUEST,
         }
     }
 }
@@ -117,6 +322,38 @@ impl AsRef<BasePolicy> for BatchPolicy {
     }
 }
 
+/// Builder for the [`BatchPolicy`], starting from its defaults.
+#[derive(Clone, Debug, Default)]
+pub struct BatchPolicyBuilder(BatchPolicy);
+
+impl BatchPolicyBuilder {
+    base_setters!();
+
+    setter!(BatchPolicy, concurrency: Concurrency);
+
+    setter!(BatchPolicy, allow_inline: bool);
+
+    setter!(BatchPolicy, allow_inline_ssd: bool);
+
+    setter!(BatchPolicy, respond_all_keys: bool);
+
+    setter!(BatchPolicy, send_set_name: bool);
+
+    setter!(BatchPolicy, short_query_threshold: usize);
+
+    setter!(BatchPolicy, max_keys_per_request: usize);
+
+    /// Create the policy.
+    #[must_use]
+    pub fn build(self) -> BatchPolicy {
+        self.0
+    }
+
+    fn base(&mut self) -> &mut BasePolicy {
+        &mut self.0.base_policy
+    }
+}
+
 /// Defines how a batch command should be executed, if it requires to be sent to multiple cluster
 /// nodes.
 #[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
@@ -130,17 +367,55 @@ pub enum Concurrency {
 }
 
 /// Parameters for creating new [`Client`](crate::Client) instances.
+#[allow(clippy::struct_excessive_bools)]
 #[derive(Clone, Debug)]
 pub struct ClientPolicy {
     /// Username and password pair to authenticate against the cluster. A value of [`None`]
     /// disabled the authentication altogether.
     pub user_password: Option<(String, String)>,
-    /// Initial timeout when creating a new connection to the server.
+    /// Initial timeout when creating a new connection to the server. This also limits the
+    /// validation of each seed host, which happens concurrently for all seeds.
     pub timeout: Option<Duration>,
+    /// Maximum time to wait for the TCP handshake when opening a new socket to a node. A value of
+    /// [`None`] waits until the operating system gives up on the connection attempt.
+    pub connect_timeout: Option<Duration>,
+    /// Delay before the next address is tried concurrently, if a host resolves to multiple
+    /// addresses and the connection to the previous ones isn't established yet.
+    ///
+    /// This follows the _Happy Eyeballs_ algorithm (RFC 8305), so unreachable addresses don't
+    /// block the connection setup until the [`Self::connect_timeout`] passed.
+    pub connect_attempt_delay: Duration,
+    /// Disable Nagle's algorithm on sockets (`TCP_NODELAY`), so small requests are sent
+    /// immediately instead of being held back to be merged with further data.
+    pub tcp_nodelay: bool,
+    /// Enable TCP keepalive probes (`SO_KEEPALIVE`) on sockets, after they were idle for the given
+    /// duration. A value of [`None`] leaves keepalive disabled.
+    ///
+    /// Firewalls and NAT gateways often drop idle connections without notifying either side,
+    /// which is only noticed once the next command fails. Keepalive probes keep such connections
+    /// alive, or detect them as broken early.
+    pub tcp_keepalive: Option<Duration>,
+    /// Interval between keepalive probes, once the first one went unanswered. A value of [`None`]
+    /// uses the operating system's default. Only used if [`Self::tcp_keepalive`] is set, and
+    /// ignored on platforms that don't allow to configure it, like OpenBSD.
+    pub tcp_keepalive_interval: Option<Duration>,
     /// Idling time after which unused connections are closed.
     pub idle_timeout: Option<Duration>,
     /// Maximum amount of socket connections per node in the cluster.
     pub max_conns_per_node: u32,
+    /// Maximum amount of socket connections per node that are reserved for scans, in addition to
+    /// the [`Self::max_conns_per_node`].
+    ///
+    /// Scans hold on to their connections for as long as records are streamed, which can starve
+    /// latency-sensitive single record commands of connections under load. With a separate pool,
+    /// scans only compete among themselves. A value of [`None`] lets all commands share a single
+    /// pool.
+    pub max_scan_conns_per_node: Option<u32>,
+    /// Percentage of commands (`1` to `100`) whose requests and responses are logged as hex dumps
+    /// at the `debug` level, with the parts of requests decoded into headers, fields and
+    /// operations. This is meant for debugging protocol mismatches against new server versions,
+    /// and should never be enabled in production. A value of [`None`] disables the logging.
+    pub trace_wire: Option<u8>,
     /// Return an error if the client is not initially connected to any nodes after creating a new
     /// instance.
     pub fail_if_not_connected: bool,
@@ -153,6 +428,14 @@ pub struct ClientPolicy {
     pub buffer_reclaim_threshold: usize,
     /// Interval at which to check for changes in the cluster (like addition or removal of nodes).
     pub tend_interval: Duration,
+    /// Interval at which seed hosts that are given as DNS names are resolved again during the
+    /// cluster tend. A value of [`None`] or _zero_ disables the re-resolution.
+    ///
+    /// The resolved addresses are used as additional seeds, replacing the ones of the previous
+    /// resolution, and addresses that don't belong to a known node are probed right away. This
+    /// allows the client to follow clusters behind a stable DNS name whose addresses change over
+    /// time, like Kubernetes headless services, even if all nodes are replaced at once.
+    pub seed_resolve_interval: Option<Duration>,
     /// Translation table for cluster node IPs that allows to remap advertised nodes from info
     /// commands to their real IP.
     ///
@@ -167,21 +450,112 @@ pub struct ClientPolicy {
     ///
     /// This should only be set if all servers support the `cluster-name` info command.
     pub cluster_name: Option<String>,
+    /// Duration for which hosts are ignored after reporting a different name than
+    /// [`Self::cluster_name`]. This avoids repeatedly connecting to seeds or peers that belong to
+    /// another cluster. A _zero_ duration disables the blacklist.
+    pub blacklist_duration: Duration,
+    /// Maximum time to wait for running commands to finish on a node that was removed from the
+    /// cluster, for example during a rolling restart. The node doesn't hand out connections while
+    /// draining, and all its connections are closed afterwards. A _zero_ duration closes the
+    /// connections right away.
+    pub drain_timeout: Duration,
+    /// Disable the discovery of further cluster nodes and send all commands to the first seed host
+    /// that could be connected to.
+    ///
+    /// This is required when connecting through a TCP load balancer or SSH tunnel, where the
+    /// addresses that the cluster nodes advertise aren't reachable by the client. As the seed host
+    /// might forward connections to different nodes, their names are not validated either.
+    pub single_node_mode: bool,
+    /// Maximum amount of commands per second that the client sends to the whole cluster. Commands
+    /// exceeding the limit are delayed until they fit in again, which protects small clusters from
+    /// sudden bursts of the application. A value of [`None`] or _zero_ disables the limit.
+    ///
+    /// The limit covers single record commands, batch requests and scans. Each request to a node
+    /// counts once, so a scan takes one permit per node command, no matter how many records it
+    /// streams. Info and admin commands are exempt, so limits never delay the cluster tend or the
+    /// handling of users and roles.
+    pub max_commands_per_second: Option<u32>,
+    /// Same as [`Self::max_commands_per_second`], but applied to each cluster node separately.
+    pub max_commands_per_second_per_node: Option<u32>,
+    /// **Experimental**: Send single record commands over one shared connection per node, instead
+    /// of checking out a connection from the pool for the whole duration of each command.
+    ///
+    /// Many commands can be in flight on the same socket at once, which drastically reduces the
+    /// amount of connections for deployments with very high throughput. The protocol doesn't tag
+    /// responses with a transaction ID, so they are matched to their requests by order, which
+    /// means a single slow command delays all others that were sent after it.
+    ///
+    /// Only used for nodes that announce support for pipelining. Scans and batch commands always
+    /// use pooled connections.
+    pub pipelining: bool,
+    /// Write [`Value::Bool`](crate::Value::Bool) bins as native booleans, as long as all cluster
+    /// nodes announce support for them. Otherwise, or if disabled, they're written as integers
+    /// `0` and `1`, which older servers accept instead of rejecting the write.
+    pub use_bool_bins: bool,
+    /// Hooks that are called before and after each single record command, for example to write
+    /// an audit log. See the [`interceptor`](crate::interceptor) module for details.
+    pub interceptor: Option<Arc<dyn CommandInterceptor>>,
+    /// Track the latency of commands in histograms, which can be inspected with
+    /// [`Client::latency_snapshot`](crate::Client::latency_snapshot). Tracking can be toggled at
+    /// runtime with [`Client::set_latency_metrics`](crate::Client::set_latency_metrics).
+    pub latency_metrics: bool,
+    /// Keep bins with particle types that this client doesn't know about as
+    /// [`Value::Unknown`](crate::Value::Unknown), instead of failing to read the whole record.
+    ///
+    /// This allows reading the remaining bins of records that contain data types introduced by
+    /// newer server versions.
+    pub keep_unknown_particles: bool,
+    /// Limits for decoding lists and maps out of server responses, which protect the client from
+    /// unbounded recursion and allocations on corrupted data.
+    pub decode_limits: DecodeLimits,
+    /// Skip bins with values larger than this many bytes while reading records, instead of
+    /// decoding them. Skipped bins are listed in
+    /// [`Record::skipped_bins`](crate::Record::skipped_bins) together with their size.
+    ///
+    /// This protects memory-constrained consumers from occasional giant bins, like large blobs.
+    /// Set to `None` to always read all bins.
+    ///
+    /// [`Client::operate_ordered`](crate::Client::operate_ordered) always reads all bins, as its
+    /// results are matched to the operations by position.
+    pub max_bin_size: Option<usize>,
+    /// Strategy for picking a node for commands that can be sent to any node. Ties between
+    /// equally suited nodes are resolved in round-robin order.
+    pub node_selection: NodeSelection,
 }
 
 impl ClientPolicy {
+    /// Default value for the [`Self::blacklist_duration`] parameter.
+    pub const DEFAULT_BLACKLIST_DURATION: Duration = Duration::from_secs(300);
     /// Default value for the [`Self::buffer_reclaim_threshold`] parameter.
     pub const DEFAULT_BUFFER_RECLAIM_THRESHOLD: usize = 65536;
+    /// Default value for the [`Self::connect_attempt_delay`] parameter.
+    pub const DEFAULT_CONNECT_ATTEMPT_DELAY: Duration = Duration::from_millis(250);
+    /// Default value for the [`Self::connect_timeout`] parameter.
+    pub const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+    /// Default value for the [`Self::drain_timeout`] parameter.
+    pub const DEFAULT_DRAIN_TIMEOUT: Duration = Duration::from_secs(5);
     /// Default value for the [`Self::fail_if_not_connected`] parameter.
     pub const DEFAULT_FAIL_IF_NOT_CONNECTED: bool = true;
     /// Default value for the [`Self::idle_timeout`] parameter.
     pub const DEFAULT_IDLE_TIMEOUT: Duration = Duration::from_secs(5);
+    /// Default value for the [`Self::keep_unknown_particles`] parameter.
+    pub const DEFAULT_KEEP_UNKNOWN_PARTICLES: bool = false;
+    /// Default value for the [`Self::latency_metrics`] parameter.
+    pub const DEFAULT_LATENCY_METRICS: bool = false;
     /// Default value for the [`Self::max_conns_per_node`] parameter.
     pub const DEFAULT_MAX_CONNS_PER_NODE: u32 = 256;
+    /// Default value for the [`Self::pipelining`] parameter.
+    pub const DEFAULT_PIPELINING: bool = false;
+    /// Default value for the [`Self::single_node_mode`] parameter.
+    pub const DEFAULT_SINGLE_NODE_MODE: bool = false;
+    /// Default value for the [`Self::tcp_nodelay`] parameter.
+    pub const DEFAULT_TCP_NODELAY: bool = true;
     /// Default value for the [`Self::tend_interval`] parameter.
     pub const DEFAULT_TEND_INTERVAL: Duration = Duration::from_secs(1);
     /// Default value for the [`Self::timeout`] parameter.
     pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+    /// Default value for the [`Self::use_bool_bins`] parameter.
+    pub const DEFAULT_USE_BOOL_BINS: bool = true;
     /// Default value for the [`Self::use_services_alternate`] parameter.
     pub const DEFAULT_USE_SERVICES_ALTERNATE: bool = false;
 
@@ -202,18 +576,149 @@ impl Default for ClientPolicy {
         Self {
             user_password: None,
             timeout: Some(Self::DEFAULT_TIMEOUT),
+            connect_timeout: Some(Self::DEFAULT_CONNECT_TIMEOUT),
+            connect_attempt_delay: Self::DEFAULT_CONNECT_ATTEMPT_DELAY,
+            tcp_nodelay: Self::DEFAULT_TCP_NODELAY,
+            tcp_keepalive: None,
+            tcp_keepalive_interval: None,
             idle_timeout: Some(Self::DEFAULT_IDLE_TIMEOUT),
             max_conns_per_node: Self::DEFAULT_MAX_CONNS_PER_NODE,
+            max_scan_conns_per_node: None,
+            trace_wire: None,
             fail_if_not_connected: Self::DEFAULT_FAIL_IF_NOT_CONNECTED,
             buffer_reclaim_threshold: Self::DEFAULT_BUFFER_RECLAIM_THRESHOLD,
             tend_interval: Self::DEFAULT_TEND_INTERVAL,
+            seed_resolve_interval: None,
             ip_map: None,
             use_services_alternate: Self::DEFAULT_USE_SERVICES_ALTERNATE,
             cluster_name: None,
+            blacklist_duration: Self::DEFAULT_BLACKLIST_DURATION,
+            drain_timeout: Self::DEFAULT_DRAIN_TIMEOUT,
+            single_node_mode: Self::DEFAULT_SINGLE_NODE_MODE,
+            max_commands_per_second: None,
+            max_commands_per_second_per_node: None,
+            pipelining: Self::DEFAULT_PIPELINING,
+            use_bool_bins: Self::DEFAULT_USE_BOOL_BINS,
+            interceptor: None,
+            latency_metrics: Self::DEFAULT_LATENCY_METRICS,
+            keep_unknown_particles: Self::DEFAULT_KEEP_UNKNOWN_PARTICLES,
+            decode_limits: DecodeLimits::default(),
+            max_bin_size: None,
+            node_selection: NodeSelection::default(),
         }
     }
 }
 
+/// Limits that are enforced while decoding list and map values (CDTs) out of server responses.
+///
+/// Values that exceed any of the limits fail the command with a
+/// [`MsgpackError`](crate::errors::MsgpackError), instead of recursing or allocating memory
+/// based on the sizes announced by a corrupted or malicious endpoint.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct DecodeLimits {
+    /// Maximum nesting depth of lists and maps, where a flat list has a depth of _one_.
+    pub max_depth: usize,
+    /// Maximum amount of elements in a single list or map.
+    pub max_elements: usize,
+    /// Maximum length in bytes of a single string or blob inside a list or map.
+    pub max_bytes: usize,
+}
+
+impl DecodeLimits {
+    /// Default value for the [`Self::max_bytes`] parameter.
+    pub const DEFAULT_MAX_BYTES: usize = 128 * 1024 * 1024;
+    /// Default value for the [`Self::max_depth`] parameter.
+    pub const DEFAULT_MAX_DEPTH: usize = 128;
+    /// Default value for the [`Self::max_elements`] parameter.
+    pub const DEFAULT_MAX_ELEMENTS: usize = 16 * 1024 * 1024;
+}
+
+impl Default for DecodeLimits {
+    fn default() -> Self {
+        Self {
+            max_depth: Self::DEFAULT_MAX_DEPTH,
+            max_elements: Self::DEFAULT_MAX_ELEMENTS,
+            max_bytes: Self::DEFAULT_MAX_BYTES,
+        }
+    }
+}
+
+/// Parameters for info commands, which are used for administrative operations like truncating
+/// sets or managing secondary indexes.
+#[derive(Clone, Debug)]
+pub struct InfoPolicy {
+    /// The duration after which the command is cancelled. Use a _zero_ duration to wait
+    /// indefinitely.
+    ///
+    /// In contrast to the [`BasePolicy::timeout`], this value isn't sent to the server, and info
+    /// commands aren't retried.
+    pub timeout: Duration,
+}
+
+impl InfoPolicy {
+    /// Default value for the [`Self::timeout`] parameter.
+    pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(1);
+}
+
+impl Default for InfoPolicy {
+    fn default() -> Self {
+        Self {
+            timeout: Self::DEFAULT_TIMEOUT,
+        }
+    }
+}
+
+/// Default policies for all single record commands on a namespace, registered with
+/// [`Client::set_namespace_defaults`](crate::Client::set_namespace_defaults).
+///
+/// The policies replace the ones that are passed to a command, as long as those equal the
+/// [`Default`] policies. Any policy that was customized is used as is.
+///
+/// Only policies can be overridden, not the routing of commands to nodes, which always follows
+/// the partition map. Whether reads of a namespace may be served by replicas is part of the read
+/// policy's [`BasePolicy::read_mode_sc`], so it can be set per namespace as well.
+///
+/// ```
+/// # use windpike::policies::{Expiration, NamespaceDefaults, WritePolicy};
+/// let cache = NamespaceDefaults {
+///     write: Some(
+///         WritePolicy::builder()
+///             .commit_master()
+///             .expiration(Expiration::Seconds(3600))
+///             .build(),
+///     ),
+///     ..NamespaceDefaults::default()
+/// };
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct NamespaceDefaults {
+    /// Policy for read commands, like [`Client::get`](crate::Client::get).
+    pub read: Option<BasePolicy>,
+    /// Policy for write commands, like [`Client::put`](crate::Client::put), as well as deletes,
+    /// touches, exists checks and operations.
+    pub write: Option<WritePolicy>,
+}
+
+/// Strategy for picking a node, when a command can be sent to any node of the cluster. This
+/// applies to info and admin commands, as well as the fallback node for retried batch keys.
+/// Commands on single records always go to the node that owns the record's partition.
+///
+/// It also breaks ties between replicas, if a read falls back to them because the partition's
+/// master is unknown, as allowed by [`ReadModeSc::AllowReplica`] and
+/// [`ReadModeSc::AllowUnavailable`].
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum NodeSelection {
+    /// Cycle through all active nodes one after another. **This is the default**.
+    #[default]
+    RoundRobin,
+    /// Pick the node with the least connections that are currently in use.
+    LeastConnections,
+    /// Pick the node with the lowest recent command latency, as reported by
+    /// [`Node::recent_latency`](crate::Node::recent_latency). Nodes without any measurements are
+    /// preferred, so all nodes get measured eventually.
+    LowestLatency,
+}
+
 /// Parameters for all scan operations.
 #[derive(Clone, Debug)]
 pub struct ScanPolicy {
@@ -222,11 +727,51 @@ pub struct ScanPolicy {
     /// Maximum amount of time to wait before the scan operation is cancelled (on the server side).
     /// A duration of _zero_ can be used to disable the timeout.
     pub socket_timeout: Duration,
+    /// Percentage of records to return from each partition, in the range of `1` to `100`. Values
+    /// of `100` or above scan all records, while _zero_ is rejected as invalid argument.
+    pub sample_percent: u8,
+    /// Approximate maximum amount of records to return from the whole scan. The limit is split
+    /// across all nodes, relative to the number of partitions each of them owns. A value of _zero_
+    /// disables the limit.
+    pub max_records: u64,
+    /// Optional expression that each record must match to be returned. This turns the scan into
+    /// an expression-only query, which requires all nodes to support partition queries
+    /// ([`FeatureSupport::PQUERY`](crate::FeatureSupport::PQUERY)).
+    pub filter_expression: Option<Expression>,
+    /// Maximum amount of commands that scan a single node in parallel, each of them covering an
+    /// equal share of the partitions that the node owns. This can improve throughput for nodes
+    /// with fast storage, at the cost of more connections. A value of _zero_ or _one_ scans each
+    /// node with a single command.
+    pub max_concurrent_partitions: usize,
+    /// Whether to return the bin data of each record. If disabled, only the record metadata
+    /// (generation and expiration) is returned, regardless of the bins selected for the scan.
+    pub include_bin_data: bool,
+    /// Whether to attach the [`Key`](crate::Key) to each returned [`Record`](crate::Record). The
+    /// key always holds the digest, and only contains the user key if it was stored with the
+    /// record ([`BasePolicy::send_key`]). If disabled, [`Record::key`](crate::Record::key) is
+    /// [`None`].
+    pub include_key: bool,
 }
 
 impl ScanPolicy {
+    /// Default value for the [`Self::include_bin_data`] parameter.
+    pub const DEFAULT_INCLUDE_BIN_DATA: bool = true;
+    /// Default value for the [`Self::include_key`] parameter.
+    pub const DEFAULT_INCLUDE_KEY: bool = true;
+    /// Default value for the [`Self::max_concurrent_partitions`] parameter.
+    pub const DEFAULT_MAX_CONCURRENT_PARTITIONS: usize = 1;
+    /// Default value for the [`Self::max_records`] parameter.
+    pub const DEFAULT_MAX_RECORDS: u64 = 0;
+    /// Default value for the [`Self::sample_percent`] parameter.
+    pub const DEFAULT_SAMPLE_PERCENT: u8 = 100;
     /// Default value for the [`Self::socket_timeout`] parameter.
     pub const DEFAULT_SOCKET_TIMEOUT: Duration = Duration::from_secs(10);
+
+    /// Create a builder, starting from the default values.
+    #[must_use]
+    pub fn builder() -> ScanPolicyBuilder {
+        ScanPolicyBuilder::default()
+    }
 }
 
 impl Default for ScanPolicy {
@@ -234,6 +779,12 @@ impl Default for ScanPolicy {
         Self {
             base_policy: BasePolicy::default(),
             socket_timeout: Self::DEFAULT_SOCKET_TIMEOUT,
+            sample_percent: Self::DEFAULT_SAMPLE_PERCENT,
+            max_records: Self::DEFAULT_MAX_RECORDS,
+            filter_expression: None,
+            max_concurrent_partitions: Self::DEFAULT_MAX_CONCURRENT_PARTITIONS,
+            include_bin_data: Self::DEFAULT_INCLUDE_BIN_DATA,
+            include_key: Self::DEFAULT_INCLUDE_KEY,
         }
     }
 }
@@ -244,8 +795,45 @@ impl AsRef<BasePolicy> for ScanPolicy {
     }
 }
 
-/// Parameters for all write operations.
+/// Builder for the [`ScanPolicy`], starting from its defaults.
 #[derive(Clone, Debug, Default)]
+pub struct ScanPolicyBuilder(ScanPolicy);
+
+impl ScanPolicyBuilder {
+    base_setters!();
+
+    setter!(ScanPolicy, socket_timeout: Duration);
+
+    setter!(ScanPolicy, sample_percent: u8);
+
+    setter!(ScanPolicy, max_records: u64);
+
+    setter!(ScanPolicy, max_concurrent_partitions: usize);
+
+    setter!(ScanPolicy, include_bin_data: bool);
+
+    setter!(ScanPolicy, include_key: bool);
+
+    /// Set the [`ScanPolicy::filter_expression`].
+    #[must_use]
+    pub fn filter_expression(mut self, filter_expression: Expression) -> Self {
+        self.0.filter_expression = Some(filter_expression);
+        self
+    }
+
+    /// Create the policy.
+    #[must_use]
+    pub fn build(self) -> ScanPolicy {
+        self.0
+    }
+
+    fn base(&mut self) -> &mut BasePolicy {
+        &mut self.0.base_policy
+    }
+}
+
+/// Parameters for all write operations.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
 pub struct WritePolicy {
     /// The base policy that this one extends.
     pub base_policy: BasePolicy,
@@ -263,7 +851,21 @@ pub struct WritePolicy {
     pub expiration: Expiration,
     /// When sending multiple operations at once, define whether a result should be returned for
     /// each operation. Note that some operations might not return a result at all.
+    ///
+    /// Operations that returned [`Value::Nil`](crate::Value::Nil) are left out of the record, so
+    /// use [`Client::operate_ordered`](crate::Client::operate_ordered) to get the result of each
+    /// operation at its position.
     pub respond_per_each_op: bool,
+    /// When sending multiple operations at once, define whether list, bitwise and HLL writes that
+    /// are denied by their write flag constraints are skipped, instead of failing the whole
+    /// command. This adds the `NO_FAIL` write flag to those operations, and the `PARTIAL` flag
+    /// to list and bitwise operations, so the remaining operations are still applied.
+    ///
+    /// If disabled, the server stops at the first failing operation and nothing is written.
+    /// Operations that fail for any other reason, or that don't have write flags like map
+    /// operations, still fail the command with [`CommandError::OperationFailed`], or a plain
+    /// [`CommandError::ServerError`] if the failed operation can't be determined.
+    pub continue_on_error: bool,
     /// Create a tombstone for deleted records, which prevents them from re-appearing after a node
     /// in the cluster failed.
     pub durable_delete: bool,
@@ -282,6 +884,62 @@ impl WritePolicy {
             ..Self::default()
         }
     }
+
+    /// Create a builder, starting from the default values.
+    ///
+    /// ```
+    /// # use windpike::policies::{Expiration, WritePolicy};
+    /// let policy = WritePolicy::builder()
+    ///     .expiration(Expiration::Seconds(60))
+    ///     .commit_master()
+    ///     .build();
+    /// ```
+    #[must_use]
+    pub fn builder() -> WritePolicyBuilder {
+        WritePolicyBuilder::default()
+    }
+
+    /// Policy that only creates new records, and fails if the record already exists.
+    #[must_use]
+    pub fn create_only() -> Self {
+        Self::with_action(RecordExistsAction::CreateOnly)
+    }
+
+    /// Policy that only updates existing records, and fails if the record doesn't exist.
+    #[must_use]
+    pub fn update_only() -> Self {
+        Self::with_action(RecordExistsAction::UpdateOnly)
+    }
+
+    /// Policy that fully replaces existing records, or creates them if they don't exist.
+    #[must_use]
+    pub fn replace() -> Self {
+        Self::with_action(RecordExistsAction::Replace)
+    }
+
+    /// Policy that only replaces existing records, and fails if the record doesn't exist.
+    #[must_use]
+    pub fn replace_only() -> Self {
+        Self::with_action(RecordExistsAction::ReplaceOnly)
+    }
+
+    /// Policy that only writes the record, if its generation on the server still equals the given
+    /// one. This allows for optimistic concurrency control, based on a previous read.
+    #[must_use]
+    pub fn expect_generation(generation: u32) -> Self {
+        Self {
+            generation_policy: GenerationPolicy::ExpectGenEqual,
+            generation,
+            ..Self::default()
+        }
+    }
+
+    fn with_action(record_exists_action: RecordExistsAction) -> Self {
+        Self {
+            record_exists_action,
+            ..Self::default()
+        }
+    }
 }
 
 impl AsRef<BasePolicy> for WritePolicy {
@@ -290,6 +948,52 @@ impl AsRef<BasePolicy> for WritePolicy {
     }
 }
 
+/// Builder for the [`WritePolicy`], starting from its defaults.
+#[derive(Clone, Debug, Default)]
+pub struct WritePolicyBuilder(WritePolicy);
+
+impl WritePolicyBuilder {
+    base_setters!();
+
+    setter!(WritePolicy, record_exists_action: RecordExistsAction);
+
+    setter!(WritePolicy, commit_level: CommitLevel);
+
+    setter!(WritePolicy, expiration: Expiration);
+
+    setter!(WritePolicy, respond_per_each_op: bool);
+
+    setter!(WritePolicy, continue_on_error: bool);
+
+    setter!(WritePolicy, durable_delete: bool);
+
+    /// Only write the record if its generation matches the given one, according to the
+    /// [`GenerationPolicy`].
+    #[must_use]
+    pub fn generation(mut self, generation_policy: GenerationPolicy, generation: u32) -> Self {
+        self.0.generation_policy = generation_policy;
+        self.0.generation = generation;
+        self
+    }
+
+    /// Consider the write complete, as soon as the master node applied it. Shorthand for
+    /// [`CommitLevel::Master`].
+    #[must_use]
+    pub fn commit_master(self) -> Self {
+        self.commit_level(CommitLevel::Master)
+    }
+
+    /// Create the policy.
+    #[must_use]
+    pub fn build(self) -> WritePolicy {
+        self.0
+    }
+
+    fn base(&mut self) -> &mut BasePolicy {
+        &mut self.0.base_policy
+    }
+}
+
 /// Action that is to be performed when a record write operation encounters an already existing
 /// entry.
 #[derive(Clone, Debug, Default, Eq, PartialEq)]
@@ -335,10 +1039,16 @@ pub enum CommitLevel {
 }
 
 /// Record expiration, also known as time-to-live (usually abbreviated as TTL).
-#[derive(Clone, Copy, Debug, Default)]
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
 pub enum Expiration {
     /// Amount of seconds (counted from now) until a record expires.
     Seconds(u32),
+    /// Amount of seconds until a record expires, like [`Self::Seconds`], plus a random amount of
+    /// up to the given jitter seconds, chosen anew for each write.
+    ///
+    /// This spreads the expiration of records that are written in bulk with the same TTL, so the
+    /// server doesn't have to expire all of them at the same moment.
+    SecondsJitter(u32, u32),
     /// Use the namespace's default TTL that the record is saved in. **This is the default**.
     #[default]
     NamespaceDefault,
@@ -352,9 +1062,89 @@ impl From<Expiration> for u32 {
     fn from(value: Expiration) -> Self {
         match value {
             Expiration::Seconds(secs) => secs,
+            Expiration::SecondsJitter(base, jitter) => base
+                .saturating_add(rand::thread_rng().gen_range(0..=jitter))
+                // Keep clear of the special values for the default TTL and for never expiring.
+                .clamp(1, u32::MAX - 2),
             Expiration::NamespaceDefault => 0,
             Expiration::Never => u32::MAX,
             Expiration::DontUpdate => u32::MAX - 1,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        BasePolicy, BatchPolicy, CommitLevel, Concurrency, Duration, Expiration, GenerationPolicy,
+        RecordExistsAction, WritePolicy,
+    };
+
+    #[test]
+    fn write_builder() {
+        let policy = WritePolicy::builder()
+            .expiration(Expiration::Never)
+            .commit_master()
+            .generation(GenerationPolicy::ExpectGenGreater, 5)
+            .timeout(Duration::from_secs(1))
+            .send_key(true)
+            .build();
+
+        assert!(matches!(policy.expiration, Expiration::Never));
+        assert_eq!(CommitLevel::Master, policy.commit_level);
+        assert_eq!(GenerationPolicy::ExpectGenGreater, policy.generation_policy);
+        assert_eq!(5, policy.generation);
+        assert_eq!(Duration::from_secs(1), policy.base_policy.timeout);
+        assert!(policy.base_policy.send_key);
+        assert_eq!(RecordExistsAction::Update, policy.record_exists_action);
+    }
+
+    #[test]
+    fn builder_defaults() {
+        let policy = BatchPolicy::builder()
+            .concurrency(Concurrency::Parallel(4))
+            .build();
+
+        assert_eq!(Concurrency::Parallel(4), policy.concurrency);
+        assert_eq!(BatchPolicy::DEFAULT_ALLOW_INLINE, policy.allow_inline);
+        assert_eq!(
+            BatchPolicy::DEFAULT_RESPOND_ALL_KEYS,
+            policy.respond_all_keys
+        );
+        assert_eq!(
+            BasePolicy::DEFAULT_TIMEOUT,
+            BasePolicy::builder().build().timeout
+        );
+    }
+
+    #[test]
+    fn write_presets() {
+        assert_eq!(
+            RecordExistsAction::CreateOnly,
+            WritePolicy::create_only().record_exists_action
+        );
+        assert_eq!(
+            RecordExistsAction::ReplaceOnly,
+            WritePolicy::replace_only().record_exists_action
+        );
+
+        let policy = WritePolicy::expect_generation(3);
+        assert_eq!(GenerationPolicy::ExpectGenEqual, policy.generation_policy);
+        assert_eq!(3, policy.generation);
+    }
+
+    #[test]
+    fn expiration_jitter() {
+        for _ in 0..100 {
+            let ttl = u32::from(Expiration::SecondsJitter(60, 10));
+            assert!((60..=70).contains(&ttl), "TTL {ttl} out of range");
+        }
+
+        assert_eq!(u32::from(Expiration::SecondsJitter(60, 0)), 60);
+        assert_eq!(u32::from(Expiration::SecondsJitter(0, 0)), 1);
+        assert_eq!(
+            u32::from(Expiration::SecondsJitter(u32::MAX, 10)),
+            u32::MAX - 2
+        );
+    }
+}
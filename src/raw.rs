@@ -0,0 +1,92 @@
+//! Low-level access to the wire protocol, for server features that the client doesn't wrap yet.
+//!
+//! [`Client::raw_command`](crate::Client::raw_command) sends a hand-crafted message to a single
+//! node, over one of its pooled connections. Only the proto header, that frames each message, is
+//! written and parsed by the client. Everything else, like the message header, fields and
+//! operations of a regular [`MessageType::Message`], is up to the caller.
+//!
+//! The server must answer with exactly one message, so streaming commands like scans can't be
+//! sent this way.
+//!
+//! This module is only available with the `raw` feature enabled, and is **exempt from semantic
+//! versioning**. Its types may change in any release, as the wire protocol evolves.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use windpike::{policies::BasePolicy, raw::MessageType, Client};
+//!
+//! # async fn run(client: &Client) -> Result<(), Box<dyn std::error::Error>> {
+//! let node = client.nodes().await.remove(0);
+//! let response = client
+//!     .raw_command(&BasePolicy::default(), &node, |request| {
+//!         request.message_type = MessageType::Info;
+//!         request.body.extend_from_slice(b"build\n");
+//!     })
+//!     .await?;
+//!
+//! println!("{}", String::from_utf8_lossy(&response.body));
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::commands::buffer::ProtoType;
+
+/// Type of a message, as declared in its proto header.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum MessageType {
+    /// Info commands, as plain text separated by newlines.
+    Info,
+    /// Security related messages like authentication and user management.
+    Security,
+    /// Regular messages that read or write records.
+    #[default]
+    Message,
+    /// Regular, but compressed messages.
+    MessageCompressed,
+    /// Any other message type, that the client doesn't know about.
+    Other(u8),
+}
+
+impl From<MessageType> for ProtoType {
+    fn from(value: MessageType) -> Self {
+        match value {
+            MessageType::Info => Self::Info,
+            MessageType::Security => Self::Security,
+            MessageType::Message => Self::Message,
+            MessageType::MessageCompressed => Self::MessageCompressed,
+            MessageType::Other(ty) => Self::from(ty),
+        }
+    }
+}
+
+impl From<ProtoType> for MessageType {
+    fn from(value: ProtoType) -> Self {
+        match value {
+            ProtoType::Info => Self::Info,
+            ProtoType::Security => Self::Security,
+            ProtoType::Message => Self::Message,
+            ProtoType::MessageCompressed => Self::MessageCompressed,
+            other => Self::Other(other.into()),
+        }
+    }
+}
+
+/// Message to send to a node, which is prefixed with a proto header for the given type and the
+/// length of the body.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Request {
+    /// Type of the message.
+    pub message_type: MessageType,
+    /// Raw content of the message, following the proto header.
+    pub body: Vec<u8>,
+}
+
+/// Message that a node answered with, without its proto header.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Response {
+    /// Type of the message.
+    pub message_type: MessageType,
+    /// Raw content of the message, following the proto header.
+    pub body: Vec<u8>,
+}
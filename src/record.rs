@@ -1,12 +1,34 @@
 use std::{
-    collections::HashMap,
+    cmp::Ordering,
+    collections::{HashMap, HashSet},
+    error::Error as StdError,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering as AtomicOrdering},
+        Arc,
+    },
     time::{Duration, SystemTime},
 };
 
 use rand::Rng;
 use tokio::sync::mpsc;
+use tokio_util::sync::{CancellationToken, DropGuard};
 
-use crate::{commands::CommandError, Key, Value};
+use crate::{
+    commands::{self, CommandError},
+    value::FromValue,
+    Key, MapKey, Value,
+};
+
+/// Name of a bin within a [`Record`].
+///
+/// Records that are read by the same command share the allocation of equal bin names, which saves
+/// from allocating the same few names over and over again in scans and batch reads. Bins can still
+/// be looked up by a plain `&str`.
+pub type BinName = Arc<str>;
+
+/// Content of a [`Record`], mapping bin names to their values.
+pub type RecordBins = HashMap<BinName, Value>;
 
 /// A single, uniquely identifiable database entry.
 #[derive(Clone, Debug)]
@@ -14,17 +36,23 @@ pub struct Record {
     /// Identifier for the record, by which it can be found in the database.
     ///
     /// When reading a record the key is usually not set, unless the
-    /// [`BasePolicy::send_key`](crate::policies::BasePolicy::send_key) parameter is set to `true`.
+    /// [`BasePolicy::send_key`](crate::policies::BasePolicy::send_key) parameter is set to `true`
+    /// and the server returned the user key that was stored with the record. This allows to
+    /// verify that the stored key matches the one used for the lookup.
     pub key: Option<Key>,
     /// Content of the record, which is categories in named bins. Each entry can contain simple
     /// values, lists, or even maps to create nested structures within.
-    pub bins: HashMap<String, Value>,
+    pub bins: RecordBins,
     /// Modification count of the record. This counter is increased on the server side for each
     /// modification (including the initial creation).
     ///
     /// In write operations, the generation can be used to create conditional writes by utilizing
     /// the [`WritePolicy::generation_policy`](crate::policies::WritePolicy::generation_policy).
     pub generation: u32,
+    /// Bins that were left out of [`Self::bins`], because their values exceeded the
+    /// [`ClientPolicy::max_bin_size`](crate::policies::ClientPolicy::max_bin_size), together with
+    /// the size of each value in bytes.
+    pub skipped_bins: Vec<(BinName, usize)>,
     /// Seconds from the _Citrusleaf epoch time_ (Jan 01 2010, 00:00:00 UTC) after which this
     /// record will expire.
     expiration: u32,
@@ -35,7 +63,7 @@ impl Record {
     #[must_use]
     pub(crate) const fn new(
         key: Option<Key>,
-        bins: HashMap<String, Value>,
+        bins: RecordBins,
         generation: u32,
         expiration: u32,
     ) -> Self {
@@ -43,6 +71,7 @@ impl Record {
             key,
             bins,
             generation,
+            skipped_bins: Vec::new(),
             expiration,
         }
     }
@@ -51,19 +80,307 @@ impl Record {
     /// for the record. If the record never expires, [`None`] is returned.
     #[must_use]
     pub fn time_to_live(&self) -> Option<Duration> {
-        (self.expiration > 0).then(|| {
-            let expiration = citrusleaf_epoch() + Duration::from_secs(u64::from(self.expiration));
+        self.expires_at().map(|expiration| {
             expiration
                 .duration_since(SystemTime::now())
                 .ok()
                 .unwrap_or(Duration::from_secs(1))
         })
     }
+
+    /// Returns the point in time at which the record expires. If the record never expires,
+    /// [`None`] is returned.
+    #[must_use]
+    pub fn expires_at(&self) -> Option<SystemTime> {
+        match self.expiration() {
+            RecordExpiration::Never => None,
+            RecordExpiration::At(time) => Some(time),
+        }
+    }
+
+    /// Returns the expiration of the record.
+    #[must_use]
+    pub fn expiration(&self) -> RecordExpiration {
+        RecordExpiration::from_citrusleaf(self.expiration)
+    }
+
+    /// Expiration in seconds since the _Citrusleaf epoch_, as sent by the server.
+    #[must_use]
+    pub(crate) const fn citrusleaf_expiration(&self) -> u32 {
+        self.expiration
+    }
+
+    /// Get the value of a bin, or an error if the record doesn't contain it.
+    pub fn get(&self, name: &str) -> Result<&Value, BinError> {
+        self.bins
+            .get(name)
+            .ok_or_else(|| BinError::Missing(name.to_owned()))
+    }
+
+    /// Get the value of an integer bin.
+    ///
+    /// ```
+    /// # use windpike::{errors::BinError, Record};
+    /// # fn run(record: &Record) -> Result<(), BinError> {
+    /// let age = record.get_i64("age")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get_i64(&self, name: &str) -> Result<i64, BinError> {
+        self.get_as(name, "integer", Value::as_i64)
+    }
+
+    /// Get the value of a string bin.
+    pub fn get_str(&self, name: &str) -> Result<&str, BinError> {
+        self.get_as(name, "string", Value::as_str)
+    }
+
+    /// Get the value of a list bin.
+    pub fn get_list(&self, name: &str) -> Result<&[Value], BinError> {
+        self.get_as(name, "list", Value::as_list)
+    }
+
+    /// Get the value of a map bin.
+    pub fn get_map(&self, name: &str) -> Result<&HashMap<MapKey, Value>, BinError> {
+        self.get_as(name, "map", Value::as_hash_map)
+    }
+
+    /// Get the value of a string bin, and parse it into any type that implements [`FromStr`].
+    ///
+    /// ```
+    /// # use std::net::IpAddr;
+    /// # use windpike::{errors::BinError, Record};
+    /// # fn run(record: &Record) -> Result<(), BinError> {
+    /// let addr = record.get_parsed::<IpAddr>("addr")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get_parsed<T>(&self, name: &str) -> Result<T, BinError>
+    where
+        T: FromStr,
+        T::Err: StdError + Send + Sync + 'static,
+    {
+        self.get_str(name)?
+            .parse()
+            .map_err(|source| BinError::Parse {
+                name: name.to_owned(),
+                source: Box::new(source),
+            })
+    }
+
+    /// Get the value of a timestamp bin, stored in any of the
+    /// [`TimestampFormat`](crate::timestamp::TimestampFormat)s.
+    #[cfg(any(feature = "chrono", feature = "time"))]
+    pub fn get_timestamp<T: crate::timestamp::Timestamp>(&self, name: &str) -> Result<T, BinError> {
+        self.get_as(name, "timestamp", Value::as_timestamp)
+    }
+
+    /// Remove a bin from the record and convert its value into any type that implements
+    /// [`FromValue`]. Missing bins are an error, unless the target type is an [`Option`].
+    ///
+    /// ```
+    /// # use windpike::{errors::BinError, Record};
+    /// # fn run(mut record: Record) -> Result<(), BinError> {
+    /// let name = record.take::<String>("name")?;
+    /// let age = record.take::<Option<u32>>("age")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn take<T: FromValue>(&mut self, name: &str) -> Result<T, BinError> {
+        let value = match self.bins.remove(name) {
+            Some(value) => value,
+            None => return T::from_missing().ok_or_else(|| BinError::Missing(name.to_owned())),
+        };
+        let actual = value.type_name();
+
+        T::from_value(value).ok_or_else(|| BinError::WrongType {
+            name: name.to_owned(),
+            expected: T::TYPE_NAME,
+            actual,
+        })
+    }
+
+    /// Get the value of a blob bin, that was written with
+    /// [`Bin::serialized`](crate::Bin::serialized), and decode it from `MessagePack` into any
+    /// [`Deserialize`](serde::Deserialize) type.
+    ///
+    /// ```
+    /// # #[cfg(feature = "serde")]
+    /// # {
+    /// # use windpike::{errors::BinError, Record};
+    /// # fn run(record: &Record) -> Result<(), BinError> {
+    /// let tags = record.deserialize_bin::<Vec<String>>("tags")?;
+    /// # Ok(())
+    /// # }
+    /// # }
+    /// ```
+    #[cfg(feature = "serde")]
+    pub fn deserialize_bin<T>(&self, name: &str) -> Result<T, BinError>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        let bytes = self.get_as(name, "blob", Value::as_bytes)?;
+
+        rmp_serde::from_slice(bytes).map_err(|source| BinError::Parse {
+            name: name.to_owned(),
+            source: Box::new(source),
+        })
+    }
+
+    fn get_as<'a, T>(
+        &'a self,
+        name: &str,
+        expected: &'static str,
+        convert: impl FnOnce(&'a Value) -> Option<T>,
+    ) -> Result<T, BinError> {
+        let value = self.get(name)?;
+        convert(value).ok_or_else(|| BinError::WrongType {
+            name: name.to_owned(),
+            expected,
+            actual: value.type_name(),
+        })
+    }
+
+    /// Decode the outcome of a UDF execution.
+    ///
+    /// The server doesn't return the result of a UDF as regular record, but reports the returned
+    /// value in a `SUCCESS` bin, or the error message of the Lua function in a `FAILURE` bin.
+    pub fn into_udf_result(mut self) -> Result<Value, UdfError> {
+        if let Some(value) = self.bins.remove(UDF_SUCCESS_BIN) {
+            return Ok(value);
+        }
+
+        match self.bins.remove(UDF_FAILURE_BIN) {
+            Some(Value::String(message)) => Err(UdfError::Failure(message)),
+            Some(value) => Err(UdfError::Failure(value.to_string())),
+            None => Err(UdfError::MissingResult),
+        }
+    }
+}
+
+/// Conversion of a whole [`Record`] into a Rust type, as used by
+/// [`Client::get_as`](crate::Client::get_as).
+///
+/// With the `derive` feature, it can be derived for structs with named fields, where each field
+/// is read from the bin of the same name through [`Record::take`]. The bin name can be changed
+/// with `#[windpike(rename = "...")]`, and fields marked with `#[windpike(skip)]` are filled with
+/// their [`Default`] value instead.
+///
+/// ```
+/// # #[cfg(feature = "derive")]
+/// # {
+/// use windpike::FromRecord;
+///
+/// #[derive(FromRecord)]
+/// struct User {
+///     name: String,
+///     #[windpike(rename = "years")]
+///     age: u32,
+///     email: Option<String>,
+///     #[windpike(skip)]
+///     cached: bool,
+/// }
+/// # }
+/// ```
+pub trait FromRecord: Sized {
+    /// Convert the record, consuming its bins.
+    fn from_record(record: Record) -> Result<Self, BinError>;
+}
+
+impl FromRecord for Record {
+    fn from_record(record: Record) -> Result<Self, BinError> {
+        Ok(record)
+    }
+}
+
+/// Deduplication of the bin names of all records that are read by a single command.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct BinNames(HashSet<BinName>);
+
+impl BinNames {
+    /// Get a shared instance of the given name, which is only allocated the first time it is seen.
+    pub(crate) fn intern(&mut self, name: &str) -> BinName {
+        if let Some(name) = self.0.get(name) {
+            return Arc::clone(name);
+        }
+
+        let name = BinName::from(name);
+        self.0.insert(Arc::clone(&name));
+        name
+    }
+}
+
+/// Name of the bin that contains the return value of a successful UDF execution.
+const UDF_SUCCESS_BIN: &str = "SUCCESS";
+/// Name of the bin that contains the error message of a failed UDF execution.
+const UDF_FAILURE_BIN: &str = "FAILURE";
+
+/// Error that occurred while accessing a bin through one of the typed accessors of a [`Record`].
+#[derive(Debug, thiserror::Error)]
+pub enum BinError {
+    /// The record doesn't contain a bin with the given name.
+    #[error("bin `{0}` not found")]
+    Missing(String),
+    /// The bin exists, but contains a different type of value.
+    #[error("bin `{name}` is of type {actual}, expected {expected}")]
+    WrongType {
+        /// Name of the bin.
+        name: String,
+        /// Type that was requested.
+        expected: &'static str,
+        /// Type of the value that the bin actually contains.
+        actual: &'static str,
+    },
+    /// The bin contains a string or serialized blob, but it couldn't be parsed into the requested
+    /// type.
+    #[error("failed parsing the value of bin `{name}`")]
+    Parse {
+        /// Name of the bin.
+        name: String,
+        /// Error reported by the parser.
+        #[source]
+        source: Box<dyn StdError + Send + Sync>,
+    },
+}
+
+/// Error that occurred while executing a UDF, as decoded by [`Record::into_udf_result`].
+#[derive(Debug, thiserror::Error)]
+pub enum UdfError {
+    /// The UDF failed on the server, and reported the contained error message.
+    #[error("UDF execution failed: {0}")]
+    Failure(String),
+    /// The record contained neither a success nor a failure result.
+    #[error("record contains no UDF result")]
+    MissingResult,
+}
+
+/// Expiration of a record, as reported by the server.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum RecordExpiration {
+    /// The record never expires.
+    Never,
+    /// The record expires at the given point in time, which may already be in the past.
+    At(SystemTime),
+}
+
+impl RecordExpiration {
+    /// Convert the seconds since the _Citrusleaf epoch_, as sent by the server, into the
+    /// expiration. A value of _zero_ means the record never expires.
+    ///
+    /// The value is unsigned, so it can represent points in time up to the year 2146 and doesn't
+    /// suffer from the year 2038 problem.
+    fn from_citrusleaf(secs: u32) -> Self {
+        if secs == 0 {
+            Self::Never
+        } else {
+            Self::At(citrusleaf_epoch() + Duration::from_secs(u64::from(secs)))
+        }
+    }
 }
 
 /// Aerospike's own epoch time, which is `Fri Jan  1 00:00:00 UTC 2010`.
 #[inline]
-fn citrusleaf_epoch() -> SystemTime {
+pub(crate) fn citrusleaf_epoch() -> SystemTime {
     SystemTime::UNIX_EPOCH + Duration::from_secs(1_262_304_000)
 }
 
@@ -71,41 +388,308 @@ fn citrusleaf_epoch() -> SystemTime {
 ///
 /// During a query/scan, multiple tasks will load the record from the cluster nodes and queue them
 /// up for consumption through this set.
-pub struct RecordSet {
-    queue: mpsc::Receiver<Result<Record, CommandError>>,
+///
+/// Dropping the set or calling [`Self::close`] stops these tasks, so the scan/query doesn't keep
+/// running on the nodes after the consumer lost interest in the remaining records.
+///
+/// The items are plain [`Record`]s by default, but can be any other type if the records were
+/// transformed while loading them, like with [`Client::scan_map`](crate::Client::scan_map).
+pub struct RecordSet<T = Record> {
+    queue: mpsc::Receiver<Result<T, CommandError>>,
     task_id: u64,
+    cancel: Option<DropGuard>,
+    progress: Arc<ProgressTracker>,
 }
 
-impl RecordSet {
+impl<T> RecordSet<T> {
     #[must_use]
-    pub(crate) fn new(queue: mpsc::Receiver<Result<Record, CommandError>>) -> Self {
+    pub(crate) fn new(
+        queue: mpsc::Receiver<Result<T, CommandError>>,
+        cancel: CancellationToken,
+    ) -> Self {
         Self {
             queue,
             task_id: rand::thread_rng().gen(),
+            cancel: Some(cancel.drop_guard()),
+            progress: Arc::default(),
         }
     }
 
-    /// Returns the task ID for the scan/query.
-    pub(crate) fn task_id(&self) -> u64 {
+    /// Track the progress of the scan/query with the given tracker.
+    pub(crate) fn set_progress(&mut self, progress: Arc<ProgressTracker>) {
+        self.progress = progress;
+    }
+
+    /// Returns the task ID for the scan/query, which identifies it in the job list of the nodes,
+    /// like in [`Client::list_jobs`](crate::Client::list_jobs).
+    ///
+    /// If a node's partitions are scanned by multiple commands in parallel, those commands use
+    /// consecutive IDs, starting from this one.
+    #[must_use]
+    pub fn task_id(&self) -> u64 {
         self.task_id
     }
 
+    /// Returns a snapshot of the progress of the scan/query, for example to show a progress bar.
+    ///
+    /// The progress is tracked per node, counting the records that were received from it and
+    /// the partitions that it finished scanning. Records that are still queued up in the set are
+    /// counted as received already.
+    #[must_use]
+    pub fn progress(&self) -> ScanProgress {
+        self.progress.snapshot()
+    }
+
     /// Get the next record in the set, potentially wait for it if not available yet. Once [`None`]
     /// is returned, the set is considered resumed and subsequent calls will always return [`None`]
     /// immediately.
-    pub async fn next(&mut self) -> Option<Result<Record, CommandError>> {
+    pub async fn next(&mut self) -> Option<Result<T, CommandError>> {
         self.queue.recv().await
     }
+
+    /// Stop the scan/query early, for example after breaking out of a loop over [`Self::next`].
+    ///
+    /// All tasks that load records from the cluster nodes are stopped, and the nodes are asked to
+    /// abort the job, if they support it. Records that were already queued up can still be
+    /// retrieved with [`Self::next`], but no further records are added.
+    pub fn close(&mut self) {
+        self.queue.close();
+        self.cancel = None;
+    }
+
+    /// Convert the set into a plain channel receiver, for example to hand the records over to a
+    /// pool of workers.
+    ///
+    /// The records are forwarded by a background task, which keeps the scan/query running. Once
+    /// the receiver is dropped, the task stops right away, even while waiting for the next record,
+    /// and stops the scan/query with it, like dropping the set directly would.
+    #[must_use]
+    pub fn into_channel(mut self) -> mpsc::Receiver<Result<T, CommandError>>
+    where
+        T: Send + 'static,
+    {
+        let (tx, rx) = mpsc::channel(1);
+        tokio::spawn(async move {
+            while let Some(Some(record)) = commands::until_stopped(tx.closed(), self.next()).await {
+                if tx.send(record).await.is_err() {
+                    break;
+                }
+            }
+        });
+        rx
+    }
+
+    /// Convert the set into a blocking iterator, for processing the records in synchronous code.
+    ///
+    /// # Panics
+    ///
+    /// Iterating panics if it happens within an asynchronous execution context. The iterator must
+    /// be used from a regular thread or within [`tokio::task::spawn_blocking`].
+    #[cfg(feature = "blocking")]
+    #[must_use]
+    pub const fn into_blocking_iter(self) -> BlockingRecords<T> {
+        BlockingRecords(self)
+    }
+
+    /// Consume the whole set and return the first `limit` records according to the given
+    /// comparison function, like the top-N records by the value of a bin.
+    ///
+    /// Nodes deliver their records in no particular order, so the records can only be ordered
+    /// once all of them were received. To keep the memory usage bounded, no more than `limit`
+    /// records are held at any time, in a heap that takes `O(log limit)` comparisons per record.
+    /// Records that compare equal keep the order in which they were received.
+    ///
+    /// The first error that is received stops the processing and is returned.
+    pub async fn sorted_by<F>(mut self, mut cmp: F, limit: usize) -> Result<Vec<T>, CommandError>
+    where
+        F: FnMut(&T, &T) -> Ordering,
+    {
+        if limit == 0 {
+            return Ok(Vec::new());
+        }
+
+        // Max-heap of the best records so far, with the worst one at the root. Records are tagged
+        // with their arrival index, so equal ones rank later ones as worse.
+        let mut heap = Vec::<(T, usize)>::new();
+        let mut worse = |a: &(T, usize), b: &(T, usize)| cmp(&a.0, &b.0).then(a.1.cmp(&b.1));
+        let mut index = 0;
+
+        while let Some(record) = self.next().await {
+            let entry = (record?, index);
+            index += 1;
+
+            if heap.len() < limit {
+                heap.push(entry);
+                sift_up(&mut heap, &mut worse);
+            } else if worse(&entry, &heap[0]) == Ordering::Less {
+                heap[0] = entry;
+                sift_down(&mut heap, &mut worse);
+            }
+        }
+
+        heap.sort_by(|a, b| worse(a, b));
+        Ok(heap.into_iter().map(|(record, _)| record).collect())
+    }
+}
+
+/// Move the last element of the heap up, until its parent isn't smaller anymore.
+fn sift_up<T>(heap: &mut [T], cmp: &mut impl FnMut(&T, &T) -> Ordering) {
+    let mut pos = heap.len() - 1;
+    while pos > 0 {
+        let parent = (pos - 1) / 2;
+        if cmp(&heap[pos], &heap[parent]) != Ordering::Greater {
+            break;
+        }
+        heap.swap(pos, parent);
+        pos = parent;
+    }
+}
+
+/// Move the root of the heap down, until none of its children is greater anymore.
+fn sift_down<T>(heap: &mut [T], cmp: &mut impl FnMut(&T, &T) -> Ordering) {
+    let mut pos = 0;
+    loop {
+        let mut largest = pos;
+        for child in [2 * pos + 1, 2 * pos + 2] {
+            if child < heap.len() && cmp(&heap[child], &heap[largest]) == Ordering::Greater {
+                largest = child;
+            }
+        }
+        if largest == pos {
+            break;
+        }
+        heap.swap(pos, largest);
+        pos = largest;
+    }
+}
+
+/// Progress of a scan/query, as returned by [`RecordSet::progress`].
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ScanProgress {
+    /// Progress of each node that is scanned.
+    pub nodes: Vec<NodeProgress>,
+}
+
+impl ScanProgress {
+    /// Total amount of records received from all nodes.
+    #[must_use]
+    pub fn records(&self) -> u64 {
+        self.nodes.iter().map(|node| node.records).sum()
+    }
+
+    /// Total amount of partitions that were scanned completely.
+    #[must_use]
+    pub fn partitions_done(&self) -> usize {
+        self.nodes.iter().map(|node| node.partitions_done).sum()
+    }
+
+    /// Total amount of partitions that are scanned.
+    #[must_use]
+    pub fn partitions_total(&self) -> usize {
+        self.nodes.iter().map(|node| node.partitions_total).sum()
+    }
+}
+
+/// Progress of a scan/query on a single node.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct NodeProgress {
+    /// Name of the node.
+    pub node: String,
+    /// Amount of records received from the node.
+    pub records: u64,
+    /// Amount of partitions that the node scanned completely.
+    pub partitions_done: usize,
+    /// Amount of partitions that the node scans.
+    pub partitions_total: usize,
+}
+
+/// Progress counters of a single node, which are updated by the tasks that scan it.
+#[derive(Debug, Default)]
+pub(crate) struct NodeProgressCounters {
+    records: AtomicU64,
+    partitions_done: AtomicUsize,
+}
+
+impl NodeProgressCounters {
+    pub fn add_record(&self) {
+        self.records.fetch_add(1, AtomicOrdering::Relaxed);
+    }
+
+    pub fn add_partitions(&self, count: usize) {
+        self.partitions_done
+            .fetch_add(count, AtomicOrdering::Relaxed);
+    }
+}
+
+/// Progress of all nodes that take part in a scan/query.
+#[derive(Debug, Default)]
+pub(crate) struct ProgressTracker {
+    nodes: Vec<(String, usize, Arc<NodeProgressCounters>)>,
+}
+
+impl ProgressTracker {
+    /// Register partitions that are scanned on the given node, and return the node's counters.
+    pub fn add_node(&mut self, node: &str, partitions: usize) -> Arc<NodeProgressCounters> {
+        if let Some((_, total, counters)) = self.nodes.iter_mut().find(|(name, ..)| name == node) {
+            *total += partitions;
+            return Arc::clone(counters);
+        }
+
+        let counters = Arc::new(NodeProgressCounters::default());
+        self.nodes
+            .push((node.to_owned(), partitions, Arc::clone(&counters)));
+        counters
+    }
+
+    fn snapshot(&self) -> ScanProgress {
+        ScanProgress {
+            nodes: self
+                .nodes
+                .iter()
+                .map(|(node, total, counters)| NodeProgress {
+                    node: node.clone(),
+                    records: counters.records.load(AtomicOrdering::Relaxed),
+                    // Retried commands might report partitions twice.
+                    partitions_done: counters
+                        .partitions_done
+                        .load(AtomicOrdering::Relaxed)
+                        .min(*total),
+                    partitions_total: *total,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Blocking iterator over the records of a [`RecordSet`], created with
+/// [`RecordSet::into_blocking_iter`].
+#[cfg(feature = "blocking")]
+pub struct BlockingRecords<T = Record>(RecordSet<T>);
+
+#[cfg(feature = "blocking")]
+impl<T> Iterator for BlockingRecords<T> {
+    type Item = Result<T, CommandError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.queue.blocking_recv()
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use std::{
         collections::HashMap,
+        sync::Arc,
         time::{Duration, SystemTime},
     };
 
-    use super::{citrusleaf_epoch, Record};
+    use tokio_util::sync::CancellationToken;
+
+    use super::{
+        citrusleaf_epoch, sift_down, sift_up, BinError, BinNames, NodeProgress, ProgressTracker,
+        Record, RecordExpiration, RecordSet, ScanProgress, UdfError,
+    };
+    use crate::Value;
 
     #[test]
     fn ttl_expiration_future() {
@@ -131,4 +715,430 @@ mod tests {
         let record = Record::new(None, HashMap::new(), 0, 0);
         assert_eq!(record.time_to_live(), None);
     }
+
+    #[test]
+    fn expiration_never() {
+        let record = Record::new(None, HashMap::new(), 0, 0);
+        assert_eq!(record.expiration(), RecordExpiration::Never);
+        assert_eq!(record.expires_at(), None);
+    }
+
+    #[test]
+    fn expiration_after_2038() {
+        // 2038-01-19T03:14:08Z, one second past the maximum of a signed 32-bit UNIX timestamp.
+        let unix_secs = 1u64 << 31;
+        let record = Record::new(None, HashMap::new(), 0, (unix_secs - 1_262_304_000) as u32);
+        assert_eq!(
+            record.expires_at(),
+            Some(SystemTime::UNIX_EPOCH + Duration::from_secs(unix_secs))
+        );
+    }
+
+    #[test]
+    fn expiration_max() {
+        let record = Record::new(None, HashMap::new(), 0, u32::MAX);
+        assert_eq!(
+            record.expiration(),
+            RecordExpiration::At(citrusleaf_epoch() + Duration::from_secs(u64::from(u32::MAX)))
+        );
+        assert!(record.time_to_live().unwrap() > Duration::from_secs(100 * 365 * 24 * 60 * 60));
+    }
+
+    #[test]
+    fn typed_accessors() {
+        let bins = HashMap::from([
+            ("int".into(), Value::from(5)),
+            ("str".into(), Value::from("value")),
+            ("num".into(), Value::from("42")),
+            ("list".into(), crate::list!(1, 2)),
+            ("map".into(), crate::map!("a" => 1)),
+        ]);
+        let record = Record::new(None, bins, 0, 0);
+
+        assert_eq!(record.get_i64("int").unwrap(), 5);
+        assert_eq!(record.get_str("str").unwrap(), "value");
+        assert_eq!(record.get_list("list").unwrap().len(), 2);
+        assert_eq!(record.get_map("map").unwrap().len(), 1);
+        assert_eq!(record.get_parsed::<u8>("num").unwrap(), 42);
+
+        assert!(matches!(record.get_i64("none"), Err(BinError::Missing(name)) if name == "none"));
+        assert!(matches!(
+            record.get_i64("str"),
+            Err(BinError::WrongType {
+                expected: "integer",
+                actual: "string",
+                ..
+            })
+        ));
+        assert!(matches!(
+            record.get_parsed::<u8>("str"),
+            Err(BinError::Parse { name, .. }) if name == "str"
+        ));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serialized_bins() {
+        use serde::{Deserialize, Serialize};
+
+        use crate::Bin;
+
+        #[derive(Debug, Deserialize, PartialEq, Serialize)]
+        struct Config {
+            name: String,
+            limits: Vec<u32>,
+        }
+
+        let config = Config {
+            name: "test".to_owned(),
+            limits: vec![1, 2],
+        };
+        let bin = Bin::serialized("config", &config).unwrap();
+        let record = Record::new(
+            None,
+            HashMap::from([
+                (bin.name.into(), bin.value),
+                ("int".into(), Value::from(1)),
+                ("raw".into(), Value::Blob(vec![0xc1])),
+            ]),
+            0,
+            0,
+        );
+
+        assert_eq!(record.deserialize_bin::<Config>("config").unwrap(), config);
+        assert!(matches!(
+            record.deserialize_bin::<Config>("int"),
+            Err(BinError::WrongType {
+                expected: "blob",
+                ..
+            })
+        ));
+        assert!(matches!(
+            record.deserialize_bin::<Config>("raw"),
+            Err(BinError::Parse { name, .. }) if name == "raw"
+        ));
+    }
+
+    #[test]
+    fn intern_bin_names() {
+        let mut names = BinNames::default();
+        let first = names.intern("name");
+        let second = names.intern("name");
+        let other = names.intern("other");
+
+        assert!(Arc::ptr_eq(&first, &second));
+        assert!(!Arc::ptr_eq(&first, &other));
+        assert_eq!(&*other, "other");
+    }
+
+    #[test]
+    fn udf_result_success() {
+        let bins = HashMap::from([("SUCCESS".into(), Value::from(5))]);
+        let record = Record::new(None, bins, 0, 0);
+        assert_eq!(record.into_udf_result().unwrap(), Value::from(5));
+    }
+
+    #[test]
+    fn udf_result_failure() {
+        let bins = HashMap::from([(
+            "FAILURE".into(),
+            Value::from("/opt/udf/test.lua:3: bad argument"),
+        )]);
+        let record = Record::new(None, bins, 0, 0);
+        assert!(matches!(
+            record.into_udf_result(),
+            Err(UdfError::Failure(msg)) if msg == "/opt/udf/test.lua:3: bad argument"
+        ));
+    }
+
+    #[test]
+    fn udf_result_missing() {
+        let record = Record::new(None, HashMap::new(), 0, 0);
+        assert!(matches!(
+            record.into_udf_result(),
+            Err(UdfError::MissingResult)
+        ));
+    }
+
+    #[test]
+    fn take_converts() {
+        let bins = HashMap::from([
+            ("int".into(), Value::from(42)),
+            ("float".into(), Value::from(1.5_f32)),
+            ("str".into(), Value::from("value")),
+            ("nil".into(), Value::Nil),
+        ]);
+        let mut record = Record::new(None, bins, 0, 0);
+
+        assert_eq!(42_u8, record.take::<u8>("int").unwrap());
+        assert_eq!(Some(1.5_f32), record.take::<Option<f32>>("float").unwrap());
+        assert_eq!("value", record.take::<String>("str").unwrap());
+        assert_eq!(None, record.take::<Option<i64>>("nil").unwrap());
+        assert_eq!(None, record.take::<Option<i64>>("missing").unwrap());
+        assert!(record.bins.is_empty());
+    }
+
+    #[test]
+    fn take_errors() {
+        let bins = HashMap::from([
+            ("big".into(), Value::from(300)),
+            ("str".into(), Value::from("value")),
+        ]);
+        let mut record = Record::new(None, bins, 0, 0);
+
+        assert!(matches!(
+            record.take::<u8>("big"),
+            Err(BinError::WrongType {
+                expected: "u8",
+                actual: "integer",
+                ..
+            })
+        ));
+        assert!(matches!(
+            record.take::<Option<i64>>("str"),
+            Err(BinError::WrongType {
+                expected: "i64",
+                actual: "string",
+                ..
+            })
+        ));
+        assert!(matches!(
+            record.take::<String>("missing"),
+            Err(BinError::Missing(name)) if name == "missing"
+        ));
+    }
+
+    #[tokio::test]
+    async fn sorted_by_limit() {
+        let (tx, rx) = tokio::sync::mpsc::channel(16);
+        for generation in [5, 1, 4, 1, 3, 9, 2] {
+            tx.send(Ok(Record::new(None, HashMap::new(), generation, 0)))
+                .await
+                .unwrap();
+        }
+        drop(tx);
+
+        let records = RecordSet::new(rx, CancellationToken::new())
+            .sorted_by(|a, b| b.generation.cmp(&a.generation), 3)
+            .await
+            .unwrap();
+        let generations = records
+            .iter()
+            .map(|record| record.generation)
+            .collect::<Vec<_>>();
+        assert_eq!(generations, [9, 5, 4]);
+    }
+
+    #[test]
+    fn sift_single_element() {
+        let mut heap = vec![1];
+        sift_up(&mut heap, &mut Ord::cmp);
+        sift_down(&mut heap, &mut Ord::cmp);
+        assert_eq!(heap, [1]);
+    }
+
+    #[test]
+    fn sift_ties() {
+        // Equal elements are never swapped, so their positions stay untouched.
+        let mut heap = vec![(1, 'a'), (1, 'b'), (1, 'c')];
+        let mut cmp = |a: &(i32, char), b: &(i32, char)| a.0.cmp(&b.0);
+
+        sift_up(&mut heap, &mut cmp);
+        assert_eq!(heap, [(1, 'a'), (1, 'b'), (1, 'c')]);
+
+        sift_down(&mut heap, &mut cmp);
+        assert_eq!(heap, [(1, 'a'), (1, 'b'), (1, 'c')]);
+
+        heap.push((2, 'd'));
+        sift_up(&mut heap, &mut cmp);
+        assert_eq!(heap, [(2, 'd'), (1, 'a'), (1, 'c'), (1, 'b')]);
+    }
+
+    #[test]
+    fn sift_keeps_heap_order() {
+        fn is_heap(heap: &[u32]) -> bool {
+            (1..heap.len()).all(|i| heap[(i - 1) / 2] >= heap[i])
+        }
+
+        let values = (0..100_u32).map(|i| (i * 7919) % 31).collect::<Vec<_>>();
+        let mut heap = Vec::new();
+        for value in &values {
+            heap.push(*value);
+            sift_up(&mut heap, &mut Ord::cmp);
+            assert!(is_heap(&heap));
+        }
+
+        // Replacing the root with the smallest value moves it all the way down.
+        heap[0] = 0;
+        sift_down(&mut heap, &mut Ord::cmp);
+        assert!(is_heap(&heap));
+
+        let mut sorted = Vec::new();
+        while let Some(last) = heap.pop() {
+            if heap.is_empty() {
+                sorted.push(last);
+            } else {
+                sorted.push(std::mem::replace(&mut heap[0], last));
+                sift_down(&mut heap, &mut Ord::cmp);
+            }
+            assert!(is_heap(&heap));
+        }
+
+        // The largest value was replaced by zero before.
+        let mut expected = values;
+        expected.sort_unstable_by(|a, b| b.cmp(a));
+        expected[0] = 0;
+        expected.sort_unstable_by(|a, b| b.cmp(a));
+        assert_eq!(expected, sorted);
+    }
+
+    #[tokio::test]
+    async fn sorted_by_stable() {
+        // Generations with many duplicates, and the arrival index as expiration.
+        let generations = (0..200_u32).map(|i| (i * 7919) % 13).collect::<Vec<_>>();
+
+        for limit in [1, 5, 13, 50, 200, 300] {
+            let (tx, rx) = tokio::sync::mpsc::channel(256);
+            for (i, generation) in (0..).zip(&generations) {
+                tx.send(Ok(Record::new(None, HashMap::new(), *generation, i)))
+                    .await
+                    .unwrap();
+            }
+            drop(tx);
+
+            let records = RecordSet::new(rx, CancellationToken::new())
+                .sorted_by(|a, b| a.generation.cmp(&b.generation), limit)
+                .await
+                .unwrap();
+
+            let mut expected = (0..).zip(&generations).collect::<Vec<_>>();
+            expected.sort_by_key(|(_, generation)| **generation);
+            expected.truncate(limit);
+
+            assert_eq!(
+                expected
+                    .into_iter()
+                    .map(|(i, generation)| (*generation, i))
+                    .collect::<Vec<_>>(),
+                records
+                    .iter()
+                    .map(|record| (record.generation, record.expiration))
+                    .collect::<Vec<_>>()
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn close_stops_producers() {
+        let (tx, rx) = tokio::sync::mpsc::channel(16);
+        let cancel = CancellationToken::new();
+        let mut records = RecordSet::new(rx, cancel.clone());
+
+        tx.send(Ok(Record::new(None, HashMap::new(), 1, 0)))
+            .await
+            .unwrap();
+        records.close();
+
+        assert!(cancel.is_cancelled());
+        assert!(tx.is_closed());
+        assert!(tx
+            .send(Ok(Record::new(None, HashMap::new(), 2, 0)))
+            .await
+            .is_err());
+
+        assert_eq!(records.next().await.unwrap().unwrap().generation, 1);
+        assert!(records.next().await.is_none());
+    }
+
+    #[test]
+    fn drop_stops_producers() {
+        let (_tx, rx) = tokio::sync::mpsc::channel(1);
+        let cancel = CancellationToken::new();
+        drop(RecordSet::<Record>::new(rx, cancel.clone()));
+
+        assert!(cancel.is_cancelled());
+    }
+
+    #[test]
+    fn progress_snapshot() {
+        let (_tx, rx) = tokio::sync::mpsc::channel(1);
+        let mut records = RecordSet::<Record>::new(rx, CancellationToken::new());
+        assert_eq!(records.progress(), ScanProgress::default());
+
+        let mut tracker = ProgressTracker::default();
+        let first = tracker.add_node("A", 3);
+        let second = tracker.add_node("B", 2);
+        let split = tracker.add_node("A", 1);
+        records.set_progress(Arc::new(tracker));
+
+        first.add_record();
+        first.add_record();
+        first.add_partitions(2);
+        split.add_record();
+        second.add_partitions(3);
+
+        let progress = records.progress();
+        assert_eq!(
+            progress.nodes,
+            [
+                NodeProgress {
+                    node: "A".to_owned(),
+                    records: 3,
+                    partitions_done: 2,
+                    partitions_total: 4,
+                },
+                NodeProgress {
+                    node: "B".to_owned(),
+                    records: 0,
+                    partitions_done: 2,
+                    partitions_total: 2,
+                },
+            ]
+        );
+        assert_eq!(progress.records(), 3);
+        assert_eq!(progress.partitions_done(), 4);
+        assert_eq!(progress.partitions_total(), 6);
+    }
+
+    #[tokio::test]
+    async fn into_channel_forwards_records() {
+        let (tx, rx) = tokio::sync::mpsc::channel(16);
+        let cancel = CancellationToken::new();
+        let mut records = RecordSet::new(rx, cancel.clone()).into_channel();
+
+        for generation in [1, 2] {
+            tx.send(Ok(Record::new(None, HashMap::new(), generation, 0)))
+                .await
+                .unwrap();
+        }
+
+        assert_eq!(records.recv().await.unwrap().unwrap().generation, 1);
+        assert!(!cancel.is_cancelled());
+
+        // No further record arrives, but the task still stops once the receiver is gone.
+        drop(records);
+        cancel.cancelled().await;
+    }
+
+    #[cfg(feature = "blocking")]
+    #[tokio::test]
+    async fn blocking_iter() {
+        let (tx, rx) = tokio::sync::mpsc::channel(16);
+        for generation in [1, 2, 3] {
+            tx.send(Ok(Record::new(None, HashMap::new(), generation, 0)))
+                .await
+                .unwrap();
+        }
+        drop(tx);
+
+        let records = RecordSet::new(rx, CancellationToken::new()).into_blocking_iter();
+        let generations = tokio::task::spawn_blocking(move || {
+            records
+                .map(|record| record.unwrap().generation)
+                .collect::<Vec<_>>()
+        })
+        .await
+        .unwrap();
+        assert_eq!(generations, [1, 2, 3]);
+    }
 }
@@ -1,7 +1,8 @@
-use std::borrow::Cow;
+use std::fmt::{self, Display};
 
 /// Database operation error codes. The error codes are defined in the server-side file proto.h.
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[non_exhaustive]
 pub enum ResultCode {
     /// Operation was successful.
     Ok,
@@ -297,85 +298,95 @@ impl ResultCode {
         }
     }
 
-    /// Convert a result code into an string.
-    #[must_use]
-    pub fn into_string(self) -> Cow<'static, str> {
+    /// Human readable description of the result code.
+    const fn description(self) -> &'static str {
         match self {
-            Self::Ok => "ok".into(),
-            Self::ServerError => "Server error".into(),
-            Self::KeyNotFoundError => "Key not found".into(),
-            Self::GenerationError => "Generation error".into(),
-            Self::ParameterError => "Parameter error".into(),
-            Self::KeyExistsError => "Key already exists".into(),
-            Self::BinExistsError => "Bin already exists".into(),
-            Self::ClusterKeyMismatch => "Cluster key mismatch".into(),
-            Self::ServerMemError => "Server memory error".into(),
-            Self::Timeout => "Timeout".into(),
-            Self::AlwaysForbidden => "Operation not allowed".into(),
-            Self::PartitionUnavailable => "Partitions unavailable".into(),
-            Self::BinTypeError => "Bin type error".into(),
-            Self::RecordTooBig => "Record too big".into(),
-            Self::KeyBusy => "Hot key".into(),
-            Self::ScanAbort => "Scan aborted".into(),
-            Self::UnsupportedFeature => "Unsupported Server Feature".into(),
-            Self::BinNotFound => "Bin not found".into(),
-            Self::DeviceOverload => "Device overload".into(),
-            Self::KeyMismatch => "Key mismatch".into(),
-            Self::InvalidNamespace => "Namespace not found".into(),
+            Self::Ok => "ok",
+            Self::ServerError => "Server error",
+            Self::KeyNotFoundError => "Key not found",
+            Self::GenerationError => "Generation error",
+            Self::ParameterError => "Parameter error",
+            Self::KeyExistsError => "Key already exists",
+            Self::BinExistsError => "Bin already exists",
+            Self::ClusterKeyMismatch => "Cluster key mismatch",
+            Self::ServerMemError => "Server memory error",
+            Self::Timeout => "Timeout",
+            Self::AlwaysForbidden => "Operation not allowed",
+            Self::PartitionUnavailable => "Partitions unavailable",
+            Self::BinTypeError => "Bin type error",
+            Self::RecordTooBig => "Record too big",
+            Self::KeyBusy => "Hot key",
+            Self::ScanAbort => "Scan aborted",
+            Self::UnsupportedFeature => "Unsupported Server Feature",
+            Self::BinNotFound => "Bin not found",
+            Self::DeviceOverload => "Device overload",
+            Self::KeyMismatch => "Key mismatch",
+            Self::InvalidNamespace => "Namespace not found",
             Self::BinNameTooLong => {
-                "Bin name length greater than 15 characters or maximum bins exceeded".into()
+                "Bin name length greater than 15 characters or maximum bins exceeded"
             }
-            Self::FailForbidden => "Operation not allowed at this time".into(),
-            Self::ElementNotFound => "Element not found".into(),
-            Self::ElementExists => "Element exists".into(),
-            Self::EnterpriseOnly => "Enterprise only".into(),
-            Self::OpNotApplicable => "Operation not applicable".into(),
-            Self::FilteredOut => "Transaction filtered out".into(),
-            Self::LostConflict => "Transaction failed due to conflict with XDR".into(),
-            Self::QueryEnd => "Query end".into(),
-            Self::SecurityNotSupported => "Security not supported".into(),
-            Self::SecurityNotEnabled => "Security not enabled".into(),
-            Self::SecuritySchemeNotSupported => "Security scheme not supported".into(),
-            Self::InvalidCommand => "Invalid command".into(),
-            Self::InvalidField => "Invalid field".into(),
-            Self::IllegalState => "Illegal state".into(),
-            Self::InvalidUser => "Invalid user".into(),
-            Self::UserAlreadyExists => "User already exists".into(),
-            Self::InvalidPassword => "Invalid password".into(),
-            Self::ExpiredPassword => "Expired password".into(),
-            Self::ForbiddenPassword => "Forbidden password".into(),
-            Self::InvalidCredential => "Invalid credential".into(),
-            Self::InvalidSession => "Login session expired".into(),
-            Self::InvalidRole => "Invalid role".into(),
-            Self::RoleAlreadyExists => "Role already exists".into(),
-            Self::InvalidPrivilege => "Invalid privilege".into(),
-            Self::InvalidWhitelist => "Invalid whitelist".into(),
-            Self::QuotasNotEnabled => "Quotas not enabled".into(),
-            Self::InvalidQuota => "Invalid quota".into(),
-            Self::NotAuthenticated => "Not authenticated".into(),
-            Self::RoleViolation => "Role violation".into(),
-            Self::NotWhitelisted => "Command not whitelisted".into(),
-            Self::QuotaExceeded => "Quota exceeded".into(),
-            Self::UdfBadResponse => "UDF returned error".into(),
-            Self::BatchDisabled => "Batch functionality has been disabled".into(),
-            Self::BatchMaxRequestsExceeded => "Batch max requests have been exceeded".into(),
-            Self::BatchQueuesFull => "All batch queues are full".into(),
-            Self::IndexAlreadyExists => "Index already exists".into(),
-            Self::IndexNotFound => "Index not found".into(),
-            Self::IndexOom => "Index out of memory".into(),
-            Self::IndexNotReadable => "Index not readable".into(),
-            Self::IndexGeneric => "Index error".into(),
-            Self::IndexNameMaxLen => "Index name max length exceeded".into(),
-            Self::IndexMaxCount => "Index count exceeds max".into(),
-            Self::QueryAborted => "Query aborted".into(),
-            Self::QueryQueueFull => "Query queue full".into(),
-            Self::QueryTimeout => "Query timeout".into(),
-            Self::QueryGeneric => "Query error".into(),
-            Self::Unknown(code) => format!("Unknown server error code: {code}").into(),
+            Self::FailForbidden => "Operation not allowed at this time",
+            Self::ElementNotFound => "Element not found",
+            Self::ElementExists => "Element exists",
+            Self::EnterpriseOnly => "Enterprise only",
+            Self::OpNotApplicable => "Operation not applicable",
+            Self::FilteredOut => "Transaction filtered out",
+            Self::LostConflict => "Transaction failed due to conflict with XDR",
+            Self::QueryEnd => "Query end",
+            Self::SecurityNotSupported => "Security not supported",
+            Self::SecurityNotEnabled => "Security not enabled",
+            Self::SecuritySchemeNotSupported => "Security scheme not supported",
+            Self::InvalidCommand => "Invalid command",
+            Self::InvalidField => "Invalid field",
+            Self::IllegalState => "Illegal state",
+            Self::InvalidUser => "Invalid user",
+            Self::UserAlreadyExists => "User already exists",
+            Self::InvalidPassword => "Invalid password",
+            Self::ExpiredPassword => "Expired password",
+            Self::ForbiddenPassword => "Forbidden password",
+            Self::InvalidCredential => "Invalid credential",
+            Self::InvalidSession => "Login session expired",
+            Self::InvalidRole => "Invalid role",
+            Self::RoleAlreadyExists => "Role already exists",
+            Self::InvalidPrivilege => "Invalid privilege",
+            Self::InvalidWhitelist => "Invalid whitelist",
+            Self::QuotasNotEnabled => "Quotas not enabled",
+            Self::InvalidQuota => "Invalid quota",
+            Self::NotAuthenticated => "Not authenticated",
+            Self::RoleViolation => "Role violation",
+            Self::NotWhitelisted => "Command not whitelisted",
+            Self::QuotaExceeded => "Quota exceeded",
+            Self::UdfBadResponse => "UDF returned error",
+            Self::BatchDisabled => "Batch functionality has been disabled",
+            Self::BatchMaxRequestsExceeded => "Batch max requests have been exceeded",
+            Self::BatchQueuesFull => "All batch queues are full",
+            Self::IndexAlreadyExists => "Index already exists",
+            Self::IndexNotFound => "Index not found",
+            Self::IndexOom => "Index out of memory",
+            Self::IndexNotReadable => "Index not readable",
+            Self::IndexGeneric => "Index error",
+            Self::IndexNameMaxLen => "Index name max length exceeded",
+            Self::IndexMaxCount => "Index count exceeds max",
+            Self::QueryAborted => "Query aborted",
+            Self::QueryQueueFull => "Query queue full",
+            Self::QueryTimeout => "Query timeout",
+            Self::QueryGeneric => "Query error",
+            Self::Unknown(_) => "Unknown server error code",
         }
     }
 }
 
+impl Display for ResultCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unknown(code) => write!(f, "{}: {code}", self.description()),
+            _ => f.write_str(self.description()),
+        }
+    }
+}
+
+impl std::error::Error for ResultCode {}
+
 impl From<u8> for ResultCode {
     fn from(value: u8) -> Self {
         Self::from_u8(value)
@@ -403,14 +414,14 @@ mod tests {
     }
 
     #[test]
-    fn into_string() {
-        let result = ResultCode::KeyNotFoundError.into_string();
+    fn display() {
+        let result = ResultCode::KeyNotFoundError.to_string();
         assert_eq!("Key not found", result);
     }
 
     #[test]
-    fn unknown_into_string() {
-        let result = ResultCode::Unknown(234).into_string();
+    fn unknown_display() {
+        let result = ResultCode::Unknown(234).to_string();
         assert_eq!("Unknown server error code: 234", result);
     }
 }
@@ -0,0 +1,1447 @@
+//! In-memory stand-in for the [`Client`](crate::Client), that allows to unit test data access
+//! layers without a running Aerospike server.
+//!
+//! The [`MockClient`] mirrors the signatures of the key-value methods of the real client and
+//! implements the [`ClientApi`] trait. It keeps all records in a shared map, and only replicates
+//! a subset of the server behavior:
+//!
+//! - Scalar reads and writes, including `add`, `append` and `prepend`.
+//! - The [`WritePolicy`] settings for existing records, generation checks and expiration.
+//! - Basic list operations (append, insert, pop, remove, set, clear, size, get and get range).
+//! - Basic map operations (put, put items, clear, size, get and remove by key).
+//! - List operations with the `NO_FAIL` write flag, as well as all operations under
+//!   [`WritePolicy::continue_on_error`], are skipped if they fail, instead of failing the command.
+//! - Batch reads, scans and truncation, where scan policies and the truncation timestamp are
+//!   ignored.
+//!
+//! Any other operation fails with [`ResultCode::UnsupportedFeature`].
+//!
+//! This module is only available with the `testing` feature enabled.
+//!
+//! # Example
+//!
+//! ```rust
+//! use windpike::{
+//!     policies::{BasePolicy, WritePolicy},
+//!     testing::MockClient,
+//!     Bin, Bins, Key, Value,
+//! };
+//!
+//! #[tokio::main]
+//! async fn main() {
+//!     let client = MockClient::new();
+//!     let key = Key::new("test", "test", "mykey");
+//!
+//!     client
+//!         .put(&WritePolicy::default(), &key, &[Bin::new("a", 1)])
+//!         .await
+//!         .unwrap();
+//!
+//!     let record = client
+//!         .get(&BasePolicy::default(), &key, Bins::All)
+//!         .await
+//!         .unwrap();
+//!     assert_eq!(record.bins.get("a"), Some(&Value::from(1)));
+//! }
+//! ```
+
+use std::{
+    borrow::Cow,
+    collections::{
+        hash_map::Entry::{Occupied, Vacant},
+        HashMap,
+    },
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+    time::SystemTime,
+};
+
+use async_trait::async_trait;
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+    commands::CommandError,
+    errors::Error,
+    operations::{
+        self,
+        cdt::{self, Argument},
+        list, map, Operation, OperationBin, OperationData, OperationType,
+    },
+    policies::{
+        BasePolicy, BatchPolicy, Expiration, GenerationPolicy, InfoPolicy, RecordExistsAction,
+        ScanPolicy, WritePolicy,
+    },
+    record::citrusleaf_epoch,
+    BatchRead, Bin, Bins, ClientApi, FloatValue, FromRecord, Key, MapKey, Record, RecordBins,
+    RecordSet, ResultCode, ToBins, Value,
+};
+
+type Result<T, E = CommandError> = std::result::Result<T, E>;
+type Store = HashMap<(String, [u8; 20]), Entry>;
+
+/// In-memory fake of the [`Client`](crate::Client). Clones share the same set of records.
+#[derive(Clone, Debug, Default)]
+pub struct MockClient {
+    records: Arc<Mutex<Store>>,
+}
+
+#[derive(Clone, Debug)]
+struct Entry {
+    set_name: String,
+    bins: RecordBins,
+    generation: u32,
+    // Seconds since the Citrusleaf epoch, or zero if the record never expires.
+    expiration: u32,
+}
+
+impl Entry {
+    fn is_expired(&self) -> bool {
+        self.expiration != 0 && self.expiration <= now()
+    }
+
+    fn into_record(self, key: Option<Key>, bins: &Bins) -> Record {
+        let bins = match bins {
+            Bins::All => self.bins,
+            Bins::None => HashMap::new(),
+            Bins::Some(names) => self
+                .bins
+                .into_iter()
+                .filter(|(name, _)| names.iter().any(|n| **n == **name))
+                .collect(),
+        };
+
+        Record::new(key, bins, self.generation, self.expiration)
+    }
+}
+
+// The methods are async to be a drop-in replacement for the real client.
+#[allow(clippy::unused_async)]
+impl MockClient {
+    /// Create a new mock client without any records.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Amount of records currently stored, including expired ones that haven't been accessed
+    /// since they expired.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    /// Whether no records are stored at all.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.lock().is_empty()
+    }
+
+    /// Remove all records.
+    pub fn clear(&self) {
+        self.lock().clear();
+    }
+
+    /// Read record for the specified key. See [`Client::get`](crate::Client::get).
+    pub async fn get<T>(
+        &self,
+        _policy: &BasePolicy,
+        key: &Key,
+        bins: T,
+    ) -> Result<Record, CommandError>
+    where
+        T: Into<Bins> + Send + Sync + 'static,
+    {
+        let entry = self.read(key)?;
+        Ok(entry.into_record(None, &bins.into()))
+    }
+
+    /// Read record for the specified key, and decode it into any type that implements
+    /// [`FromRecord`]. See [`Client::get_as`](crate::Client::get_as).
+    pub async fn get_as<R, T>(
+        &self,
+        policy: &BasePolicy,
+        key: &Key,
+        bins: T,
+    ) -> Result<R, CommandError>
+    where
+        R: FromRecord,
+        T: Into<Bins> + Send + Sync + 'static,
+    {
+        let record = self.get(policy, key, bins).await?;
+        R::from_record(record).map_err(Into::into)
+    }
+
+    /// Read multiple records for the specified batch keys in one batch call. See
+    /// [`Client::batch_get`](crate::Client::batch_get).
+    pub async fn batch_get(
+        &self,
+        _policy: &BatchPolicy,
+        mut batch_reads: Vec<BatchRead>,
+    ) -> Result<Vec<BatchRead>, Error> {
+        for batch_read in &mut batch_reads {
+            if let Ok(entry) = self.read(&batch_read.key) {
+                batch_read.record = Some(entry.into_record(None, &batch_read.bins));
+                batch_read.result = ResultCode::Ok;
+            } else {
+                batch_read.record = None;
+                batch_read.result = ResultCode::KeyNotFoundError;
+            }
+        }
+        Ok(batch_reads)
+    }
+
+    /// Read multiple records with the same bin selection in one batch call. See
+    /// [`Client::batch_get_bins`](crate::Client::batch_get_bins).
+    pub async fn batch_get_bins(
+        &self,
+        _policy: &BatchPolicy,
+        keys: &[Key],
+        bins: Bins,
+    ) -> Result<Vec<Option<Record>>, Error> {
+        Ok(keys
+            .iter()
+            .map(|key| {
+                self.read(key)
+                    .ok()
+                    .map(|entry| entry.into_record(None, &bins))
+            })
+            .collect())
+    }
+
+    /// Write record bin(s). See [`Client::put`](crate::Client::put).
+    pub async fn put(
+        &self,
+        policy: &WritePolicy,
+        key: &Key,
+        bins: &[Bin<'_>],
+    ) -> Result<(), CommandError> {
+        self.scalar_write(policy, key, bins, OperationType::Write)
+    }
+
+    /// Write the bins of any type that implements [`ToBins`]. See
+    /// [`Client::put_struct`](crate::Client::put_struct).
+    pub async fn put_struct<T>(
+        &self,
+        policy: &WritePolicy,
+        key: &Key,
+        value: &T,
+    ) -> Result<(), CommandError>
+    where
+        T: ToBins + ?Sized + Sync,
+    {
+        self.scalar_write(policy, key, &value.to_bins(), OperationType::Write)
+    }
+
+    /// Add integer bin values to existing record bin values. See
+    /// [`Client::add`](crate::Client::add).
+    pub async fn add(
+        &self,
+        policy: &WritePolicy,
+        key: &Key,
+        bins: &[Bin<'_>],
+    ) -> Result<(), CommandError> {
+        self.scalar_write(policy, key, bins, OperationType::Incr)
+    }
+
+    /// Append bin string or blob values to existing record bin values. See
+    /// [`Client::append`](crate::Client::append).
+    pub async fn append(
+        &self,
+        policy: &WritePolicy,
+        key: &Key,
+        bins: &[Bin<'_>],
+    ) -> Result<(), CommandError> {
+        self.scalar_write(policy, key, bins, OperationType::Append)
+    }
+
+    /// Prepend bin string or blob values to existing record bin values. See
+    /// [`Client::prepend`](crate::Client::prepend).
+    pub async fn prepend(
+        &self,
+        policy: &WritePolicy,
+        key: &Key,
+        bins: &[Bin<'_>],
+    ) -> Result<(), CommandError> {
+        self.scalar_write(policy, key, bins, OperationType::Prepend)
+    }
+
+    /// Delete record for specified key. See [`Client::delete`](crate::Client::delete).
+    pub async fn delete(&self, _policy: &WritePolicy, key: &Key) -> Result<bool, CommandError> {
+        let mut records = self.lock();
+        Ok(records
+            .remove(&store_key(key))
+            .map_or(false, |entry| !entry.is_expired()))
+    }
+
+    /// Reset record's time to expiration. See [`Client::touch`](crate::Client::touch).
+    pub async fn touch(&self, policy: &WritePolicy, key: &Key) -> Result<(), CommandError> {
+        self.read(key)?;
+        self.write(policy, key, |_| Ok(()))
+    }
+
+    /// Reset record's time to expiration, if the record exists. See
+    /// [`Client::touch_if_exists`](crate::Client::touch_if_exists).
+    pub async fn touch_if_exists(
+        &self,
+        policy: &WritePolicy,
+        key: &Key,
+    ) -> Result<bool, CommandError> {
+        match self.touch(policy, key).await {
+            Ok(()) => Ok(true),
+            Err(CommandError::ServerError(ResultCode::KeyNotFoundError)) => Ok(false),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Determine if a record key exists. See [`Client::exists`](crate::Client::exists).
+    pub async fn exists(&self, _policy: &WritePolicy, key: &Key) -> Result<bool, CommandError> {
+        match self.read(key) {
+            Ok(_) => Ok(true),
+            Err(CommandError::ServerError(ResultCode::KeyNotFoundError)) => Ok(false),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Perform multiple read/write operations on a single key in one batch call. See
+    /// [`Client::operate`](crate::Client::operate).
+    pub async fn operate(
+        &self,
+        policy: &WritePolicy,
+        key: &Key,
+        ops: &[Operation<'_>],
+    ) -> Result<Record, CommandError> {
+        let mut results = HashMap::new();
+
+        let has_write = ops.iter().any(|op| {
+            !matches!(
+                op.op,
+                OperationType::Read
+                    | OperationType::CdtRead
+                    | OperationType::BitRead
+                    | OperationType::HllRead
+            )
+        });
+
+        let (generation, expiration) = if has_write {
+            if ops.iter().any(|op| matches!(op.op, OperationType::Touch)) {
+                self.read(key)?;
+            }
+            self.write(policy, key, |bins| {
+                apply_all(bins, policy, ops, &mut results)
+            })?;
+            let entry = self.read(key).ok();
+            entry.map_or((0, 0), |entry| (entry.generation, entry.expiration))
+        } else {
+            let mut entry = self.read(key)?;
+            apply_all(&mut entry.bins, policy, ops, &mut results)?;
+            (entry.generation, entry.expiration)
+        };
+
+        Ok(Record::new(None, results, generation, expiration))
+    }
+
+    /// Read all records in the specified namespace and set. An empty set name reads the records
+    /// of all sets in the namespace. See [`Client::scan`](crate::Client::scan).
+    pub async fn scan<T>(
+        &self,
+        policy: &ScanPolicy,
+        namespace: &str,
+        set_name: &str,
+        bins: T,
+    ) -> Result<RecordSet, Error>
+    where
+        T: Into<Bins> + Send + Sync + 'static,
+    {
+        self.scan_map(policy, namespace, set_name, bins, |record| record)
+            .await
+    }
+
+    /// Read all records in the specified namespace and set, transformed with the given function.
+    /// See [`Client::scan_map`](crate::Client::scan_map).
+    pub async fn scan_map<B, T, F>(
+        &self,
+        _policy: &ScanPolicy,
+        namespace: &str,
+        set_name: &str,
+        bins: B,
+        f: F,
+    ) -> Result<RecordSet<T>, Error>
+    where
+        B: Into<Bins> + Send + Sync + 'static,
+        T: Send + 'static,
+        F: Fn(Record) -> T + Send + Sync + 'static,
+    {
+        let bins = bins.into();
+        let records = self
+            .lock()
+            .iter()
+            .filter(|((ns, _), entry)| {
+                ns == namespace
+                    && (set_name.is_empty() || entry.set_name == set_name)
+                    && !entry.is_expired()
+            })
+            .map(|((ns, digest), entry)| {
+                let key = Key::from_digest(ns.clone(), entry.set_name.clone(), *digest);
+                f(entry.clone().into_record(Some(key), &bins))
+            })
+            .collect::<Vec<_>>();
+
+        let (tx, rx) = mpsc::channel(records.len().max(1));
+        for record in records {
+            // The channel has enough capacity for all records.
+            tx.try_send(Ok(record)).ok();
+        }
+
+        Ok(RecordSet::new(rx, CancellationToken::new()))
+    }
+
+    /// Remove all records in the specified namespace/set. An empty set name removes the records
+    /// of all sets in the namespace. The last update timestamp is not tracked, therefore
+    /// `before_nanos` is ignored. See [`Client::truncate`](crate::Client::truncate).
+    pub async fn truncate(
+        &self,
+        _policy: &InfoPolicy,
+        namespace: &str,
+        set_name: &str,
+        _before_nanos: i64,
+    ) -> Result<(), Error> {
+        self.lock().retain(|(ns, _), entry| {
+            ns != namespace || (!set_name.is_empty() && entry.set_name != set_name)
+        });
+        Ok(())
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Store> {
+        self.records.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn read(&self, key: &Key) -> Result<Entry> {
+        let mut records = self.lock();
+        match records.entry(store_key(key)) {
+            Occupied(entry) if entry.get().is_expired() => {
+                entry.remove();
+                Err(CommandError::ServerError(ResultCode::KeyNotFoundError))
+            }
+            Occupied(entry) => Ok(entry.get().clone()),
+            Vacant(_) => Err(CommandError::ServerError(ResultCode::KeyNotFoundError)),
+        }
+    }
+
+    fn scalar_write(
+        &self,
+        policy: &WritePolicy,
+        key: &Key,
+        bins: &[Bin<'_>],
+        op: OperationType,
+    ) -> Result<()> {
+        self.write(policy, key, |record| {
+            for bin in bins {
+                apply_scalar(record, op, bin.name, &bin.value)?;
+            }
+            Ok(())
+        })
+    }
+
+    /// Apply a modification to a record, honoring the write policy. The record is only stored if
+    /// the modification succeeds as a whole.
+    fn write(
+        &self,
+        policy: &WritePolicy,
+        key: &Key,
+        modify: impl FnOnce(&mut RecordBins) -> Result<()>,
+    ) -> Result<()> {
+        let mut records = self.lock();
+        let store_key = store_key(key);
+        let existing = records
+            .get(&store_key)
+            .filter(|entry| !entry.is_expired())
+            .cloned();
+
+        match (&policy.record_exists_action, &existing) {
+            (RecordExistsAction::CreateOnly, Some(_)) => {
+                return Err(CommandError::ServerError(ResultCode::KeyExistsError));
+            }
+            (RecordExistsAction::UpdateOnly | RecordExistsAction::ReplaceOnly, None) => {
+                return Err(CommandError::ServerError(ResultCode::KeyNotFoundError));
+            }
+            _ => {}
+        }
+
+        if let Some(entry) = &existing {
+            let valid = match policy.generation_policy {
+                GenerationPolicy::None => true,
+                GenerationPolicy::ExpectGenEqual => entry.generation == policy.generation,
+                GenerationPolicy::ExpectGenGreater => policy.generation > entry.generation,
+            };
+            if !valid {
+                return Err(CommandError::ServerError(ResultCode::GenerationError));
+            }
+        }
+
+        let replace = matches!(
+            policy.record_exists_action,
+            RecordExistsAction::Replace | RecordExistsAction::ReplaceOnly
+        );
+        let (mut bins, generation, expiration) = match existing {
+            Some(entry) if !replace => (entry.bins, entry.generation, entry.expiration),
+            Some(entry) => (HashMap::new(), entry.generation, entry.expiration),
+            None => (HashMap::new(), 0, 0),
+        };
+
+        modify(&mut bins)?;
+        bins.retain(|_, value| *value != Value::Nil);
+
+        if bins.is_empty() {
+            records.remove(&store_key);
+            return Ok(());
+        }
+
+        let expiration = match policy.expiration {
+            Expiration::Seconds(_) | Expiration::SecondsJitter(..) => {
+                now().saturating_add(policy.expiration.into())
+            }
+            Expiration::NamespaceDefault | Expiration::Never => 0,
+            Expiration::DontUpdate => expiration,
+        };
+
+        records.insert(
+            store_key,
+            Entry {
+                set_name: key.set_name.clone().into_owned(),
+                bins,
+                generation: generation.wrapping_add(1),
+                expiration,
+            },
+        );
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl ClientApi for MockClient {
+    async fn get(
+        &self,
+        policy: &BasePolicy,
+        key: &Key,
+        bins: Bins,
+    ) -> Result<Record, CommandError> {
+        MockClient::get(self, policy, key, bins).await
+    }
+
+    async fn batch_get(
+        &self,
+        policy: &BatchPolicy,
+        batch_reads: Vec<BatchRead>,
+    ) -> Result<Vec<BatchRead>, Error> {
+        MockClient::batch_get(self, policy, batch_reads).await
+    }
+
+    async fn batch_get_bins(
+        &self,
+        policy: &BatchPolicy,
+        keys: &[Key],
+        bins: Bins,
+    ) -> Result<Vec<Option<Record>>, Error> {
+        MockClient::batch_get_bins(self, policy, keys, bins).await
+    }
+
+    async fn put(
+        &self,
+        policy: &WritePolicy,
+        key: &Key,
+        bins: &[Bin<'_>],
+    ) -> Result<(), CommandError> {
+        MockClient::put(self, policy, key, bins).await
+    }
+
+    async fn add(
+        &self,
+        policy: &WritePolicy,
+        key: &Key,
+        bins: &[Bin<'_>],
+    ) -> Result<(), CommandError> {
+        MockClient::add(self, policy, key, bins).await
+    }
+
+    async fn append(
+        &self,
+        policy: &WritePolicy,
+        key: &Key,
+        bins: &[Bin<'_>],
+    ) -> Result<(), CommandError> {
+        MockClient::append(self, policy, key, bins).await
+    }
+
+    async fn prepend(
+        &self,
+        policy: &WritePolicy,
+        key: &Key,
+        bins: &[Bin<'_>],
+    ) -> Result<(), CommandError> {
+        MockClient::prepend(self, policy, key, bins).await
+    }
+
+    async fn delete(&self, policy: &WritePolicy, key: &Key) -> Result<bool, CommandError> {
+        MockClient::delete(self, policy, key).await
+    }
+
+    async fn touch(&self, policy: &WritePolicy, key: &Key) -> Result<(), CommandError> {
+        MockClient::touch(self, policy, key).await
+    }
+
+    async fn touch_if_exists(&self, policy: &WritePolicy, key: &Key) -> Result<bool, CommandError> {
+        MockClient::touch_if_exists(self, policy, key).await
+    }
+
+    async fn exists(&self, policy: &WritePolicy, key: &Key) -> Result<bool, CommandError> {
+        MockClient::exists(self, policy, key).await
+    }
+
+    async fn operate(
+        &self,
+        policy: &WritePolicy,
+        key: &Key,
+        ops: &[Operation<'_>],
+    ) -> Result<Record, CommandError> {
+        MockClient::operate(self, policy, key, ops).await
+    }
+
+    async fn scan(
+        &self,
+        policy: &ScanPolicy,
+        namespace: &str,
+        set_name: &str,
+        bins: Bins,
+    ) -> Result<RecordSet, Error> {
+        MockClient::scan(self, policy, namespace, set_name, bins).await
+    }
+
+    async fn truncate(
+        &self,
+        policy: &InfoPolicy,
+        namespace: &str,
+        set_name: &str,
+        before_nanos: i64,
+    ) -> Result<(), Error> {
+        MockClient::truncate(self, policy, namespace, set_name, before_nanos).await
+    }
+}
+
+fn store_key(key: &Key) -> (String, [u8; 20]) {
+    (key.namespace.clone().into_owned(), key.digest)
+}
+
+/// Current time in seconds since the Citrusleaf epoch.
+fn now() -> u32 {
+    SystemTime::now()
+        .duration_since(citrusleaf_epoch())
+        .map_or(0, |d| u32::try_from(d.as_secs()).unwrap_or(u32::MAX))
+}
+
+fn unsupported<T>() -> Result<T> {
+    Err(CommandError::ServerError(ResultCode::UnsupportedFeature))
+}
+
+fn bin_type_error<T>() -> Result<T> {
+    Err(CommandError::ServerError(ResultCode::BinTypeError))
+}
+
+/// Add an operation result to the record, merging multiple results for the same bin into a list,
+/// the same way the client does when parsing server responses.
+fn push_result(results: &mut RecordBins, name: &str, value: Value) {
+    if value == Value::Nil {
+        return;
+    }
+
+    match results.entry(name.into()) {
+        Vacant(entry) => {
+            entry.insert(value);
+        }
+        Occupied(entry) => match entry.into_mut() {
+            Value::List(list) => list.push(value),
+            prev => {
+                *prev = crate::list!(prev.clone(), value);
+            }
+        },
+    }
+}
+
+/// Apply all operations in order. Operations with the `NO_FAIL` write flag, which
+/// [`WritePolicy::continue_on_error`] adds, are skipped if they fail, like the server does.
+fn apply_all(
+    bins: &mut RecordBins,
+    policy: &WritePolicy,
+    ops: &[Operation<'_>],
+    results: &mut RecordBins,
+) -> Result<()> {
+    for op in ops {
+        let op = if policy.continue_on_error {
+            Cow::Owned(op.with_no_fail())
+        } else {
+            Cow::Borrowed(op)
+        };
+
+        match apply(bins, &op, results) {
+            Err(CommandError::ServerError(code))
+                if operations::is_operation_error(code) && no_fail(&op) => {}
+            result => result.map_err(|err| operation_failed(ops, err))?,
+        }
+    }
+
+    Ok(())
+}
+
+fn no_fail(op: &Operation<'_>) -> bool {
+    match &op.data {
+        OperationData::CdtListOp(op) | OperationData::CdtBitOp(op) | OperationData::HllOp(op) => {
+            op.no_fail()
+        }
+        _ => false,
+    }
+}
+
+/// Attach the index of the failed operation to errors that it caused, like the client does if the
+/// operation can be determined.
+fn operation_failed(ops: &[Operation<'_>], err: CommandError) -> CommandError {
+    match err {
+        CommandError::ServerError(code) if operations::is_operation_error(code) => {
+            match operations::failed_index(ops) {
+                Some(index) => CommandError::OperationFailed { index, code },
+                None => CommandError::ServerError(code),
+            }
+        }
+        err => err,
+    }
+}
+
+fn apply(bins: &mut RecordBins, op: &Operation<'_>, results: &mut RecordBins) -> Result<()> {
+    if !op.ctx.is_empty() {
+        return unsupported();
+    }
+
+    let name = match &op.bin {
+        OperationBin::Name(name) => Some(name.as_ref()),
+        OperationBin::None | OperationBin::All => None,
+    };
+
+    match (op.op, name, &op.data) {
+        (OperationType::Read, None, _) => {
+            if matches!(op.bin, OperationBin::All) {
+                for (name, value) in bins.iter() {
+                    push_result(results, name, value.clone());
+                }
+            }
+        }
+        (OperationType::Read, Some(name), _) => {
+            if let Some(value) = bins.get(name) {
+                push_result(results, name, value.clone());
+            }
+        }
+        (OperationType::Touch, _, _) => {}
+        (OperationType::Delete, _, _) => bins.clear(),
+        (
+            OperationType::Write
+            | OperationType::Incr
+            | OperationType::Append
+            | OperationType::Prepend,
+            Some(name),
+            OperationData::Value(value),
+        ) => apply_scalar(bins, op.op, name, value)?,
+        // Reading from a missing bin doesn't return a result, and must not create the bin.
+        (OperationType::CdtRead, Some(name), _) if !bins.contains_key(name) => {}
+        (
+            OperationType::CdtRead | OperationType::CdtWrite,
+            Some(name),
+            OperationData::CdtListOp(cdt_op),
+        ) => {
+            let bin = bins
+                .entry(name.into())
+                .or_insert_with(|| Value::List(Vec::new()));
+            let list = match bin {
+                Value::List(list) => list,
+                _ => return bin_type_error(),
+            };
+            let result = apply_list(list, cdt_op)?;
+            push_result(results, name, result);
+        }
+        (
+            OperationType::CdtRead | OperationType::CdtWrite,
+            Some(name),
+            OperationData::CdtMapOp(cdt_op),
+        ) => {
+            let bin = bins
+                .entry(name.into())
+                .or_insert_with(|| Value::HashMap(HashMap::new()));
+            let map = match bin {
+                Value::HashMap(map) => map,
+                _ => return bin_type_error(),
+            };
+            let result = apply_map(map, cdt_op)?;
+            push_result(results, name, result);
+        }
+        _ => return unsupported(),
+    }
+
+    Ok(())
+}
+
+fn apply_scalar(bins: &mut RecordBins, op: OperationType, name: &str, value: &Value) -> Result<()> {
+    let current = bins.get(name).filter(|v| **v != Value::Nil);
+    let new = match (op, current, value) {
+        (OperationType::Write, _, value) | (_, None, value) => value.clone(),
+        (OperationType::Incr, Some(Value::Int(a)), Value::Int(b)) => Value::Int(a.wrapping_add(*b)),
+        (OperationType::Incr, Some(Value::Float(a)), Value::Float(b)) => {
+            Value::Float(FloatValue::F64((float(*a) + float(*b)).into()))
+        }
+        (OperationType::Append, Some(Value::String(a)), Value::String(b)) => {
+            Value::String(format!("{a}{b}"))
+        }
+        (OperationType::Prepend, Some(Value::String(a)), Value::String(b)) => {
+            Value::String(format!("{b}{a}"))
+        }
+        (OperationType::Append, Some(Value::Blob(a)), Value::Blob(b)) => {
+            Value::Blob([a.as_slice(), b].concat())
+        }
+        (OperationType::Prepend, Some(Value::Blob(a)), Value::Blob(b)) => {
+            Value::Blob([b.as_slice(), a].concat())
+        }
+        _ => return bin_type_error(),
+    };
+
+    bins.insert(name.into(), new);
+    Ok(())
+}
+
+fn float(value: FloatValue) -> f64 {
+    value.to_f64()
+}
+
+fn arg_int(args: &[Argument<'_>], index: usize) -> Result<i64> {
+    match args.get(index) {
+        Some(Argument::Int(v) | Argument::PairsReturnType(v)) => Ok(*v),
+        Some(Argument::Byte(v)) => Ok((*v).into()),
+        _ => Err(CommandError::ServerError(ResultCode::ParameterError)),
+    }
+}
+
+fn arg_value<'a>(args: &'a [Argument<'_>], index: usize) -> Result<&'a Value> {
+    match args.get(index) {
+        Some(Argument::Value(v)) => Ok(v),
+        _ => Err(CommandError::ServerError(ResultCode::ParameterError)),
+    }
+}
+
+fn arg_list<'a>(args: &'a [Argument<'_>], index: usize) -> Result<&'a [Value]> {
+    match args.get(index) {
+        Some(Argument::List(v)) => Ok(v),
+        _ => Err(CommandError::ServerError(ResultCode::ParameterError)),
+    }
+}
+
+/// Resolve a possibly negative index, counted from the end of the list.
+fn list_index(len: usize, index: i64) -> Option<usize> {
+    let len = i64::try_from(len).ok()?;
+    let index = if index < 0 { len + index } else { index };
+    usize::try_from(index).ok()
+}
+
+/// Sizes and counts are returned as signed integers by the server.
+fn count(value: usize) -> Value {
+    Value::Int(i64::try_from(value).unwrap_or(i64::MAX))
+}
+
+fn not_applicable<T>() -> Result<T> {
+    Err(CommandError::ServerError(ResultCode::OpNotApplicable))
+}
+
+const LIST_APPEND: u8 = list::OpType::Append as u8;
+const LIST_APPEND_ITEMS: u8 = list::OpType::AppendItems as u8;
+const LIST_INSERT: u8 = list::OpType::Insert as u8;
+const LIST_INSERT_ITEMS: u8 = list::OpType::InsertItems as u8;
+const LIST_POP: u8 = list::OpType::Pop as u8;
+const LIST_REMOVE: u8 = list::OpType::Remove as u8;
+const LIST_SET: u8 = list::OpType::Set as u8;
+const LIST_CLEAR: u8 = list::OpType::Clear as u8;
+const LIST_SIZE: u8 = list::OpType::Size as u8;
+const LIST_GET: u8 = list::OpType::Get as u8;
+const LIST_GET_RANGE: u8 = list::OpType::GetRange as u8;
+
+fn apply_list(list: &mut Vec<Value>, op: &cdt::Operation<'_>) -> Result<Value> {
+    let args = op.args.as_slice();
+    let size = |list: &Vec<Value>| count(list.len());
+
+    Ok(match op.op {
+        LIST_APPEND => {
+            list.push(arg_value(args, 0)?.clone());
+            size(list)
+        }
+        LIST_APPEND_ITEMS => {
+            list.extend_from_slice(arg_list(args, 0)?);
+            size(list)
+        }
+        LIST_INSERT | LIST_INSERT_ITEMS => {
+            let index = match list_index(list.len(), arg_int(args, 0)?) {
+                Some(index) => index,
+                None => return not_applicable(),
+            };
+            if index > list.len() {
+                list.resize(index, Value::Nil);
+            }
+            if op.op == LIST_INSERT {
+                list.insert(index, arg_value(args, 1)?.clone());
+            } else {
+                let items = arg_list(args, 1)?;
+                list.splice(index..index, items.iter().cloned());
+            }
+            size(list)
+        }
+        LIST_POP | LIST_REMOVE => {
+            let index = match list_index(list.len(), arg_int(args, 0)?) {
+                Some(index) if index < list.len() => index,
+                _ => return not_applicable(),
+            };
+            let value = list.remove(index);
+            if op.op == LIST_POP {
+                value
+            } else {
+                count(1)
+            }
+        }
+        LIST_SET => {
+            let index = match list_index(list.len(), arg_int(args, 0)?) {
+                Some(index) => index,
+                None => return not_applicable(),
+            };
+            let value = arg_value(args, 1)?.clone();
+            if index < list.len() {
+                list[index] = value;
+            } else {
+                list.resize(index, Value::Nil);
+                list.push(value);
+            }
+            Value::Nil
+        }
+        LIST_CLEAR => {
+            list.clear();
+            Value::Nil
+        }
+        LIST_SIZE => size(list),
+        LIST_GET => match list_index(list.len(), arg_int(args, 0)?) {
+            Some(index) if index < list.len() => list[index].clone(),
+            _ => return not_applicable(),
+        },
+        LIST_GET_RANGE => {
+            let start = list_index(list.len(), arg_int(args, 0)?)
+                .unwrap_or(0)
+                .min(list.len());
+            let end = match arg_int(args, 1) {
+                Ok(count) => start.saturating_add(usize::try_from(count).unwrap_or(0)),
+                Err(_) => list.len(),
+            }
+            .min(list.len());
+            Value::List(list[start..end].to_vec())
+        }
+        _ => return unsupported(),
+    })
+}
+
+const MAP_ADD: u8 = map::OpType::Add as u8;
+const MAP_ADD_ITEMS: u8 = map::OpType::AddItems as u8;
+const MAP_PUT: u8 = map::OpType::Put as u8;
+const MAP_PUT_ITEMS: u8 = map::OpType::PutItems as u8;
+const MAP_REPLACE: u8 = map::OpType::Replace as u8;
+const MAP_REPLACE_ITEMS: u8 = map::OpType::ReplaceItems as u8;
+const MAP_CLEAR: u8 = map::OpType::Clear as u8;
+const MAP_SIZE: u8 = map::OpType::Size as u8;
+const MAP_GET_BY_KEY: u8 = map::OpType::GetByKey as u8;
+const MAP_REMOVE_BY_KEY: u8 = map::OpType::RemoveByKey as u8;
+
+fn map_key(value: &Value) -> Result<MapKey> {
+    Ok(match value {
+        Value::Int(v) => MapKey::Int(*v),
+        Value::Uint(v) => MapKey::Uint(*v),
+        Value::Float(v) => MapKey::Float(*v),
+        Value::String(v) => MapKey::String(v.clone()),
+        _ => return Err(CommandError::ServerError(ResultCode::ParameterError)),
+    })
+}
+
+fn map_put(map: &mut HashMap<MapKey, Value>, op: u8, key: MapKey, value: Value) -> Result<()> {
+    let exists = map.contains_key(&key);
+    if matches!(op, MAP_ADD | MAP_ADD_ITEMS) && exists {
+        return Err(CommandError::ServerError(ResultCode::ElementExists));
+    }
+    if matches!(op, MAP_REPLACE | MAP_REPLACE_ITEMS) && !exists {
+        return Err(CommandError::ServerError(ResultCode::ElementNotFound));
+    }
+
+    map.insert(key, value);
+    Ok(())
+}
+
+fn apply_map(map: &mut HashMap<MapKey, Value>, op: &cdt::Operation<'_>) -> Result<Value> {
+    let args = op.args.as_slice();
+
+    Ok(match op.op {
+        MAP_ADD | MAP_PUT | MAP_REPLACE => {
+            let key = map_key(arg_value(args, 0)?)?;
+            // A nil value is left out of the arguments.
+            let value = arg_value(args, 1).map_or(Value::Nil, Value::clone);
+            map_put(map, op.op, key, value)?;
+            count(map.len())
+        }
+        MAP_ADD_ITEMS | MAP_PUT_ITEMS | MAP_REPLACE_ITEMS => {
+            let items = match args.first() {
+                Some(Argument::Map(items)) => items,
+                _ => return Err(CommandError::ServerError(ResultCode::ParameterError)),
+            };
+            for (key, value) in items.iter() {
+                map_put(map, op.op, key.clone(), value.clone())?;
+            }
+            count(map.len())
+        }
+        MAP_CLEAR => {
+            map.clear();
+            Value::Nil
+        }
+        MAP_SIZE => count(map.len()),
+        MAP_GET_BY_KEY | MAP_REMOVE_BY_KEY => {
+            let return_type = arg_int(args, 0)?;
+            let key = map_key(arg_value(args, 1)?)?;
+            let value = if op.op == MAP_REMOVE_BY_KEY {
+                map.remove(&key)
+            } else {
+                map.get(&key).cloned()
+            };
+            map_result(return_type, op.key_value_pairs(), key, value)?
+        }
+        _ => return unsupported(),
+    })
+}
+
+/// Build the result of a single item selection. Key/value results that were requested through a
+/// typed map return are given as ordered pairs, like the client decodes them.
+fn map_result(return_type: i64, pairs: bool, key: MapKey, value: Option<Value>) -> Result<Value> {
+    const NONE: i64 = map::ReturnType::None as i64;
+    const COUNT: i64 = map::ReturnType::Count as i64;
+    const KEY: i64 = map::ReturnType::Key as i64;
+    const VALUE: i64 = map::ReturnType::Value as i64;
+    const KEY_VALUE: i64 = map::ReturnType::KeyValue as i64;
+
+    Ok(match (return_type, value) {
+        (NONE, _) | (KEY | VALUE | KEY_VALUE, None) => Value::Nil,
+        (COUNT, value) => count(usize::from(value.is_some())),
+        (KEY, Some(_)) => key.into(),
+        (VALUE, Some(value)) => value,
+        (KEY_VALUE, Some(value)) if pairs => {
+            Value::List(vec![Value::List(vec![key.into(), value])])
+        }
+        (KEY_VALUE, Some(value)) => Value::HashMap(HashMap::from([(key, value)])),
+        _ => return unsupported(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::MockClient;
+    use crate::{
+        operations::{list, map, scalar, Ops},
+        policies::{
+            BasePolicy, BatchPolicy, GenerationPolicy, InfoPolicy, RecordExistsAction, ScanPolicy,
+            WritePolicy,
+        },
+        BatchRead, Bin, Bins, ClientApi, Key, MapKey, ResultCode, Value,
+    };
+
+    fn key() -> Key {
+        Key::new("test", "test", "key")
+    }
+
+    #[tokio::test]
+    async fn put_get_delete() {
+        let client = MockClient::new();
+        let policy = WritePolicy::default();
+
+        client
+            .put(&policy, &key(), &[Bin::new("a", 1), Bin::new("b", "x")])
+            .await
+            .unwrap();
+
+        let record = client
+            .get(&BasePolicy::default(), &key(), ["a"])
+            .await
+            .unwrap();
+        assert_eq!(record.bins.len(), 1);
+        assert_eq!(record.bins.get("a"), Some(&Value::from(1)));
+        assert_eq!(record.generation, 1);
+
+        assert!(client.touch_if_exists(&policy, &key()).await.unwrap());
+        assert!(client.delete(&policy, &key()).await.unwrap());
+        assert!(!client.exists(&policy, &key()).await.unwrap());
+        assert!(!client.touch_if_exists(&policy, &key()).await.unwrap());
+        let err = client
+            .get(&BasePolicy::default(), &key(), Bins::All)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("Key not found"), "{err}");
+    }
+
+    #[tokio::test]
+    async fn write_policies() {
+        let client = MockClient::new();
+        let create_only = WritePolicy {
+            record_exists_action: RecordExistsAction::CreateOnly,
+            ..WritePolicy::default()
+        };
+
+        client
+            .put(&create_only, &key(), &[Bin::new("a", 1)])
+            .await
+            .unwrap();
+        let err = client
+            .put(&create_only, &key(), &[Bin::new("a", 1)])
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            crate::errors::CommandError::ServerError(ResultCode::KeyExistsError)
+        ));
+
+        let wrong_gen = WritePolicy {
+            generation_policy: GenerationPolicy::ExpectGenEqual,
+            generation: 5,
+            ..WritePolicy::default()
+        };
+        let err = client
+            .put(&wrong_gen, &key(), &[Bin::new("a", 2)])
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            crate::errors::CommandError::ServerError(ResultCode::GenerationError)
+        ));
+    }
+
+    #[tokio::test]
+    async fn scalar_ops() {
+        let client = MockClient::new();
+        let policy = WritePolicy::default();
+
+        client
+            .put(&policy, &key(), &[Bin::new("i", 1), Bin::new("s", "b")])
+            .await
+            .unwrap();
+        client
+            .add(&policy, &key(), &[Bin::new("i", 2)])
+            .await
+            .unwrap();
+        client
+            .append(&policy, &key(), &[Bin::new("s", "c")])
+            .await
+            .unwrap();
+        client
+            .prepend(&policy, &key(), &[Bin::new("s", "a")])
+            .await
+            .unwrap();
+
+        let bin = Bin::new("i", 10);
+        let record = client
+            .operate(&policy, &key(), &[scalar::add(&bin), scalar::get()])
+            .await
+            .unwrap();
+        assert_eq!(record.bins.get("i"), Some(&Value::from(13)));
+        assert_eq!(record.bins.get("s"), Some(&Value::from("abc")));
+        assert_eq!(record.generation, 5);
+    }
+
+    #[tokio::test]
+    async fn blob_append_prepend() {
+        let client = MockClient::new();
+        let policy = WritePolicy::default();
+
+        client
+            .put(&policy, &key(), &[Bin::new("b", vec![2u8])])
+            .await
+            .unwrap();
+        client
+            .append(&policy, &key(), &[Bin::new("b", vec![3u8])])
+            .await
+            .unwrap();
+        client
+            .prepend(&policy, &key(), &[Bin::new("b", vec![1u8])])
+            .await
+            .unwrap();
+        assert!(client
+            .append(&policy, &key(), &[Bin::new("b", "s")])
+            .await
+            .is_err());
+
+        let record = client
+            .get(&BasePolicy::default(), &key(), Bins::All)
+            .await
+            .unwrap();
+        assert_eq!(record.bins.get("b"), Some(&Value::from(vec![1u8, 2, 3])));
+    }
+
+    #[tokio::test]
+    async fn list_and_map_ops() {
+        let client = MockClient::new();
+        let policy = WritePolicy::default();
+
+        let ops = Ops::new()
+            .list_append("l", 1)
+            .list_append("l", 2)
+            .list_insert("l", 0, 0)
+            .map_put("m", "k", "v")
+            .build();
+        client.operate(&policy, &key(), &ops).await.unwrap();
+
+        let ops = Ops::new()
+            .list_size("l")
+            .list_get("l", -1)
+            .map_get_by_key("m", "k", map::ReturnType::Value)
+            .build();
+        let record = client.operate(&policy, &key(), &ops).await.unwrap();
+        assert_eq!(
+            record.bins.get("l"),
+            Some(&Value::List(vec![Value::from(3), Value::from(2)]))
+        );
+        assert_eq!(record.bins.get("m"), Some(&Value::from("v")));
+
+        let record = client
+            .operate(&policy, &key(), &[list::pop("l", 0), list::size("l")])
+            .await
+            .unwrap();
+        assert_eq!(
+            record.bins.get("l"),
+            Some(&Value::List(vec![Value::from(0), Value::from(2)]))
+        );
+    }
+
+    #[tokio::test]
+    async fn unsupported_op() {
+        let client = MockClient::new();
+        let err = client
+            .operate(
+                &WritePolicy::default(),
+                &key(),
+                &[list::sort("l", list::SortFlags::empty())],
+            )
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            crate::errors::CommandError::ServerError(ResultCode::UnsupportedFeature)
+        ));
+    }
+
+    #[tokio::test]
+    async fn failed_operation_index() {
+        let client = MockClient::new();
+        let policy = WritePolicy::default();
+        let key_a = Value::from("a");
+        let one = Value::from(1);
+        let create_only = map::Policy::new(map::OrderType::Unordered, map::WriteMode::CreateOnly);
+        client
+            .operate(
+                &policy,
+                &key(),
+                &[map::put(map::Policy::default(), "m", &key_a, &one)],
+            )
+            .await
+            .unwrap();
+
+        let err = client
+            .operate(
+                &policy,
+                &key(),
+                &[
+                    scalar::get_bin("m"),
+                    map::put(create_only, "m", &key_a, &one),
+                ],
+            )
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            crate::errors::CommandError::OperationFailed {
+                index: 1,
+                code: ResultCode::ElementExists,
+            }
+        ));
+
+        // With several writes, the failed one can't be determined.
+        let err = client
+            .operate(
+                &policy,
+                &key(),
+                &[
+                    map::put(map::Policy::default(), "m", &key_a, &one),
+                    map::put(create_only, "m", &key_a, &one),
+                ],
+            )
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            crate::errors::CommandError::ServerError(ResultCode::ElementExists)
+        ));
+    }
+
+    #[tokio::test]
+    async fn map_key_value_pairs() {
+        let client = MockClient::new();
+        let policy = WritePolicy::default();
+        let key_a = Value::from("a");
+        let one = Value::from(1);
+        let ret = map::MapReturn::key_values();
+        let record = client
+            .operate(
+                &policy,
+                &key(),
+                &[
+                    map::put(map::Policy::default(), "m", &key_a, &one),
+                    map::get_by_key("m", &key_a, ret),
+                ],
+            )
+            .await
+            .unwrap();
+
+        let result = match record.bins.get("m") {
+            Some(Value::List(results)) => results[1].clone(),
+            other => panic!("unexpected result: {other:?}"),
+        };
+        assert_eq!(ret.decode(result), Some(vec![(MapKey::from("a"), one)]));
+    }
+
+    #[tokio::test]
+    async fn continue_on_error() {
+        let client = MockClient::new();
+        let one = Value::from(1);
+        let ops = [
+            list::insert(list::Policy::default(), "l", -10, &one),
+            list::append(list::Policy::default(), "l", &one),
+        ];
+
+        let err = client
+            .operate(&WritePolicy::default(), &key(), &ops)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            crate::errors::CommandError::ServerError(ResultCode::OpNotApplicable)
+        ));
+
+        let policy = WritePolicy::builder().continue_on_error(true).build();
+        client.operate(&policy, &key(), &ops).await.unwrap();
+        let record = client
+            .get(&BasePolicy::default(), &key(), Bins::All)
+            .await
+            .unwrap();
+        assert_eq!(record.bins.get("l"), Some(&Value::from(vec![one])));
+    }
+
+    #[tokio::test]
+    async fn batch_scan_truncate() {
+        let client: Arc<dyn ClientApi> = Arc::new(MockClient::new());
+        let policy = WritePolicy::default();
+
+        for i in 0..3 {
+            let key = Key::new("test", "a", i);
+            client
+                .put(&policy, &key, &[Bin::new("i", i)])
+                .await
+                .unwrap();
+        }
+        let key = Key::new("test", "b", 0);
+        client
+            .put(&policy, &key, &[Bin::new("i", 0)])
+            .await
+            .unwrap();
+
+        let reads = vec![
+            BatchRead::new(Key::new("test", "a", 1), Bins::All),
+            BatchRead::new(Key::new("test", "a", 5), Bins::All),
+        ];
+        let reads = client
+            .batch_get(&BatchPolicy::default(), reads)
+            .await
+            .unwrap();
+        assert!(reads[0].record.is_some());
+        assert_eq!(ResultCode::Ok, reads[0].result);
+        assert!(reads[1].record.is_none());
+        assert_eq!(ResultCode::KeyNotFoundError, reads[1].result);
+
+        let records = client
+            .batch_get_bins(
+                &BatchPolicy::default(),
+                &[Key::new("test", "a", 5), Key::new("test", "a", 1)],
+                Bins::All,
+            )
+            .await
+            .unwrap();
+        assert!(records[0].is_none());
+        assert!(records[1].is_some());
+
+        let mut rs = client
+            .scan(&ScanPolicy::default(), "test", "a", Bins::All)
+            .await
+            .unwrap();
+        let mut count = 0;
+        while let Some(record) = rs.next().await {
+            assert_eq!(record.unwrap().key.unwrap().set_name, "a");
+            count += 1;
+        }
+        assert_eq!(count, 3);
+
+        client
+            .truncate(&InfoPolicy::default(), "test", "a", 0)
+            .await
+            .unwrap();
+        assert!(client.exists(&policy, &key).await.unwrap());
+        assert!(!client
+            .exists(&policy, &Key::new("test", "a", 0))
+            .await
+            .unwrap());
+    }
+
+    #[tokio::test]
+    async fn scan_map() {
+        let client = MockClient::new();
+        let policy = WritePolicy::default();
+
+        for i in 0..3 {
+            client
+                .put(&policy, &Key::new("test", "a", i), &[Bin::new("i", i)])
+                .await
+                .unwrap();
+        }
+
+        let mut rs = client
+            .scan_map(&ScanPolicy::default(), "test", "a", Bins::All, |record| {
+                record.bins.get("i").cloned()
+            })
+            .await
+            .unwrap();
+        let mut values = Vec::new();
+        while let Some(value) = rs.next().await {
+            values.push(value.unwrap());
+        }
+        values.sort_by_key(|value| match value {
+            Some(Value::Int(value)) => *value,
+            _ => -1,
+        });
+
+        assert_eq!(
+            values,
+            [
+                Some(Value::Int(0)),
+                Some(Value::Int(1)),
+                Some(Value::Int(2))
+            ]
+        );
+    }
+}
@@ -0,0 +1,215 @@
+//! Conversions between bin values and the date-time types of the [`chrono`] and [`time`] crates,
+//! enabled by the features of the same name.
+//!
+//! Timestamps are stored as integer nanoseconds since the Unix epoch by default, which keeps them
+//! compact and allows comparing them on the server side, for example in filter expressions. They
+//! can be stored as human readable RFC 3339 strings instead, by converting them with
+//! [`Timestamp::to_value`]. Decoding accepts both formats.
+//!
+//! ```
+//! # #[cfg(feature = "chrono")]
+//! # {
+//! use chrono::{DateTime, TimeZone, Utc};
+//! use windpike::{
+//!     timestamp::{Timestamp, TimestampFormat},
+//!     Bin, Value,
+//! };
+//!
+//! let now = Utc.timestamp_opt(1_700_000_000, 0).unwrap();
+//! let bins = [
+//!     Bin::new("nanos", Value::try_from(now).unwrap()),
+//!     Bin::new("string", now.to_value(TimestampFormat::Rfc3339).unwrap()),
+//! ];
+//!
+//! assert_eq!(Some(now), bins[0].value.as_timestamp::<DateTime<Utc>>());
+//! assert_eq!(Some(now), bins[1].value.as_timestamp::<DateTime<Utc>>());
+//! # }
+//! ```
+//!
+//! [`chrono`]: https://docs.rs/chrono
+//! [`time`]: https://docs.rs/time
+
+use crate::Value;
+
+/// Representation of a timestamp as bin value.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum TimestampFormat {
+    /// Integer nanoseconds since the Unix epoch. This can only represent timestamps between the
+    /// years 1677 and 2262.
+    #[default]
+    Nanos,
+    /// String in the RFC 3339 format, always in UTC.
+    Rfc3339,
+}
+
+/// Errors that can happen when encoding a timestamp as bin value.
+#[derive(Debug, thiserror::Error)]
+pub enum TimestampError {
+    /// The timestamp is outside the range that the format can represent.
+    #[error("timestamp out of range for the {0:?} format")]
+    OutOfRange(TimestampFormat),
+}
+
+/// Date-time type that can be stored in bins.
+pub trait Timestamp: Sized {
+    /// Encode the timestamp as bin value in the given format. Fails if the timestamp is outside
+    /// the range that the format can represent.
+    fn to_value(&self, format: TimestampFormat) -> Result<Value, TimestampError>;
+
+    /// Decode a timestamp from a bin value that was stored in any of the [`TimestampFormat`]s.
+    /// Returns `None` if the value is of any other type, or out of range.
+    fn from_value(value: &Value) -> Option<Self>;
+}
+
+#[cfg(feature = "chrono")]
+mod chrono_impl {
+    use chrono::{DateTime, SecondsFormat, TimeZone, Utc};
+
+    use super::{Timestamp, TimestampError, TimestampFormat};
+    use crate::Value;
+
+    impl Timestamp for DateTime<Utc> {
+        fn to_value(&self, format: TimestampFormat) -> Result<Value, TimestampError> {
+            match format {
+                TimestampFormat::Nanos => self
+                    .timestamp_nanos_opt()
+                    .map(Value::Int)
+                    .ok_or(TimestampError::OutOfRange(format)),
+                TimestampFormat::Rfc3339 => Ok(Value::String(
+                    self.to_rfc3339_opts(SecondsFormat::AutoSi, true),
+                )),
+            }
+        }
+
+        fn from_value(value: &Value) -> Option<Self> {
+            match value {
+                Value::Int(nanos) => Some(Utc.timestamp_nanos(*nanos)),
+                Value::String(value) => DateTime::parse_from_rfc3339(value)
+                    .ok()
+                    .map(|value| value.with_timezone(&Utc)),
+                _ => None,
+            }
+        }
+    }
+
+    impl TryFrom<DateTime<Utc>> for Value {
+        type Error = TimestampError;
+
+        fn try_from(value: DateTime<Utc>) -> Result<Self, Self::Error> {
+            value.to_value(TimestampFormat::Nanos)
+        }
+    }
+}
+
+#[cfg(feature = "time")]
+mod time_impl {
+    use time::{format_description::well_known::Rfc3339, OffsetDateTime, UtcOffset};
+
+    use super::{Timestamp, TimestampError, TimestampFormat};
+    use crate::Value;
+
+    impl Timestamp for OffsetDateTime {
+        fn to_value(&self, format: TimestampFormat) -> Result<Value, TimestampError> {
+            match format {
+                TimestampFormat::Nanos => self
+                    .unix_timestamp_nanos()
+                    .try_into()
+                    .map(Value::Int)
+                    .map_err(|_| TimestampError::OutOfRange(format)),
+                TimestampFormat::Rfc3339 => self
+                    .to_offset(UtcOffset::UTC)
+                    .format(&Rfc3339)
+                    .map(Value::String)
+                    .map_err(|_| TimestampError::OutOfRange(format)),
+            }
+        }
+
+        fn from_value(value: &Value) -> Option<Self> {
+            match value {
+                Value::Int(nanos) => Self::from_unix_timestamp_nanos(i128::from(*nanos)).ok(),
+                Value::String(value) => Self::parse(value, &Rfc3339).ok(),
+                _ => None,
+            }
+        }
+    }
+
+    impl TryFrom<OffsetDateTime> for Value {
+        type Error = TimestampError;
+
+        fn try_from(value: OffsetDateTime) -> Result<Self, Self::Error> {
+            value.to_value(TimestampFormat::Nanos)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn chrono_round_trip() {
+        use chrono::{DateTime, TimeZone, Utc};
+
+        use super::{Timestamp, TimestampError, TimestampFormat};
+        use crate::Value;
+
+        let time = Utc.timestamp_opt(1_700_000_000, 123_456_789).unwrap();
+
+        assert_eq!(
+            Value::try_from(time).unwrap(),
+            Value::Int(1_700_000_000_123_456_789)
+        );
+        assert_eq!(
+            time.to_value(TimestampFormat::Rfc3339).unwrap(),
+            Value::from("2023-11-14T22:13:20.123456789Z")
+        );
+
+        for format in [TimestampFormat::Nanos, TimestampFormat::Rfc3339] {
+            let value = time.to_value(format).unwrap();
+            assert_eq!(Some(time), DateTime::<Utc>::from_value(&value));
+        }
+
+        let far = Utc.with_ymd_and_hms(3000, 1, 1, 0, 0, 0).unwrap();
+        assert!(matches!(
+            Value::try_from(far),
+            Err(TimestampError::OutOfRange(TimestampFormat::Nanos))
+        ));
+
+        assert_eq!(None, DateTime::<Utc>::from_value(&Value::from("now")));
+        assert_eq!(None, DateTime::<Utc>::from_value(&Value::from(1.5)));
+    }
+
+    #[cfg(feature = "time")]
+    #[test]
+    fn time_round_trip() {
+        use time::{OffsetDateTime, UtcOffset};
+
+        use super::{Timestamp, TimestampError, TimestampFormat};
+        use crate::Value;
+
+        let time = OffsetDateTime::from_unix_timestamp_nanos(1_700_000_000_123_456_789).unwrap();
+
+        assert_eq!(
+            Value::try_from(time).unwrap(),
+            Value::Int(1_700_000_000_123_456_789)
+        );
+        assert_eq!(
+            time.to_offset(UtcOffset::from_hms(2, 0, 0).unwrap())
+                .to_value(TimestampFormat::Rfc3339)
+                .unwrap(),
+            Value::from("2023-11-14T22:13:20.123456789Z")
+        );
+
+        for format in [TimestampFormat::Nanos, TimestampFormat::Rfc3339] {
+            let value = time.to_value(format).unwrap();
+            assert_eq!(Some(time), OffsetDateTime::from_value(&value));
+        }
+
+        let far = OffsetDateTime::from_unix_timestamp(32_503_680_000).unwrap();
+        assert!(matches!(
+            Value::try_from(far),
+            Err(TimestampError::OutOfRange(TimestampFormat::Nanos))
+        ));
+
+        assert_eq!(None, OffsetDateTime::from_value(&Value::from("now")));
+    }
+}
@@ -1,4 +1,11 @@
-use std::{collections::HashMap, fmt, result::Result as StdResult, vec::Vec};
+use std::{
+    cmp::Ordering,
+    collections::HashMap,
+    fmt,
+    hash::{Hash, Hasher},
+    result::Result as StdResult,
+    vec::Vec,
+};
 
 use ordered_float::OrderedFloat;
 
@@ -6,6 +13,7 @@ use crate::{
     commands::{buffer::BufferError, ParseParticleError, ParticleType},
     errors::Result,
     msgpack::{self, decoder, encoder, MsgpackError},
+    policies::DecodeLimits,
 };
 
 macro_rules! from {
@@ -19,7 +27,12 @@ macro_rules! from {
 }
 
 /// Container for floating point bin values stored in the Aerospike database.
-#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+///
+/// The server stores all floating point numbers as 64-bit doubles. Therefore, 32-bit numbers are
+/// widened to `f64` when they're sent to the server and are always read back as [`Self::F64`].
+/// To keep these round-trips transparent, values are compared and hashed by their widened value,
+/// so `F32(1.5)` and `F64(1.5)` are considered equal, also when used in a [`MapKey`].
+#[derive(Clone, Copy, Debug)]
 pub enum FloatValue {
     /// 32-bit floating point number.
     F32(OrderedFloat<f32>),
@@ -65,6 +78,49 @@ impl FloatValue {
             Self::F64(value) => Some(value.0),
         }
     }
+
+    /// Get the value as 64-bit floating point number, widening 32-bit numbers losslessly. This is
+    /// the representation that the server stores.
+    ///
+    /// ```
+    /// # use windpike::FloatValue;
+    /// assert_eq!(1.5, FloatValue::from(1.5_f32).to_f64());
+    /// assert_eq!(1.5, FloatValue::from(1.5_f64).to_f64());
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn to_f64(&self) -> f64 {
+        match self {
+            Self::F32(value) => value.0.into(),
+            Self::F64(value) => value.0,
+        }
+    }
+}
+
+impl PartialEq for FloatValue {
+    fn eq(&self, other: &Self) -> bool {
+        OrderedFloat(self.to_f64()) == OrderedFloat(other.to_f64())
+    }
+}
+
+impl Eq for FloatValue {}
+
+impl Hash for FloatValue {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        OrderedFloat(self.to_f64()).hash(state);
+    }
+}
+
+impl PartialOrd for FloatValue {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for FloatValue {
+    fn cmp(&self, other: &Self) -> Ordering {
+        OrderedFloat(self.to_f64()).cmp(&OrderedFloat(other.to_f64()))
+    }
 }
 
 from!(FloatValue, F32, f32);
@@ -81,6 +137,8 @@ impl fmt::Display for FloatValue {
 
 /// Key for a [`Value::HashMap`] entry, which is a subset of the [`Value`] type, as only a limited
 /// set of its variants are allowed to be used as map keys.
+///
+/// Keys are ordered the same way as the corresponding [`Value`] variants.
 #[derive(Clone, Debug, Eq, Hash, PartialEq)]
 pub enum MapKey {
     /// 64-bit signed integer.
@@ -207,6 +265,38 @@ impl MapKey {
     }
 }
 
+impl PartialOrd for MapKey {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for MapKey {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let rank = |key: &Self| match key {
+            Self::Int(_) | Self::Uint(_) => 2,
+            Self::String(_) => 3,
+            Self::Float(_) => 7,
+        };
+
+        match (self, other) {
+            (Self::Int(a), Self::Int(b)) => a.cmp(b),
+            (Self::Uint(a), Self::Uint(b)) => a.cmp(b),
+            (Self::Int(a), Self::Uint(b)) => cmp_int_uint(*a, *b),
+            (Self::Uint(a), Self::Int(b)) => cmp_int_uint(*b, *a).reverse(),
+            (Self::String(a), Self::String(b)) => a.cmp(b),
+            (Self::Float(a), Self::Float(b)) => a.cmp(b),
+            _ => rank(self).cmp(&rank(other)),
+        }
+    }
+}
+
+/// Compare a signed with an unsigned integer by their numeric value. Equal numbers are ordered
+/// signed first, to stay consistent with their equality.
+fn cmp_int_uint(int: i64, uint: u64) -> Ordering {
+    i128::from(int).cmp(&i128::from(uint)).then(Ordering::Less)
+}
+
 from!(MapKey, Int, i8, i16, i32, i64, u8, u16, u32);
 from!(MapKey, Uint, u64);
 from!(MapKey, Float, f32, f64);
@@ -225,6 +315,24 @@ impl From<usize> for MapKey {
 }
 
 /// Container for bin values stored in the Aerospike database.
+///
+/// # Ordering
+///
+/// Values are ordered the same way as the server orders the elements of lists and maps, for
+/// example when sorting them or selecting elements by rank. Values of different types are ordered
+/// by their type first:
+///
+/// [`Nil`](Self::Nil) < [`Bool`](Self::Bool) < [`Int`](Self::Int) and [`Uint`](Self::Uint) <
+/// [`String`](Self::String) < [`List`](Self::List) < [`HashMap`](Self::HashMap) <
+/// [`Blob`](Self::Blob) < [`Float`](Self::Float) < [`GeoJson`](Self::GeoJson)
+///
+/// Values of the same type are compared by their content. Integers are compared numerically,
+/// strings and blobs byte by byte and lists element by element, where a shorter list comes first
+/// if it's a prefix of the other. Maps with fewer entries come first, and maps of the same size
+/// are compared by their entries, sorted by key.
+///
+/// [`Hll`](Self::Hll) and [`Unknown`](Self::Unknown) values can't be elements of lists or maps,
+/// and are ordered after all other types.
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub enum Value {
     /// Empty value.
@@ -259,13 +367,26 @@ pub enum Value {
     GeoJson(String),
     /// [HyperLogLog](https://docs.aerospike.com/server/guide/data-types/hll) value.
     Hll(Vec<u8>),
+    /// Value of a particle type that this client doesn't know about, like a data type that was
+    /// introduced by a newer server version.
+    ///
+    /// These are only returned if enabled with
+    /// [`ClientPolicy::keep_unknown_particles`](crate::policies::ClientPolicy::keep_unknown_particles).
+    /// Otherwise, reading a record with such a bin fails as a whole. Writing the value back stores
+    /// the raw bytes with the same particle type.
+    Unknown {
+        /// Particle type as sent by the server.
+        particle_type: u8,
+        /// Undecoded content of the particle.
+        raw: Vec<u8>,
+    },
 }
 
 impl Value {
     /// Determine the particle type for the value used in the wire protocol.
     #[must_use]
-    pub(crate) fn particle_type(&self) -> ParticleType {
-        match self {
+    pub(crate) fn particle_type(&self) -> u8 {
+        let particle_type = match self {
             Self::Nil => ParticleType::Null,
             Self::Bool(_) => ParticleType::Bool,
             Self::Int(_) => ParticleType::Integer,
@@ -280,6 +401,27 @@ impl Value {
             Self::HashMap(_) => ParticleType::Map,
             Self::GeoJson(_) => ParticleType::GeoJson,
             Self::Hll(_) => ParticleType::Hll,
+            Self::Unknown { particle_type, .. } => return *particle_type,
+        };
+        particle_type as u8
+    }
+
+    /// Human readable name of the value's type, for use in error messages.
+    #[must_use]
+    pub(crate) const fn type_name(&self) -> &'static str {
+        match self {
+            Self::Nil => "nil",
+            Self::Bool(_) => "bool",
+            Self::Int(_) => "integer",
+            Self::Uint(_) => "unsigned integer",
+            Self::Float(_) => "float",
+            Self::String(_) => "string",
+            Self::Blob(_) => "blob",
+            Self::List(_) => "list",
+            Self::HashMap(_) => "map",
+            Self::GeoJson(_) => "GeoJSON",
+            Self::Hll(_) => "HyperLogLog",
+            Self::Unknown { .. } => "unknown",
         }
     }
 
@@ -460,6 +602,16 @@ impl Value {
         }
     }
 
+    /// If this value is a timestamp in any of the
+    /// [`TimestampFormat`](crate::timestamp::TimestampFormat)s, return it as the requested
+    /// date-time type. Return `None` oterwhise.
+    #[cfg(any(feature = "chrono", feature = "time"))]
+    #[inline]
+    #[must_use]
+    pub fn as_timestamp<T: crate::timestamp::Timestamp>(&self) -> Option<T> {
+        T::from_value(self)
+    }
+
     /// If this value is a string, return the associated `String`. Return `None` oterwhise. In
     /// contrast to [`Self::as_str`], this method consumes the value to return the owned string.
     ///
@@ -557,7 +709,7 @@ impl Value {
             Self::Blob(b) => b.len(),
             Self::List(_) | Self::HashMap(_) => encoder::pack_value(&mut msgpack::Sink, self),
             Self::GeoJson(s) => 3 + s.len(),
-            Self::Hll(h) => h.len(),
+            Self::Hll(h) | Self::Unknown { raw: h, .. } => h.len(),
         }
     }
 
@@ -571,24 +723,37 @@ impl Value {
                 "Aerospike doesn't support 64-bit unsigned integers natively. Cast forth and back \
                  between i64 to store u64 values."
             ),
-            Self::Float(value) => match value {
-                FloatValue::F32(value) => w.write_f32(value.0),
-                FloatValue::F64(value) => w.write_f64(value.0),
-            },
+            // The float particle is always a double.
+            Self::Float(value) => w.write_f64(value.to_f64()),
             Self::String(value) => w.write_str(value),
-            Self::Blob(value) | Self::Hll(value) => w.write_bytes(value),
+            Self::Blob(value) | Self::Hll(value) | Self::Unknown { raw: value, .. } => {
+                w.write_bytes(value)
+            }
             Self::List(_) | Self::HashMap(_) => encoder::pack_value(w, self),
             Self::GeoJson(value) => w.write_geo(value),
         }
     }
 
-    /// Deserialize the value out of the given reader.
+    /// Deserialize the value out of the given reader. Particle types that aren't known are kept as
+    /// [`Self::Unknown`] if [`ReadOptions::keep_unknown`] is set, and fail otherwise.
     pub(crate) fn read_from(
         r: &mut impl msgpack::Read,
         particle_type: u8,
         length: usize,
+        options: ReadOptions,
     ) -> Result<Self, ParticleError> {
-        match ParticleType::try_from(particle_type)? {
+        let known = match ParticleType::try_from(particle_type) {
+            Ok(known) => known,
+            Err(_) if options.keep_unknown => {
+                return Ok(Value::Unknown {
+                    particle_type,
+                    raw: r.read_bytes(length),
+                })
+            }
+            Err(err) => return Err(err.into()),
+        };
+
+        match known {
             ParticleType::Null => Ok(Value::Nil),
             ParticleType::Integer => Ok(Value::Int(r.read_i64())),
             ParticleType::Float => Ok(Value::Float(r.read_f64().into())),
@@ -596,13 +761,73 @@ impl Value {
             ParticleType::Blob => Ok(Value::Blob(r.read_bytes(length))),
             ParticleType::Bool => Ok(Value::Bool(r.read_bool())),
             ParticleType::Hll => Ok(Value::Hll(r.read_bytes(length))),
-            ParticleType::Map => Ok(decoder::unpack_value_map(r)?),
-            ParticleType::List => Ok(decoder::unpack_value_list(r)?),
+            ParticleType::Map if options.map_pairs => {
+                Ok(decoder::unpack_value_map_pairs(r, &options.limits)?)
+            }
+            ParticleType::Map => Ok(decoder::unpack_value_map(r, &options.limits)?),
+            ParticleType::List => Ok(decoder::unpack_value_list(r, &options.limits)?),
             ParticleType::GeoJson => Ok(Value::GeoJson(r.read_geo(length)?)),
         }
     }
 }
 
+impl PartialOrd for Value {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Value {
+    fn cmp(&self, other: &Self) -> Ordering {
+        fn sorted(map: &HashMap<MapKey, Value>) -> Vec<(&MapKey, &Value)> {
+            let mut entries = map.iter().collect::<Vec<_>>();
+            entries.sort_unstable_by(|a, b| a.0.cmp(b.0));
+            entries
+        }
+
+        let rank = |value: &Self| match value {
+            Self::Nil => 0,
+            Self::Bool(_) => 1,
+            Self::Int(_) | Self::Uint(_) => 2,
+            Self::String(_) => 3,
+            Self::List(_) => 4,
+            Self::HashMap(_) => 5,
+            Self::Blob(_) => 6,
+            Self::Float(_) => 7,
+            Self::GeoJson(_) => 8,
+            Self::Hll(_) => 9,
+            Self::Unknown { .. } => 10,
+        };
+
+        match (self, other) {
+            (Self::Bool(a), Self::Bool(b)) => a.cmp(b),
+            (Self::Int(a), Self::Int(b)) => a.cmp(b),
+            (Self::Uint(a), Self::Uint(b)) => a.cmp(b),
+            (Self::Int(a), Self::Uint(b)) => cmp_int_uint(*a, *b),
+            (Self::Uint(a), Self::Int(b)) => cmp_int_uint(*b, *a).reverse(),
+            (Self::Float(a), Self::Float(b)) => a.cmp(b),
+            (Self::String(a), Self::String(b)) | (Self::GeoJson(a), Self::GeoJson(b)) => a.cmp(b),
+            (Self::Blob(a), Self::Blob(b)) | (Self::Hll(a), Self::Hll(b)) => a.cmp(b),
+            (Self::List(a), Self::List(b)) => a.cmp(b),
+            (Self::HashMap(a), Self::HashMap(b)) => a
+                .len()
+                .cmp(&b.len())
+                .then_with(|| sorted(a).cmp(&sorted(b))),
+            (
+                Self::Unknown {
+                    particle_type: a_type,
+                    raw: a_raw,
+                },
+                Self::Unknown {
+                    particle_type: b_type,
+                    raw: b_raw,
+                },
+            ) => (a_type, a_raw).cmp(&(b_type, b_raw)),
+            _ => rank(self).cmp(&rank(other)),
+        }
+    }
+}
+
 impl fmt::Display for Value {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> StdResult<(), fmt::Error> {
         match self {
@@ -615,6 +840,9 @@ impl fmt::Display for Value {
             Self::Blob(value) | Self::Hll(value) => write!(f, "{value:?}"),
             Self::List(value) => write!(f, "{value:?}"),
             Self::HashMap(value) => write!(f, "{value:?}"),
+            Self::Unknown { particle_type, raw } => {
+                write!(f, "<unknown particle type {particle_type}: {raw:?}>")
+            }
         }
     }
 }
@@ -675,6 +903,249 @@ impl From<MapKey> for Value {
     }
 }
 
+/// Collect values into a [`Value::List`].
+///
+/// ```
+/// use windpike::Value;
+///
+/// let list = (1..=3).collect::<Value>();
+/// assert_eq!(
+///     Value::from(vec![Value::from(1), Value::from(2), Value::from(3)]),
+///     list
+/// );
+/// ```
+impl<V> FromIterator<V> for Value
+where
+    V: Into<Value>,
+{
+    fn from_iter<I: IntoIterator<Item = V>>(iter: I) -> Self {
+        Self::List(iter.into_iter().map(Into::into).collect())
+    }
+}
+
+/// Collect key-value pairs into a [`Value::HashMap`].
+///
+/// ```
+/// use windpike::Value;
+///
+/// let map = [("a", 1), ("b", 2)].into_iter().collect::<Value>();
+/// assert_eq!(
+///     Some(&Value::from(2)),
+///     map.as_hash_map().and_then(|map| map.get(&"b".into()))
+/// );
+/// ```
+impl<K, V> FromIterator<(K, V)> for Value
+where
+    K: Into<MapKey>,
+    V: Into<Value>,
+{
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        Self::HashMap(
+            iter.into_iter()
+                .map(|(k, v)| (k.into(), v.into()))
+                .collect(),
+        )
+    }
+}
+
+/// Append values to a [`Value::List`], where [`Value::Nil`] is treated as an empty list.
+///
+/// # Panics
+///
+/// Panics if the value is neither a list nor nil.
+impl<V> Extend<V> for Value
+where
+    V: Into<Value>,
+{
+    fn extend<I: IntoIterator<Item = V>>(&mut self, iter: I) {
+        if *self == Self::Nil {
+            *self = Self::List(Vec::new());
+        }
+
+        match self {
+            Self::List(list) => list.extend(iter.into_iter().map(Into::into)),
+            other => panic!("can't extend a {} value with list items", other.type_name()),
+        }
+    }
+}
+
+/// Insert key-value pairs into a [`Value::HashMap`], where [`Value::Nil`] is treated as an empty
+/// map.
+///
+/// # Panics
+///
+/// Panics if the value is neither a map nor nil.
+impl<K, V> Extend<(K, V)> for Value
+where
+    K: Into<MapKey>,
+    V: Into<Value>,
+{
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        if *self == Self::Nil {
+            *self = Self::HashMap(HashMap::new());
+        }
+
+        match self {
+            Self::HashMap(map) => map.extend(iter.into_iter().map(|(k, v)| (k.into(), v.into()))),
+            other => panic!(
+                "can't extend a {} value with map entries",
+                other.type_name()
+            ),
+        }
+    }
+}
+
+/// Conversion of a bin value into a Rust type, as used by [`Record::take`](crate::Record::take)
+/// and the fields of types that implement [`FromRecord`](crate::FromRecord).
+pub trait FromValue: Sized {
+    /// Name of the expected value type, which is reported when the conversion fails.
+    const TYPE_NAME: &'static str;
+
+    /// Convert the value, or return [`None`] if it is of a different type.
+    fn from_value(value: Value) -> Option<Self>;
+
+    /// Value to use if the bin is missing from the record. By default, missing bins are an error.
+    #[must_use]
+    fn from_missing() -> Option<Self> {
+        None
+    }
+}
+
+macro_rules! from_value_int {
+    ($($ty:ty),+) => {
+        $(impl FromValue for $ty {
+            const TYPE_NAME: &'static str = stringify!($ty);
+
+            fn from_value(value: Value) -> Option<Self> {
+                match value {
+                    Value::Int(value) => value.try_into().ok(),
+                    Value::Uint(value) => value.try_into().ok(),
+                    _ => None,
+                }
+            }
+        })+
+    };
+}
+
+from_value_int!(i8, i16, i32, i64, isize, u8, u16, u32, u64, usize);
+
+impl FromValue for Value {
+    const TYPE_NAME: &'static str = "any";
+
+    fn from_value(value: Value) -> Option<Self> {
+        Some(value)
+    }
+}
+
+/// Clusters without boolean support store boolean bins as the integers `0` and `1`, which are
+/// accepted as well.
+impl FromValue for bool {
+    const TYPE_NAME: &'static str = "bool";
+
+    fn from_value(value: Value) -> Option<Self> {
+        match value {
+            Value::Bool(value) => Some(value),
+            Value::Int(0) => Some(false),
+            Value::Int(1) => Some(true),
+            _ => None,
+        }
+    }
+}
+
+/// Floats are always stored as `f64` on the server, so reading them into an `f32` narrows them.
+impl FromValue for f32 {
+    const TYPE_NAME: &'static str = "float";
+
+    #[allow(clippy::cast_possible_truncation)]
+    fn from_value(value: Value) -> Option<Self> {
+        match value {
+            Value::Float(value) => Some(value.to_f64() as f32),
+            _ => None,
+        }
+    }
+}
+
+impl FromValue for f64 {
+    const TYPE_NAME: &'static str = "float";
+
+    fn from_value(value: Value) -> Option<Self> {
+        match value {
+            Value::Float(value) => Some(value.to_f64()),
+            _ => None,
+        }
+    }
+}
+
+impl FromValue for String {
+    const TYPE_NAME: &'static str = "string";
+
+    fn from_value(value: Value) -> Option<Self> {
+        value.into_string()
+    }
+}
+
+impl FromValue for Vec<u8> {
+    const TYPE_NAME: &'static str = "blob";
+
+    fn from_value(value: Value) -> Option<Self> {
+        value.into_bytes()
+    }
+}
+
+impl FromValue for Vec<Value> {
+    const TYPE_NAME: &'static str = "list";
+
+    fn from_value(value: Value) -> Option<Self> {
+        value.into_list()
+    }
+}
+
+#[allow(clippy::implicit_hasher)]
+impl FromValue for HashMap<MapKey, Value> {
+    const TYPE_NAME: &'static str = "map";
+
+    fn from_value(value: Value) -> Option<Self> {
+        value.into_hash_map()
+    }
+}
+
+/// Optional bins, which are [`None`] if the bin is missing or contains [`Value::Nil`].
+impl<T: FromValue> FromValue for Option<T> {
+    const TYPE_NAME: &'static str = T::TYPE_NAME;
+
+    fn from_value(value: Value) -> Option<Self> {
+        match value {
+            Value::Nil => Some(None),
+            value => T::from_value(value).map(Some),
+        }
+    }
+
+    fn from_missing() -> Option<Self> {
+        Some(None)
+    }
+}
+
+/// Options for deserializing values out of server responses, derived from the
+/// [`ClientPolicy`](crate::policies::ClientPolicy).
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct ReadOptions {
+    /// Keep values of unknown particle types as [`Value::Unknown`].
+    pub keep_unknown: bool,
+    /// Limits for decoding lists and maps.
+    pub limits: DecodeLimits,
+    /// Skip bins whose values are larger than this many bytes.
+    pub max_bin_size: Option<usize>,
+    /// Decode maps into a [`Value::List`] of key/value pairs, which keeps the order of the entries.
+    pub map_pairs: bool,
+}
+
+impl ReadOptions {
+    /// Whether a bin value of the given size in bytes must be skipped instead of decoded.
+    pub fn skip_bin(&self, size: usize) -> bool {
+        self.max_bin_size.map_or(false, |max| size > max)
+    }
+}
+
 /// Errors that can happen when parsing content markers from the wire format of an encoded value.
 #[derive(Debug, thiserror::Error)]
 pub enum ParticleError {
@@ -711,4 +1182,147 @@ mod tests {
             String::from(r#"{"type":"Point"}"#)
         );
     }
+
+    #[test]
+    fn bool_from_int() {
+        use super::FromValue;
+
+        assert_eq!(Some(true), bool::from_value(Value::Bool(true)));
+        assert_eq!(Some(false), bool::from_value(Value::Int(0)));
+        assert_eq!(Some(true), bool::from_value(Value::Int(1)));
+        assert_eq!(None, bool::from_value(Value::Int(2)));
+        assert_eq!(None, bool::from_value(Value::Uint(1 << 63)));
+    }
+
+    #[test]
+    fn float_widening() {
+        use std::collections::HashMap;
+
+        use super::{FloatValue, MapKey};
+
+        assert_eq!(FloatValue::from(1.5_f32), FloatValue::from(1.5_f64));
+        assert_ne!(FloatValue::from(0.1_f32), FloatValue::from(0.1_f64));
+
+        let map = HashMap::from([(MapKey::from(1.5_f64), Value::from(1))]);
+        assert_eq!(map.get(&MapKey::from(1.5_f32)), Some(&Value::from(1)));
+
+        let mut buf = Vec::new();
+        assert_eq!(Value::from(1.5_f32).write_to(&mut buf), 8);
+        assert_eq!(buf, 1.5_f64.to_be_bytes());
+    }
+
+    #[test]
+    fn unknown_particle() {
+        use super::{ParticleError, ReadOptions};
+        use crate::{commands::buffer::Buffer, msgpack::Write};
+
+        let keep = ReadOptions {
+            keep_unknown: true,
+            ..ReadOptions::default()
+        };
+
+        let value = Value::Unknown {
+            particle_type: 99,
+            raw: vec![1, 2, 3],
+        };
+        assert_eq!(99, value.particle_type());
+        assert_eq!(3, value.estimate_size());
+
+        let mut buf = Buffer::new(0);
+        assert_eq!(3, value.write_to(&mut buf));
+        assert_eq!(Value::read_from(&mut buf, 99, 3, keep).unwrap(), value);
+
+        buf.write_bytes(&[1, 2, 3]);
+        assert!(matches!(
+            Value::read_from(&mut buf, 99, 3, ReadOptions::default()),
+            Err(ParticleError::UnrecognizedParticle(_))
+        ));
+    }
+
+    #[test]
+    fn collect_and_extend() {
+        let mut list = (1..=2).collect::<Value>();
+        list.extend(["a"]);
+        assert_eq!(crate::list!(1, 2, "a"), list);
+
+        let mut map = Value::Nil;
+        map.extend([("a", crate::list!(1)), ("b", Value::Nil)]);
+        map.extend(vec![("c", (0..2).collect::<Value>())]);
+        assert_eq!(
+            crate::map!("a" => crate::list!(1), "b" => Value::Nil, "c" => crate::list!(0, 1)),
+            map
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "can't extend a map value with list items")]
+    fn extend_mismatch() {
+        let mut map = [("a", 1)].into_iter().collect::<Value>();
+        map.extend([1]);
+    }
+
+    #[test]
+    fn server_ordering() {
+        use std::collections::HashMap;
+
+        use super::MapKey;
+
+        let mut values = vec![
+            Value::GeoJson(r#"{"type":"Point"}"#.to_owned()),
+            Value::from(0.5),
+            Value::from(vec![1_u8, 2]),
+            Value::from([(MapKey::from("a"), Value::from(1))]),
+            Value::from([Value::from(1)]),
+            Value::from("a"),
+            Value::Uint(u64::MAX),
+            Value::from(-1),
+            Value::Bool(true),
+            Value::Bool(false),
+            Value::Nil,
+        ];
+        values.sort();
+        assert_eq!(Value::Nil, values[0]);
+        assert_eq!(Value::Bool(false), values[1]);
+        assert_eq!(Value::Bool(true), values[2]);
+        assert_eq!(Value::from(-1), values[3]);
+        assert_eq!(Value::Uint(u64::MAX), values[4]);
+        assert!(matches!(values[5], Value::String(_)));
+        assert!(matches!(values[6], Value::List(_)));
+        assert!(matches!(values[7], Value::HashMap(_)));
+        assert!(matches!(values[8], Value::Blob(_)));
+        assert!(matches!(values[9], Value::Float(_)));
+        assert!(matches!(values[10], Value::GeoJson(_)));
+
+        // Integers are compared numerically, but stay consistent with equality.
+        assert!(Value::Int(2) > Value::Uint(1));
+        assert!(Value::Int(1) < Value::Uint(1));
+        assert!(Value::from(1.5_f32) == Value::from(1.5_f64));
+        assert!(Value::from(1.5_f32).cmp(&Value::from(1.5_f64)).is_eq());
+
+        // Shorter lists come first if they're a prefix, otherwise the elements decide.
+        let list =
+            |items: &[i64]| Value::from(items.iter().copied().map(Value::from).collect::<Vec<_>>());
+        assert!(list(&[1, 2]) < list(&[1, 2, 0]));
+        assert!(list(&[1, 3]) > list(&[1, 2, 0]));
+
+        // Smaller maps come first, then the entries in key order decide.
+        let map = |entries: &[(i64, i64)]| {
+            Value::from(
+                entries
+                    .iter()
+                    .map(|&(k, v)| (MapKey::from(k), Value::from(v)))
+                    .collect::<HashMap<_, _>>(),
+            )
+        };
+        assert!(map(&[(9, 9)]) < map(&[(1, 1), (2, 2)]));
+        assert!(map(&[(2, 1), (1, 2)]) > map(&[(1, 1), (2, 2)]));
+        assert!(map(&[(1, 1), (3, 0)]) > map(&[(2, 2), (1, 1)]));
+
+        let mut keys = vec![MapKey::from(0.5), MapKey::from("a"), MapKey::from(7)];
+        keys.sort();
+        assert_eq!(
+            vec![MapKey::from(7), MapKey::from("a"), MapKey::from(0.5)],
+            keys
+        );
+    }
 }
@@ -0,0 +1,461 @@
+//! Loading of large amounts of records, with bounded concurrency.
+//!
+//! The [`BulkWriter`] takes care of the parts that every data loader needs: it keeps a fixed
+//! amount of writes in flight, retries writes that failed for transient reasons, and collects the
+//! outcome of all writes together with throughput statistics.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use std::sync::Arc;
+//!
+//! use windpike::{
+//!     policies::{ClientPolicy, WritePolicy},
+//!     writer::BulkWriter,
+//!     Bin, Client, Key,
+//! };
+//!
+//! # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+//! let client = Arc::new(Client::new(&ClientPolicy::default(), "localhost:3000").await?);
+//! let writer = BulkWriter::new(client, WritePolicy::default()).concurrency(64);
+//!
+//! writer
+//!     .write_all((0..10_000).map(|i| (Key::new("test", "test", i), vec![Bin::new("value", i)])))
+//!     .await;
+//!
+//! let report = writer.finish().await;
+//! println!(
+//!     "wrote {} records ({:.0}/s), {} failed",
+//!     report.stats.written,
+//!     report.stats.throughput(),
+//!     report.failures.len(),
+//! );
+//! # Ok(())
+//! # }
+//! ```
+
+use std::{
+    future::Future,
+    mem,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex, PoisonError,
+    },
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use futures_core::Stream;
+use tokio::sync::Semaphore;
+
+use crate::{
+    commands::{self, CommandError},
+    policies::WritePolicy,
+    Bin, ClientApi, Key, ResultCode,
+};
+
+/// Writes records concurrently, with a bound on the amount of writes that are in flight at the
+/// same time.
+///
+/// Records are handed to the writer with [`Self::write`], [`Self::write_all`] or
+/// [`Self::write_stream`], which only wait
+/// until there is room for another write, so producers are naturally slowed down to the pace of
+/// the cluster. Once all records are handed over, [`Self::finish`] waits for the remaining writes
+/// and returns the [`BulkReport`].
+pub struct BulkWriter {
+    client: Arc<dyn ClientApi>,
+    policy: Arc<WritePolicy>,
+    concurrency: usize,
+    max_retries: usize,
+    retry_delay: Duration,
+    permits: Arc<Semaphore>,
+    state: Arc<State>,
+}
+
+/// Progress that is shared between the writer and its spawned writes.
+struct State {
+    started: Instant,
+    written: AtomicU64,
+    retries: AtomicU64,
+    failures: Mutex<Vec<BulkFailure>>,
+}
+
+impl BulkWriter {
+    /// Default value for the [`Self::concurrency`] parameter.
+    pub const DEFAULT_CONCURRENCY: usize = 16;
+    /// Default value for the [`Self::max_retries`] parameter.
+    pub const DEFAULT_MAX_RETRIES: usize = 3;
+    /// Default value for the [`Self::retry_delay`] parameter.
+    pub const DEFAULT_RETRY_DELAY: Duration = Duration::from_millis(100);
+
+    /// Create a new writer, that writes all records with the given policy.
+    #[must_use]
+    pub fn new(client: Arc<dyn ClientApi>, policy: WritePolicy) -> Self {
+        Self {
+            client,
+            policy: Arc::new(policy),
+            concurrency: Self::DEFAULT_CONCURRENCY,
+            max_retries: Self::DEFAULT_MAX_RETRIES,
+            retry_delay: Self::DEFAULT_RETRY_DELAY,
+            permits: Arc::new(Semaphore::new(Self::DEFAULT_CONCURRENCY)),
+            state: Arc::new(State {
+                started: Instant::now(),
+                written: AtomicU64::new(0),
+                retries: AtomicU64::new(0),
+                failures: Mutex::new(Vec::new()),
+            }),
+        }
+    }
+
+    /// Set the maximum amount of writes that are in flight at the same time. The value is clamped
+    /// to the range of `1` to [`u32::MAX`].
+    #[must_use]
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.clamp(1, u32::MAX as usize);
+        self.permits = Arc::new(Semaphore::new(self.concurrency));
+        self
+    }
+
+    /// Set how many times a write is retried, if it failed for a transient reason like a timeout
+    /// or an overloaded server. This comes on top of the retries of the
+    /// [`BasePolicy::max_retries`](crate::policies::BasePolicy::max_retries), which only cover
+    /// network errors.
+    #[must_use]
+    pub fn max_retries(mut self, max_retries: usize) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Set the delay before retrying a failed write, which doubles with each attempt.
+    #[must_use]
+    pub fn retry_delay(mut self, retry_delay: Duration) -> Self {
+        self.retry_delay = retry_delay;
+        self
+    }
+
+    /// Write a single record in the background. This only waits until there is room for another
+    /// write, but not for the write itself to complete.
+    ///
+    /// # Panics
+    /// Panics if called from outside of a Tokio runtime.
+    pub async fn write(&self, key: Key, bins: Vec<Bin<'static>>) {
+        // The semaphore is never closed.
+        let permit = match Arc::clone(&self.permits).acquire_owned().await {
+            Ok(permit) => permit,
+            Err(_) => return,
+        };
+
+        let client = Arc::clone(&self.client);
+        let policy = Arc::clone(&self.policy);
+        let state = Arc::clone(&self.state);
+        let max_retries = self.max_retries;
+        let mut delay = self.retry_delay;
+
+        tokio::spawn(async move {
+            let mut attempt = 0;
+            let result = loop {
+                match client.put(&policy, &key, &bins).await {
+                    Err(err) if attempt < max_retries && is_retryable(&err) => {
+                        attempt += 1;
+                        state.retries.fetch_add(1, Ordering::Relaxed);
+                        tokio::time::sleep(delay).await;
+                        delay = delay.saturating_mul(2);
+                    }
+                    result => break result,
+                }
+            };
+
+            match result {
+                Ok(()) => {
+                    state.written.fetch_add(1, Ordering::Relaxed);
+                }
+                Err(error) => state
+                    .failures
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .push(BulkFailure {
+                        key,
+                        attempts: attempt + 1,
+                        error,
+                    }),
+            }
+
+            drop(permit);
+        });
+    }
+
+    /// Write all records of the iterator, one after the other with [`Self::write`].
+    pub async fn write_all<I>(&self, records: I)
+    where
+        I: IntoIterator<Item = (Key, Vec<Bin<'static>>)>,
+    {
+        for (key, bins) in records {
+            self.write(key, bins).await;
+        }
+    }
+
+    /// Write all records of the stream, one after the other with [`Self::write`]. This allows
+    /// feeding the writer from asynchronous producers, like a channel or a file that is read
+    /// asynchronously.
+    pub async fn write_stream<S>(&self, records: S)
+    where
+        S: Stream<Item = (Key, Vec<Bin<'static>>)>,
+    {
+        tokio::pin!(records);
+        while let Some((key, bins)) = next(records.as_mut()).await {
+            self.write(key, bins).await;
+        }
+    }
+
+    /// Snapshot of the progress so far, which includes writes that are still in flight.
+    #[must_use]
+    pub fn stats(&self) -> BulkStats {
+        self.state
+            .stats(self.concurrency - self.permits.available_permits())
+    }
+
+    /// Wait for all writes to complete and report their outcome.
+    pub async fn finish(self) -> BulkReport {
+        // All permits are only available again, once every spawned write completed.
+        let permits = u32::try_from(self.concurrency).unwrap_or(u32::MAX);
+        let _permits = self.permits.acquire_many(permits).await;
+
+        BulkReport {
+            stats: self.state.stats(0),
+            failures: mem::take(
+                &mut *self
+                    .state
+                    .failures
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner),
+            ),
+        }
+    }
+}
+
+impl State {
+    fn stats(&self, in_flight: usize) -> BulkStats {
+        BulkStats {
+            written: self.written.load(Ordering::Relaxed),
+            failed: self
+                .failures
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .len() as u64,
+            retries: self.retries.load(Ordering::Relaxed),
+            in_flight,
+            elapsed: self.started.elapsed(),
+        }
+    }
+}
+
+/// Wait for the next item of the stream.
+fn next<S: Stream>(stream: Pin<&mut S>) -> Next<'_, S> {
+    Next { stream }
+}
+
+/// Future that resolves to the next item of a stream.
+struct Next<'a, S> {
+    stream: Pin<&'a mut S>,
+}
+
+impl<S: Stream> Future for Next<'_, S> {
+    type Output = Option<S::Item>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.stream.as_mut().poll_next(cx)
+    }
+}
+
+/// Whether a write may succeed when it's attempted again.
+fn is_retryable(err: &CommandError) -> bool {
+    commands::is_network_error(err)
+        || matches!(
+            err,
+            CommandError::ServerError(
+                ResultCode::Timeout
+                    | ResultCode::KeyBusy
+                    | ResultCode::DeviceOverload
+                    | ResultCode::PartitionUnavailable
+            )
+        )
+}
+
+/// Counters of a [`BulkWriter`].
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct BulkStats {
+    /// Amount of records that were written successfully.
+    pub written: u64,
+    /// Amount of records that couldn't be written, even after retrying.
+    pub failed: u64,
+    /// Amount of retried writes, across all records.
+    pub retries: u64,
+    /// Amount of writes that were still in progress when the stats were taken.
+    pub in_flight: usize,
+    /// Time since the writer was created.
+    pub elapsed: Duration,
+}
+
+impl BulkStats {
+    /// Completed writes per second, both successful and failed ones.
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn throughput(&self) -> f64 {
+        let secs = self.elapsed.as_secs_f64();
+        if secs > 0.0 {
+            (self.written + self.failed) as f64 / secs
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Outcome of all writes of a [`BulkWriter`]. Every record that isn't listed in the failures was
+/// written successfully.
+#[derive(Debug)]
+pub struct BulkReport {
+    /// Final counters of the writer.
+    pub stats: BulkStats,
+    /// Records that couldn't be written, in the order the writes completed.
+    pub failures: Vec<BulkFailure>,
+}
+
+/// A single record that a [`BulkWriter`] failed to write.
+#[derive(Debug)]
+pub struct BulkFailure {
+    /// Key of the record.
+    pub key: Key,
+    /// How many times the write was attempted.
+    pub attempts: usize,
+    /// Error of the last attempt.
+    pub error: CommandError,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::is_retryable;
+    use crate::{commands::CommandError, ResultCode};
+
+    #[test]
+    fn retryable_errors() {
+        assert!(is_retryable(&CommandError::Timeout));
+        assert!(is_retryable(&CommandError::ServerError(
+            ResultCode::KeyBusy
+        )));
+        assert!(is_retryable(&CommandError::ServerError(
+            ResultCode::DeviceOverload
+        )));
+        assert!(!is_retryable(&CommandError::ServerError(
+            ResultCode::KeyExistsError
+        )));
+        assert!(!is_retryable(&CommandError::Cancelled));
+    }
+
+    #[cfg(feature = "testing")]
+    #[tokio::test]
+    async fn write_with_mock() {
+        use std::sync::Arc;
+
+        use super::BulkWriter;
+        use crate::{
+            policies::{BasePolicy, RecordExistsAction, WritePolicy},
+            testing::MockClient,
+            Bin, Bins, ClientApi, Key, Value,
+        };
+
+        let client = Arc::new(MockClient::new());
+        let key = |i: i64| Key::new("test", "bulk", i);
+        client
+            .put(&WritePolicy::default(), &key(3), &[Bin::new("v", 0)])
+            .await
+            .unwrap();
+
+        let policy = WritePolicy {
+            record_exists_action: RecordExistsAction::CreateOnly,
+            ..WritePolicy::default()
+        };
+        let writer =
+            BulkWriter::new(Arc::clone(&client) as Arc<dyn ClientApi>, policy).concurrency(4);
+        writer
+            .write_all((0..10).map(|i| (key(i), vec![Bin::new("v", i)])))
+            .await;
+        let report = writer.finish().await;
+
+        assert_eq!(9, report.stats.written);
+        assert_eq!(1, report.stats.failed);
+        assert_eq!(0, report.stats.in_flight);
+        assert_eq!(1, report.failures.len());
+        assert_eq!(key(3), report.failures[0].key);
+        assert_eq!(1, report.failures[0].attempts);
+        assert!(matches!(
+            report.failures[0].error,
+            CommandError::ServerError(ResultCode::KeyExistsError)
+        ));
+
+        let record = client
+            .get(&BasePolicy::default(), &key(7), Bins::All)
+            .await
+            .unwrap();
+        assert_eq!(Some(&Value::from(7)), record.bins.get("v"));
+    }
+
+    #[cfg(feature = "testing")]
+    #[tokio::test]
+    async fn write_stream_with_mock() {
+        use std::{
+            pin::Pin,
+            sync::Arc,
+            task::{Context, Poll},
+        };
+
+        use futures_core::Stream;
+        use tokio::sync::mpsc;
+
+        use super::BulkWriter;
+        use crate::{
+            policies::{BasePolicy, WritePolicy},
+            testing::MockClient,
+            Bin, Bins, ClientApi, Key, Value,
+        };
+
+        struct Records(mpsc::Receiver<(Key, Vec<Bin<'static>>)>);
+
+        impl Stream for Records {
+            type Item = (Key, Vec<Bin<'static>>);
+
+            fn poll_next(
+                mut self: Pin<&mut Self>,
+                cx: &mut Context<'_>,
+            ) -> Poll<Option<Self::Item>> {
+                self.0.poll_recv(cx)
+            }
+        }
+
+        let key = |i: i64| Key::new("test", "bulk", i);
+        let (tx, rx) = mpsc::channel(2);
+        tokio::spawn(async move {
+            for i in 0..10 {
+                tx.send((key(i), vec![Bin::new("v", i)])).await.unwrap();
+            }
+        });
+
+        let client = Arc::new(MockClient::new());
+        let writer = BulkWriter::new(
+            Arc::clone(&client) as Arc<dyn ClientApi>,
+            WritePolicy::default(),
+        )
+        .concurrency(4);
+        writer.write_stream(Records(rx)).await;
+        let report = writer.finish().await;
+
+        assert_eq!(10, report.stats.written);
+        assert!(report.failures.is_empty());
+
+        let record = client
+            .get(&BasePolicy::default(), &key(9), Bins::All)
+            .await
+            .unwrap();
+        assert_eq!(Some(&Value::from(9)), record.bins.get("v"));
+    }
+}
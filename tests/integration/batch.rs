@@ -1,6 +1,6 @@
 use windpike::{
     policies::{BatchPolicy, Concurrency, WritePolicy},
-    BatchRead, Bin, Bins, Key,
+    BatchRead, Bin, Bins, Key, ResultCode,
 };
 
 use crate::common::{self, NAMESPACE};
@@ -45,6 +45,7 @@ async fn batch_get() {
 
     let result = results.remove(0);
     assert_eq!(result.key, key1);
+    assert_eq!(result.result, ResultCode::Ok);
     let record = result.record.unwrap();
     assert_eq!(record.bins.keys().count(), 1);
 
@@ -60,7 +61,108 @@ async fn batch_get() {
 
     let result = results.remove(0);
     assert_eq!(result.key, key4);
+    assert_eq!(result.result, ResultCode::KeyNotFoundError);
     let record = result.record;
     assert!(record.is_none());
     client.close();
 }
+
+#[tokio::test]
+async fn batch_get_bins() {
+    let client = common::client().await;
+    let set_name = common::rand_str(10);
+    let wpolicy = WritePolicy::default();
+
+    let keys = (0..20)
+        .map(|i| Key::new(NAMESPACE, set_name.clone(), i))
+        .collect::<Vec<_>>();
+    for (i, key) in keys.iter().enumerate().filter(|(i, _)| i % 2 == 0) {
+        let bins = [Bin::new("i", i as i64), Bin::new("other", 1)];
+        client.put(&wpolicy, key, &bins).await.unwrap();
+    }
+
+    let records = client
+        .batch_get_bins(&BatchPolicy::default(), &keys, Bins::from(["i"]))
+        .await
+        .unwrap();
+
+    assert_eq!(records.len(), keys.len());
+    for (i, record) in records.into_iter().enumerate() {
+        if i % 2 == 0 {
+            let record = record.unwrap();
+            assert_eq!(record.bins.len(), 1);
+            assert_eq!(record.get_i64("i").unwrap(), i as i64);
+        } else {
+            assert!(record.is_none());
+        }
+    }
+
+    client.close();
+}
+
+#[tokio::test]
+async fn batch_get_digest_only() {
+    let client = common::client().await;
+    let set_name = common::rand_str(10);
+    let wpolicy = WritePolicy::default();
+
+    let key1 = Key::new(NAMESPACE, set_name.clone(), 1);
+    client
+        .put(&wpolicy, &key1, &[Bin::new("a", 1)])
+        .await
+        .unwrap();
+
+    let key2 = Key::new(NAMESPACE, set_name.clone(), 2);
+    client
+        .put(&wpolicy, &key2, &[Bin::new("a", 2)])
+        .await
+        .unwrap();
+
+    let batch = vec![
+        BatchRead::new(Key::from_digest(NAMESPACE, "", key1.digest()), Bins::All),
+        BatchRead::new(
+            Key::from_digest(NAMESPACE, set_name, key2.digest()),
+            Bins::All,
+        ),
+    ];
+    let results = client
+        .batch_get(&BatchPolicy::default(), batch)
+        .await
+        .unwrap();
+
+    assert_eq!(results.len(), 2);
+    for result in results {
+        let record = result.record.unwrap();
+        assert_eq!(record.bins.len(), 1);
+    }
+
+    client.close();
+}
+
+#[tokio::test]
+async fn batch_get_short_query() {
+    let client = common::client().await;
+    let set_name = common::rand_str(10);
+    let bpolicy = BatchPolicy {
+        allow_inline: false,
+        short_query_threshold: 10,
+        ..BatchPolicy::default()
+    };
+    let wpolicy = WritePolicy::default();
+
+    let key = Key::new(NAMESPACE, set_name, 1);
+    client
+        .put(&wpolicy, &key, &[Bin::new("a", 1)])
+        .await
+        .unwrap();
+
+    let results = client
+        .batch_get(&bpolicy, vec![BatchRead::new(key, Bins::All)])
+        .await
+        .unwrap();
+
+    assert_eq!(results.len(), 1);
+    assert!(results[0].record.is_some());
+
+    client.close();
+}
@@ -1,7 +1,8 @@
 use windpike::{
-    operations::{list, scalar},
+    errors::CommandError,
+    operations::{cdt, list, scalar},
     policies::{BasePolicy, WritePolicy},
-    Bin, Bins, Key, Value,
+    Bin, Bins, Key, ResultCode, Value,
 };
 
 use crate::common::{self, NAMESPACE};
@@ -450,3 +451,112 @@ async fn cdt_list() {
     assert_eq!(*rec.bins.get("bin").unwrap(), windpike::list!(8, 9));
     client.close();
 }
+
+#[tokio::test]
+async fn cdt_list_persisted_index() {
+    let client = common::client().await;
+    let wpolicy = WritePolicy::default();
+    let key = Key::new(NAMESPACE, common::rand_str(10), -1);
+    let lpolicy = list::Policy::default();
+
+    client.delete(&wpolicy, &key).await.unwrap();
+
+    let values = vec![3.into(), 1.into(), 2.into()];
+    let ops = &[
+        list::create_with_index("bin", list::OrderType::Ordered, false, true),
+        list::append_items(lpolicy, "bin", &values).unwrap(),
+        scalar::get_bin("bin"),
+    ];
+    let rec = client.operate(&wpolicy, &key, ops).await.unwrap();
+    assert_eq!(
+        *rec.bins.get("bin").unwrap(),
+        windpike::list!(3, windpike::list!(1, 2, 3))
+    );
+
+    client.close();
+}
+
+#[tokio::test]
+async fn cdt_list_context_create() {
+    let client = common::client().await;
+
+    let wpolicy = WritePolicy::default();
+    let key = Key::new(NAMESPACE, common::rand_str(10), -1);
+    let lpolicy = list::Policy::default();
+
+    client.delete(&wpolicy, &key).await.unwrap();
+    client
+        .put(
+            &wpolicy,
+            &key,
+            &[Bin::new("bin", windpike::list!(windpike::list!(1)))],
+        )
+        .await
+        .unwrap();
+
+    let value = Value::from(5);
+    let ctx = [cdt::Context::list_index(2)];
+    let ops = [list::append(lpolicy, "bin", &value).set_context(&ctx)];
+    assert!(client.operate(&wpolicy, &key, &ops).await.is_err());
+
+    let ctx = [cdt::Context::list_index_create(
+        2,
+        list::OrderType::Unordered,
+        true,
+    )];
+    let ops = [
+        list::append(lpolicy, "bin", &value).set_context(&ctx),
+        scalar::get_bin("bin"),
+    ];
+    let rec = client.operate(&wpolicy, &key, &ops).await.unwrap();
+    assert_eq!(
+        *rec.bins.get("bin").unwrap(),
+        windpike::list!(
+            1,
+            windpike::list!(windpike::list!(1), Value::Nil, windpike::list!(5))
+        )
+    );
+
+    client.close();
+}
+
+#[tokio::test]
+async fn cdt_list_continue_on_error() {
+    let client = common::client().await;
+
+    let wpolicy = WritePolicy::default();
+    let key = Key::new(NAMESPACE, common::rand_str(10), -1);
+    let unique = list::Policy::new(list::OrderType::Unordered, list::WriteFlags::ADD_UNIQUE);
+
+    client.delete(&wpolicy, &key).await.unwrap();
+    client
+        .put(&wpolicy, &key, &[Bin::new("bin", windpike::list!(1))])
+        .await
+        .unwrap();
+
+    let one = Value::from(1);
+    let two = Value::from(2);
+    let ops = [scalar::get_bin("bin"), list::append(unique, "bin", &one)];
+    let err = client.operate(&wpolicy, &key, &ops).await.unwrap_err();
+    assert!(matches!(
+        err,
+        CommandError::OperationFailed {
+            index: 1,
+            code: ResultCode::ElementExists,
+        }
+    ));
+
+    let cpolicy = WritePolicy::builder().continue_on_error(true).build();
+    let ops = [
+        list::append(unique, "bin", &one),
+        list::append(unique, "bin", &two),
+        scalar::get_bin("bin"),
+    ];
+    let rec = client.operate(&cpolicy, &key, &ops).await.unwrap();
+    assert_eq!(
+        *rec.bins.get("bin").unwrap(),
+        windpike::list!(2, windpike::list!(1, 2))
+    );
+
+    client.close();
+}
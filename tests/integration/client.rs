@@ -1,10 +1,17 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
 use windpike::{
-    errors::{ClusterError, Error},
-    policies::ClientPolicy,
-    Client,
+    errors::{ClusterError, CommandError, Error},
+    interceptor::{CommandContext, CommandInterceptor},
+    metrics::CommandKind,
+    policies::{BasePolicy, ClientPolicy, InfoPolicy, NamespaceDefaults, WritePolicy},
+    Bin, Bins, Client, FeatureSupport, Key, ResultCode, Value,
 };
 
-use crate::common::{self, HOSTS};
+use crate::common::{self, HOSTS, NAMESPACE};
 
 #[tokio::test]
 async fn cluster_name() {
@@ -18,6 +25,31 @@ async fn cluster_name() {
     assert!(matches!(err, Error::Cluster(ClusterError::Connection)));
 }
 
+#[cfg(feature = "raw")]
+#[tokio::test]
+async fn raw_command() {
+    use windpike::raw::MessageType;
+
+    let client = common::client().await;
+    let node = client.nodes().await.remove(0);
+
+    let response = client
+        .raw_command(&BasePolicy::default(), &node, |request| {
+            request.message_type = MessageType::Info;
+            request.body.extend_from_slice(b"node\n");
+        })
+        .await
+        .unwrap();
+
+    assert_eq!(MessageType::Info, response.message_type);
+    assert_eq!(
+        format!("node\t{}\n", node.name()),
+        String::from_utf8(response.body).unwrap()
+    );
+
+    client.close();
+}
+
 #[tokio::test]
 async fn node_names() {
     let client = common::client().await;
@@ -26,6 +58,41 @@ async fn node_names() {
     client.close();
 }
 
+#[tokio::test]
+async fn nodes() {
+    let client = common::client().await;
+
+    let nodes = client.nodes().await;
+    assert!(!nodes.is_empty());
+    assert!(nodes.iter().all(|node| node.is_active()));
+    assert!(nodes
+        .iter()
+        .all(|node| node.supports(FeatureSupport::PEERS | FeatureSupport::REPLICAS)));
+
+    let key = Key::new(NAMESPACE, common::rand_str(10), 1);
+    let node = client.node_for_key(&key).await.unwrap();
+    assert!(nodes.iter().any(|other| other.name() == node.name()));
+    assert!(!node.address().await.address().is_empty());
+
+    let owners = client
+        .node_names_for_partition(NAMESPACE, key.partition_id())
+        .await;
+    assert_eq!(owners.master.as_deref(), Some(node.name()));
+    assert!(!owners.replicas.iter().any(|name| name == node.name()));
+
+    let info = node.info(&InfoPolicy::default(), &["node"]).await.unwrap();
+    assert_eq!(info.get("node").map(String::as_str), Some(node.name()));
+
+    client.close();
+}
+
+#[tokio::test]
+async fn whoami_without_credentials() {
+    let client = common::client().await;
+    assert_eq!(client.whoami().await.unwrap(), None);
+    client.close();
+}
+
 #[tokio::test]
 async fn close() {
     let client = common::client().await;
@@ -37,3 +104,276 @@ async fn close() {
         "the client did not disconnect"
     );
 }
+
+#[tokio::test]
+async fn single_node_mode() {
+    common::init_logger();
+
+    let policy = ClientPolicy {
+        single_node_mode: true,
+        ..ClientPolicy::default()
+    };
+    let client = Client::new(&policy, HOSTS).await.unwrap();
+    assert_eq!(client.node_names().await.len(), 1);
+    client.close();
+}
+
+#[tokio::test]
+async fn tcp_options() {
+    common::init_logger();
+
+    let policy = ClientPolicy {
+        connect_timeout: Some(Duration::from_secs(2)),
+        tcp_nodelay: false,
+        tcp_keepalive: Some(Duration::from_secs(30)),
+        tcp_keepalive_interval: Some(Duration::from_secs(5)),
+        ..ClientPolicy::default()
+    };
+    let client = Client::new(&policy, HOSTS).await.unwrap();
+    let key = Key::new(NAMESPACE, common::rand_str(10), 1);
+
+    client
+        .put(&WritePolicy::default(), &key, &[Bin::new("i", 1)])
+        .await
+        .unwrap();
+    assert!(client.exists(&WritePolicy::default(), &key).await.unwrap());
+
+    client.close();
+}
+
+#[tokio::test]
+async fn wait_until_stable_and_reconnect() {
+    let client = common::client().await;
+    client
+        .wait_until_stable(Duration::from_secs(5))
+        .await
+        .unwrap();
+
+    client.reconnect().await.unwrap();
+    assert!(client.is_connected().await);
+
+    client.close();
+    let err = client.reconnect().await.unwrap_err();
+    assert!(matches!(err, ClusterError::Closed));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn pipelining() {
+    common::init_logger();
+
+    let policy = ClientPolicy {
+        pipelining: true,
+        ..ClientPolicy::default()
+    };
+    let client = Client::new(&policy, HOSTS).await.unwrap();
+    let set_name = common::rand_str(10);
+
+    let tasks = (0..100)
+        .map(|i| {
+            let client = client.clone();
+            let key = Key::new(NAMESPACE, set_name.clone(), i);
+            tokio::spawn(async move {
+                client
+                    .put(&WritePolicy::default(), &key, &[Bin::new("i", i)])
+                    .await
+                    .unwrap();
+                client
+                    .get(&BasePolicy::default(), &key, Bins::All)
+                    .await
+                    .unwrap()
+            })
+        })
+        .collect::<Vec<_>>();
+
+    for (i, task) in tasks.into_iter().enumerate() {
+        let record = task.await.unwrap();
+        assert_eq!(record.bins.get("i"), Some(&Value::from(i as i64)));
+    }
+
+    client.close();
+}
+
+#[tokio::test]
+async fn partition_health() {
+    let client = common::client().await;
+
+    let health = client.partition_health().await;
+    assert!(health.iter().any(|health| health.namespace == NAMESPACE));
+    assert!(health.iter().all(|health| health.is_healthy()));
+
+    client.close();
+}
+
+#[tokio::test]
+async fn cluster_info() {
+    let client = common::client().await;
+
+    let info = client.cluster_info(&InfoPolicy::default()).await.unwrap();
+    assert_eq!(4096, info.partition_count);
+    assert!(info.cluster_key.is_some());
+    assert_eq!(client.nodes().await.len(), info.partition_generations.len());
+
+    client.close();
+}
+
+#[derive(Debug, Default)]
+struct Recorder {
+    calls: Mutex<Vec<(CommandKind, bool)>>,
+}
+
+impl CommandInterceptor for Recorder {
+    fn before(&self, context: &CommandContext<'_>) -> Result<(), CommandError> {
+        if context.key.set_name == "forbidden" {
+            return Err(CommandError::ServerError(ResultCode::RoleViolation));
+        }
+        Ok(())
+    }
+
+    fn after(
+        &self,
+        context: &CommandContext<'_>,
+        outcome: Result<(), &CommandError>,
+        _elapsed: Duration,
+    ) {
+        self.calls
+            .lock()
+            .unwrap()
+            .push((context.kind, outcome.is_ok()));
+    }
+}
+
+#[tokio::test]
+async fn interceptor() {
+    common::init_logger();
+
+    let recorder = Arc::new(Recorder::default());
+    let policy = ClientPolicy {
+        interceptor: Some(Arc::clone(&recorder) as _),
+        ..ClientPolicy::default()
+    };
+    let client = Client::new(&policy, HOSTS).await.unwrap();
+
+    let key = Key::new(NAMESPACE, common::rand_str(10), 1);
+    let wpolicy = WritePolicy::default();
+    client
+        .put(&wpolicy, &key, &[Bin::new("i", 1)])
+        .await
+        .unwrap();
+    client.delete(&wpolicy, &key).await.unwrap();
+    client
+        .get(&BasePolicy::default(), &key, Bins::All)
+        .await
+        .unwrap_err();
+
+    let forbidden = Key::new(NAMESPACE, "forbidden", 1);
+    let err = client
+        .put(&wpolicy, &forbidden, &[Bin::new("i", 1)])
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        CommandError::ServerError(ResultCode::RoleViolation)
+    ));
+
+    assert_eq!(
+        vec![
+            (CommandKind::Write, true),
+            (CommandKind::Write, true),
+            (CommandKind::Read, false),
+        ],
+        *recorder.calls.lock().unwrap()
+    );
+
+    client.close();
+}
+
+#[tokio::test]
+async fn info_all() {
+    let client = common::client().await;
+
+    let responses = client
+        .info_all(&InfoPolicy::default(), &["node", "partition-generation"])
+        .await
+        .unwrap();
+
+    let names = client.node_names().await;
+    assert_eq!(names.len(), responses.len());
+    for name in names {
+        let response = &responses[&name];
+        assert_eq!(Some(&name), response.get("node"));
+        assert!(response.contains_key("partition-generation"));
+    }
+
+    client.close();
+}
+
+#[tokio::test]
+async fn latency_metrics() {
+    common::init_logger();
+
+    let policy = ClientPolicy {
+        latency_metrics: true,
+        ..ClientPolicy::default()
+    };
+    let client = Client::new(&policy, HOSTS).await.unwrap();
+    assert!(client.latency_metrics_enabled());
+
+    let key = Key::new(NAMESPACE, common::rand_str(10), 1);
+    let wpolicy = WritePolicy::default();
+    client
+        .put(&wpolicy, &key, &[Bin::new("i", 1)])
+        .await
+        .unwrap();
+    client
+        .get(&BasePolicy::default(), &key, Bins::All)
+        .await
+        .unwrap();
+    client.delete(&wpolicy, &key).await.unwrap();
+
+    let writes = client.latency_snapshot(CommandKind::Write);
+    assert_eq!(2, writes.count());
+    assert!(writes.percentile(50.0) <= writes.max());
+    assert_eq!(1, client.latency_snapshot(CommandKind::Read).count());
+
+    client.set_latency_metrics(false);
+    client.exists(&wpolicy, &key).await.unwrap();
+    assert_eq!(1, client.latency_snapshot(CommandKind::Read).count());
+
+    client.reset_latency_metrics();
+    assert_eq!(0, client.latency_snapshot(CommandKind::Write).count());
+
+    client.close();
+}
+
+#[tokio::test]
+async fn namespace_defaults() {
+    let client = common::client().await;
+    client.set_namespace_defaults(
+        NAMESPACE,
+        NamespaceDefaults {
+            write: Some(WritePolicy::create_only()),
+            ..NamespaceDefaults::default()
+        },
+    );
+
+    let key = Key::new(NAMESPACE, common::rand_str(10), 1);
+    let wpolicy = WritePolicy::default();
+    let bins = [Bin::new("i", 1)];
+    client.put(&wpolicy, &key, &bins).await.unwrap();
+    let err = client.put(&wpolicy, &key, &bins).await.unwrap_err();
+    assert!(matches!(
+        err,
+        CommandError::ServerError(ResultCode::KeyExistsError)
+    ));
+
+    // Customized policies are used as is.
+    client
+        .put(&WritePolicy::update_only(), &key, &bins)
+        .await
+        .unwrap();
+
+    client.clear_namespace_defaults(NAMESPACE);
+    client.put(&wpolicy, &key, &bins).await.unwrap();
+
+    client.close();
+}
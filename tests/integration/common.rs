@@ -5,6 +5,12 @@ use windpike::{policies::ClientPolicy, Client};
 pub const HOSTS: &str = "127.0.0.1";
 pub const NAMESPACE: &str = "test";
 
+/// Namespace with strong consistency enabled, which must be configured on the server explicitly.
+/// Tests that need it are skipped unless it's named in the `SC_NAMESPACE` environment variable.
+pub fn sc_namespace() -> Option<String> {
+    std::env::var("SC_NAMESPACE").ok()
+}
+
 pub async fn client() -> Client {
     init_logger();
     Client::new(&ClientPolicy::default(), HOSTS).await.unwrap()
@@ -0,0 +1,123 @@
+use windpike::{
+    errors::{BinError, CommandError},
+    policies::{BasePolicy, WritePolicy},
+    testing::MockClient,
+    Bin, Bins, FromRecord, Key, ToBins,
+};
+
+use crate::common;
+
+#[derive(Debug, FromRecord, PartialEq, ToBins)]
+struct User {
+    name: String,
+    #[windpike(rename = "years")]
+    age: u32,
+    email: Option<String>,
+    #[windpike(skip)]
+    cached: bool,
+}
+
+#[tokio::test]
+async fn get_as() {
+    let client = MockClient::new();
+    let key = Key::new(common::NAMESPACE, "derive", "user");
+
+    client
+        .put(
+            &WritePolicy::default(),
+            &key,
+            &[Bin::new("name", "alice"), Bin::new("years", 32)],
+        )
+        .await
+        .unwrap();
+
+    let user = client
+        .get_as::<User, _>(&BasePolicy::default(), &key, Bins::All)
+        .await
+        .unwrap();
+    assert_eq!(
+        User {
+            name: "alice".to_owned(),
+            age: 32,
+            email: None,
+            cached: false,
+        },
+        user
+    );
+}
+
+#[tokio::test]
+async fn get_as_wrong_type() {
+    let client = MockClient::new();
+    let key = Key::new(common::NAMESPACE, "derive", "wrong");
+
+    client
+        .put(
+            &WritePolicy::default(),
+            &key,
+            &[Bin::new("name", 1), Bin::new("years", 32)],
+        )
+        .await
+        .unwrap();
+
+    let res = client
+        .get_as::<User, _>(&BasePolicy::default(), &key, Bins::All)
+        .await;
+    assert!(matches!(
+        res,
+        Err(CommandError::Bin(BinError::WrongType { name, .. })) if name == "name"
+    ));
+}
+
+#[tokio::test]
+async fn put_struct() {
+    let client = MockClient::new();
+    let key = Key::new(common::NAMESPACE, "derive", "put");
+    let user = User {
+        name: "bob".to_owned(),
+        age: 41,
+        email: Some("bob@example.com".to_owned()),
+        cached: true,
+    };
+
+    assert_eq!(
+        vec![
+            Bin::new("name", "bob"),
+            Bin::new("years", 41),
+            Bin::new("email", "bob@example.com"),
+        ],
+        user.to_bins()
+    );
+
+    client
+        .put_struct(&WritePolicy::default(), &key, &user)
+        .await
+        .unwrap();
+
+    let read = client
+        .get_as::<User, _>(&BasePolicy::default(), &key, Bins::All)
+        .await
+        .unwrap();
+    assert_eq!(
+        User {
+            cached: false,
+            ..user
+        },
+        read
+    );
+}
+
+#[test]
+fn to_bins_skips_none() {
+    let user = User {
+        name: "carol".to_owned(),
+        age: 25,
+        email: None,
+        cached: false,
+    };
+
+    assert_eq!(
+        vec![Bin::new("name", "carol"), Bin::new("years", 25)],
+        user.to_bins()
+    );
+}
@@ -1,4 +1,12 @@
-use windpike::{index::IndexType, policies::WritePolicy, Bin, Client, Key};
+use std::time::Duration;
+
+use windpike::{
+    errors::Error,
+    index::{CollectionIndexType, IndexBuilder, IndexType, Status},
+    operations::cdt::Context,
+    policies::{InfoPolicy, WritePolicy},
+    Bin, Client, Key, Value,
+};
 
 use crate::common::{self, NAMESPACE};
 
@@ -25,11 +33,14 @@ async fn recreate_index() {
     let set = create_test_set(&client, EXPECTED).await;
     let bin = "bin";
     let index = format!("{NAMESPACE}_{set}_{bin}");
+    let policy = InfoPolicy::default();
 
-    let _ = client.drop_index(NAMESPACE, &set, &index).await;
+    if let Ok(task) = client.drop_index(&policy, NAMESPACE, &set, &index).await {
+        task.wait_till_complete(None).await.unwrap();
+    }
 
     client
-        .create_index(NAMESPACE, &set, bin, &index, IndexType::Numeric)
+        .create_index(&policy, NAMESPACE, &set, bin, &index, IndexType::Numeric)
         .await
         .expect("failed to create index")
         .wait_till_complete(None)
@@ -37,7 +48,7 @@ async fn recreate_index() {
         .unwrap();
 
     client
-        .create_index(NAMESPACE, &set, bin, &index, IndexType::Numeric)
+        .create_index(&policy, NAMESPACE, &set, bin, &index, IndexType::Numeric)
         .await
         .unwrap()
         .wait_till_complete(None)
@@ -65,6 +76,7 @@ async fn index_task_test() {
 
     let index_task = client
         .create_index(
+            &InfoPolicy::default(),
             NAMESPACE,
             &set_name,
             &bin_name,
@@ -78,3 +90,92 @@ async fn index_task_test() {
 
     client.close();
 }
+
+#[tokio::test]
+async fn create_index_with_context() {
+    let client = common::client().await;
+    let set_name = common::rand_str(10);
+    let index_name = common::rand_str(10);
+    let policy = InfoPolicy::default();
+
+    let invalid = IndexBuilder::numeric(NAMESPACE, "bin", "idx;bad").set_name(&set_name);
+    assert!(matches!(
+        client.create_index_with(&policy, &invalid).await,
+        Err(Error::InvalidArgument(_))
+    ));
+
+    let index = IndexBuilder::numeric(NAMESPACE, "bin", &index_name)
+        .set_name(&set_name)
+        .collection(CollectionIndexType::List)
+        .context(&[Context::map_key(Value::from("scores"))]);
+
+    client
+        .create_index_with(&policy, &index)
+        .await
+        .unwrap()
+        .wait_till_complete(None)
+        .await
+        .unwrap();
+
+    client
+        .drop_index(&policy, NAMESPACE, &set_name, &index_name)
+        .await
+        .unwrap();
+
+    client.close();
+}
+
+#[tokio::test]
+async fn drop_index_wait() {
+    let client = common::client().await;
+    let set_name = create_test_set(&client, 10).await;
+    let index_name = common::rand_str(10);
+    let policy = InfoPolicy::default();
+
+    client
+        .create_index(
+            &policy,
+            NAMESPACE,
+            &set_name,
+            "bin",
+            &index_name,
+            IndexType::Numeric,
+        )
+        .await
+        .unwrap()
+        .wait_till_complete(None)
+        .await
+        .unwrap();
+
+    let task = client
+        .drop_index(&policy, NAMESPACE, &set_name, &index_name)
+        .await
+        .unwrap();
+    task.wait_till_complete(Some(Duration::from_secs(10)))
+        .await
+        .unwrap();
+    assert!(matches!(task.query_status().await, Ok(Status::Complete)));
+
+    // The index can be recreated right away once the removal completed.
+    client
+        .create_index(
+            &policy,
+            NAMESPACE,
+            &set_name,
+            "bin",
+            &index_name,
+            IndexType::Numeric,
+        )
+        .await
+        .unwrap()
+        .wait_till_complete(None)
+        .await
+        .unwrap();
+
+    client
+        .drop_index(&policy, NAMESPACE, &set_name, &index_name)
+        .await
+        .unwrap();
+
+    client.close();
+}
@@ -1,7 +1,8 @@
 use windpike::{
-    operations::scalar,
-    policies::{BasePolicy, WritePolicy},
-    Bin, Bins, Key, Value,
+    errors::CommandError,
+    operations::{list, scalar, Ops},
+    policies::{BasePolicy, ConsistencyLevel, ReadModeSc, WritePolicy},
+    Bin, Bins, CancellationToken, Key, Value,
 };
 
 use crate::common::{self, NAMESPACE};
@@ -82,5 +83,180 @@ async fn connect() {
     let existed = client.delete(&wpolicy, &key).await.unwrap();
     assert!(!existed);
 
+    let touched = client.touch_if_exists(&wpolicy, &key).await.unwrap();
+    assert!(!touched);
+
+    client.close();
+}
+
+#[tokio::test]
+async fn blob_append_prepend() {
+    let client = common::client().await;
+    let policy = BasePolicy::default();
+    let wpolicy = WritePolicy::default();
+    let key = Key::new(NAMESPACE, common::rand_str(10), -1);
+
+    client.delete(&wpolicy, &key).await.unwrap();
+
+    client
+        .put(&wpolicy, &key, &[Bin::new("log", vec![2u8])])
+        .await
+        .unwrap();
+    client
+        .append(&wpolicy, &key, &[Bin::new("log", vec![3u8, 4])])
+        .await
+        .unwrap();
+    client
+        .prepend(&wpolicy, &key, &[Bin::new("log", vec![1u8])])
+        .await
+        .unwrap();
+
+    let record = client.get(&policy, &key, Bins::All).await.unwrap();
+    assert_eq!(
+        record.bins.get("log"),
+        Some(&Value::from(vec![1u8, 2, 3, 4]))
+    );
+
+    let bin = Bin::new("log", vec![5u8]);
+    let record = client
+        .operate(&wpolicy, &key, &[scalar::append(&bin), scalar::get()])
+        .await
+        .unwrap();
+    assert_eq!(
+        record.bins.get("log"),
+        Some(&Value::from(vec![1u8, 2, 3, 4, 5]))
+    );
+
+    client.delete(&wpolicy, &key).await.unwrap();
+    client.close();
+}
+
+fn counter_ops(bin: &str) -> Ops {
+    Ops::new()
+        .add(bin, 1)
+        .list_append("history", bin)
+        .map_put("seen", bin, true)
+        .read(bin)
+}
+
+#[tokio::test]
+async fn operate_builder() {
+    let client = common::client().await;
+    let wpolicy = WritePolicy::default();
+    let key = Key::new(NAMESPACE, common::rand_str(10), -1);
+
+    client.delete(&wpolicy, &key).await.unwrap();
+
+    let ops = counter_ops("count");
+    assert_eq!(ops.len(), 4);
+    let ops = ops.build();
+    client.operate(&wpolicy, &key, &ops).await.unwrap();
+    let record = client.operate(&wpolicy, &key, &ops).await.unwrap();
+    assert_eq!(record.bins.get("count"), Some(&Value::from(2)));
+
+    client.delete(&wpolicy, &key).await.unwrap();
+    client.close();
+}
+
+#[tokio::test]
+async fn get_with_cancel() {
+    let client = common::client().await;
+    let policy = BasePolicy::default();
+    let wpolicy = WritePolicy::default();
+    let key = Key::new(NAMESPACE, common::rand_str(10), -1);
+
+    client
+        .put(&wpolicy, &key, &[Bin::new("bin", 1)])
+        .await
+        .unwrap();
+
+    let token = CancellationToken::new();
+    let record = client
+        .get_with_cancel(&policy, &key, Bins::All, &token)
+        .await
+        .unwrap();
+    assert_eq!(record.bins.get("bin"), Some(&Value::from(1)));
+
+    token.cancel();
+    let err = client
+        .get_with_cancel(&policy, &key, Bins::All, &token)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, CommandError::Cancelled));
+
+    // The client must still be usable afterwards.
+    client.get(&policy, &key, Bins::All).await.unwrap();
+
+    client.delete(&wpolicy, &key).await.unwrap();
+    client.close();
+}
+
+#[tokio::test]
+async fn operate_ordered() {
+    let client = common::client().await;
+    let wpolicy = WritePolicy::default();
+    let key = Key::new(NAMESPACE, common::rand_str(10), -1);
+
+    let bin = Bin::new("a", 1);
+    let value = Value::from(1);
+    let ops = [
+        scalar::put(&bin),
+        list::append(list::Policy::default(), "l", &value),
+        scalar::get_bin("a"),
+        scalar::get_bin("missing"),
+    ];
+    let results = client.operate_ordered(&wpolicy, &key, &ops).await.unwrap();
+
+    assert_eq!(results.len(), 4);
+    assert_eq!(&*results[1].bin, "l");
+    assert_eq!(results[1].value, Value::from(1));
+    assert_eq!(results[2].value, Value::from(1));
+    assert!(!results[1].skipped);
+    assert_eq!(results[3].value, Value::Nil);
+
+    client.delete(&wpolicy, &key).await.unwrap();
+    client.close();
+}
+
+#[tokio::test]
+async fn strong_consistency_reads() {
+    let namespace = match common::sc_namespace() {
+        Some(namespace) => namespace,
+        None => return,
+    };
+    let client = common::client().await;
+    let wpolicy = WritePolicy::default();
+    let key = Key::new(namespace, common::rand_str(10), -1);
+
+    client
+        .put(&wpolicy, &key, &[Bin::new("a", 1)])
+        .await
+        .unwrap();
+
+    let record = client
+        .get_linearizable(&BasePolicy::default(), &key, Bins::All)
+        .await
+        .unwrap();
+    assert_eq!(record.bins.get("a"), Some(&Value::from(1)));
+
+    for read_mode_sc in [
+        ReadModeSc::Session,
+        ReadModeSc::Linearize,
+        ReadModeSc::AllowReplica,
+        ReadModeSc::AllowUnavailable,
+    ] {
+        let policy = BasePolicy::builder()
+            .read_mode_sc(read_mode_sc)
+            .consistency_level(ConsistencyLevel::All)
+            .build();
+        let record = client.get(&policy, &key, Bins::All).await.unwrap();
+        assert_eq!(
+            record.bins.get("a"),
+            Some(&Value::from(1)),
+            "{read_mode_sc:?}"
+        );
+    }
+
+    client.delete(&wpolicy, &key).await.unwrap();
     client.close();
 }
@@ -4,6 +4,8 @@ mod cdt_list;
 mod cdt_map;
 mod client;
 mod common;
+#[cfg(all(feature = "derive", feature = "testing"))]
+mod derive;
 mod hll;
 mod index;
 mod kv;
@@ -1,13 +1,19 @@
-use std::sync::{
-    atomic::{AtomicUsize, Ordering},
-    Arc,
+use std::{
+    collections::HashSet,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
 };
 
 use tokio::sync::Mutex;
 use windpike::{
     self,
-    policies::{ScanPolicy, WritePolicy},
-    Bin, Bins, Client, Key, RecordSet,
+    errors::Error,
+    expressions::{int_bin, lt, val},
+    operations::Ops,
+    policies::{InfoPolicy, ScanPolicy, WritePolicy},
+    Bin, Bins, Client, Key, RecordSet, Value,
 };
 
 use crate::common::{self, NAMESPACE};
@@ -77,6 +83,305 @@ async fn scan_multi_consumer() {
     client.close();
 }
 
+#[tokio::test]
+async fn scan_map() {
+    let client = common::client().await;
+    let set_name = create_test_set(&client, EXPECTED).await;
+
+    let mut rs = client
+        .scan_map(
+            &ScanPolicy::default(),
+            NAMESPACE,
+            &set_name,
+            Bins::All,
+            |record| record.bins.get("bin").cloned(),
+        )
+        .await
+        .unwrap();
+
+    let mut values = Vec::with_capacity(EXPECTED);
+    while let Some(value) = rs.next().await {
+        values.push(value.unwrap());
+    }
+    values.sort_by_key(|value| match value {
+        Some(Value::Int(value)) => *value,
+        _ => -1,
+    });
+
+    assert_eq!(
+        values,
+        (0..EXPECTED as i64)
+            .map(|i| Some(Value::Int(i)))
+            .collect::<Vec<_>>()
+    );
+
+    client.close();
+}
+
+#[tokio::test]
+async fn scan_progress() {
+    let client = common::client().await;
+    let set_name = create_test_set(&client, EXPECTED).await;
+
+    let mut rs = client
+        .scan(&ScanPolicy::default(), NAMESPACE, &set_name, Bins::None)
+        .await
+        .unwrap();
+
+    let progress = rs.progress();
+    assert_eq!(progress.partitions_total(), 4096);
+    assert!(!progress.nodes.is_empty());
+
+    let count = count_results(&mut rs).await;
+    assert_eq!(count, EXPECTED);
+
+    let progress = rs.progress();
+    assert_eq!(progress.records(), EXPECTED as u64);
+    assert_eq!(progress.partitions_done(), 4096);
+
+    client.close();
+}
+
+#[tokio::test]
+async fn scan_max_records() {
+    let client = common::client().await;
+    let set_name = create_test_set(&client, EXPECTED).await;
+
+    let policy = ScanPolicy {
+        max_records: 100,
+        ..ScanPolicy::default()
+    };
+    let mut rs = client
+        .scan(&policy, NAMESPACE, &set_name, Bins::All)
+        .await
+        .unwrap();
+
+    let count = count_results(&mut rs).await;
+    assert!(count <= 100, "expected at most 100 records, got {count}");
+
+    client.close();
+}
+
+#[tokio::test]
+async fn scan_projection() {
+    let client = common::client().await;
+    let set_name = create_test_set(&client, 10).await;
+
+    let policy = ScanPolicy {
+        include_bin_data: false,
+        include_key: false,
+        ..ScanPolicy::default()
+    };
+    let mut rs = client
+        .scan(&policy, NAMESPACE, &set_name, Bins::All)
+        .await
+        .unwrap();
+
+    let mut count = 0;
+    while let Some(record) = rs.next().await {
+        let record = record.unwrap();
+        assert!(record.key.is_none());
+        assert!(record.bins.is_empty());
+        count += 1;
+    }
+    assert_eq!(count, 10);
+
+    let policy = ScanPolicy {
+        include_bin_data: false,
+        ..ScanPolicy::default()
+    };
+    let mut rs = client
+        .scan(&policy, NAMESPACE, &set_name, Bins::from(["bin"]))
+        .await
+        .unwrap();
+
+    while let Some(record) = rs.next().await {
+        let record = record.unwrap();
+        assert!(record.key.is_some());
+        assert!(record.bins.is_empty());
+    }
+
+    client.close();
+}
+
+#[tokio::test]
+async fn scan_partitioned() {
+    let client = common::client().await;
+    let set_name = create_test_set(&client, EXPECTED).await;
+
+    let chunks = client
+        .scan_partitioned(&ScanPolicy::default(), NAMESPACE, &set_name, Bins::All, 3)
+        .await
+        .unwrap();
+    assert_eq!(3, chunks.len());
+
+    let mut digests = HashSet::new();
+    for mut chunk in chunks {
+        while let Some(record) = chunk.next().await {
+            let key = record.unwrap().key.unwrap();
+            assert!(digests.insert(key.digest()), "record returned twice");
+        }
+    }
+    assert_eq!(EXPECTED, digests.len());
+
+    assert!(matches!(
+        client
+            .scan_partitioned(&ScanPolicy::default(), NAMESPACE, &set_name, Bins::All, 0)
+            .await,
+        Err(Error::InvalidArgument(_))
+    ));
+
+    client.close();
+}
+
+#[tokio::test]
+async fn scan_concurrent_partitions() {
+    let client = common::client().await;
+    let set_name = create_test_set(&client, EXPECTED).await;
+
+    let policy = ScanPolicy {
+        max_concurrent_partitions: 4,
+        ..ScanPolicy::default()
+    };
+    let mut rs = client
+        .scan(&policy, NAMESPACE, &set_name, Bins::All)
+        .await
+        .unwrap();
+
+    let count = count_results(&mut rs).await;
+    assert_eq!(count, EXPECTED);
+
+    client.close();
+}
+
+#[tokio::test]
+async fn scan_filter_expression() {
+    let client = common::client().await;
+    let set_name = create_test_set(&client, EXPECTED).await;
+
+    let policy = ScanPolicy {
+        filter_expression: Some(lt(int_bin("bin"), val(10))),
+        ..ScanPolicy::default()
+    };
+    let mut rs = client
+        .scan(&policy, NAMESPACE, &set_name, Bins::All)
+        .await
+        .unwrap();
+
+    let count = count_results(&mut rs).await;
+    assert_eq!(count, 10);
+
+    client.close();
+}
+
+#[tokio::test]
+async fn scan_sample_percent() {
+    let client = common::client().await;
+    let set_name = create_test_set(&client, EXPECTED).await;
+
+    let policy = ScanPolicy {
+        sample_percent: 10,
+        ..ScanPolicy::default()
+    };
+    let mut rs = client
+        .scan(&policy, NAMESPACE, &set_name, Bins::All)
+        .await
+        .unwrap();
+
+    let count = count_results(&mut rs).await;
+    assert!(count < EXPECTED, "expected a sample, got {count} records");
+
+    let policy = ScanPolicy {
+        sample_percent: 0,
+        ..ScanPolicy::default()
+    };
+    let result = client.scan(&policy, NAMESPACE, &set_name, Bins::All).await;
+    assert!(matches!(result, Err(Error::InvalidArgument(_))));
+
+    client.close();
+}
+
+#[tokio::test]
+async fn scan_close_early() {
+    let client = common::client().await;
+    let set_name = create_test_set(&client, EXPECTED).await;
+
+    let mut rs = client
+        .scan(&ScanPolicy::default(), NAMESPACE, &set_name, Bins::All)
+        .await
+        .unwrap();
+
+    assert!(matches!(rs.next().await, Some(Ok(_))));
+    rs.close();
+
+    let count = count_results(&mut rs).await;
+    assert!(
+        count < EXPECTED,
+        "expected an early stop, got {count} records"
+    );
+
+    client.close();
+}
+
+#[tokio::test]
+async fn count_records() {
+    let client = common::client().await;
+    let set_name = create_test_set(&client, EXPECTED).await;
+
+    let exact = client
+        .count_exact(&ScanPolicy::default(), NAMESPACE, &set_name)
+        .await
+        .unwrap();
+    assert_eq!(exact, EXPECTED as u64);
+
+    let estimate = client
+        .count(&InfoPolicy::default(), NAMESPACE, &set_name)
+        .await
+        .unwrap();
+    assert_eq!(estimate, EXPECTED as u64);
+
+    client.close();
+}
+
+#[tokio::test]
+async fn scan_operate() {
+    let client = common::client().await;
+    let set_name = common::rand_str(10);
+    let wpolicy = WritePolicy::default();
+
+    for i in 0..10_i64 {
+        let key = Key::new(NAMESPACE, set_name.clone(), i);
+        let bins = [
+            Bin::new("list", (0..i).collect::<Value>()),
+            Bin::new("other", i),
+        ];
+        client.put(&wpolicy, &key, &bins).await.unwrap();
+    }
+
+    let ops = Ops::new().list_size("list").build();
+    let mut rs = client
+        .scan_operate(&ScanPolicy::default(), NAMESPACE, &set_name, ops)
+        .await
+        .unwrap();
+
+    let mut sizes = Vec::new();
+    while let Some(record) = rs.next().await {
+        let record = record.unwrap();
+        assert_eq!(record.bins.len(), 1);
+        sizes.push(record.bins["list"].as_i64().unwrap());
+    }
+    sizes.sort_unstable();
+    assert_eq!(sizes, (0..10).collect::<Vec<_>>());
+
+    let writes = Ops::new().add("other", 1).build();
+    let result = client
+        .scan_operate(&ScanPolicy::default(), NAMESPACE, &set_name, writes)
+        .await;
+    assert!(matches!(result, Err(Error::InvalidArgument(_))));
+
+    client.close();
+}
+
 async fn count_results(rs: &mut RecordSet) -> usize {
     let mut count = 0;
     while let Some(Ok(_)) = rs.next().await {
@@ -85,3 +390,24 @@ async fn count_results(rs: &mut RecordSet) -> usize {
 
     count
 }
+
+#[tokio::test]
+async fn list_and_kill_jobs() {
+    let client = common::client().await;
+    let set_name = create_test_set(&client, EXPECTED).await;
+    let policy = InfoPolicy::default();
+
+    let mut rs = client
+        .scan(&ScanPolicy::default(), NAMESPACE, &set_name, Bins::All)
+        .await
+        .unwrap();
+    count_results(&mut rs).await;
+
+    let jobs = client.list_jobs(&policy).await.unwrap();
+    assert!(jobs.iter().any(|job| job.set_name == set_name));
+
+    let err = client.kill_job(&policy, u64::MAX).await.unwrap_err();
+    assert!(matches!(err, Error::JobNotFound(u64::MAX)));
+
+    client.close();
+}
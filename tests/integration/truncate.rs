@@ -1,14 +1,61 @@
+use std::time::Duration;
+
+use windpike::{
+    errors::Error,
+    policies::{InfoPolicy, WritePolicy},
+    Bin, Key,
+};
+
 use crate::common::{self, NAMESPACE};
 
 #[tokio::test]
 async fn truncate() {
     let client = common::client().await;
+    let set_name = common::rand_str(10);
 
-    let result = common::client()
+    let key = Key::new(NAMESPACE, set_name.clone(), 1);
+    client
+        .put(&WritePolicy::default(), &key, &[Bin::new("a", 1)])
         .await
-        .truncate(NAMESPACE, &common::rand_str(10), 0)
+        .unwrap();
+
+    let result = client
+        .truncate(&InfoPolicy::default(), NAMESPACE, &set_name, 0)
         .await;
     assert!(result.is_ok());
 
     client.close();
 }
+
+#[tokio::test]
+async fn truncate_missing() {
+    let client = common::client().await;
+
+    let result = client
+        .truncate(&InfoPolicy::default(), NAMESPACE, &common::rand_str(10), 0)
+        .await;
+    assert!(matches!(result, Err(Error::SetNotFound { .. })));
+
+    let namespace = common::rand_str(10);
+    let result = client
+        .truncate(&InfoPolicy::default(), &namespace, "", 0)
+        .await;
+    assert!(matches!(result, Err(Error::NamespaceNotFound(ns)) if ns == namespace));
+
+    client.close();
+}
+
+#[tokio::test]
+async fn truncate_timeout() {
+    let client = common::client().await;
+
+    let policy = InfoPolicy {
+        timeout: Duration::from_nanos(1),
+    };
+    let result = client
+        .truncate(&policy, NAMESPACE, &common::rand_str(10), 0)
+        .await;
+    assert!(result.is_err());
+
+    client.close();
+}
@@ -0,0 +1,76 @@
+use syn::{spanned::Spanned, Data, DeriveInput, Field, Fields, LitStr, Result};
+
+/// Maximum length of a bin name, as enforced by the server.
+const MAX_BIN_NAME_LEN: usize = 15;
+
+/// Settings of a single struct field, as given by the `#[windpike(...)]` attributes.
+pub struct FieldAttrs {
+    /// Name of the bin that the field maps to.
+    pub bin: LitStr,
+    /// Whether the field is ignored.
+    pub skip: bool,
+}
+
+impl FieldAttrs {
+    fn parse(field: &Field) -> Result<Self> {
+        let ident = field
+            .ident
+            .as_ref()
+            .ok_or_else(|| syn::Error::new(field.span(), "only named fields are supported"))?;
+        let mut bin = LitStr::new(&ident.to_string(), ident.span());
+        let mut skip = false;
+
+        for attr in field.attrs.iter().filter(|a| a.path().is_ident("windpike")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("rename") {
+                    bin = meta.value()?.parse()?;
+                    Ok(())
+                } else if meta.path.is_ident("skip") {
+                    skip = true;
+                    Ok(())
+                } else {
+                    Err(meta.error("unknown windpike attribute"))
+                }
+            })?;
+        }
+
+        if !skip && bin.value().len() > MAX_BIN_NAME_LEN {
+            return Err(syn::Error::new(
+                bin.span(),
+                format_args!(
+                    "bin name `{}` is longer than {MAX_BIN_NAME_LEN} bytes, use \
+                     `#[windpike(rename = \"...\")]` to shorten it",
+                    bin.value()
+                ),
+            ));
+        }
+
+        Ok(Self { bin, skip })
+    }
+}
+
+/// Collect the named fields of a struct, together with their attributes.
+pub fn named_fields(input: &DeriveInput) -> Result<Vec<(&Field, FieldAttrs)>> {
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(syn::Error::new(
+                    input.ident.span(),
+                    "only structs with named fields are supported",
+                ))
+            }
+        },
+        _ => {
+            return Err(syn::Error::new(
+                input.ident.span(),
+                "only structs are supported",
+            ))
+        }
+    };
+
+    fields
+        .iter()
+        .map(|field| FieldAttrs::parse(field).map(|attrs| (field, attrs)))
+        .collect()
+}
@@ -0,0 +1,37 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{DeriveInput, Result};
+
+use crate::attr;
+
+pub fn expand(input: &DeriveInput) -> Result<TokenStream> {
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let fields = attr::named_fields(input)?
+        .into_iter()
+        .map(|(field, attrs)| {
+            let ident = &field.ident;
+            let bin = &attrs.bin;
+
+            if attrs.skip {
+                quote! { #ident: ::core::default::Default::default() }
+            } else {
+                quote! { #ident: record.take(#bin)? }
+            }
+        });
+
+    Ok(quote! {
+        #[automatically_derived]
+        impl #impl_generics ::windpike::FromRecord for #name #ty_generics #where_clause {
+            #[allow(unused_mut, unused_variables)]
+            fn from_record(
+                mut record: ::windpike::Record,
+            ) -> ::core::result::Result<Self, ::windpike::errors::BinError> {
+                ::core::result::Result::Ok(Self {
+                    #(#fields,)*
+                })
+            }
+        }
+    })
+}
@@ -0,0 +1,36 @@
+//! Derive macros for the [`windpike`](https://docs.rs/windpike) crate. These are re-exported by
+//! the main crate when its `derive` feature is enabled, and shouldn't be used directly.
+
+use proc_macro::TokenStream;
+use syn::{parse_macro_input, DeriveInput};
+
+mod attr;
+mod from_record;
+mod to_bins;
+
+/// Implement `FromRecord` for a struct with named fields, reading each field from the bin of the
+/// same name.
+///
+/// Fields accept the following attributes:
+///
+/// - `#[windpike(rename = "name")]` reads the field from a differently named bin.
+/// - `#[windpike(skip)]` doesn't read the field at all, and fills it with its `Default` value.
+#[proc_macro_derive(FromRecord, attributes(windpike))]
+pub fn derive_from_record(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    from_record::expand(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Implement `ToBins` for a struct with named fields, writing each field to the bin of the same
+/// name. Fields of type `Option` are left out if they're `None`.
+///
+/// Fields accept the same attributes as for [`FromRecord`](macro@FromRecord).
+#[proc_macro_derive(ToBins, attributes(windpike))]
+pub fn derive_to_bins(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    to_bins::expand(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
@@ -0,0 +1,68 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{DeriveInput, GenericArgument, PathArguments, Result, Type};
+
+use crate::attr;
+
+pub fn expand(input: &DeriveInput) -> Result<TokenStream> {
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let fields = attr::named_fields(input)?
+        .into_iter()
+        .filter(|(_, attrs)| !attrs.skip)
+        .map(|(field, attrs)| {
+            let ident = &field.ident;
+            let bin = &attrs.bin;
+
+            if is_option(&field.ty) {
+                quote! {
+                    if let ::core::option::Option::Some(value) = &self.#ident {
+                        bins.push(::windpike::Bin::new(
+                            #bin,
+                            ::core::clone::Clone::clone(value),
+                        ));
+                    }
+                }
+            } else {
+                quote! {
+                    bins.push(::windpike::Bin::new(
+                        #bin,
+                        ::core::clone::Clone::clone(&self.#ident),
+                    ));
+                }
+            }
+        })
+        .collect::<Vec<_>>();
+    let capacity = fields.len();
+
+    Ok(quote! {
+        #[automatically_derived]
+        impl #impl_generics ::windpike::ToBins for #name #ty_generics #where_clause {
+            #[allow(unused_mut)]
+            fn to_bins(&self) -> ::std::vec::Vec<::windpike::Bin<'_>> {
+                let mut bins = ::std::vec::Vec::with_capacity(#capacity);
+                #(#fields)*
+                bins
+            }
+        }
+    })
+}
+
+/// Check whether the type is an [`Option`], which is only detected by its name, as proc-macros
+/// don't have access to type information.
+fn is_option(ty: &Type) -> bool {
+    let path = match ty {
+        Type::Path(ty) if ty.qself.is_none() => &ty.path,
+        _ => return false,
+    };
+
+    path.segments.last().map_or(false, |segment| {
+        segment.ident == "Option"
+            && matches!(
+                &segment.arguments,
+                PathArguments::AngleBracketed(args)
+                    if matches!(args.args.first(), Some(GenericArgument::Type(_)))
+            )
+    })
+}